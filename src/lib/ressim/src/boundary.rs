@@ -0,0 +1,204 @@
+//! Constant-pressure and prescribed-flux boundary conditions on the outer faces of the grid,
+//! and the faces of Fetkovich aquifers (see `aquifer`).
+//!
+//! A condition covers one outer face of the root grid (`x-`, `x+`, `y-`, `y+`, `z-` or `z+`),
+//! optionally restricted to a set of layers, and is resolved into one boundary connection per
+//! cell on that face: the half-cell transmissibility from the cell centre to the face. Where a
+//! boundary cell is refined, its children on the face take its place.
+//!
+//! A pressure boundary acts as a ghost cell beside each face cell, at the cell's depth, holding
+//! the boundary pressure and the given saturations; flow in either direction is upwinded
+//! between the cell and the ghost exactly as across an interior face. A flux boundary injects a
+//! prescribed reservoir rate (negative to withdraw), split over its faces in proportion to their
+//! transmissibility. Inflow carries the phase mix the boundary saturations would flow, outflow
+//! the cell's own. Without free gas at the boundary, inflowing oil carries the face cell's
+//! dissolved-gas ratio at the start of the step, capped at saturation.
+
+use crate::ReservoirSimulator;
+use crate::fim::flux::FaceCellInput;
+use crate::fim::state::HydrocarbonState;
+
+/// What a boundary condition holds fixed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum BoundaryDrive {
+    /// Ghost-cell pressure [bar] at the depth of each face cell.
+    Pressure { pressure_bar: f64 },
+    /// Reservoir rate [m³/day] into the grid; negative for outflow.
+    Rate { rate_m3_day: f64 },
+    /// Face of Fetkovich aquifer `aquifer` (see `aquifer`), taking `productivity_index`
+    /// [m³/(day·bar)]: the aquifer's whole index on a condition, a face's share of it on a
+    /// connection.
+    Aquifer {
+        aquifer: usize,
+        productivity_index: f64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BoundaryCondition {
+    /// Face normal: `'x'`, `'y'` or `'z'`.
+    pub(crate) dim: char,
+    /// Whether the condition sits on the `+` (last-cell) face rather than the `-` face.
+    pub(crate) plus: bool,
+    /// Layers `k` the condition covers; empty for all of them.
+    pub(crate) layers: Vec<usize>,
+    pub(crate) drive: BoundaryDrive,
+    /// Saturations of the fluid outside the face.
+    pub(crate) sat_water: f64,
+    pub(crate) sat_gas: f64,
+}
+
+/// One face of a boundary condition. For a rate drive, `drive` holds this face's share.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct BoundaryConnection {
+    pub(crate) cell: usize,
+    /// Half-cell transmissibility [mD·m²/m].
+    pub(crate) transmissibility: f64,
+    pub(crate) drive: BoundaryDrive,
+    pub(crate) sat_water: f64,
+    pub(crate) sat_gas: f64,
+}
+
+impl ReservoirSimulator {
+    /// Cells on the outer face of `condition`, with refined cells replaced by their children on
+    /// that face.
+    fn boundary_cells(&self, condition: &BoundaryCondition) -> Vec<usize> {
+        let (nx, ny, nz) = (self.nx, self.ny, self.nz);
+        let axis = match condition.dim {
+            'x' => 0,
+            'y' => 1,
+            _ => 2,
+        };
+        let extent = [nx, ny, nz][axis];
+        let face_index = if condition.plus { extent - 1 } else { 0 };
+        let mut on_face = vec![false; self.cell_count()];
+        for k in 0..nz {
+            if !condition.layers.is_empty() && !condition.layers.contains(&k) {
+                continue;
+            }
+            for j in 0..ny {
+                for i in 0..nx {
+                    if [i, j, k][axis] == face_index {
+                        on_face[self.idx(i, j, k)] = true;
+                    }
+                }
+            }
+        }
+        // Children follow their parents, so one pass reaches nested refinements.
+        let root_cells = self.root_cell_count();
+        for (position, cell) in self.local_cells.iter().enumerate() {
+            let refinement = self.local_grids[cell.grid].refinement[axis];
+            let child_face_index = if condition.plus { refinement - 1 } else { 0 };
+            if on_face[cell.parent] && cell.offset[axis] == child_face_index {
+                on_face[root_cells + position] = true;
+            }
+        }
+        on_face
+            .iter()
+            .enumerate()
+            .filter_map(|(id, &on)| on.then_some(id))
+            .collect()
+    }
+
+    /// Every open boundary face in condition order, then cell order. Faces of inactive cells are
+    /// left out, and a rate is shared only among the open faces of its condition, an aquifer's
+    /// productivity index among the open faces of all of its conditions.
+    pub(crate) fn boundary_connections(&self) -> Vec<BoundaryConnection> {
+        let mut connections = Vec::new();
+        let mut aquifer_t = vec![0.0; self.aquifers.len()];
+        for condition in &self.boundary_conditions {
+            let faces: Vec<(usize, f64)> = self
+                .boundary_cells(condition)
+                .into_iter()
+                .map(|cell| {
+                    let t = self.boundary_transmissibility(cell, condition.dim, condition.plus);
+                    (cell, t)
+                })
+                .filter(|&(_, t)| t > 0.0)
+                .collect();
+            let total_t: f64 = faces.iter().map(|&(_, t)| t).sum();
+            for (cell, transmissibility) in faces {
+                let drive = match condition.drive {
+                    BoundaryDrive::Rate { rate_m3_day } => BoundaryDrive::Rate {
+                        rate_m3_day: rate_m3_day * transmissibility / total_t,
+                    },
+                    BoundaryDrive::Aquifer { aquifer, .. } => {
+                        aquifer_t[aquifer] += transmissibility;
+                        condition.drive
+                    }
+                    pressure => pressure,
+                };
+                connections.push(BoundaryConnection {
+                    cell,
+                    transmissibility,
+                    drive,
+                    sat_water: condition.sat_water,
+                    sat_gas: condition.sat_gas,
+                });
+            }
+        }
+        for connection in &mut connections {
+            if let BoundaryDrive::Aquifer {
+                aquifer,
+                productivity_index,
+            } = &mut connection.drive
+            {
+                *productivity_index *= connection.transmissibility / aquifer_t[*aquifer];
+            }
+        }
+        connections
+    }
+
+    /// Free gas outside a boundary face; always zero in two-phase mode.
+    pub(crate) fn boundary_gas_saturation(&self, connection: &BoundaryConnection) -> f64 {
+        if self.three_phase_mode {
+            connection.sat_gas
+        } else {
+            0.0
+        }
+    }
+
+    /// Dissolved-gas ratio [Sm³/Sm³] of the oil outside a boundary face at `pressure_bar`.
+    pub(crate) fn boundary_rs(&self, connection: &BoundaryConnection, pressure_bar: f64) -> f64 {
+        match &self.pvt_table {
+            Some(table) if self.three_phase_mode => {
+                let rs_sat = table.interpolate(pressure_bar).rs_m3m3;
+                if connection.sat_gas > 0.0 {
+                    rs_sat
+                } else {
+                    self.rs[connection.cell].min(rs_sat)
+                }
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// FIM primary variables of the fluid outside a boundary face at `pressure_bar`.
+    pub(crate) fn boundary_face_input(
+        &self,
+        connection: &BoundaryConnection,
+        pressure_bar: f64,
+    ) -> FaceCellInput<f64> {
+        let undersaturated =
+            self.three_phase_mode && self.pvt_table.is_some() && connection.sat_gas <= 0.0;
+        let (regime, hydrocarbon_var) = if undersaturated {
+            (
+                HydrocarbonState::Undersaturated,
+                self.rs[connection.cell].max(0.0),
+            )
+        } else {
+            (
+                HydrocarbonState::Saturated,
+                self.boundary_gas_saturation(connection),
+            )
+        };
+        FaceCellInput {
+            p: pressure_bar,
+            sw: connection.sat_water,
+            hydrocarbon_var,
+            regime,
+            depth: self.depth_at(connection.cell),
+            drsdt0_base_rs: None,
+        }
+    }
+}
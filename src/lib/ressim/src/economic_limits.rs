@@ -0,0 +1,363 @@
+//! Economic and operating limits on producers (Eclipse `WECON` / `GECON`).
+//!
+//! A physical well or a group — the field included — may carry a minimum oil rate and maximum
+//! water cut, gas-oil ratio and gas-liquid ratio. After every step each limited producer, and
+//! the summed producers of each limited group, are checked against the rates they flow at the
+//! end-of-step state, and the first limit broken triggers the limit's action:
+//!
+//! - shut the well, or for a group its worst-offending well;
+//! - close the worst-offending completion, shutting the well once its last one is closed;
+//! - stop the run, after which further steps do nothing.
+//!
+//! The worst offender is the one with the highest value of the broken ratio, or the lowest oil
+//! rate for a minimum-rate limit. Each action is recorded as an [`EconomicLimitEvent`] on the
+//! last rate-history point.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ReservoirSimulator;
+
+/// What breaking an economic limit does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EconomicAction {
+    ShutWell,
+    CloseCompletion,
+    StopRun,
+}
+
+impl EconomicAction {
+    pub(crate) fn parse(action: &str) -> Option<Self> {
+        match action.trim().to_ascii_lowercase().as_str() {
+            "shut" => Some(Self::ShutWell),
+            "close_completion" => Some(Self::CloseCompletion),
+            "stop" => Some(Self::StopRun),
+            _ => None,
+        }
+    }
+}
+
+/// Producer limits; `None` leaves a quantity unchecked.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EconomicLimits {
+    /// Minimum oil rate [Sm³/day].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_oil_rate_sm3_day: Option<f64>,
+    /// Maximum water cut, water over liquid at surface conditions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_water_cut: Option<f64>,
+    /// Maximum gas-oil ratio [Sm³/Sm³].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gor: Option<f64>,
+    /// Maximum gas-liquid ratio [Sm³/Sm³].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_glr: Option<f64>,
+    pub action: EconomicAction,
+}
+
+/// An action taken on breaking an economic limit.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EconomicLimitEvent {
+    /// Simulation time [days].
+    pub time: f64,
+    /// Physical well id or group name whose limit was broken.
+    pub source: String,
+    /// The broken limit and the value that broke it.
+    pub reason: String,
+    /// What was done, e.g. `shut well P1`.
+    pub action: String,
+}
+
+/// Quantity a limit bounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LimitedQuantity {
+    OilRate,
+    WaterCut,
+    Gor,
+    Glr,
+}
+
+impl LimitedQuantity {
+    /// Value of the quantity for surface rates `[water, oil, gas]`.
+    fn value(self, [water, oil, gas]: [f64; 3]) -> f64 {
+        let ratio = |numerator: f64, denominator: f64| {
+            if denominator > 0.0 {
+                numerator / denominator
+            } else if numerator > 0.0 {
+                f64::INFINITY
+            } else {
+                0.0
+            }
+        };
+        match self {
+            Self::OilRate => oil,
+            Self::WaterCut => ratio(water, water + oil),
+            Self::Gor => ratio(gas, oil),
+            Self::Glr => ratio(gas, water + oil),
+        }
+    }
+
+    /// How badly `rates` offend; higher is worse.
+    fn offence(self, rates: [f64; 3]) -> f64 {
+        match self {
+            Self::OilRate => -self.value(rates),
+            _ => self.value(rates),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::OilRate => "oil rate",
+            Self::WaterCut => "water cut",
+            Self::Gor => "GOR",
+            Self::Glr => "GLR",
+        }
+    }
+}
+
+impl EconomicLimits {
+    /// First limit `rates` break, with the offending value.
+    fn broken(&self, rates: [f64; 3]) -> Option<(LimitedQuantity, f64, f64)> {
+        let checks = [
+            (LimitedQuantity::OilRate, self.min_oil_rate_sm3_day),
+            (LimitedQuantity::WaterCut, self.max_water_cut),
+            (LimitedQuantity::Gor, self.max_gor),
+            (LimitedQuantity::Glr, self.max_glr),
+        ];
+        checks.into_iter().find_map(|(quantity, limit)| {
+            let limit = limit?;
+            let value = quantity.value(rates);
+            let broken = match quantity {
+                LimitedQuantity::OilRate => value < limit,
+                _ => value > limit,
+            };
+            broken.then_some((quantity, value, limit))
+        })
+    }
+}
+
+/// An open producing completion with its end-of-step surface rates.
+struct CompletionRates {
+    entry: usize,
+    well_id: String,
+    rates: [f64; 3],
+}
+
+enum Closure {
+    Shut(String),
+    Close(usize),
+    Stop,
+}
+
+/// Distinct physical well ids of `completions`, in order of first appearance.
+fn well_ids<'a>(completions: impl Iterator<Item = &'a CompletionRates>) -> Vec<&'a str> {
+    let mut ids: Vec<&str> = Vec::new();
+    for completion in completions {
+        if !ids.contains(&completion.well_id.as_str()) {
+            ids.push(&completion.well_id);
+        }
+    }
+    ids
+}
+
+fn sum_rates<'a>(completions: impl Iterator<Item = &'a CompletionRates>) -> [f64; 3] {
+    completions.fold([0.0; 3], |mut total, completion| {
+        for (sum, rate) in total.iter_mut().zip(completion.rates) {
+            *sum += rate;
+        }
+        total
+    })
+}
+
+impl ReservoirSimulator {
+    /// Check every economic limit against the end-of-step rates and carry out what they call
+    /// for.
+    pub(crate) fn apply_economic_limits(&mut self) {
+        let has_limits = self.wells.iter().any(|well| well.economic_limits.is_some())
+            || self
+                .well_groups
+                .iter()
+                .any(|group| group.economic_limits.is_some());
+        if !has_limits {
+            return;
+        }
+        let completions = self.producing_completion_rates();
+        let mut closures: Vec<(Closure, EconomicLimitEvent)> = Vec::new();
+        for well_id in well_ids(completions.iter()) {
+            let own: Vec<&CompletionRates> = completions
+                .iter()
+                .filter(|completion| completion.well_id == well_id)
+                .collect();
+            let Some(limits) = self.wells[own[0].entry].economic_limits else {
+                continue;
+            };
+            let Some((quantity, value, limit)) = limits.broken(sum_rates(own.iter().copied()))
+            else {
+                continue;
+            };
+            closures.push(self.economic_closure(
+                limits.action,
+                quantity,
+                &own,
+                well_id,
+                value,
+                limit,
+            ));
+        }
+
+        for group in &self.well_groups {
+            let Some(limits) = group.economic_limits else {
+                continue;
+            };
+            let members: Vec<&CompletionRates> = completions
+                .iter()
+                .filter(|completion| group.holds(&completion.well_id))
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            let Some((quantity, value, limit)) = limits.broken(sum_rates(members.iter().copied()))
+            else {
+                continue;
+            };
+            closures.push(self.economic_closure(
+                limits.action,
+                quantity,
+                &members,
+                &group.name,
+                value,
+                limit,
+            ));
+        }
+
+        self.carry_out_closures(closures);
+    }
+
+    /// The closure a broken limit calls for among `completions`, the producing completions of
+    /// the well or group `source`.
+    fn economic_closure(
+        &self,
+        action: EconomicAction,
+        quantity: LimitedQuantity,
+        completions: &[&CompletionRates],
+        source: &str,
+        value: f64,
+        limit: f64,
+    ) -> (Closure, EconomicLimitEvent) {
+        let worst_completion = completions
+            .iter()
+            .max_by(|a, b| {
+                quantity
+                    .offence(a.rates)
+                    .total_cmp(&quantity.offence(b.rates))
+            })
+            .expect("a limited well or group has producing completions");
+        let (closure, action) = match action {
+            EconomicAction::StopRun => (Closure::Stop, "stop run".to_string()),
+            EconomicAction::ShutWell => {
+                let worst_well = well_ids(completions.iter().copied())
+                    .into_iter()
+                    .max_by(|a, b| {
+                        let rates = |id: &str| {
+                            sum_rates(completions.iter().copied().filter(|c| c.well_id == id))
+                        };
+                        quantity
+                            .offence(rates(a))
+                            .total_cmp(&quantity.offence(rates(b)))
+                    })
+                    .unwrap_or(source);
+                (
+                    Closure::Shut(worst_well.to_string()),
+                    format!("shut well {}", worst_well),
+                )
+            }
+            EconomicAction::CloseCompletion => {
+                let well = &self.wells[worst_completion.entry];
+                (
+                    Closure::Close(worst_completion.entry),
+                    format!(
+                        "close completion ({}, {}, {}) of well {}",
+                        well.i, well.j, well.k, worst_completion.well_id
+                    ),
+                )
+            }
+        };
+        let event = EconomicLimitEvent {
+            time: self.time_days,
+            source: source.to_string(),
+            reason: match quantity {
+                LimitedQuantity::OilRate => format!(
+                    "{} {:.4} below minimum {:.4}",
+                    quantity.label(),
+                    value,
+                    limit
+                ),
+                _ => format!(
+                    "{} {:.4} above maximum {:.4}",
+                    quantity.label(),
+                    value,
+                    limit
+                ),
+            },
+            action,
+        };
+        (closure, event)
+    }
+
+    fn carry_out_closures(&mut self, closures: Vec<(Closure, EconomicLimitEvent)>) {
+        if closures.is_empty() {
+            return;
+        }
+        let mut events = Vec::with_capacity(closures.len());
+        for (closure, event) in closures {
+            match closure {
+                Closure::Stop => self.run_stopped = true,
+                Closure::Shut(id) => self.shut_physical_well(&id),
+                Closure::Close(entry) => {
+                    self.wells[entry].open = false;
+                    if let Some(id) = self.wells[entry].physical_well_id.clone() {
+                        self.shut_well_without_open_completions(&id);
+                    }
+                }
+            }
+            events.push(event);
+        }
+        self.update_dynamic_well_productivity_indices();
+        if let Some(point) = self.rate_history.last_mut() {
+            point.events.extend(events);
+        }
+    }
+
+    fn shut_physical_well(&mut self, id: &str) {
+        for well in &mut self.wells {
+            if well.physical_well_id.as_deref() == Some(id) {
+                well.schedule.enabled = false;
+            }
+        }
+    }
+
+    /// Surface rates of every open, enabled producer completion with a physical well id, at the
+    /// current state and under the well's control.
+    fn producing_completion_rates(&self) -> Vec<CompletionRates> {
+        self.wells
+            .iter()
+            .enumerate()
+            .filter(|(_, well)| {
+                !well.injector && well.open && self.well_control_config(well).enabled
+            })
+            .filter_map(|(entry, well)| {
+                let well_id = well.physical_well_id.clone()?;
+                let q_m3_day = self
+                    .well_rate_m3_day_for_pressures(well, &self.pressure)
+                    .unwrap_or(0.0)
+                    .max(0.0);
+                let rates = self.producer_surface_rates(well, &self.pressure, q_m3_day);
+                Some(CompletionRates {
+                    entry,
+                    well_id,
+                    rates: rates.map(|rate| rate.max(0.0)),
+                })
+            })
+            .collect()
+    }
+}
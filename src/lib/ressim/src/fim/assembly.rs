@@ -24,6 +24,8 @@ use crate::fim::wells::{
     build_well_topology, fischer_burmeister_gradient, perforation_local_block, well_local_block,
 };
 #[cfg(test)]
+use crate::fim::wells_segments::multi_segment_well_terms;
+#[cfg(test)]
 use crate::timing::PerfTimer;
 
 pub(crate) const DARCY_METRIC_FACTOR: f64 = 8.526_988_8e-3;
@@ -53,6 +55,8 @@ pub(crate) struct CellResidualBreakdown {
     pub(crate) y_plus: f64,
     pub(crate) z_minus: f64,
     pub(crate) z_plus: f64,
+    pub(crate) non_neighbor: f64,
+    pub(crate) boundary: f64,
     pub(crate) well_source: f64,
     pub(crate) total: f64,
 }
@@ -276,6 +280,7 @@ pub(crate) fn assemble_fim_system(
             options.flow_resv_context,
             &mut tri,
         );
+        add_exact_multi_segment_well_jacobian(sim, state, topology, options.dt_days, &mut tri);
     }
 
     let jacobian = tri.to_csr();
@@ -305,6 +310,9 @@ fn add_exact_well_source_jacobian(
     tri: &mut TriMatI<f64, usize>,
 ) {
     for perf_idx in 0..topology.perforations.len() {
+        if topology.perforations[perf_idx].segment_index.is_some() {
+            continue;
+        }
         if let Some(context) =
             flow_resv_context_for_perforation(flow_resv_context, topology, perf_idx)
         {
@@ -349,6 +357,9 @@ fn add_exact_well_source_cell_jacobian(
     tri: &mut TriMatI<f64, usize>,
 ) {
     for perf_idx in 0..topology.perforations.len() {
+        if topology.perforations[perf_idx].segment_index.is_some() {
+            continue;
+        }
         if flow_resv_context_for_perforation(flow_resv_context, topology, perf_idx).is_some() {
             continue;
         }
@@ -378,6 +389,9 @@ fn add_exact_well_constraint_jacobian(
     tri: &mut TriMatI<f64, usize>,
 ) {
     for well_idx in 0..topology.wells.len() {
+        if !topology.wells[well_idx].segment_indices.is_empty() {
+            continue;
+        }
         if let FimWellRoute::FlowResvGasInjector(context) =
             fim_well_route(flow_resv_context, topology, well_idx)
         {
@@ -435,6 +449,9 @@ fn add_exact_well_constraint_cell_jacobian(
     tri: &mut TriMatI<f64, usize>,
 ) {
     for well_idx in 0..topology.wells.len() {
+        if !topology.wells[well_idx].segment_indices.is_empty() {
+            continue;
+        }
         if matches!(
             fim_well_route(flow_resv_context, topology, well_idx),
             FimWellRoute::FlowResvGasInjector(_)
@@ -478,6 +495,9 @@ fn add_exact_perforation_jacobian(
     tri: &mut TriMatI<f64, usize>,
 ) {
     for perf_idx in 0..topology.perforations.len() {
+        if topology.perforations[perf_idx].segment_index.is_some() {
+            continue;
+        }
         if let Some(context) =
             flow_resv_context_for_perforation(flow_resv_context, topology, perf_idx)
         {
@@ -518,6 +538,30 @@ fn add_exact_perforation_jacobian(
     }
 }
 
+/// A multi-segment well's rows all come from `multi_segment_well_terms`, which the per-
+/// perforation and per-well loops above skip.
+#[cfg(test)]
+fn add_exact_multi_segment_well_jacobian(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    topology: &FimWellTopology,
+    dt_days: f64,
+    tri: &mut TriMatI<f64, usize>,
+) {
+    for (well_idx, well) in topology.wells.iter().enumerate() {
+        if well.segment_indices.is_empty() {
+            continue;
+        }
+        for term in multi_segment_well_terms(sim, state, topology, well_idx, dt_days) {
+            for (column, value) in term.columns {
+                if value.abs() > 1e-14 {
+                    tri.add_triplet(term.row, column, value);
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 fn add_exact_perforation_cell_pressure_jacobian(
     sim: &ReservoirSimulator,
@@ -527,6 +571,9 @@ fn add_exact_perforation_cell_pressure_jacobian(
     tri: &mut TriMatI<f64, usize>,
 ) {
     for perf_idx in 0..topology.perforations.len() {
+        if topology.perforations[perf_idx].segment_index.is_some() {
+            continue;
+        }
         if flow_resv_context_for_perforation(flow_resv_context, topology, perf_idx).is_some() {
             continue;
         }
@@ -645,6 +692,8 @@ pub(crate) fn cell_equation_residual_breakdown(
     let in_layer = cell_idx % cells_per_layer;
     let j = in_layer / sim.nx;
     let i = in_layer % sim.nx;
+    // Local-grid children connect only through non-neighbour faces.
+    let root = cell_idx < sim.root_cell_count();
 
     let mut breakdown = CellResidualBreakdown {
         accumulation,
@@ -654,11 +703,13 @@ pub(crate) fn cell_equation_residual_breakdown(
         y_plus: 0.0,
         z_minus: 0.0,
         z_plus: 0.0,
+        non_neighbor: 0.0,
+        boundary: 0.0,
         well_source: 0.0,
         total: 0.0,
     };
 
-    if i > 0 {
+    if root && i > 0 {
         let neighbor = sim.idx(i - 1, j, k);
         let derived_neighbor = state.derive_cell(sim, neighbor);
         if let Some(flux) = interface_flux_contribution(
@@ -668,15 +719,13 @@ pub(crate) fn cell_equation_residual_breakdown(
             neighbor,
             cell_idx,
             'x',
-            k,
-            k,
             &derived_neighbor,
             &derived_cell,
         ) {
             breakdown.x_minus = flux[1][component];
         }
     }
-    if i + 1 < sim.nx {
+    if root && i + 1 < sim.nx {
         let neighbor = sim.idx(i + 1, j, k);
         let derived_neighbor = state.derive_cell(sim, neighbor);
         if let Some(flux) = interface_flux_contribution(
@@ -686,15 +735,13 @@ pub(crate) fn cell_equation_residual_breakdown(
             cell_idx,
             neighbor,
             'x',
-            k,
-            k,
             &derived_cell,
             &derived_neighbor,
         ) {
             breakdown.x_plus = flux[0][component];
         }
     }
-    if j > 0 {
+    if root && j > 0 {
         let neighbor = sim.idx(i, j - 1, k);
         let derived_neighbor = state.derive_cell(sim, neighbor);
         if let Some(flux) = interface_flux_contribution(
@@ -704,15 +751,13 @@ pub(crate) fn cell_equation_residual_breakdown(
             neighbor,
             cell_idx,
             'y',
-            k,
-            k,
             &derived_neighbor,
             &derived_cell,
         ) {
             breakdown.y_minus = flux[1][component];
         }
     }
-    if j + 1 < sim.ny {
+    if root && j + 1 < sim.ny {
         let neighbor = sim.idx(i, j + 1, k);
         let derived_neighbor = state.derive_cell(sim, neighbor);
         if let Some(flux) = interface_flux_contribution(
@@ -722,15 +767,13 @@ pub(crate) fn cell_equation_residual_breakdown(
             cell_idx,
             neighbor,
             'y',
-            k,
-            k,
             &derived_cell,
             &derived_neighbor,
         ) {
             breakdown.y_plus = flux[0][component];
         }
     }
-    if k > 0 {
+    if root && k > 0 {
         let neighbor = sim.idx(i, j, k - 1);
         let derived_neighbor = state.derive_cell(sim, neighbor);
         if let Some(flux) = interface_flux_contribution(
@@ -740,15 +783,13 @@ pub(crate) fn cell_equation_residual_breakdown(
             neighbor,
             cell_idx,
             'z',
-            k - 1,
-            k,
             &derived_neighbor,
             &derived_cell,
         ) {
             breakdown.z_minus = flux[1][component];
         }
     }
-    if k + 1 < sim.nz {
+    if root && k + 1 < sim.nz {
         let neighbor = sim.idx(i, j, k + 1);
         let derived_neighbor = state.derive_cell(sim, neighbor);
         if let Some(flux) = interface_flux_contribution(
@@ -758,8 +799,6 @@ pub(crate) fn cell_equation_residual_breakdown(
             cell_idx,
             neighbor,
             'z',
-            k,
-            k + 1,
             &derived_cell,
             &derived_neighbor,
         ) {
@@ -767,6 +806,33 @@ pub(crate) fn cell_equation_residual_breakdown(
         }
     }
 
+    for nnc in sim.open_non_neighbor_connections() {
+        let (neighbor, sign) = if nnc.cell_a == cell_idx {
+            (nnc.cell_b, 1.0)
+        } else if nnc.cell_b == cell_idx {
+            (nnc.cell_a, -1.0)
+        } else {
+            continue;
+        };
+        let derived_neighbor = state.derive_cell(sim, neighbor);
+        let (derived_a, derived_b) = if sign > 0.0 {
+            (&derived_cell, &derived_neighbor)
+        } else {
+            (&derived_neighbor, &derived_cell)
+        };
+        if let Some(terms) = interface_flux_terms_for_transmissibility(
+            sim,
+            state,
+            nnc.cell_a,
+            nnc.cell_b,
+            DARCY_METRIC_FACTOR * nnc.transmissibility,
+            derived_a,
+            derived_b,
+        ) {
+            breakdown.non_neighbor += sign * terms.flux_sc_day[component] * dt_days;
+        }
+    }
+
     for (perf_idx, perforation) in topology.perforations.iter().enumerate() {
         if perforation.cell_index == cell_idx {
             breakdown.well_source +=
@@ -782,6 +848,8 @@ pub(crate) fn cell_equation_residual_breakdown(
         + breakdown.y_plus
         + breakdown.z_minus
         + breakdown.z_plus
+        + breakdown.non_neighbor
+        + breakdown.boundary
         + breakdown.well_source;
 
     Some(breakdown)
@@ -793,7 +861,7 @@ pub(crate) fn cell_face_phase_flux_diagnostics(
     dt_days: f64,
     cell_idx: usize,
 ) -> Option<CellFacePhaseDiagnostics> {
-    if cell_idx >= state.cells.len() {
+    if cell_idx >= state.cells.len() || cell_idx >= sim.root_cell_count() {
         return None;
     }
 
@@ -814,8 +882,6 @@ pub(crate) fn cell_face_phase_flux_diagnostics(
             neighbor,
             cell_idx,
             'x',
-            k,
-            k,
             &derived_neighbor,
             &derived_cell,
             1,
@@ -834,8 +900,6 @@ pub(crate) fn cell_face_phase_flux_diagnostics(
             cell_idx,
             neighbor,
             'x',
-            k,
-            k,
             &derived_cell,
             &derived_neighbor,
             0,
@@ -854,8 +918,6 @@ pub(crate) fn cell_face_phase_flux_diagnostics(
             neighbor,
             cell_idx,
             'y',
-            k,
-            k,
             &derived_neighbor,
             &derived_cell,
             1,
@@ -874,8 +936,6 @@ pub(crate) fn cell_face_phase_flux_diagnostics(
             cell_idx,
             neighbor,
             'y',
-            k,
-            k,
             &derived_cell,
             &derived_neighbor,
             0,
@@ -1024,8 +1084,6 @@ fn assemble_residual_with_flags(
                             id,
                             id_j,
                             'x',
-                            k,
-                            k,
                             &derived[id],
                             &derived[id_j],
                             &mut residual,
@@ -1040,8 +1098,6 @@ fn assemble_residual_with_flags(
                             id,
                             id_j,
                             'y',
-                            k,
-                            k,
                             &derived[id],
                             &derived[id_j],
                             &mut residual,
@@ -1056,8 +1112,6 @@ fn assemble_residual_with_flags(
                             id,
                             id_j,
                             'z',
-                            k,
-                            k + 1,
                             &derived[id],
                             &derived[id_j],
                             &mut residual,
@@ -1091,6 +1145,7 @@ fn assemble_residual_with_flags(
             options.flow_resv_context,
             &mut residual,
         );
+        add_multi_segment_well_equations(sim, state, topology, options.dt_days, &mut residual);
     }
 
     residual
@@ -1337,12 +1392,10 @@ fn interface_flux_contribution(
     id_i: usize,
     id_j: usize,
     dim: char,
-    k_i: usize,
-    k_j: usize,
     derived_i: &FimCellDerived,
     derived_j: &FimCellDerived,
 ) -> Option<[[f64; 3]; 2]> {
-    let terms = interface_flux_terms(sim, state, id_i, id_j, dim, k_i, k_j, derived_i, derived_j)?;
+    let terms = interface_flux_terms(sim, state, id_i, id_j, dim, derived_i, derived_j)?;
     let flux_sc = [
         terms.flux_sc_day[0] * dt_days,
         terms.flux_sc_day[1] * dt_days,
@@ -1360,8 +1413,21 @@ pub(crate) fn interface_flux_terms(
     id_i: usize,
     id_j: usize,
     dim: char,
-    k_i: usize,
-    k_j: usize,
+    derived_i: &FimCellDerived,
+    derived_j: &FimCellDerived,
+) -> Option<InterfaceFluxTerms> {
+    let geom_t = DARCY_METRIC_FACTOR * sim.geometric_transmissibility(id_i, id_j, dim);
+    interface_flux_terms_for_transmissibility(sim, state, id_i, id_j, geom_t, derived_i, derived_j)
+}
+
+/// [`interface_flux_terms`] across a connection of known `geom_t`, the
+/// `DARCY_METRIC_FACTOR`-scaled transmissibility, such as a non-neighbour connection.
+fn interface_flux_terms_for_transmissibility(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    id_i: usize,
+    id_j: usize,
+    geom_t: f64,
     derived_i: &FimCellDerived,
     derived_j: &FimCellDerived,
 ) -> Option<InterfaceFluxTerms> {
@@ -1370,9 +1436,8 @@ pub(crate) fn interface_flux_terms(
 
     let p_i = cell_i.pressure_bar;
     let p_j = cell_j.pressure_bar;
-    let depth_i = sim.depth_at_k(k_i);
-    let depth_j = sim.depth_at_k(k_j);
-    let geom_t = DARCY_METRIC_FACTOR * sim.geometric_transmissibility(id_i, id_j, dim);
+    let depth_i = sim.depth_at(id_i);
+    let depth_j = sim.depth_at(id_j);
 
     if geom_t <= 0.0 {
         return None;
@@ -1451,13 +1516,11 @@ fn oriented_face_phase_diagnostics(
     id_i: usize,
     id_j: usize,
     dim: char,
-    k_i: usize,
-    k_j: usize,
     derived_i: &FimCellDerived,
     derived_j: &FimCellDerived,
     target_side: usize,
 ) -> Option<FacePhaseDiagnostics> {
-    let terms = interface_flux_terms(sim, state, id_i, id_j, dim, k_i, k_j, derived_i, derived_j)?;
+    let terms = interface_flux_terms(sim, state, id_i, id_j, dim, derived_i, derived_j)?;
     let sign = if target_side == 0 { 1.0 } else { -1.0 };
 
     Some(FacePhaseDiagnostics {
@@ -1505,8 +1568,6 @@ fn add_exact_flux_jacobian(
                         id,
                         id_j,
                         'x',
-                        k,
-                        k,
                         &derived[id],
                         &derived[id_j],
                         &sensitivities[id],
@@ -1523,8 +1584,6 @@ fn add_exact_flux_jacobian(
                         id,
                         id_j,
                         'y',
-                        k,
-                        k,
                         &derived[id],
                         &derived[id_j],
                         &sensitivities[id],
@@ -1541,8 +1600,6 @@ fn add_exact_flux_jacobian(
                         id,
                         id_j,
                         'z',
-                        k,
-                        k + 1,
                         &derived[id],
                         &derived[id_j],
                         &sensitivities[id],
@@ -1563,8 +1620,6 @@ fn add_exact_interface_flux_jacobian(
     id_i: usize,
     id_j: usize,
     dim: char,
-    k_i: usize,
-    k_j: usize,
     derived_i: &FimCellDerived,
     derived_j: &FimCellDerived,
     local_i: &LocalFluxCellSensitivity,
@@ -1577,8 +1632,8 @@ fn add_exact_interface_flux_jacobian(
 
     let p_i = cell_i.pressure_bar;
     let p_j = cell_j.pressure_bar;
-    let depth_i = sim.depth_at_k(k_i);
-    let depth_j = sim.depth_at_k(k_j);
+    let depth_i = sim.depth_at(id_i);
+    let depth_j = sim.depth_at(id_j);
     let geom_t = DARCY_METRIC_FACTOR * sim.geometric_transmissibility(id_i, id_j, dim);
     if geom_t <= 0.0 {
         return;
@@ -1715,8 +1770,6 @@ fn add_local_flux_jacobian_fd(
                         id,
                         sim.idx(i + 1, j, k),
                         'x',
-                        k,
-                        k,
                         tri,
                     );
                 }
@@ -1728,8 +1781,6 @@ fn add_local_flux_jacobian_fd(
                         id,
                         sim.idx(i, j + 1, k),
                         'y',
-                        k,
-                        k,
                         tri,
                     );
                 }
@@ -1741,8 +1792,6 @@ fn add_local_flux_jacobian_fd(
                         id,
                         sim.idx(i, j, k + 1),
                         'z',
-                        k,
-                        k + 1,
                         tri,
                     );
                 }
@@ -1759,8 +1808,6 @@ fn add_interface_flux_jacobian_fd(
     id_i: usize,
     id_j: usize,
     dim: char,
-    k_i: usize,
-    k_j: usize,
     tri: &mut TriMatI<f64, usize>,
 ) {
     let base_derived_i = state.derive_cell(sim, id_i);
@@ -1772,8 +1819,6 @@ fn add_interface_flux_jacobian_fd(
         id_i,
         id_j,
         dim,
-        k_i,
-        k_j,
         &base_derived_i,
         &base_derived_j,
     ) else {
@@ -1801,8 +1846,6 @@ fn add_interface_flux_jacobian_fd(
                 id_i,
                 id_j,
                 dim,
-                k_i,
-                k_j,
                 &perturbed_derived_i,
                 &perturbed_derived_j,
             ) else {
@@ -1835,6 +1878,9 @@ fn add_well_source_terms(
     residual: &mut DVector<f64>,
 ) {
     for (perf_idx, perforation) in topology.perforations.iter().enumerate() {
+        if perforation.segment_index.is_some() {
+            continue;
+        }
         if let Some(context) =
             flow_resv_context_for_perforation(flow_resv_context, topology, perf_idx)
         {
@@ -1862,6 +1908,9 @@ fn add_well_constraint_equations(
     residual: &mut DVector<f64>,
 ) {
     for well_idx in 0..topology.wells.len() {
+        if !topology.wells[well_idx].segment_indices.is_empty() {
+            continue;
+        }
         if let FimWellRoute::FlowResvGasInjector(context) =
             fim_well_route(flow_resv_context, topology, well_idx)
         {
@@ -1892,6 +1941,9 @@ fn add_perforation_equations(
     residual: &mut DVector<f64>,
 ) {
     for perf_idx in 0..topology.perforations.len() {
+        if topology.perforations[perf_idx].segment_index.is_some() {
+            continue;
+        }
         if let Some(context) =
             flow_resv_context_for_perforation(flow_resv_context, topology, perf_idx)
         {
@@ -1909,6 +1961,24 @@ fn add_perforation_equations(
     }
 }
 
+#[cfg(test)]
+fn add_multi_segment_well_equations(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    topology: &FimWellTopology,
+    dt_days: f64,
+    residual: &mut DVector<f64>,
+) {
+    for (well_idx, well) in topology.wells.iter().enumerate() {
+        if well.segment_indices.is_empty() {
+            continue;
+        }
+        for term in multi_segment_well_terms(sim, state, topology, well_idx, dt_days) {
+            residual[term.row] += term.value;
+        }
+    }
+}
+
 #[cfg(test)]
 fn add_interface_flux(
     sim: &ReservoirSimulator,
@@ -1917,15 +1987,13 @@ fn add_interface_flux(
     id_i: usize,
     id_j: usize,
     dim: char,
-    k_i: usize,
-    k_j: usize,
     derived_i: &FimCellDerived,
     derived_j: &FimCellDerived,
     residual: &mut DVector<f64>,
 ) {
-    let Some(flux) = interface_flux_contribution(
-        sim, state, dt_days, id_i, id_j, dim, k_i, k_j, derived_i, derived_j,
-    ) else {
+    let Some(flux) =
+        interface_flux_contribution(sim, state, dt_days, id_i, id_j, dim, derived_i, derived_j)
+    else {
         return;
     };
 
@@ -1969,49 +2037,25 @@ pub(crate) fn collect_face_upwind_snapshot(
                 let id = sim.idx(i, j, k);
                 if i + 1 < sim.nx {
                     let id_j = sim.idx(i + 1, j, k);
-                    if let Some(sample) = face_upwind_sample(
-                        sim,
-                        state,
-                        id,
-                        id_j,
-                        'x',
-                        k,
-                        k,
-                        &derived[id],
-                        &derived[id_j],
-                    ) {
+                    if let Some(sample) =
+                        face_upwind_sample(sim, state, id, id_j, 'x', &derived[id], &derived[id_j])
+                    {
                         samples.push(sample);
                     }
                 }
                 if j + 1 < sim.ny {
                     let id_j = sim.idx(i, j + 1, k);
-                    if let Some(sample) = face_upwind_sample(
-                        sim,
-                        state,
-                        id,
-                        id_j,
-                        'y',
-                        k,
-                        k,
-                        &derived[id],
-                        &derived[id_j],
-                    ) {
+                    if let Some(sample) =
+                        face_upwind_sample(sim, state, id, id_j, 'y', &derived[id], &derived[id_j])
+                    {
                         samples.push(sample);
                     }
                 }
                 if k + 1 < sim.nz {
                     let id_j = sim.idx(i, j, k + 1);
-                    if let Some(sample) = face_upwind_sample(
-                        sim,
-                        state,
-                        id,
-                        id_j,
-                        'z',
-                        k,
-                        k + 1,
-                        &derived[id],
-                        &derived[id_j],
-                    ) {
+                    if let Some(sample) =
+                        face_upwind_sample(sim, state, id, id_j, 'z', &derived[id], &derived[id_j])
+                    {
                         samples.push(sample);
                     }
                 }
@@ -2027,12 +2071,10 @@ fn face_upwind_sample(
     id_i: usize,
     id_j: usize,
     dim: char,
-    k_i: usize,
-    k_j: usize,
     derived_i: &FimCellDerived,
     derived_j: &FimCellDerived,
 ) -> Option<FaceUpwindSample> {
-    let terms = interface_flux_terms(sim, state, id_i, id_j, dim, k_i, k_j, derived_i, derived_j)?;
+    let terms = interface_flux_terms(sim, state, id_i, id_j, dim, derived_i, derived_j)?;
     let upwind = [
         if terms.dphi[0] >= 0.0 { 0u8 } else { 1u8 },
         if terms.dphi[1] >= 0.0 { 0u8 } else { 1u8 },
@@ -18,7 +18,8 @@ use nalgebra::DVector;
 use sprs::TriMatI;
 
 use crate::ReservoirSimulator;
-use crate::fim::ad::Ad;
+use crate::boundary::{BoundaryConnection, BoundaryDrive};
+use crate::fim::ad::{Ad, Scalar};
 use crate::fim::assembly::{
     CellResidualBreakdown, DARCY_METRIC_FACTOR, FimAssembly, FimAssemblyOptions, FimAssemblyTiming,
     equation_offset, unknown_offset,
@@ -27,7 +28,10 @@ use crate::fim::flow_resv::{
     FimWellRoute, FlowResvInjectorResidual, FlowResvReportStepContext, fim_well_route,
     flow_resv_context_for_perforation, flow_resv_injector_residual,
 };
-use crate::fim::flux::{FaceCellInput, face_flux_jacobian_blocks, face_flux_residual_f64};
+use crate::fim::flux::{
+    FaceCellInput, boundary_rate_jacobian_block, boundary_rate_terms_generic,
+    face_flux_jacobian_blocks, face_flux_residual_f64,
+};
 use crate::fim::properties::{
     accumulation_jacobian_block, cell_accumulation_generic, cell_props_generic,
 };
@@ -37,15 +41,18 @@ use crate::fim::scaling::{
 use crate::fim::state::FimState;
 use crate::fim::wells::{
     FimWellTopology, build_well_topology, effective_injected_fluid, geometric_well_index,
-    perforation_head_offset_bar, perforation_local_block, physical_well_control,
+    perforation_component_rates_sc_day, perforation_head_offset_bar, perforation_local_block,
+    perforation_non_darcy_coefficient, physical_well_control, physical_well_injected_fluid,
 };
 use crate::fim::wells_ad::{
     WellCellInput, WellControlValuesGeneric, WellPerforationInputGeneric,
     component_rate_coefficients_generic, connection_rate_generic, mass_balance_neighbor_jacobian,
     mass_balance_own_jacobian, producer_fractions_generic, rate_consistency_cell_bhp_jacobian,
-    well_constraint_bhp_column_and_fb_gradient, well_constraint_neighbor_rate_jacobian,
-    well_constraint_own_perforation_rate_jacobian, well_constraint_residual_fb_generic,
+    thp_constraint_source_rate_gradient, well_constraint_bhp_column_and_fb_gradient,
+    well_constraint_neighbor_rate_jacobian, well_constraint_own_perforation_rate_jacobian,
+    well_constraint_residual_fb_generic,
 };
+use crate::fim::wells_segments::multi_segment_well_terms;
 
 fn cell_drsdt0_base_rs(sim: &ReservoirSimulator, cell_idx: usize) -> Option<f64> {
     if sim.gas_redissolution_enabled {
@@ -92,7 +99,6 @@ fn face_cell_input(
     sim: &ReservoirSimulator,
     state: &FimState,
     cell_idx: usize,
-    depth_k: usize,
 ) -> FaceCellInput<f64> {
     let cell = state.cell(cell_idx);
     FaceCellInput {
@@ -100,7 +106,7 @@ fn face_cell_input(
         sw: cell.sw,
         hydrocarbon_var: cell.hydrocarbon_var,
         regime: cell.regime,
-        depth: sim.depth_at_k(depth_k),
+        depth: sim.depth_at(cell_idx),
         drsdt0_base_rs: cell_drsdt0_base_rs(sim, cell_idx),
     }
 }
@@ -160,7 +166,8 @@ pub(crate) fn flow_resv_terms_ad(
     let q = connection_rate_generic(
         sim,
         wi,
-        perforation_head_offset_bar(sim, perforation),
+        perforation_non_darcy_coefficient(sim, perforation),
+        perforation_head_offset_bar(sim, state, topology, perf_idx),
         true,
         &seeded,
         bhp,
@@ -194,7 +201,8 @@ pub(crate) fn flow_resv_terms_f64(
     let q = connection_rate_generic(
         sim,
         geometric_well_index(sim, perforation)?,
-        perforation_head_offset_bar(sim, perforation),
+        perforation_non_darcy_coefficient(sim, perforation),
+        perforation_head_offset_bar(sim, state, topology, perf_idx),
         true,
         &cell,
         state.well_bhp[perforation.physical_well_index],
@@ -230,6 +238,8 @@ pub(crate) fn well_control_generic(
         target_rate: control.target_rate,
         bhp_limit: control.bhp_limit,
         bhp_target: control.bhp_target,
+        thp: control.thp,
+        surface_phase: control.surface_phase,
     }
 }
 
@@ -244,7 +254,6 @@ fn add_well_residual_terms(
     flow_resv_context: Option<FlowResvReportStepContext>,
     residual: &mut DVector<f64>,
 ) {
-    let injected_fluid = effective_injected_fluid(sim);
     let mut well_perf_inputs: Vec<Vec<WellPerforationInputGeneric<f64>>> =
         (0..topology.wells.len()).map(|_| Vec::new()).collect();
 
@@ -259,8 +268,12 @@ fn add_well_residual_terms(
             residual[state.perforation_equation_offset(perf_idx)] += terms.perforation;
             continue;
         }
+        if perforation.segment_index.is_some() {
+            continue;
+        }
         let well_idx = perforation.physical_well_index;
         let injector = topology.wells[well_idx].injector;
+        let injected_fluid = physical_well_injected_fluid(sim, topology, well_idx);
         let cell = well_cell_input(sim, state, perforation.cell_index);
         let bhp = state.well_bhp[well_idx];
         let q = state
@@ -275,7 +288,12 @@ fn add_well_residual_terms(
             .collect();
         let fractions = (!injector).then(|| producer_fractions_generic::<f64>(sim, &neighborhood));
 
-        well_perf_inputs[well_idx].push(WellPerforationInputGeneric { cell, fractions, q });
+        well_perf_inputs[well_idx].push(WellPerforationInputGeneric {
+            cell,
+            fractions,
+            backflow: None,
+            q,
+        });
 
         let coefficients = component_rate_coefficients_generic(
             sim,
@@ -293,7 +311,8 @@ fn add_well_residual_terms(
             let connection = connection_rate_generic::<f64>(
                 sim,
                 wi_geom,
-                perforation_head_offset_bar(sim, perforation),
+                perforation_non_darcy_coefficient(sim, perforation),
+                perforation_head_offset_bar(sim, state, topology, perf_idx),
                 injector,
                 &cell,
                 bhp,
@@ -311,7 +330,14 @@ fn add_well_residual_terms(
             residual[state.well_equation_offset(well_idx)] += terms.control;
             continue;
         }
+        if !topology.wells[well_idx].segment_indices.is_empty() {
+            for term in multi_segment_well_terms(sim, state, topology, well_idx, dt_days) {
+                residual[term.row] += term.value;
+            }
+            continue;
+        }
         let injector = topology.wells[well_idx].injector;
+        let injected_fluid = physical_well_injected_fluid(sim, topology, well_idx);
         let control = well_control_generic(&physical_well_control(sim, topology, well_idx));
         let bhp = state.well_bhp[well_idx];
         if let Some(value) = well_constraint_residual_fb_generic(
@@ -338,7 +364,6 @@ fn add_well_jacobian_terms(
     flow_resv_context: Option<FlowResvReportStepContext>,
     tri: &mut TriMatI<f64, usize>,
 ) {
-    let injected_fluid = effective_injected_fluid(sim);
     let mut well_perf_inputs: Vec<Vec<WellPerforationInputGeneric<f64>>> =
         (0..topology.wells.len()).map(|_| Vec::new()).collect();
 
@@ -376,8 +401,12 @@ fn add_well_jacobian_terms(
             add_if_nonzero(tri, perf_row, primary_col, terms.perforation.deriv()[4]);
             continue;
         }
+        if perforation.segment_index.is_some() {
+            continue;
+        }
         let well_idx = perforation.physical_well_index;
         let injector = topology.wells[well_idx].injector;
+        let injected_fluid = physical_well_injected_fluid(sim, topology, well_idx);
         let cell = well_cell_input(sim, state, perforation.cell_index);
         let bhp = state.well_bhp[well_idx];
         let q = state
@@ -398,7 +427,12 @@ fn add_well_jacobian_terms(
             (!injector).then_some((neighborhood.as_slice(), connected_index));
         let fractions = (!injector).then(|| producer_fractions_generic::<f64>(sim, &neighborhood));
 
-        well_perf_inputs[well_idx].push(WellPerforationInputGeneric { cell, fractions, q });
+        well_perf_inputs[well_idx].push(WellPerforationInputGeneric {
+            cell,
+            fractions,
+            backflow: None,
+            q,
+        });
 
         let perf_row = state.perforation_equation_offset(perf_idx);
         let q_col = state.perforation_rate_unknown_offset(perf_idx);
@@ -408,7 +442,8 @@ fn add_well_jacobian_terms(
             let ([dp, dsw, dhc], dbhp) = rate_consistency_cell_bhp_jacobian(
                 sim,
                 wi_geom,
-                perforation_head_offset_bar(sim, perforation),
+                perforation_non_darcy_coefficient(sim, perforation),
+                perforation_head_offset_bar(sim, state, topology, perf_idx),
                 injector,
                 &cell,
                 bhp,
@@ -480,13 +515,37 @@ fn add_well_jacobian_terms(
             add_if_nonzero(tri, row, primary_col, context.reference.bg_rm3_per_sm3);
             continue;
         }
+        if !topology.wells[well_idx].segment_indices.is_empty() {
+            for term in multi_segment_well_terms(sim, state, topology, well_idx, dt_days) {
+                for (column, value) in term.columns {
+                    add_if_nonzero(tri, term.row, column, value);
+                }
+            }
+            continue;
+        }
         let injector = topology.wells[well_idx].injector;
+        let injected_fluid = physical_well_injected_fluid(sim, topology, well_idx);
         let control_real = physical_well_control(sim, topology, well_idx);
         let control = well_control_generic(&control_real);
         let bhp = state.well_bhp[well_idx];
         let row = state.well_equation_offset(well_idx);
         let bhp_col = state.well_bhp_unknown_offset(well_idx);
 
+        if let Some(thp) = control_real.thp.filter(|_| control_real.enabled) {
+            tri.add_triplet(row, bhp_col, 1.0);
+            if let Some(gradient) = thp_constraint_source_rate_gradient(
+                sim,
+                injector,
+                injected_fluid,
+                thp,
+                control_real.bhp_limit,
+                &well_perf_inputs[well_idx],
+            ) {
+                add_thp_constraint_rate_jacobian(sim, state, topology, well_idx, gradient, tri);
+            }
+            continue;
+        }
+
         if !control_real.enabled || !control_real.rate_controlled {
             tri.add_triplet(row, bhp_col, 1.0);
             continue;
@@ -530,7 +589,7 @@ fn add_well_jacobian_terms(
                 sim,
                 injector,
                 injected_fluid,
-                control_real.uses_surface_target,
+                control_real.surface_target(),
                 &cell,
                 producer_neighborhood,
                 q,
@@ -560,8 +619,14 @@ fn add_well_jacobian_terms(
                     if n_idx == connected_index {
                         continue;
                     }
-                    let cross =
-                        well_constraint_neighbor_rate_jacobian(sim, &cell, &neighborhood, n_idx, q);
+                    let cross = well_constraint_neighbor_rate_jacobian(
+                        sim,
+                        control_real.surface_phase,
+                        &cell,
+                        &neighborhood,
+                        n_idx,
+                        q,
+                    );
                     for v in 0..3 {
                         add_if_nonzero(
                             tri,
@@ -576,6 +641,85 @@ fn add_well_jacobian_terms(
     }
 }
 
+/// Cell and `q` columns of a THP-controlled well's constraint row: the row's
+/// gradient w.r.t. the well's summed source-term rates, chained through each
+/// perforation's mass-balance own and neighbour blocks.
+fn add_thp_constraint_rate_jacobian(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    topology: &FimWellTopology,
+    well_idx: usize,
+    gradient: [f64; 3],
+    tri: &mut TriMatI<f64, usize>,
+) {
+    let injector = topology.wells[well_idx].injector;
+    let injected_fluid = physical_well_injected_fluid(sim, topology, well_idx);
+    let row = state.well_equation_offset(well_idx);
+    let chain = |column: usize, block: &[[f64; 4]; 3]| -> f64 {
+        (0..3)
+            .map(|phase| gradient[phase] * block[phase][column])
+            .sum()
+    };
+
+    for &perf_idx in &topology.wells[well_idx].perforation_indices {
+        let perforation = &topology.perforations[perf_idx];
+        let cell = well_cell_input(sim, state, perforation.cell_index);
+        let q = state
+            .reservoir_connection_q(perf_idx)
+            .expect("historical assembly requires a reservoir-q primary");
+        let neighborhood_cells =
+            perforation_local_block(topology, state, perf_idx).control_influence_cells(sim);
+        let neighborhood: Vec<WellCellInput<f64>> = neighborhood_cells
+            .iter()
+            .map(|&c| well_cell_input(sim, state, c))
+            .collect();
+        let connected_index = neighborhood_cells
+            .iter()
+            .position(|&c| c == perforation.cell_index)
+            .unwrap_or(0);
+        let producer_neighborhood =
+            (!injector).then_some((neighborhood.as_slice(), connected_index));
+
+        let own = mass_balance_own_jacobian(
+            sim,
+            injector,
+            injected_fluid,
+            &cell,
+            producer_neighborhood,
+            q,
+        );
+        for v in 0..3 {
+            add_if_nonzero(
+                tri,
+                row,
+                unknown_offset(perforation.cell_index, v),
+                chain(v, &own),
+            );
+        }
+        add_if_nonzero(
+            tri,
+            row,
+            state.perforation_rate_unknown_offset(perf_idx),
+            chain(3, &own),
+        );
+
+        if !injector {
+            for (n_idx, &neighbor_cell_idx) in neighborhood_cells.iter().enumerate() {
+                if n_idx == connected_index {
+                    continue;
+                }
+                let cross = mass_balance_neighbor_jacobian(sim, &cell, &neighborhood, n_idx, q);
+                let chained: [f64; 3] = std::array::from_fn(|v| {
+                    (0..3).map(|phase| gradient[phase] * cross[phase][v]).sum()
+                });
+                for (v, value) in chained.into_iter().enumerate() {
+                    add_if_nonzero(tri, row, unknown_offset(neighbor_cell_idx, v), value);
+                }
+            }
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn add_face_residual(
     sim: &ReservoirSimulator,
@@ -583,17 +727,15 @@ fn add_face_residual(
     dt_days: f64,
     id_i: usize,
     id_j: usize,
-    dim: char,
-    k_i: usize,
-    k_j: usize,
+    transmissibility: f64,
     residual: &mut DVector<f64>,
 ) {
-    let geom_t = DARCY_METRIC_FACTOR * sim.geometric_transmissibility(id_i, id_j, dim);
+    let geom_t = DARCY_METRIC_FACTOR * transmissibility;
     if geom_t <= 0.0 {
         return;
     }
-    let i = face_cell_input(sim, state, id_i, k_i);
-    let j = face_cell_input(sim, state, id_j, k_j);
+    let i = face_cell_input(sim, state, id_i);
+    let j = face_cell_input(sim, state, id_j);
     let r = face_flux_residual_f64(sim, geom_t, dt_days, &i, &j);
     for component in 0..3 {
         residual[equation_offset(id_i, component)] += r[component];
@@ -601,6 +743,66 @@ fn add_face_residual(
     }
 }
 
+/// Residual contribution `[water, oil, gas]` of one boundary face to its cell's equations.
+fn boundary_residual(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    dt_days: f64,
+    connection: &BoundaryConnection,
+) -> [f64; 3] {
+    let cell = face_cell_input(sim, state, connection.cell);
+    match connection.drive {
+        BoundaryDrive::Pressure { pressure_bar } => {
+            let geom_t = DARCY_METRIC_FACTOR * connection.transmissibility;
+            let boundary = sim.boundary_face_input(connection, pressure_bar);
+            let r = face_flux_residual_f64(sim, geom_t, dt_days, &cell, &boundary);
+            [r[0], r[1], r[2]]
+        }
+        BoundaryDrive::Rate { rate_m3_day } => {
+            let boundary = sim.boundary_face_input(connection, cell.p);
+            boundary_rate_terms_generic(sim, rate_m3_day, &cell, &boundary)
+                .map(|flux| flux * dt_days)
+        }
+        BoundaryDrive::Aquifer { .. } => {
+            aquifer_residual_generic(sim, connection, cell.p, dt_days).map(|flux| flux * dt_days)
+        }
+    }
+}
+
+/// Component outflow rates [sc/day] of an aquifer face at cell pressure `p`: the aquifer
+/// only moves water, converted at the cell's pressure.
+fn aquifer_residual_generic<S: Scalar>(
+    sim: &ReservoirSimulator,
+    connection: &BoundaryConnection,
+    p: S,
+    dt_days: f64,
+) -> [S; 3] {
+    let influx_m3_day = sim.aquifer_face_influx(connection, p, dt_days);
+    [
+        -(influx_m3_day * sim.water_inverse_fvf_generic(p)),
+        S::from_f64(0.0),
+        S::from_f64(0.0),
+    ]
+}
+
+/// Net inflow `[water, oil, gas]` [sc/day] through every boundary face at `state` over a step
+/// of `dt_days`, the negated boundary residual terms, for the material balance.
+pub(crate) fn boundary_inflow_sc_day(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    dt_days: f64,
+) -> [f64; 3] {
+    let dt_days = dt_days.max(1e-12);
+    let mut inflow = [0.0; 3];
+    for connection in sim.boundary_connections() {
+        let residual = boundary_residual(sim, state, dt_days, &connection);
+        for component in 0..3 {
+            inflow[component] -= residual[component] / dt_days;
+        }
+    }
+    inflow
+}
+
 /// Observation-only decomposition of one live AD reservoir row into accumulation, signed face
 /// fluxes, and well source. Every term calls the same generic helper as
 /// [`assemble_fim_system_ad`], including the selected Flow-RESV source route, so its `total`
@@ -643,6 +845,8 @@ pub(crate) fn cell_equation_residual_breakdown_ad(
         y_plus: 0.0,
         z_minus: 0.0,
         z_plus: 0.0,
+        non_neighbor: 0.0,
+        boundary: 0.0,
         well_source: 0.0,
         total: 0.0,
     };
@@ -652,42 +856,67 @@ pub(crate) fn cell_equation_residual_breakdown_ad(
     let j = in_layer / sim.nx;
     let i = in_layer % sim.nx;
 
-    let face = |id_i: usize, id_j: usize, dim: char, k_i: usize, k_j: usize| {
+    let face = |id_i: usize, id_j: usize, dim: char| {
         let geom_t = DARCY_METRIC_FACTOR * sim.geometric_transmissibility(id_i, id_j, dim);
         if geom_t <= 0.0 {
             return [0.0; 6];
         }
-        let left = face_cell_input(sim, state, id_i, k_i);
-        let right = face_cell_input(sim, state, id_j, k_j);
+        let left = face_cell_input(sim, state, id_i);
+        let right = face_cell_input(sim, state, id_j);
         face_flux_residual_f64(sim, geom_t, dt_days, &left, &right)
     };
 
-    if i > 0 {
+    // Local-grid children connect only through non-neighbour faces.
+    let root = cell_idx < sim.root_cell_count();
+    if root && i > 0 {
         let neighbor = sim.idx(i - 1, j, k);
-        breakdown.x_minus = face(neighbor, cell_idx, 'x', k, k)[3 + component];
+        breakdown.x_minus = face(neighbor, cell_idx, 'x')[3 + component];
     }
-    if i + 1 < sim.nx {
+    if root && i + 1 < sim.nx {
         let neighbor = sim.idx(i + 1, j, k);
-        breakdown.x_plus = face(cell_idx, neighbor, 'x', k, k)[component];
+        breakdown.x_plus = face(cell_idx, neighbor, 'x')[component];
     }
-    if j > 0 {
+    if root && j > 0 {
         let neighbor = sim.idx(i, j - 1, k);
-        breakdown.y_minus = face(neighbor, cell_idx, 'y', k, k)[3 + component];
+        breakdown.y_minus = face(neighbor, cell_idx, 'y')[3 + component];
     }
-    if j + 1 < sim.ny {
+    if root && j + 1 < sim.ny {
         let neighbor = sim.idx(i, j + 1, k);
-        breakdown.y_plus = face(cell_idx, neighbor, 'y', k, k)[component];
+        breakdown.y_plus = face(cell_idx, neighbor, 'y')[component];
     }
-    if k > 0 {
+    if root && k > 0 {
         let neighbor = sim.idx(i, j, k - 1);
-        breakdown.z_minus = face(neighbor, cell_idx, 'z', k - 1, k)[3 + component];
+        breakdown.z_minus = face(neighbor, cell_idx, 'z')[3 + component];
     }
-    if k + 1 < sim.nz {
+    if root && k + 1 < sim.nz {
         let neighbor = sim.idx(i, j, k + 1);
-        breakdown.z_plus = face(cell_idx, neighbor, 'z', k, k + 1)[component];
+        breakdown.z_plus = face(cell_idx, neighbor, 'z')[component];
+    }
+    for nnc in sim.open_non_neighbor_connections() {
+        let side = if nnc.cell_a == cell_idx {
+            component
+        } else if nnc.cell_b == cell_idx {
+            3 + component
+        } else {
+            continue;
+        };
+        let left = face_cell_input(sim, state, nnc.cell_a);
+        let right = face_cell_input(sim, state, nnc.cell_b);
+        breakdown.non_neighbor += face_flux_residual_f64(
+            sim,
+            DARCY_METRIC_FACTOR * nnc.transmissibility,
+            dt_days,
+            &left,
+            &right,
+        )[side];
+    }
+
+    for connection in sim.boundary_connections() {
+        if connection.cell == cell_idx {
+            breakdown.boundary += boundary_residual(sim, state, dt_days, &connection)[component];
+        }
     }
 
-    let injected_fluid = effective_injected_fluid(sim);
     for (perf_idx, perforation) in topology.perforations.iter().enumerate() {
         if perforation.cell_index != cell_idx {
             continue;
@@ -702,7 +931,14 @@ pub(crate) fn cell_equation_residual_breakdown_ad(
             }
             continue;
         }
+        if perforation.segment_index.is_some() {
+            breakdown.well_source +=
+                perforation_component_rates_sc_day(sim, state, topology, perf_idx)[component]
+                    * dt_days;
+            continue;
+        }
         let injector = topology.wells[perforation.physical_well_index].injector;
+        let injected_fluid = effective_injected_fluid(sim, perforation);
         let cell_input = well_cell_input(sim, state, cell_idx);
         let neighborhood_cells =
             perforation_local_block(topology, state, perf_idx).control_influence_cells(sim);
@@ -729,6 +965,8 @@ pub(crate) fn cell_equation_residual_breakdown_ad(
         + breakdown.y_plus
         + breakdown.z_minus
         + breakdown.z_plus
+        + breakdown.non_neighbor
+        + breakdown.boundary
         + breakdown.well_source;
     Some(breakdown)
 }
@@ -759,17 +997,15 @@ fn add_face_jacobian(
     dt_days: f64,
     id_i: usize,
     id_j: usize,
-    dim: char,
-    k_i: usize,
-    k_j: usize,
+    transmissibility: f64,
     tri: &mut TriMatI<f64, usize>,
 ) {
-    let geom_t = DARCY_METRIC_FACTOR * sim.geometric_transmissibility(id_i, id_j, dim);
+    let geom_t = DARCY_METRIC_FACTOR * transmissibility;
     if geom_t <= 0.0 {
         return;
     }
-    let i = face_cell_input(sim, state, id_i, k_i);
-    let j = face_cell_input(sim, state, id_j, k_j);
+    let i = face_cell_input(sim, state, id_i);
+    let j = face_cell_input(sim, state, id_j);
     let (bii, bij, bji, bjj) = face_flux_jacobian_blocks(sim, geom_t, dt_days, &i, &j);
 
     scatter_block(tri, id_i, id_i, bii);
@@ -778,6 +1014,33 @@ fn add_face_jacobian(
     scatter_block(tri, id_j, id_j, bjj);
 }
 
+fn add_boundary_jacobian(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    dt_days: f64,
+    connection: &BoundaryConnection,
+    tri: &mut TriMatI<f64, usize>,
+) {
+    let cell = face_cell_input(sim, state, connection.cell);
+    let block = match connection.drive {
+        BoundaryDrive::Pressure { pressure_bar } => {
+            let geom_t = DARCY_METRIC_FACTOR * connection.transmissibility;
+            let boundary = sim.boundary_face_input(connection, pressure_bar);
+            face_flux_jacobian_blocks(sim, geom_t, dt_days, &cell, &boundary).0
+        }
+        BoundaryDrive::Rate { rate_m3_day } => {
+            let boundary = sim.boundary_face_input(connection, cell.p);
+            boundary_rate_jacobian_block(sim, rate_m3_day, dt_days, &cell, &boundary)
+        }
+        BoundaryDrive::Aquifer { .. } => {
+            let terms =
+                aquifer_residual_generic(sim, connection, Ad::<3>::variable(cell.p, 0), dt_days);
+            terms.map(|flux| [flux.d(0) * dt_days, 0.0, 0.0])
+        }
+    };
+    scatter_block(tri, connection.cell, connection.cell, block);
+}
+
 /// AD-based drop-in replacement for `assembly::assemble_fim_system`.
 pub(crate) fn assemble_fim_system_ad(
     sim: &ReservoirSimulator,
@@ -804,6 +1067,10 @@ pub(crate) fn assemble_fim_system_ad(
     let mut residual = DVector::zeros(n_unknowns);
 
     for cell_idx in 0..n_cells {
+        // Inactive cells have no faces or completions, so their rows stay zero.
+        if !sim.is_active(cell_idx) {
+            continue;
+        }
         let cell = state.cell(cell_idx);
         let prev_cell = previous_state.cell(cell_idx);
         let drsdt0 = cell_drsdt0_base_rs(sim, cell_idx);
@@ -825,50 +1092,24 @@ pub(crate) fn assemble_fim_system_ad(
         }
     }
 
-    for k in 0..sim.nz {
-        for j in 0..sim.ny {
-            for i in 0..sim.nx {
-                let id = sim.idx(i, j, k);
-                if i + 1 < sim.nx {
-                    add_face_residual(
-                        sim,
-                        state,
-                        options.dt_days,
-                        id,
-                        sim.idx(i + 1, j, k),
-                        'x',
-                        k,
-                        k,
-                        &mut residual,
-                    );
-                }
-                if j + 1 < sim.ny {
-                    add_face_residual(
-                        sim,
-                        state,
-                        options.dt_days,
-                        id,
-                        sim.idx(i, j + 1, k),
-                        'y',
-                        k,
-                        k,
-                        &mut residual,
-                    );
-                }
-                if k + 1 < sim.nz {
-                    add_face_residual(
-                        sim,
-                        state,
-                        options.dt_days,
-                        id,
-                        sim.idx(i, j, k + 1),
-                        'z',
-                        k,
-                        k + 1,
-                        &mut residual,
-                    );
-                }
-            }
+    let connections = sim.flow_connections();
+    for connection in &connections {
+        add_face_residual(
+            sim,
+            state,
+            options.dt_days,
+            connection.id_i,
+            connection.id_j,
+            connection.transmissibility,
+            &mut residual,
+        );
+    }
+
+    let boundary_connections = sim.boundary_connections();
+    for connection in &boundary_connections {
+        let r = boundary_residual(sim, state, options.dt_days, connection);
+        for (component, value) in r.into_iter().enumerate() {
+            residual[equation_offset(connection.cell, component)] += value;
         }
     }
 
@@ -896,6 +1137,17 @@ pub(crate) fn assemble_fim_system_ad(
     let mut tri = TriMatI::<f64, usize>::new((n_unknowns, n_unknowns));
 
     for cell_idx in 0..n_cells {
+        // An identity block pins an inactive cell's update to zero and keeps the full Jacobian
+        // nonsingular; the linear solve then drops these blocks from the numbering.
+        if !sim.is_active(cell_idx) {
+            scatter_block(
+                &mut tri,
+                cell_idx,
+                cell_idx,
+                [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            );
+            continue;
+        }
         let cell = state.cell(cell_idx);
         let prev_cell = previous_state.cell(cell_idx);
         let drsdt0 = cell_drsdt0_base_rs(sim, cell_idx);
@@ -915,51 +1167,20 @@ pub(crate) fn assemble_fim_system_ad(
         scatter_block(&mut tri, cell_idx, cell_idx, block);
     }
 
-    for k in 0..sim.nz {
-        for j in 0..sim.ny {
-            for i in 0..sim.nx {
-                let id = sim.idx(i, j, k);
-                if i + 1 < sim.nx {
-                    add_face_jacobian(
-                        sim,
-                        state,
-                        options.dt_days,
-                        id,
-                        sim.idx(i + 1, j, k),
-                        'x',
-                        k,
-                        k,
-                        &mut tri,
-                    );
-                }
-                if j + 1 < sim.ny {
-                    add_face_jacobian(
-                        sim,
-                        state,
-                        options.dt_days,
-                        id,
-                        sim.idx(i, j + 1, k),
-                        'y',
-                        k,
-                        k,
-                        &mut tri,
-                    );
-                }
-                if k + 1 < sim.nz {
-                    add_face_jacobian(
-                        sim,
-                        state,
-                        options.dt_days,
-                        id,
-                        sim.idx(i, j, k + 1),
-                        'z',
-                        k,
-                        k + 1,
-                        &mut tri,
-                    );
-                }
-            }
-        }
+    for connection in &connections {
+        add_face_jacobian(
+            sim,
+            state,
+            options.dt_days,
+            connection.id_i,
+            connection.id_j,
+            connection.transmissibility,
+            &mut tri,
+        );
+    }
+
+    for connection in &boundary_connections {
+        add_boundary_jacobian(sim, state, options.dt_days, connection, &mut tri);
     }
 
     if options.include_wells {
@@ -1486,9 +1707,77 @@ mod tests {
     #[test]
     fn jacobian_matches_numerical_of_real_residual_with_wells() {
         let (sim, previous_state, state) = reservoir_with_wells_fixture();
+        assert_jacobian_matches_real_residual(&sim, &previous_state, &state);
+    }
+
+    /// Non-Darcy connections on a gas injector and a producer: the closed-form rate-dependent
+    /// skin must carry exact cell and BHP derivatives into the rate-consistency rows.
+    #[test]
+    fn jacobian_matches_numerical_of_real_residual_with_non_darcy_wells() {
+        let (mut sim, previous_state, state) = reservoir_with_wells_fixture();
+        sim.set_injected_fluid("gas").unwrap();
         let options = with_wells_options();
+        let darcy = assemble_fim_system(&sim, &previous_state, &state, &options);
+        for well in sim.wells.iter_mut() {
+            well.d_factor = Some(1e-3);
+        }
+        let non_darcy = assemble_fim_system(&sim, &previous_state, &state, &options);
+        for perf_idx in 0..2 {
+            let row = state.perforation_equation_offset(perf_idx);
+            assert!(
+                (non_darcy.residual[row] - darcy.residual[row]).abs() > 1.0,
+                "perforation {perf_idx}: the D-factor should cut the connection rate, \
+                 Darcy residual {} non-Darcy {}",
+                darcy.residual[row],
+                non_darcy.residual[row]
+            );
+        }
+        assert_jacobian_matches_real_residual(&sim, &previous_state, &state);
+    }
 
-        let generic = assemble_fim_system_ad(&sim, &previous_state, &state, &options);
+    /// Fetkovich aquifer faces on two sides, with gravity: the influx is implicit in the cell
+    /// pressure, so the water rows must carry its derivative.
+    #[test]
+    fn jacobian_matches_numerical_of_real_residual_with_aquifer_faces() {
+        let (mut sim, previous_state, state) = reservoir_with_wells_fixture();
+        let options = with_wells_options();
+        let closed = assemble_fim_system_ad(&sim, &previous_state, &state, &options);
+        sim.add_fetkovich_aquifer(
+            vec!["x-".to_string(), "y+".to_string()],
+            Vec::new(),
+            250.0,
+            20.0,
+            500.0,
+        )
+        .unwrap();
+        let supported = assemble_fim_system_ad(&sim, &previous_state, &state, &options);
+        let corner = equation_offset(sim.idx(0, 0, 0), 0);
+        assert!(
+            (supported.residual[corner] - closed.residual[corner]).abs() > 1e-3,
+            "the aquifer should feed water into the x- face cells"
+        );
+        assert_jacobian_matches_residual_of(&sim, &previous_state, &state, assemble_fim_system_ad);
+    }
+
+    fn assert_jacobian_matches_real_residual(
+        sim: &ReservoirSimulator,
+        previous_state: &FimState,
+        state: &FimState,
+    ) {
+        assert_jacobian_matches_residual_of(sim, previous_state, state, assemble_fim_system);
+    }
+
+    /// Checks the AD Jacobian against central differences of the residual `assemble` builds.
+    /// The legacy assembler has no boundary faces, so those are differenced through the AD one.
+    fn assert_jacobian_matches_residual_of(
+        sim: &ReservoirSimulator,
+        previous_state: &FimState,
+        state: &FimState,
+        assemble: fn(&ReservoirSimulator, &FimState, &FimState, &FimAssemblyOptions) -> FimAssembly,
+    ) {
+        let options = with_wells_options();
+
+        let generic = assemble_fim_system_ad(sim, previous_state, state, &options);
         let n = generic.residual.len();
 
         let mut analytic = vec![vec![0.0; n]; n];
@@ -1510,6 +1799,15 @@ mod tests {
                 .iter()
                 .map(|primary| primary.value),
         );
+        let segment_start = x0.len();
+        x0.extend(state.segments.iter().flat_map(|segment| {
+            [
+                segment.pressure_bar,
+                segment.flow_m3_day,
+                segment.water_fraction,
+                segment.gas_fraction,
+            ]
+        }));
 
         let residual = |x: &[f64]| {
             let mut perturbed = state.clone();
@@ -1524,7 +1822,14 @@ mod tests {
             for (idx, primary) in perturbed.perforation_primaries.iter_mut().enumerate() {
                 primary.value = x[3 * n_cells + n_wells + idx];
             }
-            assemble_fim_system(&sim, &previous_state, &perturbed, &options)
+            for (idx, segment) in perturbed.segments.iter_mut().enumerate() {
+                let block = &x[segment_start + 4 * idx..segment_start + 4 * idx + 4];
+                segment.pressure_bar = block[0];
+                segment.flow_m3_day = block[1];
+                segment.water_fraction = block[2];
+                segment.gas_fraction = block[3];
+            }
+            assemble(sim, previous_state, &perturbed, &options)
                 .residual
                 .iter()
                 .copied()
@@ -1534,6 +1839,115 @@ mod tests {
 
         assert_jacobian_matches(&analytic, &numerical, 1e-5, 1e-6);
     }
+
+    /// A three-completion horizontal producer in a saturated three-phase reservoir, with every
+    /// node carrying a live mixture.
+    fn multi_segment_producer_states() -> (ReservoirSimulator, FimState, FimState) {
+        let (fixture, _, _) = reservoir_with_wells_fixture();
+        let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+        sim.set_three_phase_mode_enabled(true);
+        sim.pvt_table = fixture.pvt_table;
+        sim.set_three_phase_rel_perm_props(
+            0.1, 0.1, 0.05, 0.05, 0.15, 2.0, 2.0, 2.0, 1.0, 1.0, 1.0,
+        )
+        .unwrap();
+        sim.set_cell_dimensions(100.0, 20.0, 10.0).unwrap();
+        sim.set_permeability_random(200.0, 200.0).unwrap();
+        for i in 0..3 {
+            sim.add_directional_well(
+                i,
+                0,
+                0,
+                "x".to_string(),
+                200.0,
+                0.1,
+                0.0,
+                false,
+                "P".to_string(),
+            )
+            .unwrap();
+        }
+        sim.set_multi_segment_well("P".to_string(), 0.05, 1e-5, Vec::new())
+            .unwrap();
+
+        let previous_state = FimState::from_simulator(&sim);
+        let mut state = previous_state.clone();
+        for (idx, cell) in state.cells.iter_mut().enumerate() {
+            cell.pressure_bar = 180.0 + 5.0 * idx as f64;
+            cell.sw = 0.3;
+            cell.hydrocarbon_var = 0.1;
+            cell.regime = HydrocarbonState::Saturated;
+        }
+        state.well_bhp[0] = 150.0;
+        for (idx, primary) in state.perforation_primaries.iter_mut().enumerate() {
+            primary.value = 300.0 + 100.0 * idx as f64;
+        }
+        for (idx, segment) in state.segments.iter_mut().enumerate() {
+            segment.pressure_bar = 150.5 + 0.5 * idx as f64;
+            segment.flow_m3_day = 900.0 - 300.0 * idx as f64;
+            segment.water_fraction = 0.3;
+            segment.gas_fraction = 0.1;
+        }
+        (sim, previous_state, state)
+    }
+
+    /// A multi-segment producer carries node pressure, flow and mixture unknowns. Every row the
+    /// well touches — including the cell columns of its perforation rows — is checked against
+    /// central differences of both the AD and the legacy residual, in the turbulent range.
+    #[test]
+    fn jacobian_matches_numerical_of_real_residual_with_multi_segment_well() {
+        let (sim, previous_state, state) = multi_segment_producer_states();
+        assert_eq!(state.segments.len(), 3);
+
+        let assembly = assemble_fim_system_ad(&sim, &previous_state, &state, &with_wells_options());
+        let toe_row = state.perforation_equation_offset(2);
+        assert!(
+            assembly
+                .jacobian
+                .get(toe_row, unknown_offset(2, 0))
+                .is_some_and(|value| value.abs() > 1e-6),
+            "the toe completion row must depend on its cell pressure"
+        );
+        assert!(
+            assembly
+                .jacobian
+                .get(toe_row, state.segment_unknown_offset(2, 0))
+                .is_some_and(|value| value.abs() > 1e-6),
+            "the toe completion row must depend on its node pressure"
+        );
+        assert!(
+            assembly
+                .jacobian
+                .get(toe_row, state.well_bhp_unknown_offset(0))
+                .is_none_or(|value| *value == 0.0),
+            "a segmented completion sees its node, not the wellhead BHP"
+        );
+
+        assert_jacobian_matches_residual_of(&sim, &previous_state, &state, assemble_fim_system_ad);
+        assert_jacobian_matches_real_residual(&sim, &previous_state, &state);
+    }
+
+    /// A completion whose cell sits below its node pressure takes wellbore mixture back into
+    /// the reservoir; the signed rate and its node-mixture columns must stay exact.
+    #[test]
+    fn jacobian_matches_numerical_of_real_residual_with_multi_segment_crossflow() {
+        let (sim, previous_state, mut state) = multi_segment_producer_states();
+        state.cells[1].pressure_bar = 140.0;
+        state.perforation_primaries[1].value = -150.0;
+
+        let connection_q =
+            crate::fim::wells::perforation_local_block(&build_well_topology(&sim), &state, 1)
+                .residual_diagnostics(&sim)
+                .expect("producer perforation has a connection")
+                .q_connection_m3_day;
+        assert!(
+            connection_q < 0.0,
+            "the middle completion must cross-flow into its cell, got {connection_q}"
+        );
+
+        assert_jacobian_matches_residual_of(&sim, &previous_state, &state, assemble_fim_system_ad);
+        assert_jacobian_matches_real_residual(&sim, &previous_state, &state);
+    }
 }
 
 #[cfg(test)]
@@ -373,6 +373,7 @@ fn assembly_scaffold_matches_state_size() {
         ],
         well_bhp: Vec::new(),
         perforation_primaries: Vec::new(),
+        segments: Vec::new(),
     };
 
     let assembly = assemble_fim_system(
@@ -506,6 +507,69 @@ fn residual_only_two_cell_flux_is_component_conservative_for_oil_water() {
     assert!(oil_sum.abs() < 1e-12);
 }
 
+#[test]
+fn residual_breakdown_includes_non_neighbor_connection_flux() {
+    // Cells 0 and 2 of a row are joined only through a non-neighbour connection.
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+    sim.set_non_neighbor_connections(vec![0], vec![2], vec![50.0])
+        .unwrap();
+    let previous_state = FimState::from_simulator(&sim);
+    let mut state = previous_state.clone();
+    state.cells[0].pressure_bar = 250.0;
+    state.cells[1].pressure_bar = 200.0;
+    state.cells[2].pressure_bar = 150.0;
+    state.cells[0].sw = 0.30;
+    state.cells[2].sw = 0.55;
+
+    let topology = build_well_topology(&sim);
+    for component in 0..2 {
+        let near = cell_equation_residual_breakdown(
+            &sim,
+            &previous_state,
+            &state,
+            &topology,
+            1.0,
+            0,
+            component,
+        )
+        .unwrap();
+        let far = cell_equation_residual_breakdown(
+            &sim,
+            &previous_state,
+            &state,
+            &topology,
+            1.0,
+            2,
+            component,
+        )
+        .unwrap();
+        assert!(near.non_neighbor.abs() > 1e-9, "component {component}");
+        assert!((near.non_neighbor + far.non_neighbor).abs() < 1e-12);
+
+        for (cell_idx, legacy) in [(0, near), (2, far)] {
+            let ad = crate::fim::assembly_ad::cell_equation_residual_breakdown_ad(
+                &sim,
+                &previous_state,
+                &state,
+                &topology,
+                1.0,
+                None,
+                cell_idx,
+                component,
+            )
+            .unwrap();
+            assert!(
+                (legacy.non_neighbor - ad.non_neighbor).abs() < 1e-9,
+                "cell {cell_idx} component {component}"
+            );
+            assert!(
+                (legacy.total - ad.total).abs() < 1e-9,
+                "cell {cell_idx} component {component}"
+            );
+        }
+    }
+}
+
 #[test]
 fn residual_only_two_cell_flux_is_component_conservative_for_three_phase_gas() {
     let mut sim = ReservoirSimulator::new(2, 1, 1, 0.2);
@@ -706,8 +770,8 @@ fn direct_transmissibility_formula_matches_homogeneous_two_cell_oil_flux() {
         .expect("cell 0 should have an x+ face")
         .oil;
 
-    let area = sim.dy * sim.dz_at(0);
-    let expected_t = sim.perm_x[0] * area / sim.dx;
+    let area = sim.dy_at(0) * sim.dz_at(0);
+    let expected_t = sim.perm_x[0] * area / sim.dx_at(0);
     let expected_flux = DARCY_METRIC_FACTOR
         * expected_t
         * (state.cells[0].pressure_bar - state.cells[1].pressure_bar)
@@ -750,9 +814,9 @@ fn direct_transmissibility_formula_matches_heterogeneous_two_cell_oil_flux() {
         .expect("cell 0 should have an x+ face")
         .oil;
 
-    let area = sim.dy * sim.dz_at(0);
+    let area = sim.dy_at(0) * sim.dz_at(0);
     let harmonic_k = 2.0 * sim.perm_x[0] * sim.perm_x[1] / (sim.perm_x[0] + sim.perm_x[1]);
-    let expected_t = harmonic_k * area / sim.dx;
+    let expected_t = harmonic_k * area / sim.dx_at(0);
     let expected_flux = DARCY_METRIC_FACTOR
         * expected_t
         * (state.cells[0].pressure_bar - state.cells[1].pressure_bar)
@@ -130,7 +130,7 @@ pub(crate) fn begin_flow_resv_report_step_context(
     // G4b3 supplies a route-aware `(bhp,u)` inner solve. The flag is accepted here and carried
     // by Newton state update; historical wells still use their q-coordinate local systems.
     let _ = nested_well_solve;
-    if !sim.three_phase_mode || sim.injected_fluid != InjectedFluid::Gas {
+    if !sim.three_phase_mode {
         return Err("requires a three-phase gas injector".to_string());
     }
     if sim.pvt_table.is_none() {
@@ -157,12 +157,18 @@ pub(crate) fn begin_flow_resv_report_step_context(
     if !well.injector || !well.schedule.enabled {
         return Err("requires one enabled gas injector".to_string());
     }
+    if sim.well_injected_fluid(well) != InjectedFluid::Gas {
+        return Err("requires a three-phase gas injector".to_string());
+    }
     if physical.perforation_indices.len() != 1 {
         return Err(format!(
             "requires one open perforation, found {}",
             physical.perforation_indices.len()
         ));
     }
+    if topology.segment_count() > 0 {
+        return Err("multi-segment wells are outside G4b0".to_string());
+    }
     if well.schedule.target_surface_rate_m3_day.is_some() {
         return Err("RESV probe cannot also specify a surface-rate target".to_string());
     }
@@ -202,7 +208,7 @@ fn capture_reference(
     sim: &ReservoirSimulator,
     state: &FimState,
 ) -> Result<FlowResvReference, String> {
-    if state.cells.len() != sim.nx * sim.ny * sim.nz {
+    if state.cells.len() != sim.cell_count() {
         return Err("state/grid cell count mismatch".to_string());
     }
 
@@ -300,6 +306,9 @@ mod tests {
             target_surface_rate_m3_day: None,
             bhp_limit: None,
             enabled: true,
+            injected_fluid: None,
+            thp_target_bar: None,
+            vfp_table: None,
         };
         sim.set_fim_flow_resv_injector(true);
         sim
@@ -211,6 +211,72 @@ pub(crate) fn face_flux_residual_f64(
     ]
 }
 
+/// Component outflow rates [sc/day] of a boundary face with a prescribed reservoir rate
+/// `rate_m3_day` (positive into `cell`), generic over `S`. Inflow takes the phase mix that
+/// `boundary`'s saturations would flow, outflow the cell's own; both convert at the cell's
+/// pressure. Same sign convention as [`face_flux_terms_generic`] with the boundary as cell `j`.
+pub(crate) fn boundary_rate_terms_generic<S: Scalar>(
+    sim: &ReservoirSimulator,
+    rate_m3_day: f64,
+    cell: &FaceCellInput<S>,
+    boundary: &FaceCellInput<f64>,
+) -> [S; 3] {
+    let (sw, hydrocarbon_var, regime, drsdt0_base_rs) = if rate_m3_day >= 0.0 {
+        (
+            S::from_f64(boundary.sw),
+            S::from_f64(boundary.hydrocarbon_var),
+            boundary.regime,
+            boundary.drsdt0_base_rs,
+        )
+    } else {
+        (
+            cell.sw,
+            cell.hydrocarbon_var,
+            cell.regime,
+            cell.drsdt0_base_rs,
+        )
+    };
+    let props = cell_props_generic(sim, regime, cell.p, sw, hydrocarbon_var, drsdt0_base_rs);
+    let mob = sim.phase_mobilities_for_state_generic(sw, props.sg, cell.p, props.rs);
+    let mobility_t = mob.water + mob.oil + mob.gas;
+    if mobility_t.value() <= 0.0 {
+        return [S::from_f64(0.0); 3];
+    }
+
+    let q_per_mobility = -(S::from_f64(rate_m3_day) / mobility_t);
+    let q_w_sc_day = q_per_mobility * mob.water * sim.water_inverse_fvf_generic(cell.p);
+    let q_o_sc_day = q_per_mobility * mob.oil / props.bo.max_floor(1e-9);
+    let q_g_sc_day = q_per_mobility * mob.gas / props.bg.max_floor(1e-9) + q_o_sc_day * props.rs;
+    [q_w_sc_day, q_o_sc_day, q_g_sc_day]
+}
+
+/// Jacobian block `d(residual_cell[eq]) / d(unknown_cell[var])` of a rate boundary face, by
+/// seeding `Ad<3>` against the cell's `[p, sw, hc]`.
+pub(crate) fn boundary_rate_jacobian_block(
+    sim: &ReservoirSimulator,
+    rate_m3_day: f64,
+    dt_days: f64,
+    cell: &FaceCellInput<f64>,
+    boundary: &FaceCellInput<f64>,
+) -> [[f64; 3]; 3] {
+    let cell_ad = FaceCellInput {
+        p: Ad::<3>::variable(cell.p, 0),
+        sw: Ad::<3>::variable(cell.sw, 1),
+        hydrocarbon_var: Ad::<3>::variable(cell.hydrocarbon_var, 2),
+        regime: cell.regime,
+        depth: cell.depth,
+        drsdt0_base_rs: cell.drsdt0_base_rs,
+    };
+    let terms = boundary_rate_terms_generic(sim, rate_m3_day, &cell_ad, boundary);
+    let mut block = [[0.0; 3]; 3];
+    for (eq, flux) in terms.iter().enumerate() {
+        for (var, value) in block[eq].iter_mut().enumerate() {
+            *value = flux.d(var) * dt_days;
+        }
+    }
+    block
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -371,12 +437,13 @@ mod tests {
             ],
             well_bhp: Vec::new(),
             perforation_primaries: Vec::new(),
+            segments: Vec::new(),
         };
 
         let derived_0 = state.derive_cell(&sim, 0);
         let derived_1 = state.derive_cell(&sim, 1);
         let legacy =
-            assembly::interface_flux_terms(&sim, &state, 0, 1, 'x', 0, 0, &derived_0, &derived_1)
+            assembly::interface_flux_terms(&sim, &state, 0, 1, 'x', &derived_0, &derived_1)
                 .expect("nonzero transmissibility");
 
         let geom_t = DARCY_METRIC_FACTOR * sim.geometric_transmissibility(0, 1, 'x');
@@ -385,14 +452,14 @@ mod tests {
             0.3,
             0.1,
             HydrocarbonState::Saturated,
-            sim.depth_at_k(0),
+            sim.depth_at(0),
         );
         let j = input(
             150.0,
             0.25,
             0.08,
             HydrocarbonState::Saturated,
-            sim.depth_at_k(0),
+            sim.depth_at(0),
         );
         let generic = face_flux_terms_generic(&sim, geom_t, &i, &j);
 
@@ -451,6 +518,38 @@ mod tests {
         assert_jacobian_matches(&analytic, &numerical, 1e-6, 1e-9);
     }
 
+    /// AD-vs-numerical gate for a prescribed-rate boundary face, injecting the boundary's
+    /// phase mix and producing the cell's own.
+    #[test]
+    fn boundary_rate_gate_inflow_and_outflow() {
+        let sim = three_phase_sim(false, true);
+        let dt_days = 0.4_f64;
+        let boundary = input(0.0, 0.6, 0.2, HydrocarbonState::Saturated, 0.0);
+        let cell = input(160.0, 0.3, 0.1, HydrocarbonState::Saturated, 0.0);
+
+        for rate_m3_day in [12.0, -12.0] {
+            let block = boundary_rate_jacobian_block(&sim, rate_m3_day, dt_days, &cell, &boundary);
+            let analytic: Vec<Vec<f64>> = block.iter().map(|row| row.to_vec()).collect();
+            let x0 = [cell.p, cell.sw, cell.hydrocarbon_var];
+            let residual = |x: &[f64]| {
+                let cell = FaceCellInput {
+                    p: x[0],
+                    sw: x[1],
+                    hydrocarbon_var: x[2],
+                    ..cell
+                };
+                boundary_rate_terms_generic(&sim, rate_m3_day, &cell, &boundary)
+                    .map(|flux| flux * dt_days)
+                    .to_vec()
+            };
+            let numerical = central_difference_jacobian(&x0, 3, residual);
+            assert_jacobian_matches(&analytic, &numerical, 1e-6, 1e-9);
+
+            let water = boundary_rate_terms_generic(&sim, rate_m3_day, &cell, &boundary)[0];
+            assert_eq!(water < 0.0, rate_m3_day > 0.0);
+        }
+    }
+
     /// WATER-015 source-parity gate for Flow's TPFA linearizer contract.
     ///
     /// Flow seeds only the current ("interior") cell, adds that derivative as one
@@ -0,0 +1,143 @@
+//! Inactive-cell removal (Eclipse `ACTNUM`).
+//!
+//! The FIM state keeps one cell block per grid cell so that `state.cell(id)` and the
+//! `unknown_offset` numbering stay a plain function of the grid index. An inactive cell has no
+//! pore volume and no open faces, and assembly gives it a decoupled identity block with a zero
+//! residual. This module takes those blocks out of the linear system before it reaches a
+//! backend — the reduced system numbers only active cells, followed by the unchanged
+//! well-BHP/perforation tail — and scatters the reduced correction back with a zero update for
+//! every inactive cell.
+
+use nalgebra::DVector;
+use sprs::{CsMat, TriMatI};
+
+use super::{
+    FimLinearBlockLayout, FimLinearSolveOptions, FimLinearSolveReport, solve_linearized_system,
+};
+
+/// Solves the Newton system with every inactive cell block removed from the numbering.
+///
+/// Falls straight through to [`solve_linearized_system`] when every cell is active or there is
+/// no block layout to reduce, so a fully active grid takes exactly the historical path.
+pub(crate) fn solve_linearized_system_on_active_cells(
+    jacobian: &CsMat<f64>,
+    rhs: &DVector<f64>,
+    options: &FimLinearSolveOptions,
+    layout: Option<FimLinearBlockLayout>,
+    cell_active: &[bool],
+) -> FimLinearSolveReport {
+    let Some(layout) = layout else {
+        return solve_linearized_system(jacobian, rhs, options, layout, None);
+    };
+    let inactive_count = cell_active.iter().filter(|&&active| !active).count();
+    if inactive_count == 0 {
+        return solve_linearized_system(jacobian, rhs, options, Some(layout), None);
+    }
+
+    let block_size = layout.cell_block_size;
+    let full_rows = jacobian.rows();
+    let reduced_rows = full_rows - inactive_count * block_size;
+    let mut reduced_index = vec![None; full_rows];
+    let mut next = 0;
+    for (row, slot) in reduced_index.iter_mut().enumerate() {
+        let keep = row >= layout.cell_unknown_count() || cell_active[row / block_size];
+        if keep {
+            *slot = Some(next);
+            next += 1;
+        }
+    }
+
+    let mut tri = TriMatI::<f64, usize>::new((reduced_rows, reduced_rows));
+    let mut reduced_rhs = DVector::zeros(reduced_rows);
+    for (row, slot) in reduced_index.iter().enumerate() {
+        let Some(reduced_row) = *slot else {
+            continue;
+        };
+        reduced_rhs[reduced_row] = rhs[row];
+        if let Some(view) = jacobian.outer_view(row) {
+            for (col, &value) in view.indices().iter().zip(view.data().iter()) {
+                if let Some(reduced_col) = reduced_index[*col] {
+                    tri.add_triplet(reduced_row, reduced_col, value);
+                }
+            }
+        }
+    }
+
+    let removed = inactive_count * block_size;
+    let reduced_layout = FimLinearBlockLayout {
+        cell_block_count: layout.cell_block_count - inactive_count,
+        cell_block_size: block_size,
+        well_bhp_count: layout.well_bhp_count,
+        perforation_tail_start: layout.perforation_tail_start - removed,
+    };
+    let mut report = solve_linearized_system(
+        &tri.to_csr(),
+        &reduced_rhs,
+        options,
+        Some(reduced_layout),
+        None,
+    );
+
+    // Inactive rows carry a zero residual, so the reduced RHS and residual norms already equal
+    // the full-system ones; only the correction needs expanding.
+    let mut solution = DVector::zeros(full_rows);
+    for (row, slot) in reduced_index.iter().enumerate() {
+        if let Some(reduced_row) = *slot {
+            solution[row] = report.solution[reduced_row];
+        }
+    }
+    report.solution = solution;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fim::linear::FimLinearSolverKind;
+
+    #[test]
+    fn inactive_blocks_are_removed_and_return_zero_update() {
+        // Three one-unknown cells plus one well row; the middle cell is inactive and carries
+        // an identity row, exactly as assembly produces it.
+        let mut tri = TriMatI::<f64, usize>::new((4, 4));
+        for (row, col, value) in [
+            (0, 0, 4.0),
+            (0, 3, 1.0),
+            (1, 1, 1.0),
+            (2, 2, 5.0),
+            (2, 0, -1.0),
+            (3, 3, 2.0),
+            (3, 2, 1.0),
+        ] {
+            tri.add_triplet(row, col, value);
+        }
+        let jacobian = tri.to_csr();
+        let rhs = DVector::from_vec(vec![5.0, 0.0, 4.0, 3.0]);
+        let layout = FimLinearBlockLayout {
+            cell_block_count: 3,
+            cell_block_size: 1,
+            well_bhp_count: 1,
+            perforation_tail_start: 4,
+        };
+        let options = FimLinearSolveOptions {
+            kind: FimLinearSolverKind::SparseLuDebug,
+            ..FimLinearSolveOptions::default()
+        };
+
+        let full = solve_linearized_system(&jacobian, &rhs, &options, Some(layout), None);
+        let reduced = solve_linearized_system_on_active_cells(
+            &jacobian,
+            &rhs,
+            &options,
+            Some(layout),
+            &[true, false, true],
+        );
+
+        assert!(reduced.converged);
+        assert_eq!(reduced.solution.len(), 4);
+        assert_eq!(reduced.solution[1], 0.0);
+        for row in 0..4 {
+            assert!((reduced.solution[row] - full.solution[row]).abs() < 1e-10);
+        }
+    }
+}
@@ -252,6 +252,7 @@ fn try_write_capture(
             write_scale_row(&mut out, "gas_component", &scaling.gas_component)?;
             write_scale_row(&mut out, "well_constraint", &scaling.well_constraint)?;
             write_scale_row(&mut out, "perforation_flow", &scaling.perforation_flow)?;
+            write_scale_row(&mut out, "segment", &scaling.segment)?;
         }
         None => writeln!(out, "equation_scaling 0")?,
     }
@@ -453,6 +454,7 @@ fn parse_capture(content: &str) -> Result<FimCapturedSystem, String> {
             gas_component: parse_scale_row(lines.next(), "gas_component")?,
             well_constraint: parse_scale_row(lines.next(), "well_constraint")?,
             perforation_flow: parse_scale_row(lines.next(), "perforation_flow")?,
+            segment: parse_scale_row(lines.next(), "segment")?,
         }),
         other => return Err(format!("unexpected equation_scaling flag {other:?}")),
     };
@@ -701,6 +703,7 @@ mod tests {
             gas_component: vec![10.0],
             well_constraint: vec![],
             perforation_flow: vec![],
+            segment: Vec::new(),
         };
         let _ = fs::remove_dir_all(&dir);
         write_capture(
@@ -1,6 +1,7 @@
 use nalgebra::DVector;
 use sprs::CsMat;
 
+mod active_cells;
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) mod capture;
 mod dense_lu_debug;
@@ -12,6 +13,8 @@ mod solver_lab;
 mod sparse_lu_debug;
 mod well_schur;
 
+pub(crate) use active_cells::solve_linearized_system_on_active_cells;
+
 const DIRECT_SOLVE_ROW_THRESHOLD: usize = 512;
 const WASM_DIRECT_SOLVE_ROW_THRESHOLD: usize = DIRECT_SOLVE_ROW_THRESHOLD;
 
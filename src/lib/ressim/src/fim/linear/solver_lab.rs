@@ -1415,6 +1415,7 @@ fn worst_family_overshoot(
             "perforation_flow",
             ratio(current.perforation_flow, initial.perforation_flow),
         ),
+        ("segment", ratio(current.segment, initial.segment)),
     ];
     candidates
         .into_iter()
@@ -20,8 +20,8 @@
 //! [ J_WR  J_WW ] [dx_W] = [ r_W ]
 //! ```
 //!
-//! where `R` is the reservoir-cell rows and `W` is the well-BHP + perforation-rate tail
-//! (contiguous, `well_bhp_start..rows()`, confirmed block-diagonal per well by direct assembly
+//! where `R` is the reservoir-cell rows and `W` is the well-BHP + perforation-rate tail, plus
+//! any multi-segment wellbore node unknowns (contiguous, `well_bhp_start..rows()`, confirmed block-diagonal per well by direct assembly
 //! read — no well ever couples to another well's rows/columns), the reduced reservoir-only
 //! system is
 //!
@@ -184,7 +184,7 @@ pub(super) fn eliminate_wells(
     };
 
     // The reduced system has no well/perforation rows left, so any `EquationScaling` passed
-    // through must drop its `well_constraint`/`perforation_flow` vectors — otherwise
+    // through must drop its `well_constraint`/`perforation_flow`/`segment` vectors — otherwise
     // `family_peaks` indexes past the end of the (now shorter) residual vector. The cell-level
     // scaling (`water`/`oil_component`/`gas_component`) is unchanged, since the reduced system's
     // cell rows are identical to the original's.
@@ -194,6 +194,7 @@ pub(super) fn eliminate_wells(
         gas_component: scaling.gas_component.clone(),
         well_constraint: Vec::new(),
         perforation_flow: Vec::new(),
+        segment: Vec::new(),
     });
 
     Some(WellEliminationResult {
@@ -20,6 +20,7 @@ pub(crate) mod trace_sink;
 pub(crate) mod wells;
 pub(crate) mod wells_ad;
 pub(crate) mod wells_inner;
+pub(crate) mod wells_segments;
 
 #[cfg(test)]
 mod tests;
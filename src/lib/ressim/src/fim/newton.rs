@@ -21,7 +21,8 @@ use crate::fim::flow_resv::FlowResvReportStepContext;
 use crate::fim::flow_resv::flow_resv_injector_residual;
 use crate::fim::linear::{
     FimLinearBlockLayout, FimLinearFailureReason, FimLinearSolveOptions, FimLinearSolveReport,
-    FimLinearSolverKind, active_direct_solve_row_threshold, solve_linearized_system,
+    FimLinearSolverKind, active_direct_solve_row_threshold,
+    solve_linearized_system_on_active_cells,
 };
 use crate::fim::state::{FimState, HydrocarbonState};
 use crate::fim::wells::{build_well_topology, perforation_local_block, physical_well_control};
@@ -110,6 +111,12 @@ fn y2b2_state_checksum(state: &FimState) -> u64 {
     for primary in state.perforation_primaries() {
         add(primary.value.to_bits());
     }
+    for segment in &state.segments {
+        add(segment.pressure_bar.to_bits());
+        add(segment.flow_m3_day.to_bits());
+        add(segment.water_fraction.to_bits());
+        add(segment.gas_fraction.to_bits());
+    }
     hash
 }
 
@@ -205,6 +212,7 @@ pub(crate) enum FimHotspotSite {
     },
     Well(usize),
     Perforation(usize),
+    Segment(usize),
 }
 
 impl FimHotspotSite {
@@ -222,6 +230,7 @@ impl FimHotspotSite {
             ),
             Self::Well(well_idx) => format!("well{}", well_idx),
             Self::Perforation(perf_idx) => format!("perf{}", perf_idx),
+            Self::Segment(segment_idx) => format!("seg{}", segment_idx),
         }
     }
 }
@@ -1923,21 +1932,21 @@ pub(crate) fn run_fim_timestep(
             )
             .unwrap_or_else(|error| panic!("Y2d6d Flow lifecycle setup failed: {error}"))
         } else {
-            solve_linearized_system(
+            solve_linearized_system_on_active_cells(
                 &assembly.jacobian,
                 &rhs,
                 &linear_options,
                 block_layout,
-                None,
+                &sim.cell_active,
             )
         };
         #[cfg(target_arch = "wasm32")]
-        let mut linear_report = solve_linearized_system(
+        let mut linear_report = solve_linearized_system_on_active_cells(
             &assembly.jacobian,
             &rhs,
             &linear_options,
             block_layout,
-            None,
+            &sim.cell_active,
         );
         linear_solve_time_ms += linear_report.total_time_ms;
         linear_preconditioner_build_time_ms += linear_report.preconditioner_build_time_ms;
@@ -2125,12 +2134,12 @@ pub(crate) fn run_fim_timestep(
                 }
                 let mut fallback_options = options.linear;
                 fallback_options.kind = direct_fallback_kind_for_rows(assembly.jacobian.rows());
-                linear_report = solve_linearized_system(
+                linear_report = solve_linearized_system_on_active_cells(
                     &assembly.jacobian,
                     &rhs,
                     &fallback_options,
                     block_layout,
-                    None,
+                    &sim.cell_active,
                 );
                 used_fallback = true;
                 linear_report.used_fallback = true;
@@ -2960,7 +2969,7 @@ pub(crate) fn run_fim_timestep(
                     0,
                 ) {
                     crate::fim::trace_sink::write_line(&format!(
-                        "WELLJAC-WATER iter={:>2} cell={} accum={:.6e} x-={:.6e} x+={:.6e} y-={:.6e} y+={:.6e} z-={:.6e} z+={:.6e} well={:.6e} total={:.6e}",
+                        "WELLJAC-WATER iter={:>2} cell={} accum={:.6e} x-={:.6e} x+={:.6e} y-={:.6e} y+={:.6e} z-={:.6e} z+={:.6e} nnc={:.6e} well={:.6e} total={:.6e}",
                         iteration,
                         cell_idx,
                         water_breakdown.accumulation,
@@ -2970,6 +2979,7 @@ pub(crate) fn run_fim_timestep(
                         water_breakdown.y_plus,
                         water_breakdown.z_minus,
                         water_breakdown.z_plus,
+                        water_breakdown.non_neighbor,
                         water_breakdown.well_source,
                         water_breakdown.total,
                     ));
@@ -2989,7 +2999,7 @@ pub(crate) fn run_fim_timestep(
                     {
                         let assembled = assembly.residual[equation_offset(cell_idx, component)];
                         crate::fim::trace_sink::write_line(&format!(
-                            "RESERVOIR-PARTITION iter={:>2} cell={} component={} accumulation={:.9e} x-={:.9e} x+={:.9e} y-={:.9e} y+={:.9e} z-={:.9e} z+={:.9e} well_source={:.9e} total={:.9e} assembled={:.9e} reconstruction_delta={:.9e}",
+                            "RESERVOIR-PARTITION iter={:>2} cell={} component={} accumulation={:.9e} x-={:.9e} x+={:.9e} y-={:.9e} y+={:.9e} z-={:.9e} z+={:.9e} nnc={:.9e} boundary={:.9e} well_source={:.9e} total={:.9e} assembled={:.9e} reconstruction_delta={:.9e}",
                             iteration,
                             cell_idx,
                             label,
@@ -3000,6 +3010,8 @@ pub(crate) fn run_fim_timestep(
                             partition.y_plus,
                             partition.z_minus,
                             partition.z_plus,
+                            partition.non_neighbor,
+                            partition.boundary,
                             partition.well_source,
                             partition.total,
                             assembled,
@@ -1,4 +1,5 @@
 use super::*;
+use crate::fim::state::SEGMENT_BLOCK_SIZE;
 
 pub(super) fn scaled_residual_inf_norm(
     residual: &DVector<f64>,
@@ -21,6 +22,10 @@ pub(super) fn scaled_residual_inf_norm(
     for i in 0..scaling.perforation_flow.len() {
         max_norm = max_norm.max(residual[offset + i].abs() / scaling.perforation_flow[i]);
     }
+    offset += scaling.perforation_flow.len();
+    for i in 0..scaling.segment.len() {
+        max_norm = max_norm.max(residual[offset + i].abs() / scaling.segment[i]);
+    }
 
     max_norm
 }
@@ -46,6 +51,10 @@ pub(super) fn scaled_update_inf_norm(
     for i in 0..scaling.perforation_rate.len() {
         max_norm = max_norm.max(update[offset + i].abs() / scaling.perforation_rate[i]);
     }
+    offset += scaling.perforation_rate.len();
+    for i in 0..scaling.segment.len() {
+        max_norm = max_norm.max(update[offset + i].abs() / scaling.segment[i]);
+    }
 
     max_norm
 }
@@ -57,6 +66,7 @@ pub(super) enum UpdateVariableFamily {
     HydrocarbonVariable,
     WellBhp,
     PerforationRate,
+    Segment,
 }
 
 impl UpdateVariableFamily {
@@ -67,6 +77,7 @@ impl UpdateVariableFamily {
             Self::HydrocarbonVariable => "hc",
             Self::WellBhp => "bhp",
             Self::PerforationRate => "perf-rate",
+            Self::Segment => "segment",
         }
     }
 }
@@ -153,6 +164,16 @@ pub(super) fn scaled_update_peak(
             i,
         );
     }
+    offset += scaling.perforation_rate.len();
+    for i in 0..scaling.segment.len() {
+        update_variable_peak(
+            &mut peak,
+            UpdateVariableFamily::Segment,
+            update[offset + i].abs() / scaling.segment[i],
+            offset + i,
+            i / SEGMENT_BLOCK_SIZE,
+        );
+    }
 
     peak.expect("update diagnostics require at least one unknown")
 }
@@ -218,6 +239,30 @@ pub(super) fn scaled_applied_update_peak(
             idx,
         );
     }
+    offset += state.perforation_primaries().len();
+    for (idx, (current, next)) in state
+        .segments
+        .iter()
+        .zip(candidate.segments.iter())
+        .enumerate()
+    {
+        let changes = [
+            next.pressure_bar - current.pressure_bar,
+            next.flow_m3_day - current.flow_m3_day,
+            next.water_fraction - current.water_fraction,
+            next.gas_fraction - current.gas_fraction,
+        ];
+        for (local, change) in changes.into_iter().enumerate() {
+            let row = idx * SEGMENT_BLOCK_SIZE + local;
+            update_variable_peak(
+                &mut peak,
+                UpdateVariableFamily::Segment,
+                change.abs() / scaling.segment[row],
+                offset + row,
+                idx,
+            );
+        }
+    }
 
     peak.expect("applied update diagnostics require at least one unknown")
 }
@@ -238,6 +283,7 @@ pub(crate) fn iterate_has_material_change(previous_state: &FimState, state: &Fim
     const RS_EPS: f64 = 1e-12;
     const WELL_BHP_EPS: f64 = 1e-12;
     const PERF_RATE_EPS: f64 = 1e-12;
+    const SEGMENT_EPS: f64 = 1e-12;
 
     previous_state
         .cells
@@ -262,6 +308,16 @@ pub(crate) fn iterate_has_material_change(previous_state: &FimState, state: &Fim
                 (current.value - previous.value).abs() > PERF_RATE_EPS
                     || current.kind != previous.kind
             })
+        || previous_state
+            .segments
+            .iter()
+            .zip(state.segments.iter())
+            .any(|(previous, current)| {
+                (current.pressure_bar - previous.pressure_bar).abs() > SEGMENT_EPS
+                    || (current.flow_m3_day - previous.flow_m3_day).abs() > SEGMENT_EPS
+                    || (current.water_fraction - previous.water_fraction).abs() > SEGMENT_EPS
+                    || (current.gas_fraction - previous.gas_fraction).abs() > SEGMENT_EPS
+            })
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -290,6 +346,7 @@ pub(super) enum ResidualRowFamily {
     GasComponent,
     WellConstraint,
     PerforationFlow,
+    Segment,
 }
 
 impl ResidualRowFamily {
@@ -300,6 +357,7 @@ impl ResidualRowFamily {
             Self::GasComponent => "gas",
             Self::WellConstraint => "well",
             Self::PerforationFlow => "perf",
+            Self::Segment => "segment",
         }
     }
 }
@@ -319,6 +377,7 @@ pub(super) struct ResidualFamilyDiagnostics {
     pub(super) gas_component: ResidualFamilyPeak,
     pub(super) well_constraint: Option<ResidualFamilyPeak>,
     pub(super) perforation_flow: Option<ResidualFamilyPeak>,
+    pub(super) segment: Option<ResidualFamilyPeak>,
     pub(super) global: ResidualFamilyPeak,
 }
 
@@ -483,14 +542,20 @@ pub(super) fn cnv_mb_from_parts(
 ) -> CnvMbDiagnostics {
     let n_cells = pore_volumes_m3.len();
 
+    // Zero-pore-volume (inactive) cells are outside the flow problem and carry no weight.
     let mut b_avg = [0.0_f64; 3];
-    for fvf in fvf_per_cell {
+    let mut weighted_cells = 0_usize;
+    for (fvf, &pv) in fvf_per_cell.iter().zip(pore_volumes_m3) {
+        if pv <= 0.0 {
+            continue;
+        }
+        weighted_cells += 1;
         for c in 0..3 {
             b_avg[c] += fvf[c];
         }
     }
     for avg in &mut b_avg {
-        *avg /= n_cells.max(1) as f64;
+        *avg /= weighted_cells.max(1) as f64;
     }
 
     let mut max_coeff = [0.0_f64; 3];
@@ -499,6 +564,9 @@ pub(super) fn cnv_mb_from_parts(
     let mut pv_sum = 0.0_f64;
     let mut violating_pv = 0.0_f64;
     for i in 0..n_cells {
+        if pore_volumes_m3[i] <= 0.0 {
+            continue;
+        }
         let pv = pore_volumes_m3[i].max(1e-9);
         pv_sum += pv;
         let mut cell_max_cnv = 0.0_f64;
@@ -741,6 +809,7 @@ pub(super) fn residual_family_diagnostics(
     let mut gas_component = None;
     let mut well_constraint = None;
     let mut perforation_flow = None;
+    let mut segment = None;
 
     for i in 0..n_cells {
         update_family_peak(
@@ -786,6 +855,16 @@ pub(super) fn residual_family_diagnostics(
             i,
         );
     }
+    offset += scaling.perforation_flow.len();
+    for i in 0..scaling.segment.len() {
+        update_family_peak(
+            &mut segment,
+            ResidualRowFamily::Segment,
+            residual[offset + i].abs() / scaling.segment[i],
+            offset + i,
+            i / SEGMENT_BLOCK_SIZE,
+        );
+    }
 
     let water = water.expect("residual diagnostics require at least one cell");
     let oil_component = oil_component.expect("residual diagnostics require at least one cell");
@@ -796,6 +875,7 @@ pub(super) fn residual_family_diagnostics(
         Some(gas_component),
         well_constraint,
         perforation_flow,
+        segment,
     ]
     .into_iter()
     .flatten()
@@ -811,6 +891,7 @@ pub(super) fn residual_family_diagnostics(
         gas_component,
         well_constraint,
         perforation_flow,
+        segment,
         global,
     }
 }
@@ -842,6 +923,12 @@ pub(super) fn residual_family_trace(diagnostics: &ResidualFamilyDiagnostics) ->
             peak.scaled_value, peak.item_index
         ));
     }
+    if let Some(peak) = diagnostics.segment {
+        parts.push(format!(
+            "segment={:.3e}@seg{}",
+            peak.scaled_value, peak.item_index
+        ));
+    }
     parts.push(format!(
         "top={} row={} item={}",
         diagnostics.global.family.label(),
@@ -917,12 +1004,7 @@ pub(super) fn cell_index_to_ijk(
     sim: &ReservoirSimulator,
     cell_idx: usize,
 ) -> (usize, usize, usize) {
-    let cells_per_layer = sim.nx * sim.ny;
-    let k = cell_idx / cells_per_layer;
-    let in_layer = cell_idx % cells_per_layer;
-    let j = in_layer / sim.nx;
-    let i = in_layer % sim.nx;
-    (i, j, k)
+    sim.root_cell_ijk(cell_idx)
 }
 
 pub(super) fn format_phase_flux_diagnostic(
@@ -1006,7 +1088,7 @@ pub(super) fn cell_residual_detail_trace(
     let face_diagnostics = cell_face_phase_flux_diagnostics(sim, state, dt_days, cell_idx)?;
 
     Some(format!(
-        "eq={} cell{}=({}, {}, {}) p={:.3} sw={:.4} so={:.4} sg={:.4} rs={:.4} regime={:?} accum={:.3e} x-={:.3e} x+={:.3e} y-={:.3e} y+={:.3e} z-={:.3e} z+={:.3e} well={:.3e} total={:.3e} faces={}",
+        "eq={} cell{}=({}, {}, {}) p={:.3} sw={:.4} so={:.4} sg={:.4} rs={:.4} regime={:?} accum={:.3e} x-={:.3e} x+={:.3e} y-={:.3e} y+={:.3e} z-={:.3e} z+={:.3e} nnc={:.3e} well={:.3e} total={:.3e} faces={}",
         equation,
         cell_idx,
         i,
@@ -1025,6 +1107,7 @@ pub(super) fn cell_residual_detail_trace(
         breakdown.y_plus,
         breakdown.z_minus,
         breakdown.z_plus,
+        breakdown.non_neighbor,
         breakdown.well_source,
         breakdown.total,
         format_cell_face_phase_diagnostics(sim, &face_diagnostics),
@@ -1155,6 +1238,19 @@ pub(super) fn residual_family_detail_trace(
             }
             Some(parts.join(" "))
         }
+        ResidualRowFamily::Segment => {
+            let segment_idx = diagnostics.global.item_index;
+            let segment = state.segments.get(segment_idx)?;
+            Some(format!(
+                "seg{} row={} p={:.3} Q={:.3e} aw={:.3} ag={:.3}",
+                segment_idx,
+                diagnostics.global.row - state.segment_equation_offset(segment_idx, 0),
+                segment.pressure_bar,
+                segment.flow_m3_day,
+                segment.water_fraction,
+                segment.gas_fraction,
+            ))
+        }
     }
 }
 
@@ -426,10 +426,7 @@ pub(super) struct NonlinearHistoryStabilizationDecision {
 }
 
 pub(super) fn cell_ijk(sim: &ReservoirSimulator, cell_idx: usize) -> (usize, usize, usize) {
-    let i = cell_idx % sim.nx;
-    let j = (cell_idx / sim.nx) % sim.ny;
-    let k = cell_idx / (sim.nx * sim.ny);
-    (i, j, k)
+    sim.root_cell_ijk(cell_idx)
 }
 
 pub(super) fn exact_residual_hotspot_site(peak: &ResidualFamilyPeak) -> FimHotspotSite {
@@ -439,6 +436,7 @@ pub(super) fn exact_residual_hotspot_site(peak: &ResidualFamilyPeak) -> FimHotsp
         | ResidualRowFamily::GasComponent => FimHotspotSite::Cell(peak.item_index),
         ResidualRowFamily::WellConstraint => FimHotspotSite::Well(peak.item_index),
         ResidualRowFamily::PerforationFlow => FimHotspotSite::Perforation(peak.item_index),
+        ResidualRowFamily::Segment => FimHotspotSite::Segment(peak.item_index),
     }
 }
 
@@ -646,7 +644,7 @@ pub(super) fn nonlinear_history_stabilization_trace(
 // system (`FIM-LINEAR-010`) showed the *identical* oscillation — proving it is not a linear-
 // system-structure artifact this detector should have been blind to, but a genuine nonlinear
 // residual oscillation OPM's own (family-agnostic) test is designed to catch. Widened to include
-// `well_constraint`/`perforation_flow`.
+// `well_constraint`/`perforation_flow`, and later to the multi-segment wellbore `segment` rows.
 
 const OSCILLATION_RELAX_REL_TOL: f64 = 0.2;
 const OSCILLATION_RELAX_INCREMENT: f64 = 0.1;
@@ -660,6 +658,7 @@ pub(super) struct PerFamilyNorms {
     pub(super) gas_component: f64,
     pub(super) well_constraint: f64,
     pub(super) perforation_flow: f64,
+    pub(super) segment: f64,
 }
 
 impl Default for PerFamilyNorms {
@@ -670,6 +669,7 @@ impl Default for PerFamilyNorms {
             gas_component: f64::INFINITY,
             well_constraint: f64::INFINITY,
             perforation_flow: f64::INFINITY,
+            segment: f64::INFINITY,
         }
     }
 }
@@ -686,6 +686,9 @@ impl PerFamilyNorms {
             perforation_flow: diagnostics
                 .perforation_flow
                 .map_or(f64::INFINITY, |peak| peak.scaled_value),
+            segment: diagnostics
+                .segment
+                .map_or(f64::INFINITY, |peak| peak.scaled_value),
         }
     }
 }
@@ -748,6 +751,7 @@ pub(super) fn detect_oscillation(
             prev1.perforation_flow,
             prev2.perforation_flow,
         ),
+        family_is_oscillating(current.segment, prev1.segment, prev2.segment),
     ]
     .into_iter()
     .filter(|&osc| osc)
@@ -4,6 +4,9 @@ pub(super) fn small_dt_hotspot_neighborhood_indices(
     sim: &ReservoirSimulator,
     center_idx: usize,
 ) -> Vec<usize> {
+    if center_idx >= sim.root_cell_count() {
+        return vec![center_idx];
+    }
     let center_i = center_idx % sim.nx;
     let center_j = (center_idx / sim.nx) % sim.ny;
     let center_k = center_idx / (sim.nx * sim.ny);
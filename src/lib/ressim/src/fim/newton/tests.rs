@@ -527,6 +527,7 @@ fn iterate_has_material_change_detects_well_and_perforation_updates() {
         perforation_primaries: vec![
             crate::fim::state::FimPerforationPrimary::reservoir_connection_q(-150.0),
         ],
+        segments: Vec::new(),
     };
 
     let mut bhp_changed = previous_state.clone();
@@ -676,6 +677,7 @@ fn accepted_state_convergence_rejects_guard_band_material_balance_violation() {
             cells: Vec::new(),
             well_bhp: Vec::new(),
             perforation_primaries: Vec::new(),
+            segments: Vec::new(),
         },
         residual_inf_norm: 1.5e-5,
         residual_diagnostics: ResidualFamilyDiagnostics {
@@ -705,6 +707,7 @@ fn accepted_state_convergence_rejects_guard_band_material_balance_violation() {
                 row: 0,
                 item_index: 0,
             },
+            segment: None,
         },
         residual_detail: None,
         material_balance_inf_norm: 1.5e-5,
@@ -737,6 +740,7 @@ fn scaled_update_peak_reports_dominant_family() {
         hydrocarbon_var: vec![1.0],
         well_bhp: vec![1000.0],
         perforation_rate: vec![1.0],
+        segment: Vec::new(),
     };
 
     let peak = scaled_update_peak(&update, &scaling);
@@ -760,6 +764,7 @@ fn scaled_applied_update_peak_reports_effective_family() {
         perforation_primaries: vec![
             crate::fim::state::FimPerforationPrimary::reservoir_connection_q(10.0),
         ],
+        segments: Vec::new(),
     };
 
     let candidate = FimState {
@@ -773,6 +778,7 @@ fn scaled_applied_update_peak_reports_effective_family() {
         perforation_primaries: vec![
             crate::fim::state::FimPerforationPrimary::reservoir_connection_q(10.2),
         ],
+        segments: Vec::new(),
     };
 
     let scaling = crate::fim::scaling::VariableScaling {
@@ -781,6 +787,7 @@ fn scaled_applied_update_peak_reports_effective_family() {
         hydrocarbon_var: vec![100.0],
         well_bhp: vec![1000.0],
         perforation_rate: vec![100.0],
+        segment: Vec::new(),
     };
 
     let peak = scaled_applied_update_peak(&state, &candidate, &scaling);
@@ -800,6 +807,7 @@ fn residual_family_diagnostics_reports_global_peak_family() {
         gas_component: vec![10.0, 10.0],
         well_constraint: vec![10.0, 5.0],
         perforation_flow: vec![2.0],
+        segment: Vec::new(),
     };
 
     let diagnostics = residual_family_diagnostics(&residual, &scaling);
@@ -843,6 +851,7 @@ fn global_material_balance_diagnostics_normalizes_component_sums() {
         gas_component: vec![10.0, 10.0],
         well_constraint: vec![5.0, 5.0],
         perforation_flow: vec![2.0],
+        segment: Vec::new(),
     };
 
     let diagnostics = global_material_balance_diagnostics(&residual, &scaling);
@@ -883,6 +892,7 @@ fn failure_classification_marks_clean_cpr_failure_as_nonlinear_bad() {
             row: 0,
             item_index: 0,
         },
+        segment: None,
     };
     let report = FimLinearSolveReport {
         solution: DVector::zeros(1),
@@ -942,6 +952,7 @@ fn failure_classification_marks_direct_backend_as_nonlinear_bad_when_clean() {
             row: 0,
             item_index: 0,
         },
+        segment: None,
     };
     let report = FimLinearSolveReport {
         solution: DVector::zeros(1),
@@ -992,6 +1003,7 @@ fn failure_classification_marks_weak_cpr_as_mixed() {
             row: 0,
             item_index: 0,
         },
+        segment: None,
     };
     let report = FimLinearSolveReport {
         solution: DVector::zeros(1),
@@ -1051,6 +1063,7 @@ fn failure_classification_marks_converged_fallback_path_as_nonlinear_bad() {
             row: 1,
             item_index: 0,
         },
+        segment: None,
     };
     let report = FimLinearSolveReport {
         solution: DVector::zeros(1),
@@ -1131,6 +1144,7 @@ fn repeated_zero_move_direct_bypass_groups_nearby_non_gas_cells_in_same_layer()
         well_constraint: None,
         perforation_flow: None,
         global: peak,
+        segment: None,
     };
 
     assert!(should_enable_repeated_zero_move_direct_bypass(
@@ -1161,6 +1175,7 @@ fn repeated_zero_move_direct_bypass_does_not_group_vertical_non_gas_shift() {
         well_constraint: None,
         perforation_flow: None,
         global: peak,
+        segment: None,
     };
 
     assert!(!should_enable_repeated_zero_move_direct_bypass(
@@ -1276,6 +1291,7 @@ fn failure_classification_keeps_nonconverged_fallback_path_linear_bad() {
             row: 1,
             item_index: 0,
         },
+        segment: None,
     };
     let report = FimLinearSolveReport {
         solution: DVector::zeros(1),
@@ -1317,6 +1333,7 @@ fn repeated_nonlinear_hotspot_streak_groups_phase_rows_by_cell_site() {
         well_constraint: None,
         perforation_flow: None,
         global: current_peak,
+        segment: None,
     };
 
     let streak = repeated_nonlinear_hotspot_streak(
@@ -1352,6 +1369,7 @@ fn repeated_nonlinear_hotspot_streak_resets_after_strong_progress() {
         well_constraint: None,
         perforation_flow: None,
         global: peak,
+        segment: None,
     };
 
     let streak = repeated_nonlinear_hotspot_streak(
@@ -1387,6 +1405,7 @@ fn repeated_nonlinear_hotspot_streak_relaxes_threshold_for_gas_hotspot_site() {
         well_constraint: None,
         perforation_flow: None,
         global: current_peak,
+        segment: None,
     };
 
     let streak = repeated_nonlinear_hotspot_streak(
@@ -1422,6 +1441,7 @@ fn repeated_nonlinear_hotspot_streak_keeps_stricter_threshold_for_non_gas_sites(
         well_constraint: None,
         perforation_flow: None,
         global: current_peak,
+        segment: None,
     };
 
     let streak = repeated_nonlinear_hotspot_streak(
@@ -1457,6 +1477,7 @@ fn repeated_nonlinear_hotspot_streak_groups_nearby_non_gas_cells_in_same_layer()
         well_constraint: None,
         perforation_flow: None,
         global: peak,
+        segment: None,
     };
 
     let streak = repeated_nonlinear_hotspot_streak(
@@ -1492,6 +1513,7 @@ fn repeated_nonlinear_hotspot_streak_does_not_group_vertical_non_gas_shift() {
         well_constraint: None,
         perforation_flow: None,
         global: peak,
+        segment: None,
     };
 
     let streak = repeated_nonlinear_hotspot_streak(
@@ -1541,6 +1563,7 @@ fn detect_oscillation_flags_single_phase_two_step_relative_change() {
         gas_component: 1.0,
         well_constraint: 1.0,
         perforation_flow: 1.0,
+        segment: f64::INFINITY,
     };
     let prev1 = PerFamilyNorms {
         water: 2.0,
@@ -1548,6 +1571,7 @@ fn detect_oscillation_flags_single_phase_two_step_relative_change() {
         gas_component: 1.0,
         well_constraint: 1.0,
         perforation_flow: 1.0,
+        segment: f64::INFINITY,
     };
     let prev2 = PerFamilyNorms {
         water: 1.01,
@@ -1555,6 +1579,7 @@ fn detect_oscillation_flags_single_phase_two_step_relative_change() {
         gas_component: 1.0,
         well_constraint: 1.0,
         perforation_flow: 1.0,
+        segment: f64::INFINITY,
     };
     assert_eq!(detect_oscillation(current, prev1, prev2), 1);
 }
@@ -1569,6 +1594,7 @@ fn detect_oscillation_flags_perforation_flow_two_step_relative_change() {
         gas_component: 1.0,
         perforation_flow: 2.137e-5,
         well_constraint: 1.0,
+        segment: f64::INFINITY,
     };
     let prev1 = PerFamilyNorms {
         water: 1.0,
@@ -1576,6 +1602,7 @@ fn detect_oscillation_flags_perforation_flow_two_step_relative_change() {
         gas_component: 1.0,
         perforation_flow: 3.419e-5,
         well_constraint: 1.0,
+        segment: f64::INFINITY,
     };
     let prev2 = PerFamilyNorms {
         water: 1.0,
@@ -1583,6 +1610,7 @@ fn detect_oscillation_flags_perforation_flow_two_step_relative_change() {
         gas_component: 1.0,
         perforation_flow: 2.137e-5,
         well_constraint: 1.0,
+        segment: f64::INFINITY,
     };
     assert_eq!(detect_oscillation(current, prev1, prev2), 1);
 }
@@ -1597,6 +1625,7 @@ fn detect_oscillation_ignores_missing_well_and_perforation_families() {
         gas_component: 1.0,
         well_constraint: f64::INFINITY,
         perforation_flow: f64::INFINITY,
+        segment: f64::INFINITY,
     };
     assert_eq!(detect_oscillation(missing, missing, missing), 0);
 }
@@ -1610,6 +1639,7 @@ fn detect_oscillation_requires_below_two_step_above_one_step_threshold() {
         gas_component: 1.0,
         well_constraint: 1.0,
         perforation_flow: 1.0,
+        segment: f64::INFINITY,
     };
     let prev1 = PerFamilyNorms {
         water: 2.0,
@@ -1617,6 +1647,7 @@ fn detect_oscillation_requires_below_two_step_above_one_step_threshold() {
         gas_component: 1.0,
         well_constraint: 1.0,
         perforation_flow: 1.0,
+        segment: f64::INFINITY,
     };
     let prev2 = PerFamilyNorms {
         water: 4.0,
@@ -1624,6 +1655,7 @@ fn detect_oscillation_requires_below_two_step_above_one_step_threshold() {
         gas_component: 1.0,
         well_constraint: 1.0,
         perforation_flow: 1.0,
+        segment: f64::INFINITY,
     };
     assert_eq!(detect_oscillation(current, prev1, prev2), 0);
 
@@ -1634,6 +1666,7 @@ fn detect_oscillation_requires_below_two_step_above_one_step_threshold() {
         gas_component: 1.0,
         well_constraint: 1.0,
         perforation_flow: 1.0,
+        segment: f64::INFINITY,
     };
     assert_eq!(detect_oscillation(steady, steady, steady), 0);
 }
@@ -1681,6 +1714,7 @@ fn nonlinear_history_stabilization_caps_damping_for_repeated_weak_progress() {
         well_constraint: None,
         perforation_flow: None,
         global: peak,
+        segment: None,
     };
     let report = FimLinearSolveReport {
         solution: DVector::zeros(1),
@@ -1747,6 +1781,7 @@ fn nonlinear_history_stabilization_allows_converged_fallback_path() {
         well_constraint: None,
         perforation_flow: None,
         global: peak,
+        segment: None,
     };
     let report = FimLinearSolveReport {
         solution: DVector::zeros(1),
@@ -1787,6 +1822,7 @@ fn appleyard_damping_limits_combined_oil_saturation_change() {
         }],
         well_bhp: Vec::new(),
         perforation_primaries: Vec::new(),
+        segments: Vec::new(),
     };
     let mut update = DVector::zeros(state.n_unknowns());
     update[1] = 0.15;
@@ -1823,6 +1859,7 @@ fn local_cell_move_deltas_tracks_pressure_and_phase_changes() {
         }],
         well_bhp: Vec::new(),
         perforation_primaries: Vec::new(),
+        segments: Vec::new(),
     };
     let candidate_state = FimState {
         cells: vec![crate::fim::state::FimCellState {
@@ -1833,6 +1870,7 @@ fn local_cell_move_deltas_tracks_pressure_and_phase_changes() {
         }],
         well_bhp: Vec::new(),
         perforation_primaries: Vec::new(),
+        segments: Vec::new(),
     };
 
     let (pressure_delta_bar, water_delta, oil_delta, gas_delta) =
@@ -1855,6 +1893,7 @@ fn candidate_update_bounds_include_oil_saturation_change() {
         }],
         well_bhp: Vec::new(),
         perforation_primaries: Vec::new(),
+        segments: Vec::new(),
     };
     let candidate_state = FimState {
         cells: vec![crate::fim::state::FimCellState {
@@ -1865,6 +1904,7 @@ fn candidate_update_bounds_include_oil_saturation_change() {
         }],
         well_bhp: Vec::new(),
         perforation_primaries: Vec::new(),
+        segments: Vec::new(),
     };
 
     let (max_pressure_change, max_saturation_change) =
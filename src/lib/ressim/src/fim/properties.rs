@@ -307,6 +307,7 @@ mod tests {
                 }],
                 well_bhp: Vec::new(),
                 perforation_primaries: Vec::new(),
+                segments: Vec::new(),
             };
             let derived = state.derive_cell(&sim, 0);
             let drsdt0 = if !sim.gas_redissolution_enabled {
@@ -497,6 +498,7 @@ mod tests {
             }],
             well_bhp: Vec::new(),
             perforation_primaries: Vec::new(),
+            segments: Vec::new(),
         };
         let state = FimState {
             cells: vec![FimCellState {
@@ -507,6 +509,7 @@ mod tests {
             }],
             well_bhp: Vec::new(),
             perforation_primaries: Vec::new(),
+            segments: Vec::new(),
         };
         let topology = build_well_topology(&sim);
         let dt_days = 0.5;
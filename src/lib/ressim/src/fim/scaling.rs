@@ -2,7 +2,7 @@ use nalgebra::DVector;
 
 use crate::ReservoirSimulator;
 use crate::fim::flow_resv::FlowResvReportStepContext;
-use crate::fim::state::{FimState, HydrocarbonState};
+use crate::fim::state::{FimSegmentState, FimState, HydrocarbonState, SEGMENT_BLOCK_SIZE};
 use crate::fim::wells::FimWellTopology;
 use crate::fim::wells::{physical_well_control, well_local_block};
 
@@ -13,6 +13,8 @@ pub(crate) struct EquationScaling {
     pub(crate) gas_component: Vec<f64>,
     pub(crate) well_constraint: Vec<f64>,
     pub(crate) perforation_flow: Vec<f64>,
+    /// Multi-segment wellbore node rows, `SEGMENT_BLOCK_SIZE` per segment (see `segment_scales`).
+    pub(crate) segment: Vec<f64>,
 }
 
 /// Per-equation-family peak of a scaled residual vector (row-space, not variable-space).
@@ -26,12 +28,13 @@ pub(crate) struct EquationFamilyPeaks {
     pub(crate) gas_component: f64,
     pub(crate) well_constraint: f64,
     pub(crate) perforation_flow: f64,
+    pub(crate) segment: f64,
 }
 
 impl EquationScaling {
     /// Per-family peak of `|residual[row]| / scale[row]`, using this scaling's own row
     /// partition (cell rows in `water, oil_component, gas_component` interleaved triples,
-    /// then well-constraint rows, then perforation-flow rows). `residual` must be laid out
+    /// then well-constraint rows, then perforation-flow rows, then segment rows). `residual` must be laid out
     /// in exactly that row order (true for both the Newton residual and a FIM linear
     /// system's residual, which share the same unknown/equation ordering).
     pub(crate) fn family_peaks(&self, residual: &DVector<f64>) -> EquationFamilyPeaks {
@@ -60,6 +63,10 @@ impl EquationScaling {
                 .perforation_flow
                 .max(residual[offset + i].abs() / scale);
         }
+        offset += self.perforation_flow.len();
+        for (i, scale) in self.segment.iter().enumerate() {
+            peaks.segment = peaks.segment.max(residual[offset + i].abs() / scale);
+        }
 
         peaks
     }
@@ -87,6 +94,7 @@ impl EquationFamilyPeaks {
             && ok(self.gas_component, initial.gas_component)
             && ok(self.well_constraint, initial.well_constraint)
             && ok(self.perforation_flow, initial.perforation_flow)
+            && ok(self.segment, initial.segment)
     }
 }
 
@@ -97,6 +105,7 @@ pub(crate) struct VariableScaling {
     pub(crate) hydrocarbon_var: Vec<f64>,
     pub(crate) well_bhp: Vec<f64>,
     pub(crate) perforation_rate: Vec<f64>,
+    pub(crate) segment: Vec<f64>,
 }
 
 /// `well_constraint` row scale for one well: `1.0` for a rate-controlled well with feasible
@@ -118,6 +127,18 @@ pub(crate) fn perforation_flow_scale(rate_m3_day: f64) -> f64 {
     rate_m3_day.abs().max(1.0)
 }
 
+/// Row and unknown scales for one wellbore segment's block: pressure `|p|.max(1.0)`, flow
+/// `|Q|.max(1.0)`, and O(1) for the two mixture fractions. Shared by both scalings and the
+/// nested well solve, for the same reason as `well_constraint_scale`.
+pub(crate) fn segment_scales(segment: &FimSegmentState) -> [f64; SEGMENT_BLOCK_SIZE] {
+    [
+        segment.pressure_bar.abs().max(1.0),
+        segment.flow_m3_day.abs().max(1.0),
+        1.0,
+        1.0,
+    ]
+}
+
 /// Apply the scoped Flow RESV units after the historical scale vectors have been built. The
 /// selected tail slot is surface u, the control row is reservoir volume, and the perforation row
 /// is surface volume; the gas component row keeps its existing current-Bg scale.
@@ -183,6 +204,7 @@ pub(crate) fn build_equation_scaling(
         gas_component,
         well_constraint,
         perforation_flow,
+        segment: state.segments.iter().flat_map(segment_scales).collect(),
     }
 }
 
@@ -219,6 +241,7 @@ pub(crate) fn build_variable_scaling(
         hydrocarbon_var,
         well_bhp,
         perforation_rate,
+        segment: state.segments.iter().flat_map(segment_scales).collect(),
     }
 }
 
@@ -236,6 +259,7 @@ mod tests {
             gas_component: vec![10.0, 10.0],
             well_constraint: vec![1.0],
             perforation_flow: vec![1000.0],
+            segment: Vec::new(),
         }
     }
 
@@ -262,6 +286,7 @@ mod tests {
             gas_component: 100.0,
             well_constraint: 100.0,
             perforation_flow: 100.0,
+            segment: 0.0,
         };
         // All families reduced by 1% except perforation_flow, which barely moved.
         let mostly_reduced = EquationFamilyPeaks {
@@ -270,6 +295,7 @@ mod tests {
             gas_component: 1.0,
             well_constraint: 1.0,
             perforation_flow: 99.0,
+            segment: 0.0,
         };
 
         assert!(!mostly_reduced.within_relative_reduction(&initial, 1e-12, 5e-2));
@@ -303,6 +329,7 @@ mod tests {
             perforation_primaries: vec![
                 crate::fim::state::FimPerforationPrimary::reservoir_connection_q(-25.0),
             ],
+            segments: Vec::new(),
         };
 
         let scaling = build_variable_scaling(&sim, &state);
@@ -1,12 +1,12 @@
 use nalgebra::DVector;
 
-use crate::ReservoirSimulator;
 use crate::fim::flash::{classify_cell_regime, resolve_cell_flash};
 use crate::fim::flow_resv::FlowResvReportStepContext;
 use crate::fim::wells::{
-    build_well_topology, connection_rate_for_bhp, perforation_local_block, physical_well_control,
-    well_local_block,
+    build_well_topology, connection_rate_for_bhp, effective_injected_fluid,
+    perforation_local_block, physical_well_control, producer_control_state, well_local_block,
 };
+use crate::{InjectedFluid, ReservoirSimulator};
 
 /// Which well-state post-processing `apply_raw_update` applies after the raw Newton update.
 /// `.archive/docs/FIM_BUNDLE_W_PLAN.md` §5 item 1: Bundle W's `NestedSolve` replaces `Relax` as a
@@ -98,16 +98,34 @@ impl FimPerforationPrimary {
     }
 }
 
+/// Unknowns of one wellbore segment of a multi-segment well: the pressure at its completion's
+/// node, the flow it carries toward the heel (reservoir volumes, negative toward the toe), and
+/// the water and gas volume fractions of the mixture at the node.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct FimSegmentState {
+    pub(crate) pressure_bar: f64,
+    pub(crate) flow_m3_day: f64,
+    pub(crate) water_fraction: f64,
+    pub(crate) gas_fraction: f64,
+}
+
+/// Unknowns (and equations) per wellbore segment, in `FimSegmentState` field order.
+pub(crate) const SEGMENT_BLOCK_SIZE: usize = 4;
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct FimState {
     pub(crate) cells: Vec<FimCellState>,
     pub(crate) well_bhp: Vec<f64>,
     pub(crate) perforation_primaries: Vec<FimPerforationPrimary>,
+    /// Segments of every multi-segment well, in `FimWellTopology` segment order. They sit after
+    /// the perforations in the unknown vector, so the well Schur elimination absorbs them with
+    /// the rest of the well tail.
+    pub(crate) segments: Vec<FimSegmentState>,
 }
 
 impl FimState {
     pub(crate) fn from_simulator(sim: &ReservoirSimulator) -> Self {
-        let n_cells = sim.nx * sim.ny * sim.nz;
+        let n_cells = sim.cell_count();
         let topology = build_well_topology(sim);
         let mut cells = Vec::with_capacity(n_cells);
 
@@ -151,13 +169,37 @@ impl FimState {
                 FimPerforationPrimary::reservoir_connection_q(0.0);
                 topology.perforations.len()
             ],
+            segments: Vec::with_capacity(topology.segment_count()),
         };
 
+        // Wellbore nodes start on the lagged profile the wells entered the step with.
+        for well in &topology.wells {
+            for (&perf_idx, _) in well.perforation_indices.iter().zip(&well.segment_indices) {
+                let perforation = &topology.perforations[perf_idx];
+                let (water_fraction, gas_fraction) = if perforation.injector {
+                    match effective_injected_fluid(sim, perforation) {
+                        InjectedFluid::Water => (1.0, 0.0),
+                        InjectedFluid::Gas => (0.0, 1.0),
+                    }
+                } else {
+                    let producer = producer_control_state(sim, &state, perforation);
+                    (producer.water_fraction, producer.gas_fraction)
+                };
+                state.segments.push(FimSegmentState {
+                    pressure_bar: state.well_bhp[perforation.physical_well_index]
+                        + sim.wells[perforation.well_entry_index].head_offset_bar,
+                    flow_m3_day: 0.0,
+                    water_fraction,
+                    gas_fraction,
+                });
+            }
+        }
+
         for well_idx in 0..topology.wells.len() {
             if let Some((bhp_bar, _)) =
                 well_local_block(&topology, &state, well_idx).solve_bhp_from_target(sim)
             {
-                state.well_bhp[well_idx] = bhp_bar;
+                state.shift_well_pressure(&topology, well_idx, bhp_bar);
             }
         }
 
@@ -172,10 +214,61 @@ impl FimState {
                     perf.connection_rate_for_bhp(sim, bhp_bar).unwrap_or(0.0)
                 };
         }
+        for well_idx in 0..topology.wells.len() {
+            state.refresh_segment_flows(&topology, well_idx);
+            if !topology.wells[well_idx].segment_indices.is_empty() {
+                crate::fim::wells_inner::solve_well_locally(
+                    sim,
+                    &mut state,
+                    &topology,
+                    well_idx,
+                    &crate::fim::wells_inner::FimWellInnerSolveOptions::default(),
+                );
+            }
+        }
 
         state
     }
 
+    /// Move well `well_idx`'s BHP to `bhp_bar`, carrying its wellbore nodes along so the
+    /// pressure drops down the wellbore are kept.
+    fn shift_well_pressure(
+        &mut self,
+        topology: &crate::fim::wells::FimWellTopology,
+        well_idx: usize,
+        bhp_bar: f64,
+    ) {
+        let shift = bhp_bar - self.well_bhp[well_idx];
+        self.well_bhp[well_idx] = bhp_bar;
+        for &segment_idx in &topology.wells[well_idx].segment_indices {
+            if let Some(segment) = self.segments.get_mut(segment_idx) {
+                segment.pressure_bar += shift;
+            }
+        }
+    }
+
+    /// Set the flow through each segment of well `well_idx` to what its toe-side completions
+    /// put into the wellbore.
+    fn refresh_segment_flows(
+        &mut self,
+        topology: &crate::fim::wells::FimWellTopology,
+        well_idx: usize,
+    ) {
+        let well = &topology.wells[well_idx];
+        let mut flow = 0.0;
+        for (&perf_idx, &segment_idx) in well
+            .perforation_indices
+            .iter()
+            .zip(&well.segment_indices)
+            .rev()
+        {
+            flow += self.perforation_primaries[perf_idx].value;
+            if let Some(segment) = self.segments.get_mut(segment_idx) {
+                segment.flow_m3_day = flow;
+            }
+        }
+    }
+
     /// Convert the already-created historical tail into G4's scoped positive surface-rate
     /// primary before the first Newton assembly. The stored tail slot keeps its existing matrix
     /// position; every RESV route consumer is selected by the immutable context and must treat
@@ -282,6 +375,10 @@ impl FimState {
         self.perforation_primaries.len()
     }
 
+    pub(crate) fn n_segment_unknowns(&self) -> usize {
+        self.segments.len() * SEGMENT_BLOCK_SIZE
+    }
+
     pub(crate) fn cell(&self, idx: usize) -> &FimCellState {
         &self.cells[idx]
     }
@@ -292,7 +389,10 @@ impl FimState {
     }
 
     pub(crate) fn n_unknowns(&self) -> usize {
-        self.n_cell_unknowns() + self.n_well_unknowns() + self.n_perforation_unknowns()
+        self.n_cell_unknowns()
+            + self.n_well_unknowns()
+            + self.n_perforation_unknowns()
+            + self.n_segment_unknowns()
     }
 
     pub(crate) fn well_bhp_unknown_offset(&self, well_idx: usize) -> usize {
@@ -311,6 +411,21 @@ impl FimState {
         self.n_cell_unknowns() + self.n_well_unknowns() + perf_idx
     }
 
+    /// Unknown `local` (in `FimSegmentState` field order) of segment `segment_idx`.
+    pub(crate) fn segment_unknown_offset(&self, segment_idx: usize, local: usize) -> usize {
+        self.n_cell_unknowns()
+            + self.n_well_unknowns()
+            + self.n_perforation_unknowns()
+            + segment_idx * SEGMENT_BLOCK_SIZE
+            + local
+    }
+
+    /// Equation `local` of segment `segment_idx`: its pressure drop, flow balance, and water
+    /// and gas mixture rows.
+    pub(crate) fn segment_equation_offset(&self, segment_idx: usize, local: usize) -> usize {
+        self.segment_unknown_offset(segment_idx, local)
+    }
+
     pub(crate) fn classify_regimes(&mut self, sim: &ReservoirSimulator) {
         if !sim.three_phase_mode || sim.pvt_table.is_none() {
             return;
@@ -456,6 +571,18 @@ impl FimState {
                 );
             }
         }
+        self.enforce_segment_bounds();
+    }
+
+    /// Keep wellbore node pressures positive and mixture fractions a valid split.
+    pub(crate) fn enforce_segment_bounds(&mut self) {
+        for segment in &mut self.segments {
+            segment.pressure_bar = segment.pressure_bar.max(1e-6);
+            segment.water_fraction = segment.water_fraction.clamp(0.0, 1.0);
+            segment.gas_fraction = segment
+                .gas_fraction
+                .clamp(0.0, 1.0 - segment.water_fraction);
+        }
     }
 
     fn enforce_flow_resv_bhp_bounds(
@@ -489,6 +616,7 @@ impl FimState {
                 );
             }
         }
+        self.enforce_segment_bounds();
     }
 
     fn relax_well_state_toward_local_consistency(
@@ -506,7 +634,7 @@ impl FimState {
                 let control = block.control(sim);
                 let consistent_bhp = if !control.enabled {
                     Some(control.bhp_target)
-                } else if control.rate_controlled {
+                } else if control.rate_controlled || control.thp.is_some() {
                     block.solve_bhp_from_target(sim).map(|(bhp_bar, _)| bhp_bar)
                 } else {
                     Some(control.bhp_target)
@@ -522,10 +650,14 @@ impl FimState {
             let proposed_bhp = self.well_bhp[well_idx];
             let blended_bhp =
                 proposed_bhp + WELL_BHP_MANIFOLD_BLEND * (consistent_bhp - proposed_bhp);
-            self.well_bhp[well_idx] = (consistent_bhp
-                + (blended_bhp - consistent_bhp)
-                    .clamp(-WELL_BHP_TRUST_RADIUS_BAR, WELL_BHP_TRUST_RADIUS_BAR))
-            .max(1e-6);
+            self.shift_well_pressure(
+                topology,
+                well_idx,
+                (consistent_bhp
+                    + (blended_bhp - consistent_bhp)
+                        .clamp(-WELL_BHP_TRUST_RADIUS_BAR, WELL_BHP_TRUST_RADIUS_BAR))
+                .max(1e-6),
+            );
 
             for perf_idx in perforation_indices {
                 let consistent_q = if !control.enabled {
@@ -545,10 +677,13 @@ impl FimState {
                     0.0
                 } else if topology.wells[well_idx].injector {
                     q.min(0.0)
+                } else if topology.perforations[perf_idx].takes_crossflow() {
+                    q
                 } else {
                     q.max(0.0)
                 };
             }
+            self.refresh_segment_flows(topology, well_idx);
         }
     }
 
@@ -665,6 +800,13 @@ impl FimState {
             let offset = self.perforation_rate_unknown_offset(perf_idx);
             next.perforation_primaries[perf_idx].value += damping * update[offset];
         }
+        for (segment_idx, segment) in next.segments.iter_mut().enumerate() {
+            let offset = self.segment_unknown_offset(segment_idx, 0);
+            segment.pressure_bar += damping * update[offset];
+            segment.flow_m3_day += damping * update[offset + 1];
+            segment.water_fraction += damping * update[offset + 2];
+            segment.gas_fraction += damping * update[offset + 3];
+        }
 
         next
     }
@@ -710,6 +852,13 @@ impl FimState {
             let offset = self.perforation_rate_unknown_offset(perf_idx);
             next.perforation_primaries[perf_idx].value += damping * update[offset];
         }
+        for (segment_idx, segment) in next.segments.iter_mut().enumerate() {
+            let offset = self.segment_unknown_offset(segment_idx, 0);
+            segment.pressure_bar += damping * update[offset];
+            segment.flow_m3_day += damping * update[offset + 1];
+            segment.water_fraction += damping * update[offset + 2];
+            segment.gas_fraction += damping * update[offset + 3];
+        }
 
         next
     }
@@ -861,6 +1010,12 @@ impl FimState {
                 .perforation_primaries
                 .iter()
                 .all(|primary| primary.value.is_finite())
+            && self.segments.iter().all(|segment| {
+                segment.pressure_bar.is_finite()
+                    && segment.flow_m3_day.is_finite()
+                    && segment.water_fraction.is_finite()
+                    && segment.gas_fraction.is_finite()
+            })
     }
 
     pub(crate) fn respects_basic_bounds(&self, sim: &ReservoirSimulator) -> bool {
@@ -1170,6 +1325,7 @@ mod tests {
             }],
             well_bhp: Vec::new(),
             perforation_primaries: Vec::new(),
+            segments: Vec::new(),
         };
 
         let derived = state.derive_cell(&sim, 0);
@@ -1213,6 +1369,7 @@ mod tests {
             }],
             well_bhp: Vec::new(),
             perforation_primaries: Vec::new(),
+            segments: Vec::new(),
         };
 
         let pore_volume_m3 = sim.pore_volume_m3(0);
@@ -1268,6 +1425,7 @@ mod tests {
             }],
             well_bhp: Vec::new(),
             perforation_primaries: Vec::new(),
+            segments: Vec::new(),
         };
         state.classify_regimes(&sim);
         assert_eq!(state.cells[0].regime, HydrocarbonState::Saturated);
@@ -1414,6 +1572,7 @@ mod tests {
             }],
             well_bhp: Vec::new(),
             perforation_primaries: Vec::new(),
+            segments: Vec::new(),
         };
         state.classify_regimes(&sim);
         assert_eq!(state.cells[0].regime, HydrocarbonState::Undersaturated);
@@ -26,7 +26,7 @@ fn peaceman_connection_law_matches_analytical_wi() {
     let ky = sim.perm_y[id];
     let k_avg = (kx * ky).sqrt();
     let r_eq = 0.28_f64
-        * ((kx / ky).sqrt() * sim.dx.powi(2) + (ky / kx).sqrt() * sim.dy.powi(2)).sqrt()
+        * ((kx / ky).sqrt() * sim.dx[0].powi(2) + (ky / kx).sqrt() * sim.dy[0].powi(2)).sqrt()
         / ((kx / ky).powf(0.25_f64) + (ky / kx).powf(0.25_f64));
     let wi_geom = DARCY_METRIC_FACTOR * 2.0 * std::f64::consts::PI * k_avg * sim.dz_at(id)
         / ((r_eq / well.well_radius).ln() + well.skin);
@@ -51,10 +51,7 @@ enum FimRetryMemoryRegion {
 }
 
 fn cell_ijk(sim: &ReservoirSimulator, cell_idx: usize) -> (usize, usize, usize) {
-    let i = cell_idx % sim.nx;
-    let j = (cell_idx / sim.nx) % sim.ny;
-    let k = cell_idx / (sim.nx * sim.ny);
-    (i, j, k)
+    sim.root_cell_ijk(cell_idx)
 }
 
 fn representative_well_index(sim: &ReservoirSimulator, well_idx: usize) -> usize {
@@ -432,11 +429,18 @@ const OPM_SOLVER_GROWTH_FACTOR_AFTER_RESTART: f64 = 2.0;
 /// substep states (not Newton iterations) — sum-of-squares of pressure/saturation deltas,
 /// normalized by the sum-of-squares of the new state's own values. Implied `So` participates
 /// like the other phases, matching OPM's own (`NB fix me!`-flagged) mixing of pressure and
-/// saturation units verbatim — ported as-is, not "improved" (design doc §9.3).
-fn opm_relative_change(previous: &FimState, current: &FimState) -> f64 {
+/// saturation units verbatim — ported as-is, not "improved" (design doc §9.3). Inactive cells
+/// are outside the flow problem and do not enter either sum.
+fn opm_relative_change(previous: &FimState, current: &FimState, cell_active: &[bool]) -> f64 {
     let mut delta = 0.0_f64;
     let mut denom = 0.0_f64;
-    for (old_cell, new_cell) in previous.cells.iter().zip(current.cells.iter()) {
+    for ((old_cell, new_cell), _) in previous
+        .cells
+        .iter()
+        .zip(current.cells.iter())
+        .zip(cell_active)
+        .filter(|(_, active)| **active)
+    {
         let dp = new_cell.pressure_bar - old_cell.pressure_bar;
         delta += dp * dp;
         denom += new_cell.pressure_bar * new_cell.pressure_bar;
@@ -855,10 +859,28 @@ fn globally_extrapolated_state(prev: &FimState, curr: &FimState, dt_ratio: f64)
             }
         })
         .collect();
+    let segments = prev
+        .segments
+        .iter()
+        .zip(curr.segments.iter())
+        .map(|(p, c)| {
+            let water_fraction =
+                linear_extrapolate_scalar(p.water_fraction, c.water_fraction, dt_ratio)
+                    .clamp(0.0, 1.0);
+            crate::fim::state::FimSegmentState {
+                pressure_bar: linear_extrapolate_scalar(p.pressure_bar, c.pressure_bar, dt_ratio),
+                flow_m3_day: linear_extrapolate_scalar(p.flow_m3_day, c.flow_m3_day, dt_ratio),
+                water_fraction,
+                gas_fraction: linear_extrapolate_scalar(p.gas_fraction, c.gas_fraction, dt_ratio)
+                    .clamp(0.0, 1.0 - water_fraction),
+            }
+        })
+        .collect();
     FimState {
         cells,
         well_bhp,
         perforation_primaries,
+        segments,
     }
 }
 
@@ -1251,8 +1273,11 @@ impl ReservoirSimulator {
                     // problem (the hotspot-repeat pathology) that OPM's own growth ceilings
                     // (`opm-max-growth`/`opm-restart-growth`) already guard against directly.
                     let adjusted_growth_decision = if opm_aligned {
-                        let rel_change =
-                            opm_relative_change(&previous_state, &report.accepted_state);
+                        let rel_change = opm_relative_change(
+                            &previous_state,
+                            &report.accepted_state,
+                            &self.cell_active,
+                        );
                         opm_pid_errors = [opm_pid_errors[1], opm_pid_errors[2], rel_change];
                         opm_accepted_step_growth_decision(
                             trial_dt,
@@ -1847,7 +1872,7 @@ impl ReservoirSimulator {
     /// quantity; reservoir-condition `PV * Sw` changes under compression even
     /// when the component equation closes exactly.
     fn total_water_inventory_sc(&self) -> f64 {
-        (0..self.nx * self.ny * self.nz)
+        (0..self.cell_count())
             .map(|idx| {
                 self.sat_water[idx]
                     * self.pore_volume_m3(idx)
@@ -1857,7 +1882,7 @@ impl ReservoirSimulator {
     }
 
     fn total_oil_inventory_sc(&self) -> f64 {
-        (0..self.nx * self.ny * self.nz)
+        (0..self.cell_count())
             .map(|idx| {
                 let pore_volume_m3 = self.pore_volume_m3(idx).max(1e-9);
                 let bo = self.get_b_o_cell(idx, self.pressure[idx]).max(1e-9);
@@ -1871,7 +1896,7 @@ impl ReservoirSimulator {
             return 0.0;
         }
 
-        (0..self.nx * self.ny * self.nz)
+        (0..self.cell_count())
             .map(|idx| {
                 let pore_volume_m3 = self.pore_volume_m3(idx).max(1e-9);
                 let free_gas_sc =
@@ -1984,6 +2009,9 @@ mod tests {
             target_surface_rate_m3_day: None,
             bhp_limit: None,
             enabled: true,
+            injected_fluid: None,
+            thp_target_bar: None,
+            vfp_table: None,
         };
         sim.set_fim_flow_resv_injector(true);
         sim
@@ -2074,7 +2102,7 @@ mod tests {
         let mut sim = ReservoirSimulator::new(1, 1, 1, 0.2);
         sim.add_well(0, 0, 0, 500.0, 0.1, 0.0, true).unwrap();
         let state = FimState::from_simulator(&sim);
-        assert_eq!(opm_relative_change(&state, &state), 0.0);
+        assert_eq!(opm_relative_change(&state, &state, &[true]), 0.0);
     }
 
     #[test]
@@ -2083,6 +2111,7 @@ mod tests {
             cells: vec![cell(200.0, 0.3, 0.1, HydrocarbonState::Saturated)],
             well_bhp: vec![],
             perforation_primaries: vec![],
+            segments: Vec::new(),
         };
         let mut current = previous.clone();
         current.cells[0].pressure_bar = 220.0; // dp = 20
@@ -2094,7 +2123,7 @@ mod tests {
         let denom = 220.0_f64.powi(2) + 0.35_f64.powi(2) + 0.12_f64.powi(2) + 0.53_f64.powi(2);
         let expected = delta / denom;
 
-        assert!((opm_relative_change(&previous, &current) - expected).abs() < 1e-9);
+        assert!((opm_relative_change(&previous, &current, &[true]) - expected).abs() < 1e-9);
     }
 
     #[test]
@@ -2954,6 +2983,7 @@ mod tests {
                 crate::fim::state::FimPerforationPrimary::reservoir_connection_q(10.0),
                 crate::fim::state::FimPerforationPrimary::reservoir_connection_q(-5.0),
             ],
+            segments: Vec::new(),
         };
         let curr = FimState {
             cells: vec![
@@ -2975,6 +3005,7 @@ mod tests {
                 crate::fim::state::FimPerforationPrimary::reservoir_connection_q(12.0),
                 crate::fim::state::FimPerforationPrimary::reservoir_connection_q(-6.0),
             ],
+            segments: Vec::new(),
         };
         let dt_ratio = 0.5;
         let extrapolated = globally_extrapolated_state(&prev, &curr, dt_ratio);
@@ -3010,6 +3041,7 @@ mod tests {
             }],
             well_bhp: vec![],
             perforation_primaries: vec![],
+            segments: Vec::new(),
         };
         let curr = FimState {
             cells: vec![FimCellState {
@@ -3020,6 +3052,7 @@ mod tests {
             }],
             well_bhp: vec![],
             perforation_primaries: vec![],
+            segments: Vec::new(),
         };
         // dt_ratio=2 would extrapolate sw to 0.98 + (0.98-0.90)*2 = 1.14,
         // which must clamp to 1.0.
@@ -3128,7 +3161,7 @@ pub(crate) fn water024_oil_mb_line(
     .map(|b| {
         (
             b.accumulation,
-            b.x_minus + b.x_plus + b.y_minus + b.y_plus + b.z_minus + b.z_plus,
+            b.x_minus + b.x_plus + b.y_minus + b.y_plus + b.z_minus + b.z_plus + b.non_neighbor,
             b.well_source,
         )
     })
@@ -3985,6 +4018,9 @@ mod phase5_repro {
                 target_surface_rate_m3_day: None,
                 bhp_limit: None,
                 enabled: true,
+                injected_fluid: None,
+                thp_target_bar: None,
+                vfp_table: None,
             };
             sim.set_fim_flow_resv_injector(true);
         }
@@ -4046,7 +4082,7 @@ mod phase5_repro {
             let mut min_sg = f64::INFINITY;
             let mut max_sg = f64::NEG_INFINITY;
             let mut max_saturation_closure = 0.0_f64;
-            for cell_idx in 0..sim.nx * sim.ny * sim.nz {
+            for cell_idx in 0..sim.cell_count() {
                 let sw = sim.sat_water[cell_idx];
                 let so = sim.sat_oil[cell_idx];
                 let sg = sim.sat_gas[cell_idx];
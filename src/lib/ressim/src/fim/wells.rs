@@ -3,11 +3,11 @@ use std::collections::HashMap;
 use crate::fim::state::FimState;
 #[cfg(test)]
 use crate::fim::state::HydrocarbonState;
-use crate::well_control::ProducerControlState;
+use crate::vfp::ThpControl;
+use crate::well::{SurfaceRatePhase, WellScheduleControl};
+use crate::well_control::{ProducerControlState, non_darcy_rate_factor};
 use crate::{InjectedFluid, ReservoirSimulator, Well};
 
-const DARCY_METRIC_FACTOR: f64 = 8.526_988_8e-3;
-
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) struct FimPerforation {
     pub(crate) well_entry_index: usize,
@@ -17,6 +17,16 @@ pub(crate) struct FimPerforation {
     pub(crate) j: usize,
     pub(crate) k: usize,
     pub(crate) injector: bool,
+    /// Wellbore segment down to this completion when it is on a multi-segment well.
+    pub(crate) segment_index: Option<usize>,
+}
+
+impl FimPerforation {
+    /// A multi-segment producer's completion may take wellbore fluid back into its cell, the
+    /// crossflow between layers the well's own wellbore carries.
+    pub(crate) fn takes_crossflow(&self) -> bool {
+        !self.injector && self.segment_index.is_some()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -26,6 +36,9 @@ pub(crate) struct FimPhysicalWell {
     pub(crate) head_i: usize,
     pub(crate) head_j: usize,
     pub(crate) perforation_indices: Vec<usize>,
+    /// Segments of a multi-segment well, one per perforation in `perforation_indices` order;
+    /// empty for any other well.
+    pub(crate) segment_indices: Vec<usize>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -34,6 +47,15 @@ pub(crate) struct FimWellTopology {
     pub(crate) perforations: Vec<FimPerforation>,
 }
 
+impl FimWellTopology {
+    pub(crate) fn segment_count(&self) -> usize {
+        self.wells
+            .iter()
+            .map(|well| well.segment_indices.len())
+            .sum()
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct FimPerforationLocalBlock<'a> {
     topology: &'a FimWellTopology,
@@ -76,6 +98,11 @@ impl<'a> FimPerforationLocalBlock<'a> {
         connection_rate_for_bhp(sim, self.state, self.topology, self.perf_idx, bhp_bar)
     }
 
+    /// See `perforation_head_offset_bar`.
+    pub(crate) fn head_offset_bar(self, sim: &ReservoirSimulator) -> f64 {
+        perforation_head_offset_bar(sim, self.state, self.topology, self.perf_idx)
+    }
+
     #[cfg(test)]
     pub(crate) fn component_rate_derivatives_sc_day(self, sim: &ReservoirSimulator) -> [f64; 3] {
         perforation_component_rate_derivatives_sc_day(sim, self.state, self.topology, self.perf_idx)
@@ -150,8 +177,16 @@ impl<'a> FimPerforationLocalBlock<'a> {
         self,
         sim: &ReservoirSimulator,
         q_m3_day: f64,
+        surface_phase: SurfaceRatePhase,
     ) -> Option<f64> {
-        perforation_surface_rate_sc_day(sim, self.state, self.topology, self.perf_idx, q_m3_day)
+        perforation_surface_rate_sc_day(
+            sim,
+            self.state,
+            self.topology,
+            self.perf_idx,
+            q_m3_day,
+            surface_phase,
+        )
     }
 
     pub(crate) fn current_rate_unknown_m3_day(self) -> f64 {
@@ -185,7 +220,9 @@ impl<'a> FimPerforationLocalBlock<'a> {
         let well_index = geometric_well_index(sim, perforation)?;
         let connection_mobility = (mobilities.water + mobilities.oil + mobilities.gas).max(0.0);
         let bhp_bar = self.state.well_bhp[self.physical_well_idx()];
-        let drawdown_bar = cell.pressure_bar - well.connection_pressure_bar(bhp_bar);
+        let drawdown_bar = cell.pressure_bar
+            - (bhp_bar
+                + perforation_head_offset_bar(sim, self.state, self.topology, self.perf_idx));
         let raw_connection_m3_day = well_index * connection_mobility * drawdown_bar;
         if !raw_connection_m3_day.is_finite() {
             return None;
@@ -225,12 +262,14 @@ impl<'a> FimPerforationLocalBlock<'a> {
             0.0
         } else if well.injector {
             raw_connection_m3_day.min(0.0)
+        } else if perforation.takes_crossflow() {
+            raw_connection_m3_day
         } else {
             raw_connection_m3_day.max(0.0)
         };
         let q_unknown_m3_day = self.current_rate_unknown_m3_day();
         let surface_rate_unknown_sc_day = if control.enabled && control.uses_surface_target {
-            self.surface_rate_sc_day(sim, q_unknown_m3_day)
+            self.surface_rate_sc_day(sim, q_unknown_m3_day, control.surface_phase)
         } else {
             None
         };
@@ -394,12 +433,14 @@ impl<'a> FimWellLocalBlock<'a> {
                 let q_m3_day = perf.connection_rate_for_bhp(sim, bhp_bar)?;
                 if injector {
                     if control.uses_surface_target {
-                        perf.surface_rate_sc_day(sim, q_m3_day)
+                        perf.surface_rate_sc_day(sim, q_m3_day, control.surface_phase)
                     } else {
                         Some((-q_m3_day).max(0.0))
                     }
                 } else if control.uses_surface_target {
-                    perf.surface_rate_sc_day(sim, q_m3_day)
+                    perf.surface_rate_sc_day(sim, q_m3_day, control.surface_phase)
+                } else if perf.perforation().takes_crossflow() {
+                    Some(q_m3_day)
                 } else {
                     Some(q_m3_day.max(0.0))
                 }
@@ -415,12 +456,14 @@ impl<'a> FimWellLocalBlock<'a> {
                 let q_m3_day = perf.current_rate_unknown_m3_day();
                 if injector {
                     if control.uses_surface_target {
-                        perf.surface_rate_sc_day(sim, q_m3_day)
+                        perf.surface_rate_sc_day(sim, q_m3_day, control.surface_phase)
                     } else {
                         Some((-q_m3_day).max(0.0))
                     }
                 } else if control.uses_surface_target {
-                    perf.surface_rate_sc_day(sim, q_m3_day)
+                    perf.surface_rate_sc_day(sim, q_m3_day, control.surface_phase)
+                } else if perf.perforation().takes_crossflow() {
+                    Some(q_m3_day)
                 } else {
                     Some(q_m3_day.max(0.0))
                 }
@@ -430,6 +473,20 @@ impl<'a> FimWellLocalBlock<'a> {
 
     pub(crate) fn solve_bhp_from_target(self, sim: &ReservoirSimulator) -> Option<(f64, bool)> {
         let control = self.control(sim);
+        if let Some(thp) = control.thp {
+            let pressures: Vec<f64> = self
+                .state
+                .cells
+                .iter()
+                .map(|cell| cell.pressure_bar)
+                .collect();
+            return sim.solve_well_thp_bhp(
+                physical_well(sim, self.topology, self.well_idx),
+                &pressures,
+                thp,
+                control.bhp_limit,
+            );
+        }
         if !control.enabled || !control.rate_controlled {
             return None;
         }
@@ -446,8 +503,7 @@ impl<'a> FimWellLocalBlock<'a> {
         // pressures would leave a fully perforated well's zero-rate BHP
         // outside the bracket.
         let datum_zero_rate_pressure = |perf: &FimPerforationLocalBlock<'_>| {
-            self.state.cell(perf.cell_idx()).pressure_bar
-                - perforation_head_offset_bar(sim, perf.perforation())
+            self.state.cell(perf.cell_idx()).pressure_bar - perf.head_offset_bar(sim)
         };
         let min_pressure = perforations
             .iter()
@@ -568,6 +624,7 @@ pub(crate) fn build_well_topology(sim: &ReservoirSimulator) -> FimWellTopology {
                 head_i: well.i,
                 head_j: well.j,
                 perforation_indices: Vec::new(),
+                segment_indices: Vec::new(),
             });
             index
         });
@@ -576,17 +633,36 @@ pub(crate) fn build_well_topology(sim: &ReservoirSimulator) -> FimWellTopology {
         perforations.push(FimPerforation {
             well_entry_index,
             physical_well_index,
-            cell_index: sim.idx(well.i, well.j, well.k),
+            cell_index: sim.well_cell_index(well),
             i: well.i,
             j: well.j,
             k: well.k,
             injector: well.injector,
+            segment_index: None,
         });
         wells[physical_well_index]
             .perforation_indices
             .push(perforation_index);
     }
 
+    // A well is multi-segment when every one of its completions has a segment; its segments
+    // follow its perforations, heel first.
+    let mut segment_count = 0;
+    for well in &mut wells {
+        if !well.perforation_indices.iter().all(|&perf| {
+            sim.wells[perforations[perf].well_entry_index]
+                .segment
+                .is_some()
+        }) {
+            continue;
+        }
+        for &perf in &well.perforation_indices {
+            perforations[perf].segment_index = Some(segment_count);
+            well.segment_indices.push(segment_count);
+            segment_count += 1;
+        }
+    }
+
     FimWellTopology {
         wells,
         perforations,
@@ -597,13 +673,24 @@ fn perforation_well<'a>(sim: &'a ReservoirSimulator, perforation: &FimPerforatio
     &sim.wells[perforation.well_entry_index]
 }
 
-/// Hydrostatic head from this well's datum down to this completion [bar].
+/// Pressure from this well's datum down to this completion [bar].
 ///
-/// Zero unless gravity is enabled; see `Well::head_offset_bar`.
+/// The lagged hydrostatic head of `Well::head_offset_bar`, zero unless gravity is enabled. A
+/// multi-segment well's completion instead sees its wellbore node, whose pressure is an unknown
+/// of the state.
 pub(crate) fn perforation_head_offset_bar(
     sim: &ReservoirSimulator,
-    perforation: &FimPerforation,
+    state: &FimState,
+    topology: &FimWellTopology,
+    perf_idx: usize,
 ) -> f64 {
+    let perforation = &topology.perforations[perf_idx];
+    if let Some(segment) = perforation
+        .segment_index
+        .and_then(|segment_idx| state.segments.get(segment_idx))
+    {
+        return segment.pressure_bar - state.well_bhp[perforation.physical_well_index];
+    }
     perforation_well(sim, perforation).head_offset_bar
 }
 
@@ -615,12 +702,21 @@ fn physical_well<'a>(
     &sim.wells[topology.wells[well_idx].representative_well_index]
 }
 
-pub(crate) fn effective_injected_fluid(sim: &ReservoirSimulator) -> InjectedFluid {
-    if sim.three_phase_mode {
-        sim.injected_fluid
-    } else {
-        InjectedFluid::Water
-    }
+/// Phase physical well `well_idx` injects.
+pub(crate) fn physical_well_injected_fluid(
+    sim: &ReservoirSimulator,
+    topology: &FimWellTopology,
+    well_idx: usize,
+) -> InjectedFluid {
+    sim.well_injected_fluid(physical_well(sim, topology, well_idx))
+}
+
+/// Phase the physical well owning `perforation` injects.
+pub(crate) fn effective_injected_fluid(
+    sim: &ReservoirSimulator,
+    perforation: &FimPerforation,
+) -> InjectedFluid {
+    sim.well_injected_fluid(perforation_well(sim, perforation))
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -631,6 +727,17 @@ pub(crate) struct PhysicalWellControl {
     pub(crate) target_rate: Option<f64>,
     pub(crate) bhp_limit: f64,
     pub(crate) bhp_target: f64,
+    /// THP target of a `thp`-controlled well; see `WellControlConfig::thp`.
+    pub(crate) thp: Option<ThpControl>,
+    /// Phase a producer's surface-rate target meters.
+    pub(crate) surface_phase: SurfaceRatePhase,
+}
+
+impl PhysicalWellControl {
+    /// Phase of a surface-rate target, `None` for a reservoir-volume one.
+    pub(crate) fn surface_target(&self) -> Option<SurfaceRatePhase> {
+        self.uses_surface_target.then_some(self.surface_phase)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -754,7 +861,9 @@ pub(crate) fn physical_well_control(
     let rate_controlled = if !enabled {
         false
     } else if explicit_schedule {
-        matches!(well.schedule.control_mode.as_deref(), Some("rate"))
+        well.schedule
+            .control_kind()
+            .is_some_and(WellScheduleControl::is_rate_control)
     } else {
         family_rate_controlled(sim, injector)
     };
@@ -787,6 +896,28 @@ pub(crate) fn physical_well_control(
         family_bhp_limit(sim, injector)
     };
 
+    let surface_phase = well
+        .schedule
+        .control_kind()
+        .and_then(WellScheduleControl::surface_rate_phase)
+        .unwrap_or_default();
+
+    let (rate_controlled, uses_surface_target, target_rate, surface_phase) =
+        match well.group_allocation {
+            Some(allocation) if enabled => (
+                true,
+                allocation.surface,
+                Some(allocation.target_rate_m3_day.max(0.0)),
+                SurfaceRatePhase::Oil,
+            ),
+            _ => (
+                rate_controlled,
+                uses_surface_target,
+                target_rate,
+                surface_phase,
+            ),
+        };
+
     PhysicalWellControl {
         enabled,
         rate_controlled,
@@ -794,6 +925,12 @@ pub(crate) fn physical_well_control(
         target_rate,
         bhp_limit,
         bhp_target: well.bhp,
+        thp: if enabled {
+            sim.well_thp_control(well)
+        } else {
+            None
+        },
+        surface_phase,
     }
 }
 
@@ -801,28 +938,17 @@ pub(crate) fn geometric_well_index(
     sim: &ReservoirSimulator,
     perforation: &FimPerforation,
 ) -> Option<f64> {
-    let well = perforation_well(sim, perforation);
-    let id = perforation.cell_index;
-    let kx = sim.perm_x[id];
-    let ky = sim.perm_y[id];
-    if !kx.is_finite() || !ky.is_finite() || kx <= 0.0 || ky <= 0.0 {
-        return None;
-    }
-
-    let r_eq = 0.28
-        * f64::sqrt(f64::sqrt(kx / ky) * sim.dx.powi(2) + f64::sqrt(ky / kx) * sim.dy.powi(2))
-        / ((kx / ky).powf(0.25) + (ky / kx).powf(0.25));
-    if !r_eq.is_finite() || r_eq <= well.well_radius {
-        return None;
-    }
-
-    let k_avg = f64::sqrt(kx * ky);
-    let denom = f64::ln(r_eq / well.well_radius) + well.skin;
-    if !k_avg.is_finite() || k_avg <= 0.0 || !denom.is_finite() || denom.abs() <= f64::EPSILON {
-        return None;
-    }
+    sim.well_connection_factor(perforation_well(sim, perforation))
+        .ok()
+}
 
-    Some(DARCY_METRIC_FACTOR * 2.0 * std::f64::consts::PI * k_avg * sim.dz_at(id) / denom)
+/// Turbulence coefficient [day/Sm³] of the perforation's D-factor, zero for a Darcy
+/// connection (see `ReservoirSimulator::well_non_darcy_coefficient`).
+pub(crate) fn perforation_non_darcy_coefficient(
+    sim: &ReservoirSimulator,
+    perforation: &FimPerforation,
+) -> f64 {
+    sim.well_non_darcy_coefficient(perforation_well(sim, perforation))
 }
 
 /// `FIM-BUNDLE-X` (`.archive/docs/FIM_BUNDLE_X_PLAN.md`): uses only the perforated cell's own mobility,
@@ -1110,17 +1236,35 @@ pub(crate) fn connection_rate_for_bhp(
     let mobilities =
         sim.phase_mobilities_for_state(cell.sw, derived.sg, cell.pressure_bar, derived.rs);
     let wi_geom = geometric_well_index(sim, perforation)?;
+    let head_offset_bar = perforation_head_offset_bar(sim, state, topology, perf_idx);
 
     let connection_mobility = (mobilities.water + mobilities.oil + mobilities.gas).max(0.0);
 
-    let raw_rate =
-        wi_geom * connection_mobility * (cell.pressure_bar - well.connection_pressure_bar(bhp_bar));
+    let drawdown = cell.pressure_bar - bhp_bar - head_offset_bar;
+    let mut raw_rate = wi_geom * connection_mobility * drawdown;
+    let non_darcy_coefficient = perforation_non_darcy_coefficient(sim, perforation);
+    if non_darcy_coefficient > 0.0 {
+        let gas_mobility = if well.injector {
+            connection_mobility
+        } else {
+            mobilities.gas.max(0.0)
+        };
+        let darcy_gas_rate_sc = wi_geom * gas_mobility * drawdown / derived.bg.max(1e-9);
+        let flowing_gas_rate_sc = if well.injector {
+            -darcy_gas_rate_sc
+        } else {
+            darcy_gas_rate_sc
+        };
+        raw_rate *= non_darcy_rate_factor(flowing_gas_rate_sc * non_darcy_coefficient);
+    }
     if !raw_rate.is_finite() {
         return None;
     }
 
     Some(if well.injector {
         raw_rate.min(0.0)
+    } else if perforation.takes_crossflow() {
+        raw_rate
     } else {
         raw_rate.max(0.0)
     })
@@ -1150,13 +1294,14 @@ fn perforation_surface_rate_sc_day(
     topology: &FimWellTopology,
     perf_idx: usize,
     q_m3_day: f64,
+    surface_phase: SurfaceRatePhase,
 ) -> Option<f64> {
     let perforation = &topology.perforations[perf_idx];
     let well = perforation_well(sim, perforation);
     let id = perforation.cell_index;
 
     if well.injector {
-        return Some(match effective_injected_fluid(sim) {
+        return Some(match effective_injected_fluid(sim, perforation) {
             InjectedFluid::Water => {
                 (-q_m3_day).max(0.0) * sim.water_inverse_fvf(state.cell(id).pressure_bar)
             }
@@ -1164,8 +1309,56 @@ fn perforation_surface_rate_sc_day(
         });
     }
 
+    if perforation.takes_crossflow() {
+        let producer = perforation_producer_state(sim, state, topology, perf_idx, q_m3_day);
+        let coefficients = producer_surface_rate_coefficients(sim, state, perforation, &producer);
+        return Some(q_m3_day * surface_phase.rate(coefficients));
+    }
     let producer = producer_control_state(sim, state, perforation);
-    Some(q_m3_day.max(0.0) * producer.oil_fraction / producer.oil_fvf.max(1e-9))
+    let coefficients = producer_surface_rate_coefficients(sim, state, perforation, &producer);
+    Some(q_m3_day.max(0.0) * surface_phase.rate(coefficients))
+}
+
+/// `producer_control_state` of the fluid a producer's completion passes at `q_m3_day`: the
+/// cell's mobile mixture flowing in, or the wellbore node's mixture when a multi-segment well
+/// puts fluid back into the cell. Both convert to surface volumes at the cell's PVT.
+pub(crate) fn perforation_producer_state(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    topology: &FimWellTopology,
+    perf_idx: usize,
+    q_m3_day: f64,
+) -> ProducerControlState {
+    let perforation = &topology.perforations[perf_idx];
+    let mut producer = producer_control_state(sim, state, perforation);
+    if q_m3_day < 0.0
+        && perforation.takes_crossflow()
+        && let Some(segment) = perforation
+            .segment_index
+            .and_then(|segment_idx| state.segments.get(segment_idx))
+    {
+        producer.water_fraction = segment.water_fraction;
+        producer.gas_fraction = segment.gas_fraction;
+        producer.oil_fraction = 1.0 - segment.water_fraction - segment.gas_fraction;
+    }
+    producer
+}
+
+/// Producer surface rates `[water, oil, gas]` [Sm³/day] per m³/day of reservoir rate of
+/// `producer` through `perforation`.
+fn producer_surface_rate_coefficients(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    perforation: &FimPerforation,
+    producer: &ProducerControlState,
+) -> [f64; 3] {
+    [
+        producer.water_fraction
+            * sim.water_inverse_fvf(state.cell(perforation.cell_index).pressure_bar),
+        producer.oil_fraction / producer.oil_fvf.max(1e-9),
+        producer.gas_fraction / producer.gas_fvf.max(1e-9)
+            + producer.oil_fraction / producer.oil_fvf.max(1e-9) * producer.rs_sm3_sm3,
+    ]
 }
 
 #[cfg(test)]
@@ -1180,19 +1373,14 @@ pub(crate) fn perforation_component_rate_derivatives_sc_day(
     let id = perforation.cell_index;
 
     if well.injector {
-        return match effective_injected_fluid(sim) {
+        return match effective_injected_fluid(sim, perforation) {
             InjectedFluid::Water => [sim.water_inverse_fvf(state.cell(id).pressure_bar), 0.0, 0.0],
             InjectedFluid::Gas => [0.0, 0.0, 1.0 / state.derive_cell(sim, id).bg.max(1e-9)],
         };
     }
 
     let producer = producer_control_state(sim, state, perforation);
-    [
-        producer.water_fraction * sim.water_inverse_fvf(state.cell(id).pressure_bar),
-        producer.oil_fraction / producer.oil_fvf.max(1e-9),
-        producer.gas_fraction / producer.gas_fvf.max(1e-9)
-            + producer.oil_fraction / producer.oil_fvf.max(1e-9) * producer.rs_sm3_sm3,
-    ]
+    producer_surface_rate_coefficients(sim, state, perforation, &producer)
 }
 
 #[cfg(test)]
@@ -1213,7 +1401,7 @@ pub(crate) fn perforation_target_rate_derivative(
 
     if well.injector {
         if control.uses_surface_target {
-            return match effective_injected_fluid(sim) {
+            return match effective_injected_fluid(sim, perforation) {
                 InjectedFluid::Water => -sim.water_inverse_fvf(state.cell(id).pressure_bar),
                 InjectedFluid::Gas => -1.0 / state.derive_cell(sim, id).bg.max(1e-9),
             };
@@ -1222,8 +1410,14 @@ pub(crate) fn perforation_target_rate_derivative(
     }
 
     if control.uses_surface_target {
-        let producer = producer_control_state(sim, state, perforation);
-        return producer.oil_fraction / producer.oil_fvf.max(1e-9);
+        return control
+            .surface_phase
+            .rate(producer_surface_rate_coefficients(
+                sim,
+                state,
+                perforation,
+                &producer_control_state(sim, state, perforation),
+            ));
     }
 
     1.0
@@ -1242,7 +1436,7 @@ pub(crate) fn perforation_source_pressure_derivatives_sc_day(
         return [0.0, 0.0, 0.0];
     }
 
-    match effective_injected_fluid(sim) {
+    match effective_injected_fluid(sim, perforation) {
         InjectedFluid::Water => [0.0, 0.0, 0.0],
         InjectedFluid::Gas => {
             let id = perforation.cell_index;
@@ -1270,7 +1464,7 @@ pub(crate) fn perforation_surface_rate_pressure_derivative(
         return 0.0;
     }
 
-    match effective_injected_fluid(sim) {
+    match effective_injected_fluid(sim, perforation) {
         InjectedFluid::Water => 0.0,
         InjectedFluid::Gas => {
             let id = perforation.cell_index;
@@ -1300,12 +1494,12 @@ pub(crate) fn perforation_connection_bhp_derivative(
     let mobilities =
         sim.phase_mobilities_for_state(cell.sw, derived.sg, cell.pressure_bar, derived.rs);
     let wi_geom = geometric_well_index(sim, perforation)?;
+    let head_offset_bar = perforation_head_offset_bar(sim, state, topology, perf_idx);
 
     let connection_mobility = (mobilities.water + mobilities.oil + mobilities.gas).max(0.0);
 
     let active_derivative = -wi_geom * connection_mobility;
-    let raw_rate =
-        wi_geom * connection_mobility * (cell.pressure_bar - well.connection_pressure_bar(bhp_bar));
+    let raw_rate = wi_geom * connection_mobility * (cell.pressure_bar - bhp_bar - head_offset_bar);
     if !raw_rate.is_finite() {
         return None;
     }
@@ -1343,9 +1537,9 @@ pub(crate) fn perforation_connection_pressure_derivative(
     let mobilities =
         sim.phase_mobilities_for_state(cell.sw, derived.sg, cell.pressure_bar, derived.rs);
     let wi_geom = geometric_well_index(sim, perforation)?;
+    let head_offset_bar = perforation_head_offset_bar(sim, state, topology, perf_idx);
     let connection_mobility = (mobilities.water + mobilities.oil + mobilities.gas).max(0.0);
-    let raw_rate =
-        wi_geom * connection_mobility * (cell.pressure_bar - well.connection_pressure_bar(bhp_bar));
+    let raw_rate = wi_geom * connection_mobility * (cell.pressure_bar - bhp_bar - head_offset_bar);
     if !raw_rate.is_finite() {
         return None;
     }
@@ -1370,7 +1564,8 @@ pub(crate) fn perforation_connection_cell_derivatives(
     let id = perforation.cell_index;
     let cell = state.cell(id);
     let wi_geom = geometric_well_index(sim, perforation)?;
-    let drawdown = cell.pressure_bar - well.connection_pressure_bar(bhp_bar);
+    let drawdown =
+        cell.pressure_bar - bhp_bar - perforation_head_offset_bar(sim, state, topology, perf_idx);
     let local = local_phase_sensitivity(sim, state, id);
 
     let (connection_mobility, dmob_dp, dmob_dsw, dmob_dh) = if well.injector {
@@ -1426,7 +1621,7 @@ pub(crate) fn perforation_component_rate_cell_derivatives_sc_day_by_var(
         if cell_idx != perforation.cell_index {
             return [[0.0; 3]; 3];
         }
-        return match effective_injected_fluid(sim) {
+        return match effective_injected_fluid(sim, perforation) {
             InjectedFluid::Water => {
                 let q_m3_day = state
                     .reservoir_connection_q(perf_idx)
@@ -1456,6 +1651,19 @@ pub(crate) fn perforation_component_rate_cell_derivatives_sc_day_by_var(
 
     let q_m3_day = current_reservoir_connection_rate(sim, state, topology, perf_idx)
         .expect("perforation component rates require a finite connection rate");
+    producer_component_rate_cell_derivatives(sim, state, perforation, cell_idx, q_m3_day)
+}
+
+/// `d[water, oil, gas]/d(cell var)` [Sm³/day] of producer `perforation` flowing `q_m3_day`,
+/// indexed `[local_var][component]`.
+#[cfg(test)]
+fn producer_component_rate_cell_derivatives(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    perforation: &FimPerforation,
+    cell_idx: usize,
+    q_m3_day: f64,
+) -> [[f64; 3]; 3] {
     let producer = producer_rate_sensitivity(sim, state, perforation, cell_idx);
     let perforation_pressure = state.cell(perforation.cell_index).pressure_bar;
     let inv_bw = sim.water_inverse_fvf(perforation_pressure);
@@ -1500,7 +1708,7 @@ pub(crate) fn perforation_surface_rate_cell_derivatives_sc_day(
         if cell_idx != perforation.cell_index {
             return [0.0; 3];
         }
-        return match effective_injected_fluid(sim) {
+        return match effective_injected_fluid(sim, perforation) {
             InjectedFluid::Water => {
                 // The water injector's surface rate is `(-q).max(0) * water_inverse_fvf(p)`, and
                 // `1/Bw` depends on the perforated cell's pressure through the water
@@ -1530,18 +1738,11 @@ pub(crate) fn perforation_surface_rate_cell_derivatives_sc_day(
         };
     }
 
-    let producer = producer_rate_sensitivity(sim, state, perforation, cell_idx);
-    let bo = producer.oil_fvf.max(1e-9);
     let q_m3_day = state
         .reservoir_connection_q(perf_idx)
         .expect("historical well path requires a reservoir-q primary");
-    let mut derivatives = [0.0; 3];
-    for local_var in 0..3 {
-        derivatives[local_var] = q_m3_day
-            * (producer.oil_fraction_derivatives[local_var] / bo
-                - producer.oil_fraction * producer.oil_fvf_derivatives[local_var] / (bo * bo));
-    }
-    derivatives
+    producer_component_rate_cell_derivatives(sim, state, perforation, cell_idx, q_m3_day)
+        .map(|components| control.surface_phase.rate(components))
 }
 
 #[cfg(test)]
@@ -1643,7 +1844,7 @@ pub(crate) fn perforation_component_rates_sc_day(
         .expect("perforation component rates require a finite connection rate");
     let id = perforation.cell_index;
     if well.injector {
-        return match effective_injected_fluid(sim) {
+        return match effective_injected_fluid(sim, perforation) {
             InjectedFluid::Water => [
                 q_m3_day * sim.water_inverse_fvf(state.cell(id).pressure_bar),
                 0.0,
@@ -1653,7 +1854,7 @@ pub(crate) fn perforation_component_rates_sc_day(
         };
     }
 
-    let producer = producer_control_state(sim, state, perforation);
+    let producer = perforation_producer_state(sim, state, topology, perf_idx, q_m3_day);
     let water_sc_day =
         q_m3_day * producer.water_fraction * sim.water_inverse_fvf(state.cell(id).pressure_bar);
     let oil_sc_day = q_m3_day * producer.oil_fraction / producer.oil_fvf.max(1e-9);
@@ -24,6 +24,9 @@ use crate::ReservoirSimulator;
 use crate::fim::ad::{Ad, Scalar};
 use crate::fim::properties::cell_props_generic;
 use crate::fim::state::HydrocarbonState;
+use crate::vfp::ThpControl;
+use crate::well::SurfaceRatePhase;
+use crate::well_control::non_darcy_rate_factor;
 
 /// One connected cell's primary-variable inputs to a well/perforation residual.
 #[derive(Clone, Copy)]
@@ -142,26 +145,33 @@ pub(crate) fn producer_fractions_neighbor_block(
 ///
 /// `head_offset_bar` carries the well's datum `bhp` down to this completion
 /// (`Well::head_offset_bar`). It is a constant of the step, so it shifts the
-/// residual without touching any Jacobian entry.
+/// residual without touching any cell or BHP entry. A multi-segment well's
+/// completion instead passes its wellbore node pressure as `bhp` with a zero
+/// offset.
+///
+/// `non_darcy_coefficient` (`ReservoirSimulator::well_non_darcy_coefficient`)
+/// scales the Darcy rate by `non_darcy_rate_factor` of its free-gas surface
+/// rate. The factor solves the rate-dependent skin in closed form, so the rate
+/// stays a function of the cell state and BHP alone and AD carries the
+/// turbulence into every entry of the rate-consistency row.
 pub(crate) fn connection_rate_generic<S: Scalar>(
     sim: &ReservoirSimulator,
     wi_geom: f64,
+    non_darcy_coefficient: f64,
     head_offset_bar: f64,
     injector: bool,
     cell: &WellCellInput<S>,
     bhp: S,
 ) -> S {
-    let props = cell_props_generic(
+    let raw_rate = unclamped_connection_rate_generic(
         sim,
-        cell.regime,
-        cell.p,
-        cell.sw,
-        cell.hydrocarbon_var,
-        cell.drsdt0_base_rs,
+        wi_geom,
+        non_darcy_coefficient,
+        head_offset_bar,
+        injector,
+        cell,
+        bhp,
     );
-    let mob = sim.phase_mobilities_for_state_generic(cell.sw, props.sg, cell.p, props.rs);
-    let connection_mobility = (mob.water + mob.oil + mob.gas).max_floor(0.0);
-    let raw_rate = (connection_mobility * (cell.p - bhp - S::from_f64(head_offset_bar))) * wi_geom;
 
     // Mirror `wells::perforation_connection_bhp_derivative` /
     // `perforation_connection_cell_derivatives`'s explicit STRICT-inequality
@@ -187,6 +197,49 @@ pub(crate) fn connection_rate_generic<S: Scalar>(
     }
 }
 
+/// `connection_rate_generic` before its flow-direction clamp: a producer's
+/// connection turns negative once `bhp + head_offset_bar` exceeds the cell
+/// pressure. A multi-segment producer uses it as is, so a completion can take
+/// wellbore fluid back into its layer.
+pub(crate) fn unclamped_connection_rate_generic<S: Scalar>(
+    sim: &ReservoirSimulator,
+    wi_geom: f64,
+    non_darcy_coefficient: f64,
+    head_offset_bar: f64,
+    injector: bool,
+    cell: &WellCellInput<S>,
+    bhp: S,
+) -> S {
+    let props = cell_props_generic(
+        sim,
+        cell.regime,
+        cell.p,
+        cell.sw,
+        cell.hydrocarbon_var,
+        cell.drsdt0_base_rs,
+    );
+    let mob = sim.phase_mobilities_for_state_generic(cell.sw, props.sg, cell.p, props.rs);
+    let connection_mobility = (mob.water + mob.oil + mob.gas).max_floor(0.0);
+    let drawdown = cell.p - bhp - S::from_f64(head_offset_bar);
+    let mut raw_rate = (connection_mobility * drawdown) * wi_geom;
+    if non_darcy_coefficient > 0.0 {
+        // An injector connection carries only the injected gas.
+        let gas_mobility = if injector {
+            connection_mobility
+        } else {
+            mob.gas.max_floor(0.0)
+        };
+        let darcy_gas_rate_sc = (gas_mobility * drawdown) * wi_geom / props.bg.max_floor(1e-9);
+        let flowing_gas_rate_sc = if injector {
+            -darcy_gas_rate_sc
+        } else {
+            darcy_gas_rate_sc
+        };
+        raw_rate = raw_rate * non_darcy_rate_factor(flowing_gas_rate_sc * non_darcy_coefficient);
+    }
+    raw_rate
+}
+
 /// Generic mirror of `wells::perforation_component_rate_derivatives_sc_day`'s
 /// per-phase coefficient — the linear-in-`q` factor `component_rate[phase] =
 /// coefficient[phase] * q` for a frozen cell state.
@@ -237,12 +290,13 @@ pub(crate) fn component_rate_coefficients_generic<S: Scalar>(
 /// `component_rate_coefficients_generic` used for the mass-balance source
 /// term: here `q`'s "wrong-sign" contribution is truncated to zero before
 /// converting to surface volume, matching the production formula exactly —
-/// `(-q).max(0.0) / b_w_or_bg` for an injector, `q.max(0.0) * oil_fraction /
-/// oil_fvf` for a producer.
+/// `(-q).max(0.0) / b_w_or_bg` for an injector, `q.max(0.0)` times the
+/// `surface_phase` share of the surface coefficients for a producer.
 pub(crate) fn perforation_surface_rate_generic<S: Scalar>(
     sim: &ReservoirSimulator,
     injector: bool,
     injected_fluid: InjectedFluid,
+    surface_phase: SurfaceRatePhase,
     cell: &WellCellInput<S>,
     fractions: Option<&ProducerFractionsGeneric<S>>,
     q: S,
@@ -265,7 +319,13 @@ pub(crate) fn perforation_surface_rate_generic<S: Scalar>(
     }
 
     let fractions = fractions.expect("producer surface rate requires aggregated fractions");
-    q.max_floor(0.0) * fractions.oil_fraction / props.bo.max_floor(1e-9)
+    let oil = fractions.oil_fraction / props.bo.max_floor(1e-9);
+    let coefficients = [
+        fractions.water_fraction * sim.water_inverse_fvf_generic(cell.p),
+        oil,
+        fractions.gas_fraction / props.bg.max_floor(1e-9) + oil * props.rs,
+    ];
+    q.max_floor(0.0) * surface_phase.rate(coefficients)
 }
 
 /// Generic mirror of `FimWellLocalBlock::total_rate_from_unknowns`'s
@@ -285,34 +345,92 @@ pub(crate) struct WellPerforationInputGeneric<S> {
     /// Producer perforations only (mirrors `producer_control_state`, computed
     /// per-perforation from that perforation's own control neighborhood).
     pub(crate) fractions: Option<ProducerFractionsGeneric<S>>,
+    /// Wellbore node mixture a multi-segment producer's completion puts back
+    /// into its cell when `q < 0` (mirrors `wells::perforation_producer_state`).
+    /// `None` keeps the connection producing only, clamped like any other.
+    pub(crate) backflow: Option<ProducerFractionsGeneric<S>>,
     pub(crate) q: S,
 }
 
+impl<S: Scalar> WellPerforationInputGeneric<S> {
+    /// Fractions of the fluid crossing the connection at its current `q`.
+    fn crossing_fractions(&self) -> Option<&ProducerFractionsGeneric<S>> {
+        match &self.backflow {
+            Some(backflow) if self.q.value() < 0.0 => Some(backflow),
+            _ => self.fractions.as_ref(),
+        }
+    }
+}
+
+/// One perforation's `[water, oil, gas]` surface rates with the mass-balance
+/// source-term sign (`component_rate_coefficients_generic` times `q`).
+pub(crate) fn perforation_source_rates_generic<S: Scalar>(
+    sim: &ReservoirSimulator,
+    injector: bool,
+    injected_fluid: InjectedFluid,
+    perf: &WellPerforationInputGeneric<S>,
+) -> [S; 3] {
+    component_rate_coefficients_generic(
+        sim,
+        injector,
+        injected_fluid,
+        &perf.cell,
+        perf.crossing_fractions(),
+    )
+    .map(|coefficient| coefficient * perf.q)
+}
+
+/// One perforation's share of `well_actual_rate_generic`: its clamped surface
+/// rate of `surface_target`, or reservoir-volume rate when that is `None`. A
+/// completion that can take backflow counts signed, so crossflow nets out of
+/// the well rate.
+pub(crate) fn perforation_control_rate_generic<S: Scalar>(
+    sim: &ReservoirSimulator,
+    injector: bool,
+    injected_fluid: InjectedFluid,
+    surface_target: Option<SurfaceRatePhase>,
+    perf: &WellPerforationInputGeneric<S>,
+) -> S {
+    if perf.backflow.is_some() {
+        return match surface_target {
+            Some(surface_phase) => surface_phase.rate(perforation_source_rates_generic(
+                sim,
+                injector,
+                injected_fluid,
+                perf,
+            )),
+            None => perf.q,
+        };
+    }
+    if let Some(surface_phase) = surface_target {
+        perforation_surface_rate_generic(
+            sim,
+            injector,
+            injected_fluid,
+            surface_phase,
+            &perf.cell,
+            perf.fractions.as_ref(),
+            perf.q,
+        )
+    } else {
+        perforation_reservoir_rate_generic(injector, perf.q)
+    }
+}
+
 /// Generic mirror of `FimWellLocalBlock::total_rate_from_unknowns`: sums each
-/// perforation's clamped surface- or reservoir-volume rate across the whole
-/// physical well.
+/// perforation's `perforation_control_rate_generic` across the whole physical
+/// well.
 pub(crate) fn well_actual_rate_generic<S: Scalar>(
     sim: &ReservoirSimulator,
     injector: bool,
     injected_fluid: InjectedFluid,
-    uses_surface_target: bool,
+    surface_target: Option<SurfaceRatePhase>,
     perforations: &[WellPerforationInputGeneric<S>],
 ) -> S {
     let mut total = S::from_f64(0.0);
     for perf in perforations {
         total = total
-            + if uses_surface_target {
-                perforation_surface_rate_generic(
-                    sim,
-                    injector,
-                    injected_fluid,
-                    &perf.cell,
-                    perf.fractions.as_ref(),
-                    perf.q,
-                )
-            } else {
-                perforation_reservoir_rate_generic(injector, perf.q)
-            };
+            + perforation_control_rate_generic(sim, injector, injected_fluid, surface_target, perf);
     }
     total
 }
@@ -334,6 +452,54 @@ pub(crate) struct WellControlValuesGeneric {
     pub(crate) target_rate: Option<f64>,
     pub(crate) bhp_limit: f64,
     pub(crate) bhp_target: f64,
+    pub(crate) thp: Option<ThpControl>,
+    pub(crate) surface_phase: SurfaceRatePhase,
+}
+
+impl WellControlValuesGeneric {
+    /// Phase of a surface-rate target, `None` for a reservoir-volume one.
+    pub(crate) fn surface_target(&self) -> Option<SurfaceRatePhase> {
+        self.uses_surface_target.then_some(self.surface_phase)
+    }
+}
+
+/// Surface rates `[water, oil, gas]` the well's perforations carry, with the
+/// mass-balance source-term sign (positive produced, negative injected).
+fn well_source_surface_rates_generic<S: Scalar>(
+    sim: &ReservoirSimulator,
+    injector: bool,
+    injected_fluid: InjectedFluid,
+    perforations: &[WellPerforationInputGeneric<S>],
+) -> [S; 3] {
+    let mut rates = [S::from_f64(0.0); 3];
+    for perf in perforations {
+        let perforation_rates =
+            perforation_source_rates_generic(sim, injector, injected_fluid, perf);
+        for (rate, perforation_rate) in rates.iter_mut().zip(perforation_rates) {
+            *rate = *rate + perforation_rate;
+        }
+    }
+    rates
+}
+
+/// Residual `bhp - vfp(rates)` of a THP-controlled well, the VFP BHP held at
+/// the BHP limit. Rates enter as delivered (produced, or injected) surface
+/// rates, so the BHP-THP coupling is implicit in every rate's unknowns.
+fn thp_constraint_residual_generic<S: Scalar>(
+    sim: &ReservoirSimulator,
+    injector: bool,
+    thp: ThpControl,
+    bhp_limit: f64,
+    bhp: S,
+    source_rates: [S; 3],
+) -> Option<S> {
+    let table = sim.vfp_table(thp.vfp_table)?;
+    let delivered = if injector {
+        source_rates.map(|rate| -rate)
+    } else {
+        source_rates
+    };
+    Some(bhp - table.limited_bhp_generic(delivered, thp.thp_bar, bhp_limit))
 }
 
 /// Generic mirror of `FimWellLocalBlock::constraint_residual`: BHP-controlled
@@ -350,6 +516,10 @@ pub(crate) fn well_constraint_residual_fb_generic<S: Scalar>(
     bhp: S,
     perforations: &[WellPerforationInputGeneric<S>],
 ) -> Option<S> {
+    if let Some(thp) = control.thp.filter(|_| control.enabled) {
+        let rates = well_source_surface_rates_generic(sim, injector, injected_fluid, perforations);
+        return thp_constraint_residual_generic(sim, injector, thp, control.bhp_limit, bhp, rates);
+    }
     if !control.enabled || !control.rate_controlled {
         return Some(bhp - control.bhp_target);
     }
@@ -359,7 +529,7 @@ pub(crate) fn well_constraint_residual_fb_generic<S: Scalar>(
         sim,
         injector,
         injected_fluid,
-        control.uses_surface_target,
+        control.surface_target(),
         perforations,
     );
     let bhp_slack = if injector {
@@ -449,6 +619,7 @@ fn cell_as_ad4(cell: &WellCellInput<f64>) -> WellCellInput<Ad<4>> {
 pub(crate) fn rate_consistency_cell_bhp_jacobian(
     sim: &ReservoirSimulator,
     wi_geom: f64,
+    non_darcy_coefficient: f64,
     head_offset_bar: f64,
     injector: bool,
     cell: &WellCellInput<f64>,
@@ -456,8 +627,15 @@ pub(crate) fn rate_consistency_cell_bhp_jacobian(
 ) -> ([f64; 3], f64) {
     let cell_ad = cell_as_ad4(cell);
     let bhp_ad = Ad::<4>::variable(bhp, 3);
-    let connection =
-        connection_rate_generic(sim, wi_geom, head_offset_bar, injector, &cell_ad, bhp_ad);
+    let connection = connection_rate_generic(
+        sim,
+        wi_geom,
+        non_darcy_coefficient,
+        head_offset_bar,
+        injector,
+        &cell_ad,
+        bhp_ad,
+    );
     let d = connection.deriv();
     ([-d[0], -d[1], -d[2]], -d[3])
 }
@@ -551,7 +729,7 @@ pub(crate) fn well_constraint_bhp_column_and_fb_gradient(
         sim,
         injector,
         injected_fluid,
-        control.uses_surface_target,
+        control.surface_target(),
         perforations,
     );
     let bhp_slack = if injector {
@@ -571,6 +749,30 @@ pub(crate) fn well_constraint_bhp_column_and_fb_gradient(
     Some((bhp_column, dphi_db, rate_scale))
 }
 
+/// Derivative of a THP-controlled well's constraint row w.r.t. each of the
+/// well's summed `[water, oil, gas]` source-term rates, seeded as `Ad<3>`.
+/// Chained with `mass_balance_own_jacobian` / `mass_balance_neighbor_jacobian`
+/// per perforation it gives the row's cell and `q` columns; the BHP column is
+/// exactly `1.0`.
+pub(crate) fn thp_constraint_source_rate_gradient(
+    sim: &ReservoirSimulator,
+    injector: bool,
+    injected_fluid: InjectedFluid,
+    thp: ThpControl,
+    bhp_limit: f64,
+    perforations: &[WellPerforationInputGeneric<f64>],
+) -> Option<[f64; 3]> {
+    let rates = well_source_surface_rates_generic(sim, injector, injected_fluid, perforations);
+    let seeded = [
+        Ad::<3>::variable(rates[0], 0),
+        Ad::<3>::variable(rates[1], 1),
+        Ad::<3>::variable(rates[2], 2),
+    ];
+    let residual =
+        thp_constraint_residual_generic(sim, injector, thp, bhp_limit, Ad::constant(0.0), seeded)?;
+    Some(*residual.deriv())
+}
+
 /// One perforation's own contribution to the well constraint row's Jacobian
 /// (`-dphi_db/rate_scale * d(actual_rate)/d(theta)` restricted to that
 /// perforation's own `[p, sw, hydrocarbon_var, q]`), via
@@ -579,7 +781,7 @@ pub(crate) fn well_constraint_own_perforation_rate_jacobian(
     sim: &ReservoirSimulator,
     injector: bool,
     injected_fluid: InjectedFluid,
-    uses_surface_target: bool,
+    surface_target: Option<SurfaceRatePhase>,
     cell: &WellCellInput<f64>,
     producer_neighborhood: Option<(&[WellCellInput<f64>], usize)>,
     q: f64,
@@ -587,13 +789,14 @@ pub(crate) fn well_constraint_own_perforation_rate_jacobian(
     let cell_ad = cell_as_ad4(cell);
     let q_ad = Ad::<4>::variable(q, 3);
 
-    let rate = if uses_surface_target {
+    let rate = if let Some(surface_phase) = surface_target {
         let fractions_ad =
             fractions_with_connected_cell_active(sim, cell_ad, producer_neighborhood);
         perforation_surface_rate_generic(
             sim,
             injector,
             injected_fluid,
+            surface_phase,
             &cell_ad,
             fractions_ad.as_ref(),
             q_ad,
@@ -607,9 +810,10 @@ pub(crate) fn well_constraint_own_perforation_rate_jacobian(
 
 /// One perforation's cross-term contribution to the well constraint row's
 /// Jacobian w.r.t. an OTHER (non-connected) cell in its control neighborhood
-/// (producers with a surface-rate target only).
+/// (producers with a surface-rate target of `surface_phase` only).
 pub(crate) fn well_constraint_neighbor_rate_jacobian(
     sim: &ReservoirSimulator,
+    surface_phase: SurfaceRatePhase,
     cell: &WellCellInput<f64>,
     neighborhood: &[WellCellInput<f64>],
     neighbor_idx: usize,
@@ -626,17 +830,26 @@ pub(crate) fn well_constraint_neighbor_rate_jacobian(
     let (_fractions, frac_block) =
         producer_fractions_neighbor_block(sim, neighborhood, neighbor_idx);
     let bo = props.bo.max(1e-9);
+    let bg = props.bg.max(1e-9);
+    let inv_bw = sim.water_inverse_fvf(cell.p);
     let q_clamped = q.max(0.0);
 
     let mut d = [0.0; 3];
     for v in 0..3 {
-        d[v] = (q_clamped / bo) * frac_block[1][v];
+        let oil = frac_block[1][v] / bo;
+        let components = [
+            frac_block[0][v] * inv_bw,
+            oil,
+            frac_block[2][v] / bg + oil * props.rs,
+        ];
+        d[v] = q_clamped * surface_phase.rate(components);
     }
     d
 }
 
 /// Packed 5-row residual `[rate_consistency, well_constraint, water_source,
 /// oil_source, gas_source]` for one single-perforation well, generic over `S`.
+/// The connection is taken as Darcy.
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn perforation_residual_generic<S: Scalar>(
     sim: &ReservoirSimulator,
@@ -651,10 +864,11 @@ pub(crate) fn perforation_residual_generic<S: Scalar>(
     control: &WellControlValuesGeneric,
 ) -> [S; 5] {
     let rate_consistency =
-        q - connection_rate_generic(sim, wi_geom, head_offset_bar, injector, cell, bhp);
+        q - connection_rate_generic(sim, wi_geom, 0.0, head_offset_bar, injector, cell, bhp);
     let solo = [WellPerforationInputGeneric {
         cell: *cell,
         fractions: fractions.copied(),
+        backflow: None,
         q,
     }];
     let constraint =
@@ -831,6 +1045,8 @@ mod tests {
             target_rate: None,
             bhp_limit: target_bhp,
             bhp_target: target_bhp,
+            thp: None,
+            surface_phase: SurfaceRatePhase::Oil,
         }
     }
 
@@ -846,6 +1062,8 @@ mod tests {
             target_rate: Some(target),
             bhp_limit,
             bhp_target: bhp_limit,
+            thp: None,
+            surface_phase: SurfaceRatePhase::Oil,
         }
     }
 
@@ -1098,7 +1316,7 @@ mod tests {
         let cell_idx = perforation.cell_index;
         let cell_state = state.cell(cell_idx);
         let wi_geom = geometric_well_index(&sim, perforation).expect("finite transmissibility");
-        let injected_fluid = wells::effective_injected_fluid(&sim);
+        let injected_fluid = wells::effective_injected_fluid(&sim, perforation);
         let control = physical_well_control(&sim, &topology, well_idx);
         let bhp = state.well_bhp[well_idx];
         let q = state.perforation_primaries[0].value;
@@ -1125,6 +1343,8 @@ mod tests {
             target_rate: control.target_rate,
             bhp_limit: control.bhp_limit,
             bhp_target: control.bhp_target,
+            thp: control.thp,
+            surface_phase: control.surface_phase,
         };
 
         // Rate-consistency row. `connection_rate_generic` is infallible
@@ -1135,7 +1355,8 @@ mod tests {
         let generic_rate_residual = q - connection_rate_generic(
             &sim,
             wi_geom,
-            wells::perforation_head_offset_bar(&sim, perforation),
+            0.0,
+            wells::perforation_head_offset_bar(&sim, &state, &topology, 0),
             injector,
             &cell,
             bhp,
@@ -1149,7 +1370,12 @@ mod tests {
 
         // Well constraint row.
         let real_constraint = wells::well_constraint_residual(&sim, &state, &topology, well_idx);
-        let solo = [WellPerforationInputGeneric { cell, fractions, q }];
+        let solo = [WellPerforationInputGeneric {
+            cell,
+            fractions,
+            backflow: None,
+            q,
+        }];
         let generic_constraint = well_constraint_residual_fb_generic(
             &sim,
             injector,
@@ -11,6 +11,8 @@
 //! verifies this by construction: the local residual/Jacobian entries must exactly match the
 //! corresponding rows/columns of a full `assemble_fim_system_ad` call.
 
+use std::collections::HashMap;
+
 use nalgebra::{DMatrix, DVector};
 
 use crate::ReservoirSimulator;
@@ -18,17 +20,19 @@ use crate::fim::assembly_ad::{
     flow_resv_terms_ad, flow_resv_terms_f64, well_cell_input, well_control_generic,
 };
 use crate::fim::flow_resv::FlowResvReportStepContext;
-use crate::fim::state::FimState;
+use crate::fim::state::{FimState, SEGMENT_BLOCK_SIZE};
 use crate::fim::wells::{
-    FimWellTopology, connection_rate_for_bhp, effective_injected_fluid, geometric_well_index,
-    perforation_head_offset_bar, perforation_local_block, physical_well_control,
+    FimWellTopology, connection_rate_for_bhp, geometric_well_index, perforation_head_offset_bar,
+    perforation_local_block, perforation_non_darcy_coefficient, physical_well_control,
+    physical_well_injected_fluid,
 };
 use crate::fim::wells_ad::{
-    WellCellInput, WellPerforationInputGeneric, connection_rate_generic,
+    WellCellInput, WellPerforationInputGeneric, connection_rate_generic, mass_balance_own_jacobian,
     producer_fractions_generic, rate_consistency_cell_bhp_jacobian,
-    well_constraint_bhp_column_and_fb_gradient, well_constraint_own_perforation_rate_jacobian,
-    well_constraint_residual_fb_generic,
+    thp_constraint_source_rate_gradient, well_constraint_bhp_column_and_fb_gradient,
+    well_constraint_own_perforation_rate_jacobian, well_constraint_residual_fb_generic,
 };
+use crate::fim::wells_segments::multi_segment_well_terms;
 
 /// One physical well's local residual/Jacobian, evaluated at the frozen reservoir cell state
 /// carried by `state`. Local row/unknown ordering: index `0` is the well's `well_constraint`
@@ -36,12 +40,15 @@ use crate::fim::wells_ad::{
 /// equation (unknown `q`), in the same order as `perforation_indices`. This ordering is local
 /// to this struct — callers map back to global offsets via `state.well_equation_offset`/
 /// `state.perforation_equation_offset` using `well_idx`/`perforation_indices`, not by assuming
-/// any relationship to the global unknown layout.
+/// any relationship to the global unknown layout. A multi-segment well follows them with its
+/// segments' rows (unknowns `FimSegmentState` fields), `SEGMENT_BLOCK_SIZE` per entry of
+/// `segment_indices`.
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct FimWellLocalSystem {
     pub(crate) residual: DVector<f64>,
     pub(crate) jacobian: DMatrix<f64>,
     pub(crate) perforation_indices: Vec<usize>,
+    pub(crate) segment_indices: Vec<usize>,
 }
 
 impl FimWellLocalSystem {
@@ -49,7 +56,12 @@ impl FimWellLocalSystem {
     /// `residual`/`jacobian` through the global offsets instead.
     #[cfg(test)]
     pub(crate) fn dim(&self) -> usize {
-        1 + self.perforation_indices.len()
+        1 + self.perforation_indices.len() + self.segment_indices.len() * SEGMENT_BLOCK_SIZE
+    }
+
+    /// Local index of segment `local_segment`'s unknown (and row) `local`.
+    fn segment_offset(&self, local_segment: usize, local: usize) -> usize {
+        1 + self.perforation_indices.len() + local_segment * SEGMENT_BLOCK_SIZE + local
     }
 }
 
@@ -95,8 +107,11 @@ pub(crate) fn assemble_well_local_system(
     topology: &FimWellTopology,
     well_idx: usize,
 ) -> FimWellLocalSystem {
-    let injected_fluid = effective_injected_fluid(sim);
+    if !topology.wells[well_idx].segment_indices.is_empty() {
+        return assemble_multi_segment_well_local_system(sim, state, topology, well_idx);
+    }
     let injector = topology.wells[well_idx].injector;
+    let injected_fluid = physical_well_injected_fluid(sim, topology, well_idx);
     let control_real = physical_well_control(sim, topology, well_idx);
     let control = well_control_generic(&control_real);
     let bhp = state.well_bhp[well_idx];
@@ -111,6 +126,7 @@ pub(crate) fn assemble_well_local_system(
     let mut neighborhoods: Vec<Vec<WellCellInput<f64>>> = Vec::with_capacity(n_perf);
     let mut connected_indices: Vec<usize> = Vec::with_capacity(n_perf);
     let mut wi_geoms: Vec<Option<f64>> = Vec::with_capacity(n_perf);
+    let mut non_darcy_coefficients: Vec<f64> = Vec::with_capacity(n_perf);
     let mut head_offsets: Vec<f64> = Vec::with_capacity(n_perf);
 
     for &perf_idx in &perforation_indices {
@@ -132,12 +148,18 @@ pub(crate) fn assemble_well_local_system(
             .unwrap_or(0);
         let fractions = (!injector).then(|| producer_fractions_generic::<f64>(sim, &neighborhood));
 
-        well_perf_inputs.push(WellPerforationInputGeneric { cell, fractions, q });
+        well_perf_inputs.push(WellPerforationInputGeneric {
+            cell,
+            fractions,
+            backflow: None,
+            q,
+        });
         cells.push(cell);
         neighborhoods.push(neighborhood);
         connected_indices.push(connected_index);
         wi_geoms.push(geometric_well_index(sim, perforation));
-        head_offsets.push(perforation_head_offset_bar(sim, perforation));
+        non_darcy_coefficients.push(perforation_non_darcy_coefficient(sim, perforation));
+        head_offsets.push(perforation_head_offset_bar(sim, state, topology, perf_idx));
     }
 
     let mut residual = DVector::zeros(dim);
@@ -156,7 +178,39 @@ pub(crate) fn assemble_well_local_system(
     ) {
         residual[0] = value;
     }
-    if !control_real.enabled || !control_real.rate_controlled {
+    if let Some(thp) = control_real.thp.filter(|_| control_real.enabled) {
+        jacobian[(0, 0)] = 1.0;
+        if let Some(gradient) = thp_constraint_source_rate_gradient(
+            sim,
+            injector,
+            injected_fluid,
+            thp,
+            control_real.bhp_limit,
+            &well_perf_inputs,
+        ) {
+            for (local_perf, &perf_idx) in perforation_indices.iter().enumerate() {
+                let q = state
+                    .reservoir_connection_q(perf_idx)
+                    .expect("nested well solve requires a reservoir-q primary");
+                let producer_neighborhood = (!injector).then_some((
+                    neighborhoods[local_perf].as_slice(),
+                    connected_indices[local_perf],
+                ));
+                let own = mass_balance_own_jacobian(
+                    sim,
+                    injector,
+                    injected_fluid,
+                    &cells[local_perf],
+                    producer_neighborhood,
+                    q,
+                );
+                // Only the `q` column: cell columns are frozen inputs here.
+                jacobian[(0, 1 + local_perf)] += (0..3)
+                    .map(|phase| gradient[phase] * own[phase][3])
+                    .sum::<f64>();
+            }
+        }
+    } else if !control_real.enabled || !control_real.rate_controlled {
         jacobian[(0, 0)] = 1.0;
     } else if let Some((bhp_col_value, dphi_db, rate_scale)) =
         well_constraint_bhp_column_and_fb_gradient(
@@ -182,7 +236,7 @@ pub(crate) fn assemble_well_local_system(
                 sim,
                 injector,
                 injected_fluid,
-                control_real.uses_surface_target,
+                control_real.surface_target(),
                 &cells[local_perf],
                 producer_neighborhood,
                 q,
@@ -213,6 +267,7 @@ pub(crate) fn assemble_well_local_system(
         let connection = connection_rate_generic(
             sim,
             wi_geom,
+            non_darcy_coefficients[local_perf],
             head_offsets[local_perf],
             injector,
             &cells[local_perf],
@@ -223,6 +278,7 @@ pub(crate) fn assemble_well_local_system(
         let (_cell_derivs, dbhp) = rate_consistency_cell_bhp_jacobian(
             sim,
             wi_geom,
+            non_darcy_coefficients[local_perf],
             head_offsets[local_perf],
             injector,
             &cells[local_perf],
@@ -235,7 +291,61 @@ pub(crate) fn assemble_well_local_system(
         residual,
         jacobian,
         perforation_indices,
+        segment_indices: Vec::new(),
+    }
+}
+
+/// `assemble_well_local_system` for a multi-segment well: the well rows of the same
+/// `multi_segment_well_terms` the global assembler scatters, restricted to the well's own
+/// unknowns. Cell source rows and cell columns are frozen reservoir input here.
+fn assemble_multi_segment_well_local_system(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    topology: &FimWellTopology,
+    well_idx: usize,
+) -> FimWellLocalSystem {
+    let well = &topology.wells[well_idx];
+    let mut local = FimWellLocalSystem {
+        residual: DVector::zeros(0),
+        jacobian: DMatrix::zeros(0, 0),
+        perforation_indices: well.perforation_indices.clone(),
+        segment_indices: well.segment_indices.clone(),
+    };
+    let dim = local.segment_offset(well.segment_indices.len(), 0);
+    local.residual = DVector::zeros(dim);
+    local.jacobian = DMatrix::zeros(dim, dim);
+
+    // Equation and unknown offsets coincide for the well tail, so one map serves both.
+    let mut local_indices = HashMap::with_capacity(dim);
+    local_indices.insert(state.well_bhp_unknown_offset(well_idx), 0);
+    for (local_perf, &perf_idx) in well.perforation_indices.iter().enumerate() {
+        local_indices.insert(
+            state.perforation_rate_unknown_offset(perf_idx),
+            1 + local_perf,
+        );
     }
+    for (local_segment, &segment_idx) in well.segment_indices.iter().enumerate() {
+        for unknown in 0..SEGMENT_BLOCK_SIZE {
+            local_indices.insert(
+                state.segment_unknown_offset(segment_idx, unknown),
+                local.segment_offset(local_segment, unknown),
+            );
+        }
+    }
+    let local_index = |offset: usize| local_indices.get(&offset).copied();
+
+    for term in multi_segment_well_terms(sim, state, topology, well_idx, 0.0) {
+        let Some(row) = local_index(term.row) else {
+            continue;
+        };
+        local.residual[row] += term.value;
+        for (column, value) in term.columns {
+            if let Some(column) = local_index(column) {
+                local.jacobian[(row, column)] += value;
+            }
+        }
+    }
+    local
 }
 
 /// OPM-verified inner-solve budget/tolerance/chop defaults (`.archive/docs/FIM_BUNDLE_W_PLAN.md` W0
@@ -493,15 +603,24 @@ fn well_convergence_status(
         );
         scaled_peak = scaled_peak.max(local.residual[1 + local_perf].abs() / perf_scale);
     }
+    for (local_segment, &segment_idx) in local.segment_indices.iter().enumerate() {
+        let scales = crate::fim::scaling::segment_scales(&state.segments[segment_idx]);
+        for (row, scale) in scales.into_iter().enumerate() {
+            scaled_peak = scaled_peak
+                .max(local.residual[local.segment_offset(local_segment, row)].abs() / scale);
+        }
+    }
 
+    // Crossflow through a multi-segment wellbore is a legitimate direction.
     let direction_ok = !pressure_controlled
         || local.perforation_indices.iter().all(|&perf_idx| {
-            perforation_flow_direction_ok(
-                injector,
-                state
-                    .reservoir_connection_q(perf_idx)
-                    .expect("nested well solve requires a reservoir-q primary"),
-            )
+            topology.perforations[perf_idx].takes_crossflow()
+                || perforation_flow_direction_ok(
+                    injector,
+                    state
+                        .reservoir_connection_q(perf_idx)
+                        .expect("nested well solve requires a reservoir-q primary"),
+                )
         });
 
     FimWellConvergenceStatus {
@@ -552,7 +671,7 @@ pub(crate) fn solve_well_locally(
         }
 
         let neg_residual = -local.residual.clone();
-        let Some(delta) = local.jacobian.lu().solve(&neg_residual) else {
+        let Some(delta) = local.jacobian.clone().lu().solve(&neg_residual) else {
             // Singular local Jacobian: cannot proceed. Keep the last iterate, report
             // not-converged (see doc comment above — do not paper over this).
             break;
@@ -567,6 +686,14 @@ pub(crate) fn solve_well_locally(
                 .expect("nested well solve requires a reservoir-q primary") +=
                 delta[1 + local_perf];
         }
+        for (local_segment, &segment_idx) in local.segment_indices.iter().enumerate() {
+            let segment = &mut state.segments[segment_idx];
+            segment.pressure_bar += delta[local.segment_offset(local_segment, 0)];
+            segment.flow_m3_day += delta[local.segment_offset(local_segment, 1)];
+            segment.water_fraction += delta[local.segment_offset(local_segment, 2)];
+            segment.gas_fraction += delta[local.segment_offset(local_segment, 3)];
+        }
+        state.enforce_segment_bounds();
 
         iterations += 1;
     }
@@ -825,6 +952,110 @@ mod tests {
         }
     }
 
+    fn multi_segment_producer() -> ReservoirSimulator {
+        let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+        sim.set_fim_enabled(true);
+        sim.set_cell_dimensions(100.0, 20.0, 10.0).unwrap();
+        sim.set_permeability_random(200.0, 200.0).unwrap();
+        for i in 0..3 {
+            sim.add_directional_well(
+                i,
+                0,
+                0,
+                "x".to_string(),
+                100.0,
+                0.1,
+                0.0,
+                false,
+                "P".to_string(),
+            )
+            .unwrap();
+        }
+        sim.set_multi_segment_well("P".to_string(), 0.05, 1e-5, Vec::new())
+            .unwrap();
+        sim
+    }
+
+    /// A multi-segment well's local system is the global well tail: BHP, completion rates and
+    /// every segment unknown, with the same values in both.
+    #[test]
+    fn local_system_matches_global_assembly_multi_segment() {
+        let sim = multi_segment_producer();
+        let mut state = FimState::from_simulator(&sim);
+        for (idx, primary) in state.perforation_primaries.iter_mut().enumerate() {
+            primary.value += 50.0 * (idx + 1) as f64;
+        }
+        state.segments[1].pressure_bar += 3.0;
+        state.segments[2].water_fraction = 0.4;
+        let topology = build_well_topology(&sim);
+
+        let local = assemble_well_local_system(&sim, &state, &topology, 0);
+        assert_eq!(local.segment_indices, vec![0, 1, 2]);
+        assert_eq!(local.dim(), 1 + 3 + 3 * SEGMENT_BLOCK_SIZE);
+
+        let options = FimAssemblyOptions {
+            dt_days: 0.1,
+            include_wells: true,
+            assemble_residual_only: false,
+            topology: Some(&topology),
+            flow_resv_context: None,
+        };
+        let global = assemble_fim_system_ad(&sim, &state, &state, &options);
+        let tail_start = state.well_bhp_unknown_offset(0);
+        assert_eq!(global.residual.len() - tail_start, local.dim());
+        for row in 0..local.dim() {
+            assert_eq!(
+                local.residual[row],
+                global.residual[tail_start + row],
+                "tail row {row} residual diverges from global assembly"
+            );
+            for column in 0..local.dim() {
+                assert_jacobian_entry_matches(
+                    local.jacobian[(row, column)],
+                    global
+                        .jacobian
+                        .get(tail_start + row, tail_start + column)
+                        .copied()
+                        .unwrap_or(0.0),
+                    &format!("tail ({row}, {column})"),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn multi_segment_well_converges_from_perturbed_nodes() {
+        let sim = multi_segment_producer();
+        let mut state = FimState::from_simulator(&sim);
+        for segment in state.segments.iter_mut() {
+            segment.pressure_bar += 20.0;
+            segment.flow_m3_day = 0.0;
+            segment.water_fraction = 0.5;
+        }
+        let topology = build_well_topology(&sim);
+
+        let report = solve_well_locally(
+            &sim,
+            &mut state,
+            &topology,
+            0,
+            &FimWellInnerSolveOptions::default(),
+        );
+        assert!(report.converged, "multi-segment well: {report:?}");
+        // Node pressure rows are in bar and scaled by the node pressure.
+        let local = assemble_well_local_system(&sim, &state, &topology, 0);
+        assert!(
+            local.residual.iter().all(|r| r.abs() < 1e-3),
+            "local residual not actually small: {:?}",
+            local.residual
+        );
+        let produced: f64 = state.perforation_primaries.iter().map(|p| p.value).sum();
+        assert!(
+            (state.segments[0].flow_m3_day - produced).abs() < 1e-6,
+            "the heel segment carries the whole well's rate"
+        );
+    }
+
     // --- W2: inner Newton loop tests ---
 
     #[test]
@@ -0,0 +1,467 @@
+//! Multi-segment wells in the FIM system.
+//!
+//! Beyond its BHP and completion rates, a multi-segment well (`well_segments`) carries four
+//! unknowns per segment (`FimSegmentState`): the pressure at the segment's completion node,
+//! the flow `Q_s` it carries toward the heel, and the water and gas fractions `α_s` of the
+//! mixture at the node. Each segment owns four rows:
+//!
+//! ```text
+//! pressure:  p_s − p_above − ρ_s·g·Δz_s − F(Q_s, ρ_s, μ_s) = 0
+//! flow:      Q_s − q_s − Q_below = 0
+//! mixture:   α_s − Σ w(inflow)·α_inflow / Σ w(inflow) = 0        (water and gas)
+//! ```
+//!
+//! `p_above` is the BHP for the heel segment, and `ρ_s`, `μ_s` mix the phases at `p_s` by the
+//! node fractions unless the well fixes its wellbore density. A producer's node holds what
+//! flows into it — its completion's mobile cell fluid, the segment below, and the one above
+//! once the flow turns toward the toe — weighted by a smooth positive part of each inflow, so a
+//! shut-in wellbore still has a well-defined mixture. An injector's nodes hold the injected
+//! phase.
+//!
+//! A completion connects its cell to its node rather than to the BHP, and a producer's
+//! connection is not clamped: a layer below the node pressure takes the node mixture back,
+//! which is the crossflow between layers through the wellbore. Every row of the well comes out
+//! of `multi_segment_well_terms`, the one producer shared by the global assembler and the
+//! nested per-well solve. The segment unknowns sit in the well tail, so the linear solvers
+//! absorb them with the rest of the well's unknowns.
+
+use crate::InjectedFluid;
+use crate::ReservoirSimulator;
+use crate::fim::ad::{Ad, Scalar};
+use crate::fim::assembly::{equation_offset, unknown_offset};
+use crate::fim::assembly_ad::{well_cell_input, well_control_generic};
+use crate::fim::properties::cell_props_generic;
+use crate::fim::state::{FimState, SEGMENT_BLOCK_SIZE};
+use crate::fim::wells::{
+    FimWellTopology, geometric_well_index, perforation_non_darcy_coefficient,
+    physical_well_control, physical_well_injected_fluid,
+};
+use crate::fim::wells_ad::{
+    ProducerFractionsGeneric, WellCellInput, WellPerforationInputGeneric, connection_rate_generic,
+    perforation_control_rate_generic, perforation_source_rates_generic, producer_fractions_generic,
+    thp_constraint_source_rate_gradient, unclamped_connection_rate_generic,
+    well_constraint_bhp_column_and_fb_gradient, well_constraint_residual_fb_generic,
+};
+use crate::well_control::GRAVITY_M_S2;
+use crate::well_segments::{MIXTURE_WEIGHT_FLOOR_M3_DAY, SegmentNode, friction_drop_bar};
+
+/// Derivative slots of a completion's terms: its cell's `[p, sw, hydrocarbon_var]`, its rate
+/// `q`, and its node's `[pressure, water fraction, gas fraction]`.
+const COMPLETION_SLOTS: usize = 7;
+
+/// Derivative slots of a segment's rows: its own `[pressure, flow, water, gas]`, the pressure
+/// and `[water, gas]` above it, the `[flow, water, gas]` below it, and its completion's cell
+/// `[p, sw, hydrocarbon_var]` and rate `q`.
+const SEGMENT_SLOTS: usize = 14;
+
+/// One residual row of a multi-segment well with its Jacobian entries, keyed by global
+/// equation and unknown offsets. Entries for the same column may repeat; they add.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct WellRowTerm {
+    pub(crate) row: usize,
+    pub(crate) value: f64,
+    pub(crate) columns: Vec<(usize, f64)>,
+}
+
+impl WellRowTerm {
+    fn new<const N: usize>(row: usize, value: Ad<N>, columns: &[Option<usize>; N]) -> Self {
+        let mut term = Self {
+            row,
+            value: value.value(),
+            columns: Vec::with_capacity(N),
+        };
+        term.add_derivatives(value, columns);
+        term
+    }
+
+    fn add_derivatives<const N: usize>(&mut self, value: Ad<N>, columns: &[Option<usize>; N]) {
+        self.columns.extend(
+            columns
+                .iter()
+                .zip(value.deriv())
+                .filter_map(|(column, &derivative)| column.map(|column| (column, derivative))),
+        );
+    }
+}
+
+/// Every residual row multi-segment well `well_idx` contributes, with its Jacobian: the
+/// perforation and well-constraint rows, its completions' cell source terms (times
+/// `dt_days`), and its segments' rows.
+pub(crate) fn multi_segment_well_terms(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    topology: &FimWellTopology,
+    well_idx: usize,
+    dt_days: f64,
+) -> Vec<WellRowTerm> {
+    let well = &topology.wells[well_idx];
+    let injector = well.injector;
+    let injected_fluid = physical_well_injected_fluid(sim, topology, well_idx);
+    let mut terms = Vec::with_capacity(well.segment_indices.len() * (4 + SEGMENT_BLOCK_SIZE) + 1);
+
+    let mut completions = Vec::with_capacity(well.segment_indices.len());
+    for (&perf_idx, &segment_idx) in well.perforation_indices.iter().zip(&well.segment_indices) {
+        let perforation = &topology.perforations[perf_idx];
+        let cell_idx = perforation.cell_index;
+        let segment = state.segments[segment_idx];
+        let columns = [
+            Some(unknown_offset(cell_idx, 0)),
+            Some(unknown_offset(cell_idx, 1)),
+            Some(unknown_offset(cell_idx, 2)),
+            Some(state.perforation_rate_unknown_offset(perf_idx)),
+            Some(state.segment_unknown_offset(segment_idx, 0)),
+            Some(state.segment_unknown_offset(segment_idx, 2)),
+            Some(state.segment_unknown_offset(segment_idx, 3)),
+        ];
+        let cell = seeded_cell(well_cell_input(sim, state, cell_idx), 0);
+        let q = Ad::<COMPLETION_SLOTS>::variable(
+            state
+                .reservoir_connection_q(perf_idx)
+                .expect("multi-segment well requires a reservoir-q primary"),
+            3,
+        );
+        let node_pressure = Ad::variable(segment.pressure_bar, 4);
+        let node_water = Ad::variable(segment.water_fraction, 5);
+        let node_gas = Ad::variable(segment.gas_fraction, 6);
+
+        if let Some(wi_geom) = geometric_well_index(sim, perforation) {
+            let non_darcy_coefficient = perforation_non_darcy_coefficient(sim, perforation);
+            let connection = if injector {
+                connection_rate_generic(
+                    sim,
+                    wi_geom,
+                    non_darcy_coefficient,
+                    0.0,
+                    true,
+                    &cell,
+                    node_pressure,
+                )
+            } else {
+                unclamped_connection_rate_generic(
+                    sim,
+                    wi_geom,
+                    non_darcy_coefficient,
+                    0.0,
+                    false,
+                    &cell,
+                    node_pressure,
+                )
+            };
+            terms.push(WellRowTerm::new(
+                state.perforation_equation_offset(perf_idx),
+                q - connection,
+                &columns,
+            ));
+        }
+
+        // A completion's control window is its own cell (`perforation_control_cells`).
+        let completion = WellPerforationInputGeneric {
+            cell,
+            fractions: (!injector).then(|| producer_fractions_generic(sim, &[cell])),
+            backflow: (!injector).then(|| ProducerFractionsGeneric {
+                water_fraction: node_water,
+                oil_fraction: 1.0 - node_water - node_gas,
+                gas_fraction: node_gas,
+            }),
+            q,
+        };
+        let sources = perforation_source_rates_generic(sim, injector, injected_fluid, &completion);
+        for (local_eq, source) in sources.into_iter().enumerate() {
+            terms.push(WellRowTerm::new(
+                equation_offset(cell_idx, local_eq),
+                source * dt_days,
+                &columns,
+            ));
+        }
+        completions.push((completion, columns));
+    }
+
+    terms.push(well_constraint_term(
+        sim,
+        state,
+        topology,
+        well_idx,
+        &completions,
+    ));
+
+    let group: Vec<usize> = well
+        .perforation_indices
+        .iter()
+        .map(|&perf_idx| topology.perforations[perf_idx].well_entry_index)
+        .collect();
+    let nodes = sim
+        .multi_segment_nodes(&group)
+        .expect("segmented perforations form a multi-segment well");
+    let explicit_density = sim.explicit_wellbore_density(&group);
+    for (position, node) in nodes.iter().enumerate() {
+        terms.extend(segment_terms(
+            sim,
+            state,
+            topology,
+            well_idx,
+            position,
+            node,
+            explicit_density,
+        ));
+    }
+    terms
+}
+
+/// The well's control row over its completions' rates, chained onto each completion's
+/// `COMPLETION_SLOTS` columns the way `assembly_ad` chains an ordinary well's.
+fn well_constraint_term(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    topology: &FimWellTopology,
+    well_idx: usize,
+    completions: &[(
+        WellPerforationInputGeneric<Ad<COMPLETION_SLOTS>>,
+        [Option<usize>; COMPLETION_SLOTS],
+    )],
+) -> WellRowTerm {
+    let injector = topology.wells[well_idx].injector;
+    let injected_fluid = physical_well_injected_fluid(sim, topology, well_idx);
+    let control_real = physical_well_control(sim, topology, well_idx);
+    let control = well_control_generic(&control_real);
+    let bhp = state.well_bhp[well_idx];
+    let bhp_col = state.well_bhp_unknown_offset(well_idx);
+    let values: Vec<WellPerforationInputGeneric<f64>> = completions
+        .iter()
+        .map(|(completion, _)| completion_values(completion))
+        .collect();
+
+    let mut term = WellRowTerm {
+        row: state.well_equation_offset(well_idx),
+        value: well_constraint_residual_fb_generic(
+            sim,
+            injector,
+            injected_fluid,
+            &control,
+            bhp,
+            &values,
+        )
+        .unwrap_or(0.0),
+        columns: Vec::new(),
+    };
+    if let Some(thp) = control_real.thp.filter(|_| control_real.enabled) {
+        term.columns.push((bhp_col, 1.0));
+        if let Some(gradient) = thp_constraint_source_rate_gradient(
+            sim,
+            injector,
+            injected_fluid,
+            thp,
+            control_real.bhp_limit,
+            &values,
+        ) {
+            for (completion, columns) in completions {
+                let sources =
+                    perforation_source_rates_generic(sim, injector, injected_fluid, completion);
+                let chained = sources
+                    .into_iter()
+                    .zip(gradient)
+                    .fold(Ad::constant(0.0), |sum, (source, slope)| {
+                        sum + source * slope
+                    });
+                term.add_derivatives(chained, columns);
+            }
+        }
+    } else if !control_real.enabled || !control_real.rate_controlled {
+        term.columns.push((bhp_col, 1.0));
+    } else if let Some((bhp_column, dphi_db, rate_scale)) =
+        well_constraint_bhp_column_and_fb_gradient(
+            sim,
+            injector,
+            injected_fluid,
+            &control,
+            bhp,
+            &values,
+        )
+    {
+        term.columns.push((bhp_col, bhp_column));
+        let factor = -dphi_db / rate_scale;
+        for (completion, columns) in completions {
+            let rate = perforation_control_rate_generic(
+                sim,
+                injector,
+                injected_fluid,
+                control_real.surface_target(),
+                completion,
+            );
+            term.add_derivatives(rate * factor, columns);
+        }
+    }
+    term
+}
+
+/// Pressure, flow, and water and gas mixture rows of the segment down to the `position`-th
+/// completion of multi-segment well `well_idx`.
+fn segment_terms(
+    sim: &ReservoirSimulator,
+    state: &FimState,
+    topology: &FimWellTopology,
+    well_idx: usize,
+    position: usize,
+    node: &SegmentNode,
+    explicit_density_kg_m3: Option<f64>,
+) -> [WellRowTerm; SEGMENT_BLOCK_SIZE] {
+    let well = &topology.wells[well_idx];
+    let segment_idx = well.segment_indices[position];
+    let perf_idx = well.perforation_indices[position];
+    let cell_idx = topology.perforations[perf_idx].cell_index;
+    let above = position.checked_sub(1).map(|k| well.segment_indices[k]);
+    let below = well.segment_indices.get(position + 1).copied();
+    let column = |segment: Option<usize>, local: usize| {
+        segment.map(|segment| state.segment_unknown_offset(segment, local))
+    };
+    let columns = [
+        column(Some(segment_idx), 0),
+        column(Some(segment_idx), 1),
+        column(Some(segment_idx), 2),
+        column(Some(segment_idx), 3),
+        column(above, 0).or(Some(state.well_bhp_unknown_offset(well_idx))),
+        column(above, 2),
+        column(above, 3),
+        column(below, 1),
+        column(below, 2),
+        column(below, 3),
+        Some(unknown_offset(cell_idx, 0)),
+        Some(unknown_offset(cell_idx, 1)),
+        Some(unknown_offset(cell_idx, 2)),
+        Some(state.perforation_rate_unknown_offset(perf_idx)),
+    ];
+
+    let seed = |value: f64, slot: usize| Ad::<SEGMENT_SLOTS>::variable(value, slot);
+    let current = state.segments[segment_idx];
+    let pressure = seed(current.pressure_bar, 0);
+    let flow = seed(current.flow_m3_day, 1);
+    let water = seed(current.water_fraction, 2);
+    let gas = seed(current.gas_fraction, 3);
+    let above_state = above.map(|segment| state.segments[segment]);
+    let pressure_above = seed(
+        above_state.map_or(state.well_bhp[well_idx], |segment| segment.pressure_bar),
+        4,
+    );
+    let below_state = below.map(|segment| state.segments[segment]);
+    let flow_below = seed(below_state.map_or(0.0, |segment| segment.flow_m3_day), 7);
+    let cell = seeded_cell(well_cell_input(sim, state, cell_idx), 10);
+    let q = seed(
+        state
+            .reservoir_connection_q(perf_idx)
+            .expect("multi-segment well requires a reservoir-q primary"),
+        13,
+    );
+
+    let oil = 1.0 - water - gas;
+    let rs = cell_props_generic(
+        sim,
+        cell.regime,
+        cell.p,
+        cell.sw,
+        cell.hydrocarbon_var,
+        cell.drsdt0_base_rs,
+    )
+    .rs
+    .max_floor(0.0);
+    let density = match explicit_density_kg_m3 {
+        Some(density) => Ad::constant(density),
+        None => {
+            water * sim.water_density_generic(pressure)
+                + oil * sim.oil_density_generic(pressure, rs)
+                + gas * sim.gas_density_generic(pressure)
+        }
+    };
+    let viscosity = water * sim.get_mu_w(current.pressure_bar)
+        + oil * sim.get_mu_o_for_rs_generic(pressure, rs)
+        + gas * sim.get_mu_g_generic(pressure);
+    let pressure_row = pressure
+        - pressure_above
+        - density * (GRAVITY_M_S2 * node.depth_change_m * 1e-5)
+        - friction_drop_bar(&node.segment, flow, density, viscosity);
+
+    let flow_row = flow - q - flow_below;
+
+    let (water_row, gas_row) = if well.injector {
+        let (injected_water, injected_gas) =
+            match physical_well_injected_fluid(sim, topology, well_idx) {
+                InjectedFluid::Water => (1.0, 0.0),
+                InjectedFluid::Gas => (0.0, 1.0),
+            };
+        (water - injected_water, gas - injected_gas)
+    } else {
+        let cell_fractions = producer_fractions_generic(sim, &[cell]);
+        let mut weight = inflow_weight(q);
+        let mut water_in = weight * cell_fractions.water_fraction;
+        let mut gas_in = weight * cell_fractions.gas_fraction;
+        if let Some(segment) = below_state {
+            let from_below = inflow_weight(flow_below);
+            weight += from_below;
+            water_in += from_below * seed(segment.water_fraction, 8);
+            gas_in += from_below * seed(segment.gas_fraction, 9);
+        }
+        if let Some(segment) = above_state {
+            let from_above = inflow_weight(-flow);
+            weight += from_above;
+            water_in += from_above * seed(segment.water_fraction, 5);
+            gas_in += from_above * seed(segment.gas_fraction, 6);
+        }
+        (water - water_in / weight, gas - gas_in / weight)
+    };
+
+    [pressure_row, flow_row, water_row, gas_row]
+        .into_iter()
+        .enumerate()
+        .map(|(local, value)| {
+            WellRowTerm::new(
+                state.segment_equation_offset(segment_idx, local),
+                value,
+                &columns,
+            )
+        })
+        .collect::<Vec<_>>()
+        .try_into()
+        .expect("one term per segment row")
+}
+
+/// Smooth positive part of a flow [m³/day] into a node, floored so a still wellbore keeps a
+/// mixture.
+fn inflow_weight<const N: usize>(rate_m3_day: Ad<N>) -> Ad<N> {
+    (rate_m3_day
+        + (rate_m3_day * rate_m3_day + MIXTURE_WEIGHT_FLOOR_M3_DAY * MIXTURE_WEIGHT_FLOOR_M3_DAY)
+            .sqrt())
+        * 0.5
+}
+
+fn seeded_cell<const N: usize>(
+    cell: WellCellInput<f64>,
+    first_slot: usize,
+) -> WellCellInput<Ad<N>> {
+    WellCellInput {
+        p: Ad::variable(cell.p, first_slot),
+        sw: Ad::variable(cell.sw, first_slot + 1),
+        hydrocarbon_var: Ad::variable(cell.hydrocarbon_var, first_slot + 2),
+        regime: cell.regime,
+        drsdt0_base_rs: cell.drsdt0_base_rs,
+    }
+}
+
+fn completion_values<const N: usize>(
+    completion: &WellPerforationInputGeneric<Ad<N>>,
+) -> WellPerforationInputGeneric<f64> {
+    let fraction_values = |fractions: &ProducerFractionsGeneric<Ad<N>>| ProducerFractionsGeneric {
+        water_fraction: fractions.water_fraction.value(),
+        oil_fraction: fractions.oil_fraction.value(),
+        gas_fraction: fractions.gas_fraction.value(),
+    };
+    WellPerforationInputGeneric {
+        cell: WellCellInput {
+            p: completion.cell.p.value(),
+            sw: completion.cell.sw.value(),
+            hydrocarbon_var: completion.cell.hydrocarbon_var.value(),
+            regime: completion.cell.regime,
+            drsdt0_base_rs: completion.cell.drsdt0_base_rs,
+        },
+        fractions: completion.fractions.as_ref().map(fraction_values),
+        backflow: completion.backflow.as_ref().map(fraction_values),
+        q: completion.q.value(),
+    }
+}
@@ -5,12 +5,20 @@ use rand::rngs::StdRng;
 use serde::Deserialize;
 use wasm_bindgen::prelude::*;
 
+use crate::aquifer::FetkovichAquifer;
+use crate::boundary::{BoundaryCondition, BoundaryDrive};
+use crate::dual_porosity::DualPorosity;
+use crate::economic_limits::{EconomicAction, EconomicLimits};
+use crate::group_control::{FIELD_GROUP, GroupRatePhase, GroupTarget, WellGroup};
 use crate::pvt;
-use crate::well::WellSchedule;
+use crate::schedule::ScheduleEvent;
+use crate::vfp::VfpTable;
+use crate::well::{WellSchedule, validate_surface_rate_control};
+use crate::well_segments::WellSegment;
 use crate::{
-    CapillaryPressure, FluidProperties, GasOilCapillaryPressure, InjectedFluid, ReservoirSimulator,
-    RockFluidProps, RockFluidPropsThreePhase, SweepConfig, ThreePhaseScalTables, TimePointRates,
-    Well,
+    CapillaryPressure, CompletionConnection, FluidProperties, GasOilCapillaryPressure,
+    InjectedFluid, ReservoirSimulator, RockFluidProps, RockFluidPropsThreePhase, SweepConfig,
+    ThreePhaseScalTables, TimePointRates, Well,
 };
 
 #[derive(Deserialize)]
@@ -48,10 +56,27 @@ impl ReservoirSimulator {
             nx,
             ny,
             nz,
-            dx: 10.0,
-            dy: 10.0,
+            dx: vec![10.0; nx],
+            dy: vec![10.0; ny],
             dz: vec![1.0; nz],
             porosity,
+            net_to_gross: vec![1.0; n],
+            cell_active: vec![true; n],
+            mult_x: vec![1.0; n],
+            mult_y: vec![1.0; n],
+            mult_z: vec![1.0; n],
+            faults: Vec::new(),
+            face_multipliers: [vec![1.0; n], vec![1.0; n], vec![1.0; n]],
+            non_neighbor_connections: Vec::new(),
+            non_neighbor_index: Vec::new(),
+            radial_grid: None,
+            local_grids: Vec::new(),
+            local_cells: Vec::new(),
+            local_faces: Vec::new(),
+            local_face_index: Vec::new(),
+            dual_porosity: None,
+            boundary_conditions: Vec::new(),
+            aquifers: Vec::new(),
             perm_x,
             perm_y,
             perm_z,
@@ -60,6 +85,8 @@ impl ReservoirSimulator {
             sat_oil,
             sat_gas,
             wells: Vec::new(),
+            well_groups: Vec::new(),
+            vfp_tables: Vec::new(),
             time_days: 0.0,
             pvt: FluidProperties::default_pvt(),
             scal: RockFluidProps::default_scal(),
@@ -80,11 +107,14 @@ impl ReservoirSimulator {
             well_bhp_max: 2000.0,
             rock_compressibility: 0.0,
             depth_reference_m: 0.0,
+            cell_top_depth_m: None,
             b_o: 1.0,
             b_w: 1.0,
             water_pvt_reference_pressure_bar: 300.0,
             rock_reference_pressure_bar: 300.0,
             rate_history: Vec::new(),
+            run_stopped: false,
+            schedule_events: Vec::new(),
             last_solver_warning: String::new(),
             last_fim_trace: String::new(),
             capture_fim_trace: false,
@@ -119,14 +149,123 @@ impl ReservoirSimulator {
             gas_redissolution_enabled: true,
             fim_enabled: true,
             sweep_config: None,
+            report_completion_rates: false,
+            report_full_pattern_rates: false,
+        }
+    }
+
+    fn economic_limits(
+        min_oil_rate_sm3_day: f64,
+        max_water_cut: f64,
+        max_gor: f64,
+        max_glr: f64,
+        action: &str,
+    ) -> Result<Option<EconomicLimits>, String> {
+        let action = EconomicAction::parse(action).ok_or_else(|| {
+            format!(
+                "Unknown economic limit action '{}'; expected 'shut', 'close_completion' or 'stop'",
+                action.trim()
+            )
+        })?;
+        let limit = |name: &str, value: f64| -> Result<Option<f64>, String> {
+            if !value.is_finite() {
+                return Ok(None);
+            }
+            if value < 0.0 {
+                return Err(format!("{} must be non-negative, got {}", name, value));
+            }
+            Ok(Some(value))
+        };
+        let limits = EconomicLimits {
+            min_oil_rate_sm3_day: limit("Minimum oil rate", min_oil_rate_sm3_day)?,
+            max_water_cut: limit("Maximum water cut", max_water_cut)?,
+            max_gor: limit("Maximum GOR", max_gor)?,
+            max_glr: limit("Maximum GLR", max_glr)?,
+            action,
+        };
+        if limits
+            .max_water_cut
+            .is_some_and(|water_cut| water_cut > 1.0)
+        {
+            return Err(format!(
+                "Maximum water cut must not exceed 1, got {}",
+                max_water_cut
+            ));
+        }
+        let any = limits.min_oil_rate_sm3_day.is_some()
+            || limits.max_water_cut.is_some()
+            || limits.max_gor.is_some()
+            || limits.max_glr.is_some();
+        Ok(any.then_some(limits))
+    }
+
+    fn group_target(phase: GroupRatePhase, rate: f64) -> Result<GroupTarget, String> {
+        if !rate.is_finite() || rate < 0.0 {
+            return Err(format!(
+                "Group target rate must be finite and non-negative, got {}",
+                rate
+            ));
+        }
+        Ok(GroupTarget { phase, rate })
+    }
+
+    /// Group `name`, creating `FIELD` on first use.
+    fn well_group_mut(&mut self, name: &str) -> Result<&mut WellGroup, String> {
+        let name = name.trim();
+        let name = if name.eq_ignore_ascii_case(FIELD_GROUP) {
+            FIELD_GROUP
+        } else {
+            name
+        };
+        if name == FIELD_GROUP && !self.well_groups.iter().any(|group| group.name == name) {
+            self.well_groups
+                .push(WellGroup::new(FIELD_GROUP.to_string()));
+        }
+        self.well_groups
+            .iter_mut()
+            .find(|group| group.name == name)
+            .ok_or_else(|| format!("No well group named '{}'", name))
+    }
+
+    /// Entries of physical well `physical_well_id`'s completions in root cell `(i, j, k)`.
+    fn completion_entries(
+        &self,
+        physical_well_id: &str,
+        i: usize,
+        j: usize,
+        k: usize,
+    ) -> Result<Vec<usize>, String> {
+        let well_id = physical_well_id.trim();
+        if well_id.is_empty() {
+            return Err("Physical well id must not be empty".to_string());
         }
+        let entries: Vec<usize> = self
+            .wells
+            .iter()
+            .enumerate()
+            .filter(|(_, well)| {
+                well.physical_well_id.as_deref() == Some(well_id)
+                    && (well.i, well.j, well.k) == (i, j, k)
+            })
+            .map(|(entry, _)| entry)
+            .collect();
+        if entries.is_empty() {
+            return Err(format!(
+                "No completion of well '{}' in cell ({}, {}, {})",
+                well_id, i, j, k
+            ));
+        }
+        Ok(entries)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn add_well_internal(
         &mut self,
         i: usize,
         j: usize,
         k: usize,
+        local_cell_index: Option<usize>,
+        penetration_m: Option<[f64; 3]>,
         bhp: f64,
         well_radius: f64,
         skin: f64,
@@ -151,11 +290,33 @@ impl ReservoirSimulator {
         if !skin.is_finite() {
             return Err(format!("Skin factor must be finite, got: {}", skin));
         }
+        let cell_id = local_cell_index.unwrap_or_else(|| self.idx(i, j, k));
+        if self.is_refined(cell_id) {
+            return Err(format!(
+                "Cell ({}, {}, {}) is refined by a local grid; complete the well in one of its child cells",
+                i, j, k
+            ));
+        }
+        if !self.is_active(cell_id) {
+            return Err(format!(
+                "Well completion in inactive cell is not allowed: ({}, {}, {})",
+                i, j, k
+            ));
+        }
+        if self.radial_grid.is_some() && i != 0 {
+            return Err(format!(
+                "Wells in a radial grid must be completed in the innermost ring, got i={}",
+                i
+            ));
+        }
+        if let Some(radial) = &self.radial_grid {
+            radial.check_well_radius(well_radius)?;
+        }
 
         if self
             .wells
             .iter()
-            .any(|well| well.i == i && well.j == j && well.k == k)
+            .any(|well| self.well_cell_index(well) == cell_id)
         {
             return Err(format!(
                 "Multiple well completions in the same cell are not supported: ({}, {}, {})",
@@ -163,24 +324,36 @@ impl ReservoirSimulator {
             ));
         }
 
-        let cell_id = self.idx(i, j, k);
-        let pi = self.calculate_well_productivity_index(cell_id, well_radius, skin)?;
-        let well = Well {
+        let mut well = Well {
             physical_well_id,
             schedule: WellSchedule::default(),
             i,
             j,
             k,
+            local_cell_index,
+            penetration_m,
             bhp,
-            productivity_index: pi,
+            productivity_index: 0.0,
             injector,
             datum_depth_m: None,
             wellbore_density_kg_m3: None,
+            segment: None,
+            segment_fluid: None,
             head_offset_bar: 0.0,
             flowing_bhp: None,
+            guide_rate: None,
+            group_allocation: None,
+            economic_limits: None,
             well_radius,
             skin,
+            connection_factor: None,
+            kh_md_m: None,
+            d_factor: None,
+            open: true,
+            well_fraction: 1.0,
+            fracture: None,
         };
+        well.productivity_index = self.well_productivity_index(&well)?;
         well.validate(self.nx, self.ny, self.nz)?;
         self.wells.push(well);
         self.refresh_well_head_offsets();
@@ -197,7 +370,7 @@ impl ReservoirSimulator {
         skin: f64,
         injector: bool,
     ) -> Result<(), String> {
-        self.add_well_internal(i, j, k, bhp, well_radius, skin, injector, None)
+        self.add_well_internal(i, j, k, None, None, bhp, well_radius, skin, injector, None)
     }
 
     #[wasm_bindgen(js_name = addWellWithId)]
@@ -216,6 +389,8 @@ impl ReservoirSimulator {
             i,
             j,
             k,
+            None,
+            None,
             bhp,
             well_radius,
             skin,
@@ -224,6 +399,249 @@ impl ReservoirSimulator {
         )
     }
 
+    /// Add a completion in cell `(i, j, k)` whose wellbore runs across the full cell along
+    /// `direction` (`"x"`, `"y"` or `"z"`), so a horizontal well is one such completion per cell
+    /// sharing a `physical_well_id`. Pass an empty `physical_well_id` for an unnamed completion.
+    #[wasm_bindgen(js_name = addDirectionalWell)]
+    pub fn add_directional_well(
+        &mut self,
+        i: usize,
+        j: usize,
+        k: usize,
+        direction: String,
+        bhp: f64,
+        well_radius: f64,
+        skin: f64,
+        injector: bool,
+        physical_well_id: String,
+    ) -> Result<(), String> {
+        if self.radial_grid.is_some() {
+            return Err("Completions in a radial grid run along its axis only".to_string());
+        }
+        if i >= self.nx || j >= self.ny || k >= self.nz {
+            return Err(format!(
+                "Well indices out of bounds: (i={}, j={}, k={}) for grid ({}, {}, {})",
+                i, j, k, self.nx, self.ny, self.nz
+            ));
+        }
+        let id = self.idx(i, j, k);
+        let penetration_m = match direction.trim().to_ascii_lowercase().as_str() {
+            "x" => [self.dx_at(id), 0.0, 0.0],
+            "y" => [0.0, self.dy_at(id), 0.0],
+            "z" => [0.0, 0.0, self.dz_at(id)],
+            other => {
+                return Err(format!(
+                    "Completion direction must be 'x', 'y' or 'z', got: {}",
+                    other
+                ));
+            }
+        };
+        let physical_well_id = (!physical_well_id.trim().is_empty()).then_some(physical_well_id);
+        self.add_well_internal(
+            i,
+            j,
+            k,
+            None,
+            Some(penetration_m),
+            bhp,
+            well_radius,
+            skin,
+            injector,
+            physical_well_id,
+        )
+    }
+
+    /// Add a deviated well along a trajectory of survey points given as flat
+    /// `[x0, y0, z0, x1, y1, z1, …]`: `x`, `y` [m] from the grid's `(0, 0)` corner and `z` the
+    /// true vertical depth [m TVDSS]. Every active root cell the path crosses gets a completion
+    /// carrying the path's projections through it; inactive cells are passed over.
+    #[wasm_bindgen(js_name = addDeviatedWell)]
+    pub fn add_deviated_well(
+        &mut self,
+        trajectory_xyz: Vec<f64>,
+        bhp: f64,
+        well_radius: f64,
+        skin: f64,
+        injector: bool,
+        physical_well_id: String,
+    ) -> Result<(), String> {
+        if self.radial_grid.is_some() {
+            return Err("Completions in a radial grid run along its axis only".to_string());
+        }
+        if physical_well_id.trim().is_empty() {
+            return Err("A deviated well needs a physical well id for its completions".to_string());
+        }
+        if !trajectory_xyz.len().is_multiple_of(3) || trajectory_xyz.len() < 6 {
+            return Err(format!(
+                "Trajectory needs at least two (x, y, z) points, got {} values",
+                trajectory_xyz.len()
+            ));
+        }
+        if trajectory_xyz.iter().any(|value| !value.is_finite()) {
+            return Err("Trajectory coordinates must be finite".to_string());
+        }
+        let points: Vec<[f64; 3]> = trajectory_xyz
+            .chunks_exact(3)
+            .map(|point| [point[0], point[1], point[2]])
+            .collect();
+        let completions: Vec<(usize, [f64; 3])> = self
+            .trajectory_penetrations(&points)
+            .into_iter()
+            .filter(|&(id, _)| self.is_active(id))
+            .collect();
+        if completions.is_empty() {
+            return Err("Trajectory does not pass through any active cell".to_string());
+        }
+
+        let wells_before = self.wells.len();
+        for (id, penetration_m) in completions {
+            let (i, j, k) = self.root_cell_ijk(id);
+            let added = self.add_well_internal(
+                i,
+                j,
+                k,
+                None,
+                Some(penetration_m),
+                bhp,
+                well_radius,
+                skin,
+                injector,
+                Some(physical_well_id.clone()),
+            );
+            if let Err(message) = added {
+                self.wells.truncate(wells_before);
+                self.refresh_well_head_offsets();
+                return Err(message);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a well in cell `(i, j, k)` with a vertical, infinite-conductivity hydraulic fracture
+    /// of half-length `half_length_m` [m] striking `azimuth_deg` from the x axis toward y
+    /// through the cell centre, over the thickness of layer `k`. Every active cell the fracture
+    /// crosses gets a completion fed by linear flow onto the fracture faces; inactive cells
+    /// are passed over and the fracture stops at the edge of the grid. Call once per layer
+    /// for a fracture through several.
+    #[wasm_bindgen(js_name = addFracturedWell)]
+    // Flat scalars like `addWellWithId`, plus the fracture's half-length and azimuth.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_fractured_well(
+        &mut self,
+        i: usize,
+        j: usize,
+        k: usize,
+        half_length_m: f64,
+        azimuth_deg: f64,
+        bhp: f64,
+        well_radius: f64,
+        injector: bool,
+        physical_well_id: String,
+    ) -> Result<(), String> {
+        if self.radial_grid.is_some() {
+            return Err("Fractured wells are not supported on a radial grid".to_string());
+        }
+        if physical_well_id.trim().is_empty() {
+            return Err(
+                "A fractured well needs a physical well id for its completions".to_string(),
+            );
+        }
+        if i >= self.nx || j >= self.ny || k >= self.nz {
+            return Err(format!(
+                "Well indices out of bounds: (i={}, j={}, k={}) for grid ({}, {}, {})",
+                i, j, k, self.nx, self.ny, self.nz
+            ));
+        }
+        if !half_length_m.is_finite() || half_length_m <= 0.0 {
+            return Err(format!(
+                "Fracture half-length must be positive and finite, got: {}",
+                half_length_m
+            ));
+        }
+        if !azimuth_deg.is_finite() {
+            return Err(format!(
+                "Fracture azimuth must be finite, got: {}",
+                azimuth_deg
+            ));
+        }
+        let well_cell = self.idx(i, j, k);
+        let completions: Vec<_> = self
+            .fracture_connections(i, j, k, half_length_m, azimuth_deg)
+            .into_iter()
+            .filter(|&(id, _)| id == well_cell || self.is_active(id))
+            .collect();
+
+        let wells_before = self.wells.len();
+        for (id, fracture) in completions {
+            let (ci, cj, ck) = self.root_cell_ijk(id);
+            let added = self
+                .add_well_internal(
+                    ci,
+                    cj,
+                    ck,
+                    None,
+                    None,
+                    bhp,
+                    well_radius,
+                    0.0,
+                    injector,
+                    Some(physical_well_id.clone()),
+                )
+                .and_then(|()| {
+                    let entry = self.wells.len() - 1;
+                    self.wells[entry].fracture = Some(fracture);
+                    self.wells[entry].productivity_index =
+                        self.well_productivity_index(&self.wells[entry])?;
+                    Ok(())
+                });
+            if let Err(message) = added {
+                self.wells.truncate(wells_before);
+                self.refresh_well_head_offsets();
+                return Err(message);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a completion in cell `(i, j, k)` of a local grid, indexed within the local grid.
+    /// The well's `i`, `j`, `k` report the root-grid cell the local grid refines. Pass an empty
+    /// `physical_well_id` for an unnamed completion.
+    #[wasm_bindgen(js_name = addLocalGridWell)]
+    // Same flat scalar arguments as `addWellWithId`, plus the local grid's name.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_local_grid_well(
+        &mut self,
+        local_grid: String,
+        i: usize,
+        j: usize,
+        k: usize,
+        bhp: f64,
+        well_radius: f64,
+        skin: f64,
+        injector: bool,
+        physical_well_id: String,
+    ) -> Result<(), String> {
+        let cell_id = self.local_grid_cell(&local_grid, i, j, k)?;
+        let (root_i, root_j, root_k) = self.root_cell_ijk(cell_id);
+        let physical_well_id = (!physical_well_id.trim().is_empty()).then_some(physical_well_id);
+        self.add_well_internal(
+            root_i,
+            root_j,
+            root_k,
+            Some(cell_id),
+            None,
+            bhp,
+            well_radius,
+            skin,
+            injector,
+            physical_well_id,
+        )
+    }
+
+    /// Put a physical well on `control_mode`: `"pressure"`, `"rate"` (a reservoir-rate target,
+    /// or a surface target on oil, or the injected phase, when one is given), or a producer
+    /// surface-rate target on oil, water, gas or liquid — `"orat"`, `"wrat"`, `"grat"` or
+    /// `"lrat"`. Rate controls fall back to the BHP limit when the target cannot be met.
     #[wasm_bindgen(js_name = setWellSchedule)]
     pub fn set_well_schedule(
         &mut self,
@@ -240,11 +658,12 @@ impl ReservoirSimulator {
         }
 
         let normalized_control_mode = match control_mode.trim().to_ascii_lowercase().as_str() {
-            "rate" => "rate",
-            "pressure" | "" => "pressure",
+            mode @ ("rate" | "orat" | "wrat" | "grat" | "lrat") => mode.to_string(),
+            "pressure" | "" => "pressure".to_string(),
             other => {
                 return Err(format!(
-                    "Well control mode must be 'pressure' or 'rate', got: {}",
+                    "Well control mode must be 'pressure', 'rate', 'orat', 'wrat', 'grat' or \
+                     'lrat', got: {}",
                     other
                 ));
             }
@@ -266,16 +685,30 @@ impl ReservoirSimulator {
         } else {
             None
         };
+        if normalized_control_mode != "rate" && normalized_control_mode != "pressure" {
+            for well in &self.wells {
+                if well.physical_well_id.as_deref() == Some(well_id) {
+                    validate_surface_rate_control(
+                        well.injector,
+                        &normalized_control_mode,
+                        target_surface_rate_m3_day,
+                    )?;
+                }
+            }
+        }
 
         let mut updated_any = false;
         for well in self.wells.iter_mut() {
             if well.physical_well_id.as_deref() == Some(well_id) {
                 well.schedule = WellSchedule {
-                    control_mode: Some(normalized_control_mode.to_string()),
+                    control_mode: Some(normalized_control_mode.clone()),
                     target_rate_m3_day,
                     target_surface_rate_m3_day,
                     bhp_limit,
                     enabled,
+                    injected_fluid: well.schedule.injected_fluid,
+                    thp_target_bar: None,
+                    vfp_table: None,
                 };
                 updated_any = true;
             }
@@ -288,6 +721,405 @@ impl ReservoirSimulator {
         Ok(())
     }
 
+    /// Set the phase a physical well injects in three-phase mode (`"water"` or `"gas"`), or
+    /// pass an empty string to follow `setInjectedFluid`. Takes effect from the next step, so
+    /// a well can switch phase between report steps.
+    #[wasm_bindgen(js_name = setWellInjectedFluid)]
+    pub fn set_well_injected_fluid(
+        &mut self,
+        physical_well_id: String,
+        fluid: &str,
+    ) -> Result<(), String> {
+        let well_id = physical_well_id.trim();
+        if well_id.is_empty() {
+            return Err("Physical well id must not be empty".to_string());
+        }
+        let injected_fluid = match fluid.trim().to_ascii_lowercase().as_str() {
+            "" => None,
+            "water" => Some(InjectedFluid::Water),
+            "gas" => Some(InjectedFluid::Gas),
+            other => {
+                return Err(format!(
+                    "Unknown injected fluid '{}'; expected 'water', 'gas' or empty",
+                    other
+                ));
+            }
+        };
+
+        let mut updated_any = false;
+        for well in self.wells.iter_mut() {
+            if well.physical_well_id.as_deref() == Some(well_id) {
+                well.schedule.injected_fluid = injected_fluid;
+                updated_any = true;
+            }
+        }
+        if !updated_any {
+            return Err(format!("No well found for physical well id '{}'", well_id));
+        }
+        self.refresh_well_head_offsets();
+        Ok(())
+    }
+
+    /// Add or replace production VFP table `table_number`: the BHP [bar] needed to lift each
+    /// surface liquid rate [Sm³/day] to each THP [bar] at each water cut and GOR [Sm³/Sm³].
+    /// `bhps_bar` holds one run over `rates_sm3_day` per `(thp, water cut, gor)`, GOR varying
+    /// fastest, then water cut, then THP.
+    #[wasm_bindgen(js_name = setVfpProductionTable)]
+    pub fn set_vfp_production_table(
+        &mut self,
+        table_number: u32,
+        rates_sm3_day: Vec<f64>,
+        thps_bar: Vec<f64>,
+        water_cuts: Vec<f64>,
+        gors: Vec<f64>,
+        bhps_bar: Vec<f64>,
+    ) -> Result<(), String> {
+        self.set_vfp_table(VfpTable {
+            table_number,
+            injector: false,
+            rates_sm3_day,
+            thps_bar,
+            water_cuts,
+            gors,
+            bhps_bar,
+        })
+    }
+
+    /// Add or replace injection VFP table `table_number`: the BHP [bar] needed to inject each
+    /// surface rate [Sm³/day] of the injected phase against each THP [bar], one run over
+    /// `rates_sm3_day` per THP.
+    #[wasm_bindgen(js_name = setVfpInjectionTable)]
+    pub fn set_vfp_injection_table(
+        &mut self,
+        table_number: u32,
+        rates_sm3_day: Vec<f64>,
+        thps_bar: Vec<f64>,
+        bhps_bar: Vec<f64>,
+    ) -> Result<(), String> {
+        self.set_vfp_table(VfpTable {
+            table_number,
+            injector: true,
+            rates_sm3_day,
+            thps_bar,
+            water_cuts: vec![0.0],
+            gors: vec![0.0],
+            bhps_bar,
+        })
+    }
+
+    /// Put a physical well on tubing-head pressure control: it flows at the BHP VFP table
+    /// `table_number` gives for its rates at `thp_bar`, kept within its BHP limit. The table
+    /// must be a production table for a producer and an injection table for an injector.
+    #[wasm_bindgen(js_name = setWellThpControl)]
+    pub fn set_well_thp_control(
+        &mut self,
+        physical_well_id: String,
+        table_number: u32,
+        thp_bar: f64,
+    ) -> Result<(), String> {
+        let well_id = physical_well_id.trim();
+        if well_id.is_empty() {
+            return Err("Physical well id must not be empty".to_string());
+        }
+        if !thp_bar.is_finite() {
+            return Err(format!("THP target must be finite, got {}", thp_bar));
+        }
+        let table_injector = self
+            .vfp_table(table_number)
+            .ok_or_else(|| format!("No VFP table {}", table_number))?
+            .injector;
+
+        let mut updated_any = false;
+        for well in self.wells.iter_mut() {
+            if well.physical_well_id.as_deref() == Some(well_id) {
+                if well.injector != table_injector {
+                    return Err(format!(
+                        "VFP table {} is {} table but '{}' is {}",
+                        table_number,
+                        if table_injector {
+                            "an injection"
+                        } else {
+                            "a production"
+                        },
+                        well_id,
+                        if well.injector {
+                            "an injector"
+                        } else {
+                            "a producer"
+                        }
+                    ));
+                }
+                well.schedule.control_mode = Some("thp".to_string());
+                well.schedule.thp_target_bar = Some(thp_bar);
+                well.schedule.vfp_table = Some(table_number);
+                updated_any = true;
+            }
+        }
+        if !updated_any {
+            return Err(format!("No well found for physical well id '{}'", well_id));
+        }
+        Ok(())
+    }
+
+    fn set_vfp_table(&mut self, table: VfpTable) -> Result<(), String> {
+        table.validate()?;
+        if let Some(existing) = self
+            .vfp_tables
+            .iter()
+            .find(|existing| existing.table_number == table.table_number)
+            && existing.injector != table.injector
+        {
+            return Err(format!(
+                "VFP table {} already exists as a {} table",
+                table.table_number,
+                if existing.injector {
+                    "injection"
+                } else {
+                    "production"
+                }
+            ));
+        }
+        self.vfp_tables
+            .retain(|existing| existing.table_number != table.table_number);
+        self.vfp_tables.push(table);
+        Ok(())
+    }
+
+    /// Gather physical wells into a named group, replacing any earlier members. A well belongs
+    /// to at most one group; `FIELD` holds every well and cannot be set.
+    #[wasm_bindgen(js_name = setWellGroup)]
+    pub fn set_well_group(
+        &mut self,
+        name: String,
+        physical_well_ids: Vec<String>,
+    ) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Group name must not be empty".to_string());
+        }
+        if name.eq_ignore_ascii_case(FIELD_GROUP) {
+            return Err(format!(
+                "Group '{}' holds every well and cannot be set",
+                FIELD_GROUP
+            ));
+        }
+        let mut wells: Vec<String> = Vec::with_capacity(physical_well_ids.len());
+        for id in &physical_well_ids {
+            let id = id.trim();
+            if !self
+                .wells
+                .iter()
+                .any(|well| well.physical_well_id.as_deref() == Some(id))
+            {
+                return Err(format!("No well found for physical well id '{}'", id));
+            }
+            if let Some(group) = self.well_group_of(id).filter(|group| group.name != name) {
+                return Err(format!(
+                    "Well '{}' already belongs to group '{}'",
+                    id, group.name
+                ));
+            }
+            if !wells.iter().any(|well| well == id) {
+                wells.push(id.to_string());
+            }
+        }
+        match self.well_groups.iter_mut().find(|group| group.name == name) {
+            Some(group) => group.wells = wells,
+            None => {
+                let mut group = WellGroup::new(name.to_string());
+                group.wells = wells;
+                self.well_groups.push(group);
+            }
+        }
+        Ok(())
+    }
+
+    /// Give a group, or `FIELD`, a production target: `rate` [Sm³/day, or m³/day for `"resv"`]
+    /// of `"oil"`, `"water"`, `"gas"`, `"liquid"` or `"resv"`, shared among its producers by
+    /// guide rate each step.
+    #[wasm_bindgen(js_name = setGroupProductionTarget)]
+    pub fn set_group_production_target(
+        &mut self,
+        group: String,
+        phase: &str,
+        rate: f64,
+    ) -> Result<(), String> {
+        let phase = GroupRatePhase::parse(phase).ok_or_else(|| {
+            format!(
+                "Unknown group production phase '{}'; expected 'oil', 'water', 'gas', 'liquid' or 'resv'",
+                phase.trim()
+            )
+        })?;
+        let target = Self::group_target(phase, rate)?;
+        self.well_group_mut(&group)?.production = Some(target);
+        Ok(())
+    }
+
+    /// Give a group, or `FIELD`, an injection target: `rate` [Sm³/day, or m³/day for `"resv"`]
+    /// of `"water"`, `"gas"` or `"resv"`, shared among its injectors by guide rate each step.
+    #[wasm_bindgen(js_name = setGroupInjectionTarget)]
+    pub fn set_group_injection_target(
+        &mut self,
+        group: String,
+        phase: &str,
+        rate: f64,
+    ) -> Result<(), String> {
+        let phase = match GroupRatePhase::parse(phase) {
+            Some(
+                phase @ (GroupRatePhase::Water | GroupRatePhase::Gas | GroupRatePhase::Reservoir),
+            ) => phase,
+            _ => {
+                return Err(format!(
+                    "Unknown group injection phase '{}'; expected 'water', 'gas' or 'resv'",
+                    phase.trim()
+                ));
+            }
+        };
+        let target = Self::group_target(phase, rate)?;
+        let group = self.well_group_mut(&group)?;
+        group.injection = Some(target);
+        group.voidage_replacement = None;
+        Ok(())
+    }
+
+    /// Have a group's, or `FIELD`'s, injectors replace `ratio` times the reservoir volume its
+    /// producers withdraw, in place of any injection target. The offtake is taken from the
+    /// state entering each step.
+    #[wasm_bindgen(js_name = setGroupVoidageReplacement)]
+    pub fn set_group_voidage_replacement(
+        &mut self,
+        group: String,
+        ratio: f64,
+    ) -> Result<(), String> {
+        if !ratio.is_finite() || ratio < 0.0 {
+            return Err(format!(
+                "Voidage replacement ratio must be finite and non-negative, got {}",
+                ratio
+            ));
+        }
+        let group = self.well_group_mut(&group)?;
+        group.injection = None;
+        group.voidage_replacement = Some(ratio);
+        Ok(())
+    }
+
+    /// Drop a group's production and injection targets, returning its wells to their own
+    /// controls.
+    #[wasm_bindgen(js_name = clearGroupTargets)]
+    pub fn clear_group_targets(&mut self, group: String) -> Result<(), String> {
+        let group = self.well_group_mut(&group)?;
+        group.production = None;
+        group.injection = None;
+        group.voidage_replacement = None;
+        Ok(())
+    }
+
+    /// Set the guide rate a physical well receives group targets by. Pass a non-finite value
+    /// to share by well potential instead.
+    #[wasm_bindgen(js_name = setWellGuideRate)]
+    pub fn set_well_guide_rate(
+        &mut self,
+        physical_well_id: String,
+        guide_rate: f64,
+    ) -> Result<(), String> {
+        let well_id = physical_well_id.trim();
+        if well_id.is_empty() {
+            return Err("Physical well id must not be empty".to_string());
+        }
+        let guide_rate = if guide_rate.is_finite() {
+            if guide_rate <= 0.0 {
+                return Err(format!("Guide rate must be positive, got {}", guide_rate));
+            }
+            Some(guide_rate)
+        } else {
+            None
+        };
+        let mut updated_any = false;
+        for well in self.wells.iter_mut() {
+            if well.physical_well_id.as_deref() == Some(well_id) {
+                well.guide_rate = guide_rate;
+                updated_any = true;
+            }
+        }
+        if !updated_any {
+            return Err(format!("No well found for physical well id '{}'", well_id));
+        }
+        Ok(())
+    }
+
+    /// Set a producer's economic limits, checked after every step: minimum oil rate
+    /// [Sm³/day], maximum water cut, GOR and GLR [Sm³/Sm³]. A non-finite value leaves that
+    /// quantity unchecked, and all four non-finite removes the limits. `action` is `"shut"`,
+    /// `"close_completion"` or `"stop"`.
+    #[wasm_bindgen(js_name = setWellEconomicLimits)]
+    pub fn set_well_economic_limits(
+        &mut self,
+        physical_well_id: String,
+        min_oil_rate_sm3_day: f64,
+        max_water_cut: f64,
+        max_gor: f64,
+        max_glr: f64,
+        action: &str,
+    ) -> Result<(), String> {
+        let well_id = physical_well_id.trim();
+        if well_id.is_empty() {
+            return Err("Physical well id must not be empty".to_string());
+        }
+        let limits = Self::economic_limits(
+            min_oil_rate_sm3_day,
+            max_water_cut,
+            max_gor,
+            max_glr,
+            action,
+        )?;
+        let mut updated_any = false;
+        for well in self.wells.iter_mut() {
+            if well.physical_well_id.as_deref() == Some(well_id) {
+                if well.injector {
+                    return Err(format!(
+                        "Economic limits apply to producers; '{}' is an injector",
+                        well_id
+                    ));
+                }
+                well.economic_limits = limits;
+                updated_any = true;
+            }
+        }
+        if !updated_any {
+            return Err(format!("No well found for physical well id '{}'", well_id));
+        }
+        Ok(())
+    }
+
+    /// Set economic limits on the summed producers of a group, or `FIELD`; arguments as for
+    /// `setWellEconomicLimits`. `"shut"` shuts the group's worst-offending well.
+    #[wasm_bindgen(js_name = setGroupEconomicLimits)]
+    pub fn set_group_economic_limits(
+        &mut self,
+        group: String,
+        min_oil_rate_sm3_day: f64,
+        max_water_cut: f64,
+        max_gor: f64,
+        max_glr: f64,
+        action: &str,
+    ) -> Result<(), String> {
+        let limits = Self::economic_limits(
+            min_oil_rate_sm3_day,
+            max_water_cut,
+            max_gor,
+            max_glr,
+            action,
+        )?;
+        self.well_group_mut(&group)?.economic_limits = limits;
+        Ok(())
+    }
+
+    /// Whether an economic limit has stopped the run; further steps then do nothing.
+    #[wasm_bindgen(js_name = isRunStopped)]
+    pub fn is_run_stopped(&self) -> bool {
+        self.run_stopped
+    }
+
     /// Reference the well's BHP to a depth, and optionally fix the density of
     /// the fluid column standing in the wellbore.
     ///
@@ -330,6 +1162,203 @@ impl ReservoirSimulator {
         Ok(())
     }
 
+    /// Model the physical well's wellbore as a chain of segments, one per completion in the
+    /// order they were added, heel first, so node pressures below the datum carry the friction
+    /// and mixture head of the flow through the wellbore.
+    ///
+    /// `segment_lengths_m` gives each segment's measured length from the node above (the datum
+    /// for the first); left empty, the lengths are the distances between the completion cell
+    /// centres, and the vertical distance from the datum for the first. A non-finite or
+    /// non-positive `diameter_m` turns the segments off again. Completions added later by the
+    /// schedule join the chain at its toe.
+    #[wasm_bindgen(js_name = setMultiSegmentWell)]
+    pub fn set_multi_segment_well(
+        &mut self,
+        physical_well_id: String,
+        diameter_m: f64,
+        roughness_m: f64,
+        segment_lengths_m: Vec<f64>,
+    ) -> Result<(), String> {
+        let well_id = physical_well_id.trim();
+        if well_id.is_empty() {
+            return Err("Physical well id must not be empty".to_string());
+        }
+        let entries: Vec<usize> = (0..self.wells.len())
+            .filter(|&entry| self.wells[entry].physical_well_id.as_deref() == Some(well_id))
+            .collect();
+        if entries.is_empty() {
+            return Err(format!("No well found for physical well id '{}'", well_id));
+        }
+
+        let segments = if diameter_m.is_finite() && diameter_m > 0.0 {
+            if !segment_lengths_m.is_empty() && segment_lengths_m.len() != entries.len() {
+                return Err(format!(
+                    "Well '{}' has {} completions but {} segment lengths were given",
+                    well_id,
+                    entries.len(),
+                    segment_lengths_m.len()
+                ));
+            }
+            let mut segments = Vec::with_capacity(entries.len());
+            for position in 0..entries.len() {
+                let segment = WellSegment {
+                    length_m: segment_lengths_m
+                        .get(position)
+                        .copied()
+                        .unwrap_or_else(|| self.derived_segment_length_m(&entries, position)),
+                    diameter_m,
+                    roughness_m,
+                };
+                segment.validate()?;
+                segments.push(Some(segment));
+            }
+            segments
+        } else {
+            vec![None; entries.len()]
+        };
+
+        for (entry, segment) in entries.into_iter().zip(segments) {
+            self.wells[entry].segment = segment;
+        }
+        self.refresh_well_head_offsets();
+        Ok(())
+    }
+
+    /// Set the inflow of a physical well's completion in root cell `(i, j, k)` as Eclipse
+    /// `COMPDAT` does. `connection` carries the connection factor, kh and skin overrides; see
+    /// [`CompletionConnection`] for how non-finite values fall back.
+    #[wasm_bindgen(js_name = setCompletionConnection)]
+    pub fn set_completion_connection(
+        &mut self,
+        physical_well_id: String,
+        i: usize,
+        j: usize,
+        k: usize,
+        connection: CompletionConnection,
+    ) -> Result<(), String> {
+        let CompletionConnection {
+            connection_factor,
+            kh_md_m,
+            skin,
+        } = connection;
+        let entries = self.completion_entries(&physical_well_id, i, j, k)?;
+        let mut updated = Vec::with_capacity(entries.len());
+        for &entry in &entries {
+            let mut well = self.wells[entry].clone();
+            well.connection_factor = connection_factor.is_finite().then_some(connection_factor);
+            well.kh_md_m = kh_md_m.is_finite().then_some(kh_md_m);
+            if skin.is_finite() {
+                well.skin = skin;
+            }
+            well.validate(self.nx, self.ny, self.nz)?;
+            well.productivity_index = self.well_productivity_index(&well)?;
+            updated.push(well);
+        }
+        for (entry, well) in entries.into_iter().zip(updated) {
+            self.wells[entry] = well;
+        }
+        Ok(())
+    }
+
+    /// Set the non-Darcy D-factor [day/Sm³] of every completion of a physical well: each adds
+    /// `D·|q_g|` to its skin at the free-gas surface rate `q_g` it flows, as Eclipse `COMPDAT`
+    /// item 12 does. A zero D-factor returns the well to Darcy flow.
+    #[wasm_bindgen(js_name = setWellDFactor)]
+    pub fn set_well_d_factor(
+        &mut self,
+        physical_well_id: String,
+        d_factor: f64,
+    ) -> Result<(), String> {
+        let well_id = physical_well_id.trim();
+        if well_id.is_empty() {
+            return Err("Physical well id must not be empty".to_string());
+        }
+        if !d_factor.is_finite() || d_factor < 0.0 {
+            return Err(format!(
+                "D-factor must be finite and non-negative, got: {}",
+                d_factor
+            ));
+        }
+        let d_factor = (d_factor > 0.0).then_some(d_factor);
+
+        let mut updated_any = false;
+        for well in self.wells.iter_mut() {
+            if well.physical_well_id.as_deref() == Some(well_id) {
+                well.d_factor = d_factor;
+                updated_any = true;
+            }
+        }
+        if !updated_any {
+            return Err(format!("No well found for physical well id '{}'", well_id));
+        }
+
+        self.update_dynamic_well_productivity_indices();
+        Ok(())
+    }
+
+    /// Open or shut a physical well's completion in root cell `(i, j, k)`. A shut completion
+    /// keeps its place in the well but carries no flow; shutting the last open one shuts the
+    /// well, and reopening a completion leaves the well's own status alone.
+    #[wasm_bindgen(js_name = setCompletionStatus)]
+    pub fn set_completion_status(
+        &mut self,
+        physical_well_id: String,
+        i: usize,
+        j: usize,
+        k: usize,
+        open: bool,
+    ) -> Result<(), String> {
+        let entries = self.completion_entries(&physical_well_id, i, j, k)?;
+        for &entry in &entries {
+            self.wells[entry].open = open;
+        }
+        if !open {
+            self.shut_well_without_open_completions(physical_well_id.trim());
+        }
+        self.update_dynamic_well_productivity_indices();
+        Ok(())
+    }
+
+    /// Set the fraction of a physical well inside a pattern-element model: `0.25` for a well
+    /// in a corner cell of a quarter five-spot, `0.5` for one on an edge, `1` for an interior
+    /// well. The well's Peaceman inflow is scaled to its share; rate targets stay the
+    /// element's own. A radial grid sets the fraction by its sector angle instead.
+    #[wasm_bindgen(js_name = setWellFraction)]
+    pub fn set_well_fraction(
+        &mut self,
+        physical_well_id: String,
+        well_fraction: f64,
+    ) -> Result<(), String> {
+        let well_id = physical_well_id.trim();
+        if well_id.is_empty() {
+            return Err("Physical well id must not be empty".to_string());
+        }
+        if self.radial_grid.is_some() && well_fraction != 1.0 {
+            return Err(
+                "Well fraction is set by the sector angle on a radial grid, not per well"
+                    .to_string(),
+            );
+        }
+        let entries: Vec<usize> = (0..self.wells.len())
+            .filter(|&entry| self.wells[entry].physical_well_id.as_deref() == Some(well_id))
+            .collect();
+        if entries.is_empty() {
+            return Err(format!("No well found for physical well id '{}'", well_id));
+        }
+        let mut updated = Vec::with_capacity(entries.len());
+        for &entry in &entries {
+            let mut well = self.wells[entry].clone();
+            well.well_fraction = well_fraction;
+            well.validate(self.nx, self.ny, self.nz)?;
+            well.productivity_index = self.well_productivity_index(&well)?;
+            updated.push(well);
+        }
+        for (entry, well) in entries.into_iter().zip(updated) {
+            self.wells[entry] = well;
+        }
+        Ok(())
+    }
+
     #[wasm_bindgen(js_name = setStabilityParams)]
     pub fn set_stability_params(
         &mut self,
@@ -348,6 +1377,25 @@ impl ReservoirSimulator {
         self.step_internal(target_dt_days);
     }
 
+    /// Step on to `end_time_days`, applying scheduled events as their times are reached.
+    #[wasm_bindgen(js_name = runUntil)]
+    pub fn run_until(&mut self, end_time_days: f64) {
+        if end_time_days > self.time_days {
+            self.step(end_time_days - self.time_days);
+        }
+    }
+
+    /// Replace the pending well schedule with an array of `ScheduleEvent`s:
+    /// `{ time_days, physical_well_id, kind, ... }` where `kind` is `open`, `shut`, `control`,
+    /// `thp`, `injected_fluid`, `completion` or `completion_status` with that action's fields. Events at or before
+    /// the current time apply at the start of the next step.
+    #[wasm_bindgen(js_name = setSchedule)]
+    pub fn set_schedule(&mut self, events_js: JsValue) -> Result<(), JsValue> {
+        let events: Vec<ScheduleEvent> = serde_wasm_bindgen::from_value(events_js)?;
+        self.set_schedule_events(events)
+            .map_err(|message| JsValue::from_str(&message))
+    }
+
     #[wasm_bindgen(js_name = stepWithDiagnostics)]
     pub fn step_with_diagnostics(&mut self, target_dt_days: f64) -> String {
         self.capture_fim_trace = true;
@@ -521,7 +1569,7 @@ impl ReservoirSimulator {
 
     #[wasm_bindgen(js_name = getPressures)]
     pub fn get_pressures(&self) -> Vec<f64> {
-        self.pressure.clone()
+        self.masked_cell_values(&self.pressure)
     }
 
     #[wasm_bindgen(js_name = getGridState)]
@@ -529,12 +1577,20 @@ impl ReservoirSimulator {
         let payload = Object::new();
 
         // These typed arrays are lightweight views over stable simulator buffers.
-        // The worker immediately structured-clones them into UI-owned memory.
-        let pressure = unsafe { Float64Array::view(&self.pressure) };
-        let sat_water = unsafe { Float64Array::view(&self.sat_water) };
-        let sat_oil = unsafe { Float64Array::view(&self.sat_oil) };
-        let sat_gas = unsafe { Float64Array::view(&self.sat_gas) };
-        let rs = unsafe { Float64Array::view(&self.rs) };
+        // The worker immediately structured-clones them into UI-owned memory. A grid
+        // with inactive cells needs NaN-masked copies instead.
+        let field = |values: &[f64]| {
+            if self.has_inactive_cells() {
+                Float64Array::from(self.masked_cell_values(values).as_slice())
+            } else {
+                unsafe { Float64Array::view(values) }
+            }
+        };
+        let pressure = field(&self.pressure);
+        let sat_water = field(&self.sat_water);
+        let sat_oil = field(&self.sat_oil);
+        let sat_gas = field(&self.sat_gas);
+        let rs = field(&self.rs);
 
         set_object_property(&payload, "pressure", &pressure.into());
         set_object_property(&payload, "sat_water", &sat_water.into());
@@ -547,12 +1603,12 @@ impl ReservoirSimulator {
 
     #[wasm_bindgen(js_name = getSatWater)]
     pub fn get_sat_water(&self) -> Vec<f64> {
-        self.sat_water.clone()
+        self.masked_cell_values(&self.sat_water)
     }
 
     #[wasm_bindgen(js_name = getSatOil)]
     pub fn get_sat_oil(&self) -> Vec<f64> {
-        self.sat_oil.clone()
+        self.masked_cell_values(&self.sat_oil)
     }
 
     #[wasm_bindgen(js_name = getWellState)]
@@ -616,6 +1672,7 @@ impl ReservoirSimulator {
         }
         self.water_pvt_reference_pressure_bar = pressure;
         self.rock_reference_pressure_bar = pressure;
+        self.inherit_appended_cell_state();
     }
 
     #[wasm_bindgen(js_name = setCellDimensions)]
@@ -629,26 +1686,135 @@ impl ReservoirSimulator {
                 dx, dy, dz
             ));
         }
-        self.dx = dx;
-        self.dy = dy;
+        self.dx = vec![dx; self.nx];
+        self.dy = vec![dy; self.ny];
         self.dz = vec![dz; self.nz];
+        self.radial_grid = None;
         Ok(())
     }
 
     #[wasm_bindgen(js_name = setCellDimensionsPerLayer)]
     pub fn set_cell_dimensions_per_layer(
         &mut self,
-        dx: f64,
-        dy: f64,
+        dx: f64,
+        dy: f64,
+        dz_per_layer: Vec<f64>,
+    ) -> Result<(), String> {
+        if !dx.is_finite() || !dy.is_finite() {
+            return Err("Cell dimensions must be finite numbers".to_string());
+        }
+        if dx <= 0.0 || dy <= 0.0 {
+            return Err(format!(
+                "Cell dimensions must be positive, got dx={}, dy={}",
+                dx, dy
+            ));
+        }
+        if dz_per_layer.len() != self.nz {
+            return Err(format!(
+                "dz_per_layer must have length equal to nz ({}), got {}",
+                self.nz,
+                dz_per_layer.len()
+            ));
+        }
+        for (k, &dz_k) in dz_per_layer.iter().enumerate() {
+            if !dz_k.is_finite() || dz_k <= 0.0 {
+                return Err(format!(
+                    "dz for layer {} must be positive and finite, got {}",
+                    k, dz_k
+                ));
+            }
+        }
+        self.dx = vec![dx; self.nx];
+        self.dy = vec![dy; self.ny];
+        self.dz = dz_per_layer;
+        self.radial_grid = None;
+        Ok(())
+    }
+
+    /// Set a tensor-product grid from per-column, per-row and per-layer cell sizes.
+    ///
+    /// `dx_per_column` has length `nx`, `dy_per_row` length `ny` and `dz_per_layer` length `nz`
+    /// (Eclipse `DXV`/`DYV`/`DZV`). Cell `(i, j, k)` is `dx[i] × dy[j] × dz[k]`, so a grid can be
+    /// refined towards a well or graded geometrically towards a boundary without refining the
+    /// whole model.
+    #[wasm_bindgen(js_name = setCellDimensionsTensor)]
+    pub fn set_cell_dimensions_tensor(
+        &mut self,
+        dx_per_column: Vec<f64>,
+        dy_per_row: Vec<f64>,
+        dz_per_layer: Vec<f64>,
+    ) -> Result<(), String> {
+        for (name, values, expected, axis) in [
+            ("dx_per_column", &dx_per_column, self.nx, "nx"),
+            ("dy_per_row", &dy_per_row, self.ny, "ny"),
+            ("dz_per_layer", &dz_per_layer, self.nz, "nz"),
+        ] {
+            if values.len() != expected {
+                return Err(format!(
+                    "{} must have length equal to {} ({}), got {}",
+                    name,
+                    axis,
+                    expected,
+                    values.len()
+                ));
+            }
+            for (index, &value) in values.iter().enumerate() {
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(format!(
+                        "{} entry {} must be positive and finite, got {}",
+                        name, index, value
+                    ));
+                }
+            }
+        }
+        self.dx = dx_per_column;
+        self.dy = dy_per_row;
+        self.dz = dz_per_layer;
+        self.radial_grid = None;
+        Ok(())
+    }
+
+    /// Switch to a radial `r`-`z` grid (Eclipse `RADIAL`) for single-well studies.
+    ///
+    /// The `nx` columns become rings spaced logarithmically from `inner_radius_m` to
+    /// `outer_radius_m`, the single row (`ny` must be 1) spans `sector_angle_deg` (360 for the
+    /// full cylinder, less for a sector model) and layers stack by `dz_per_layer`. Wells must be
+    /// completed in the innermost ring (`i = 0`), where the completion index uses the ring's
+    /// centre radius in place of Peaceman's equivalent radius, so every well radius must stay
+    /// inside that centre. `dx` is set to the ring widths and `dy` to the sector's outer arc;
+    /// volumes and transmissibilities come from the ring radii, not from `dx`/`dy`.
+    /// Any of the Cartesian `setCellDimensions*` setters returns to a Cartesian grid.
+    #[wasm_bindgen(js_name = setRadialGrid)]
+    pub fn set_radial_grid(
+        &mut self,
+        inner_radius_m: f64,
+        outer_radius_m: f64,
+        sector_angle_deg: f64,
         dz_per_layer: Vec<f64>,
     ) -> Result<(), String> {
-        if !dx.is_finite() || !dy.is_finite() {
-            return Err("Cell dimensions must be finite numbers".to_string());
+        if self.ny != 1 {
+            return Err(format!(
+                "A radial grid needs ny = 1 (one angular sector), got ny={}",
+                self.ny
+            ));
         }
-        if dx <= 0.0 || dy <= 0.0 {
+        if !self.local_grids.is_empty() {
+            return Err("A radial grid cannot carry local grid refinements".to_string());
+        }
+        if !inner_radius_m.is_finite()
+            || !outer_radius_m.is_finite()
+            || inner_radius_m <= 0.0
+            || outer_radius_m <= inner_radius_m
+        {
             return Err(format!(
-                "Cell dimensions must be positive, got dx={}, dy={}",
-                dx, dy
+                "Radial grid radii must be finite with 0 < inner < outer, got inner={}, outer={}",
+                inner_radius_m, outer_radius_m
+            ));
+        }
+        if !sector_angle_deg.is_finite() || sector_angle_deg <= 0.0 || sector_angle_deg > 360.0 {
+            return Err(format!(
+                "Radial sector angle must be in (0, 360] degrees, got {}",
+                sector_angle_deg
             ));
         }
         if dz_per_layer.len() != self.nz {
@@ -666,9 +1832,254 @@ impl ReservoirSimulator {
                 ));
             }
         }
-        self.dx = dx;
-        self.dy = dy;
+        if let Some(well) = self.wells.iter().find(|well| well.i != 0) {
+            return Err(format!(
+                "Wells in a radial grid must be completed in the innermost ring, got i={}",
+                well.i
+            ));
+        }
+
+        let radial = crate::grid::RadialGrid::logarithmic(
+            self.nx,
+            inner_radius_m,
+            outer_radius_m,
+            sector_angle_deg.to_radians(),
+        );
+        for well in &self.wells {
+            radial.check_well_radius(well.well_radius)?;
+        }
+        self.dx = radial
+            .ring_edges_m
+            .windows(2)
+            .map(|edges| edges[1] - edges[0])
+            .collect();
+        self.dy = vec![radial.sector_angle_rad * outer_radius_m];
         self.dz = dz_per_layer;
+        self.radial_grid = Some(radial);
+        Ok(())
+    }
+
+    /// Refine a box of cells into a local grid (Eclipse `CARFIN`).
+    ///
+    /// The box `i1..=i2`, `j1..=j2`, `k1..=k2` (0-based, inclusive) is taken in the cells of the
+    /// local grid named `parent`, or of the main grid when `parent` is empty, and each of its
+    /// cells is split into `rx × ry × rz` children. Children inherit their parent's rock and
+    /// state; later per-cell setters on the main grid re-apply to them. The refined cells leave
+    /// the flow problem and report the pore-volume-weighted mean of their children. The box must
+    /// hold only active cells without wells and must not share a face with another local grid.
+    /// Complete wells in child cells with [`add_local_grid_well`](Self::add_local_grid_well).
+    #[wasm_bindgen(js_name = addLocalGridRefinement)]
+    // The box bounds and refinement ratios stay scalars, as in Eclipse `CARFIN`, so the JS
+    // binding needs no wrapper object.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_local_grid_refinement(
+        &mut self,
+        name: String,
+        parent: String,
+        i1: usize,
+        i2: usize,
+        j1: usize,
+        j2: usize,
+        k1: usize,
+        k2: usize,
+        rx: usize,
+        ry: usize,
+        rz: usize,
+    ) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Local grid name must not be empty".to_string());
+        }
+        if self.local_grid_index(name).is_some() {
+            return Err(format!("A local grid named '{}' already exists", name));
+        }
+        if self.radial_grid.is_some() {
+            return Err("A radial grid cannot carry local grid refinements".to_string());
+        }
+        if self.dual_porosity.is_some() {
+            return Err("A dual-porosity model cannot carry local grid refinements".to_string());
+        }
+        let parent = parent.trim();
+        let parent_grid = if parent.is_empty() {
+            None
+        } else {
+            Some(
+                self.local_grid_index(parent)
+                    .ok_or_else(|| format!("No local grid found with name '{}'", parent))?,
+            )
+        };
+        self.add_local_grid(name, parent_grid, [i1, j1, k1], [i2, j2, k2], [rx, ry, rz])
+    }
+
+    /// Current pressure, saturations and `Rs` of every cell of a local grid, flat in the local
+    /// grid's own `i`, `j`, `k` order, together with its `dims`. Inactive children read NaN.
+    #[wasm_bindgen(js_name = getLocalGridState)]
+    pub fn get_local_grid_state(&self, name: String) -> Result<JsValue, String> {
+        let grid = self
+            .local_grid_index(&name)
+            .ok_or_else(|| format!("No local grid found with name '{}'", name))?;
+        let grid = &self.local_grids[grid];
+        let cells = grid.first_cell..grid.first_cell + grid.dims.iter().product::<usize>();
+        let field = |values: &[f64]| {
+            let values: Vec<f64> = cells
+                .clone()
+                .map(|id| {
+                    if self.cell_active[id] {
+                        values[id]
+                    } else {
+                        f64::NAN
+                    }
+                })
+                .collect();
+            Float64Array::from(values.as_slice())
+        };
+
+        let payload = Object::new();
+        set_object_property(
+            &payload,
+            "dims",
+            &serde_wasm_bindgen::to_value(&grid.dims).unwrap(),
+        );
+        set_object_property(&payload, "pressure", &field(&self.pressure).into());
+        set_object_property(&payload, "sat_water", &field(&self.sat_water).into());
+        set_object_property(&payload, "sat_oil", &field(&self.sat_oil).into());
+        set_object_property(&payload, "sat_gas", &field(&self.sat_gas).into());
+        set_object_property(&payload, "rs", &field(&self.rs).into());
+        Ok(payload.into())
+    }
+
+    /// Switch to a dual-porosity model (Eclipse `DUALPORO`), or with `dual_permeability` to a
+    /// dual-permeability one (`DUALPERM`).
+    ///
+    /// The current rock becomes the fracture continuum and each cell gains a matrix cell with
+    /// `matrix_porosity` and `matrix_perm_x/y/z` (same flat order and contracts as
+    /// [`set_porosity_field`](Self::set_porosity_field) and
+    /// [`set_permeability_field`](Self::set_permeability_field)). Matrix and fracture exchange
+    /// fluid through `shape_factor · k_m,x · V` with `shape_factor` in 1/m² — Kazemi's
+    /// `4 · (1/Lx² + 1/Ly² + 1/Lz²)` for matrix blocks of size `L`. A dual-permeability model
+    /// also lets the matrix flow between neighbouring cells. The matrix starts in the fracture
+    /// cell's state and later per-cell state setters re-apply to it; wells complete in the
+    /// fracture. Pass an empty `matrix_porosity` to return to a single continuum.
+    #[wasm_bindgen(js_name = setDualPorosity)]
+    pub fn set_dual_porosity(
+        &mut self,
+        matrix_porosity: Vec<f64>,
+        matrix_perm_x: Vec<f64>,
+        matrix_perm_y: Vec<f64>,
+        matrix_perm_z: Vec<f64>,
+        shape_factor: f64,
+        dual_permeability: bool,
+    ) -> Result<(), String> {
+        if matrix_porosity.is_empty() {
+            self.set_dual_porosity_continua(None);
+            return Ok(());
+        }
+        if !self.local_grids.is_empty() {
+            return Err("A dual-porosity model cannot carry local grid refinements".to_string());
+        }
+        if !shape_factor.is_finite() || shape_factor <= 0.0 {
+            return Err(format!(
+                "Shape factor must be positive and finite, got: {}",
+                shape_factor
+            ));
+        }
+        let total = self.nx * self.ny * self.nz;
+        if matrix_porosity.len() != total
+            || matrix_perm_x.len() != total
+            || matrix_perm_y.len() != total
+            || matrix_perm_z.len() != total
+        {
+            return Err(format!(
+                "Matrix porosity and permeability vectors must have length equal to nx*ny*nz ({})",
+                total
+            ));
+        }
+        for id in 0..total {
+            let phi = matrix_porosity[id];
+            if !phi.is_finite() || phi <= 0.0 || phi > 1.0 {
+                return Err(format!(
+                    "Matrix porosity for cell {} must be finite and within (0, 1], got {}",
+                    id, phi
+                ));
+            }
+            let perms = [matrix_perm_x[id], matrix_perm_y[id], matrix_perm_z[id]];
+            if perms.iter().any(|perm| !perm.is_finite() || *perm <= 0.0) {
+                return Err(format!(
+                    "Matrix permeability for cell {} must be positive and finite, got px={}, py={}, pz={}",
+                    id, perms[0], perms[1], perms[2]
+                ));
+            }
+        }
+        self.set_dual_porosity_continua(Some(DualPorosity {
+            shape_factor,
+            dual_permeability,
+            matrix_porosity,
+            matrix_perm: [matrix_perm_x, matrix_perm_y, matrix_perm_z],
+        }));
+        Ok(())
+    }
+
+    /// Current pressure, saturations and `Rs` of the matrix continuum of a dual-porosity model,
+    /// in the same flat cell order as the fracture fields. Inactive cells read NaN.
+    #[wasm_bindgen(js_name = getMatrixState)]
+    pub fn get_matrix_state(&self) -> Result<JsValue, String> {
+        if self.dual_porosity.is_none() {
+            return Err(
+                "The model has no matrix continuum; call setDualPorosity first".to_string(),
+            );
+        }
+        let root_cells = self.root_cell_count();
+        let field = |values: &[f64]| {
+            let values: Vec<f64> = (0..root_cells)
+                .map(|id| {
+                    if self.cell_active[id] {
+                        values[root_cells + id]
+                    } else {
+                        f64::NAN
+                    }
+                })
+                .collect();
+            Float64Array::from(values.as_slice())
+        };
+
+        let payload = Object::new();
+        set_object_property(&payload, "pressure", &field(&self.pressure).into());
+        set_object_property(&payload, "sat_water", &field(&self.sat_water).into());
+        set_object_property(&payload, "sat_oil", &field(&self.sat_oil).into());
+        set_object_property(&payload, "sat_gas", &field(&self.sat_gas).into());
+        set_object_property(&payload, "rs", &field(&self.rs).into());
+        Ok(payload.into())
+    }
+
+    /// Set structural top depths [m TVDSS] (Eclipse `TOPS`).
+    ///
+    /// Accepts either one top per column (`nx * ny`, flat cell order `j*nx + i`), beneath which
+    /// layers stack by their `dz`, or one top per cell (`nx * ny * nz`, same order as
+    /// [`set_permeability_field`](Self::set_permeability_field)). Either replaces the flat
+    /// `depth_reference_m` layer-cake for gravity heads and well datum offsets. Pass an empty
+    /// vector to return to the flat layers.
+    #[wasm_bindgen(js_name = setCellTops)]
+    pub fn set_cell_tops(&mut self, tops_m: Vec<f64>) -> Result<(), String> {
+        let columns = self.nx * self.ny;
+        let cells = columns * self.nz;
+        if tops_m.is_empty() {
+            self.cell_top_depth_m = None;
+            self.refresh_well_head_offsets();
+            return Ok(());
+        }
+        if tops_m.len() != columns && tops_m.len() != cells {
+            return Err(format!(
+                "Cell tops must have length nx*ny ({}) or nx*ny*nz ({}), got {}",
+                columns,
+                cells,
+                tops_m.len()
+            ));
+        }
+        if let Some((id, top)) = tops_m.iter().enumerate().find(|(_, top)| !top.is_finite()) {
+            return Err(format!("Cell top {} must be finite, got {}", id, top));
+        }
+        self.cell_top_depth_m = Some(tops_m);
+        self.refresh_well_head_offsets();
         Ok(())
     }
 
@@ -678,6 +2089,7 @@ impl ReservoirSimulator {
             self.sat_water[i] = sat_water.clamp(0.0, 1.0);
             self.sat_oil[i] = 1.0 - self.sat_water[i];
         }
+        self.inherit_appended_cell_state();
     }
 
     #[wasm_bindgen(js_name = setInitialSaturationPerLayer)]
@@ -711,6 +2123,7 @@ impl ReservoirSimulator {
                 }
             }
         }
+        self.inherit_appended_cell_state();
         Ok(())
     }
 
@@ -893,6 +2306,7 @@ impl ReservoirSimulator {
             self.perm_y[i] = rng.random_range(min_perm..=max_perm);
             self.perm_z[i] = rng.random_range(min_perm..=max_perm) / 10.0;
         }
+        self.inherit_appended_cell_rock();
         Ok(())
     }
 
@@ -925,6 +2339,7 @@ impl ReservoirSimulator {
             self.perm_y[i] = rng.random_range(min_perm..=max_perm);
             self.perm_z[i] = rng.random_range(min_perm..=max_perm) / 10.0;
         }
+        self.inherit_appended_cell_rock();
         Ok(())
     }
 
@@ -1003,7 +2418,19 @@ impl ReservoirSimulator {
             self.cumulative_injection_m3 = last.total_injection_reservoir;
             self.cumulative_production_m3 = last.total_production_liquid_reservoir;
         }
+        let aquifer_points = self
+            .rate_history
+            .last()
+            .map(|last| last.aquifers.clone())
+            .unwrap_or_default();
+        for (idx, aquifer) in self.aquifers.iter_mut().enumerate() {
+            let point = aquifer_points.get(idx);
+            aquifer.pressure_bar =
+                point.map_or(aquifer.initial_pressure_bar, |point| point.pressure);
+            aquifer.cumulative_influx_m3 = point.map_or(0.0, |point| point.cumulative_influx);
+        }
 
+        self.inherit_appended_cell_state();
         Ok(())
     }
 
@@ -1016,6 +2443,7 @@ impl ReservoirSimulator {
             self.rs[i] = table.interpolate(self.pressure[i]).rs_m3m3;
         }
         self.pvt_table = Some(table);
+        self.inherit_appended_cell_state();
         Ok(())
     }
 
@@ -1025,6 +2453,7 @@ impl ReservoirSimulator {
         for i in 0..n {
             self.rs[i] = rs;
         }
+        self.inherit_appended_cell_state();
     }
 
     #[wasm_bindgen(js_name = setInitialGasSaturation)]
@@ -1037,6 +2466,7 @@ impl ReservoirSimulator {
             self.sat_gas[i] = sg_clamped;
             self.sat_oil[i] = (1.0 - sw - sg_clamped).max(0.0);
         }
+        self.inherit_appended_cell_state();
     }
 
     #[wasm_bindgen(js_name = setInitialGasSaturationPerLayer)]
@@ -1073,17 +2503,18 @@ impl ReservoirSimulator {
                 }
             }
         }
+        self.inherit_appended_cell_state();
         Ok(())
     }
 
     #[wasm_bindgen(js_name = getSatGas)]
     pub fn get_sat_gas(&self) -> Vec<f64> {
-        self.sat_gas.clone()
+        self.masked_cell_values(&self.sat_gas)
     }
 
     #[wasm_bindgen(js_name = getRs)]
     pub fn get_rs(&self) -> Vec<f64> {
-        self.rs.clone()
+        self.masked_cell_values(&self.rs)
     }
 
     #[wasm_bindgen(js_name = setThreePhaseModeEnabled)]
@@ -1224,6 +2655,21 @@ impl ReservoirSimulator {
         Ok(())
     }
 
+    /// Record every completion's share of its well's rates in each rate-history point's
+    /// `wells[].completions`. Off by default; the per-well rates are always recorded.
+    #[wasm_bindgen(js_name = setCompletionRateReporting)]
+    pub fn set_completion_rate_reporting(&mut self, enabled: bool) {
+        self.report_completion_rates = enabled;
+    }
+
+    /// Report each well's rates and cumulatives in the rate history for the full well rather
+    /// than the share inside a pattern-element model, i.e. divided by its `well_fraction`.
+    /// Off by default; field totals always stay the element's.
+    #[wasm_bindgen(js_name = setFullPatternRateReporting)]
+    pub fn set_full_pattern_rate_reporting(&mut self, enabled: bool) {
+        self.report_full_pattern_rates = enabled;
+    }
+
     #[wasm_bindgen(js_name = setInjectedFluid)]
     pub fn set_injected_fluid(&mut self, fluid: &str) -> Result<(), String> {
         self.injected_fluid = match fluid.to_ascii_lowercase().as_str() {
@@ -1276,6 +2722,7 @@ impl ReservoirSimulator {
                 }
             }
         }
+        self.inherit_appended_cell_rock();
         Ok(())
     }
 
@@ -1320,6 +2767,511 @@ impl ReservoirSimulator {
         self.perm_x = perms_x;
         self.perm_y = perms_y;
         self.perm_z = perms_z;
+        self.inherit_appended_cell_rock();
+        Ok(())
+    }
+
+    /// Set per-cell porosity from a full-length field vector.
+    ///
+    /// Same flat cell order and length contract as
+    /// [`set_permeability_field`](Self::set_permeability_field). Every entry must be finite and
+    /// within `(0, 1]`. Porosity is defined at the rock reference pressure, so pore volume — and
+    /// every in-place and sweep report derived from it — follows the new field directly.
+    #[wasm_bindgen(js_name = setPorosityField)]
+    pub fn set_porosity_field(&mut self, porosity: Vec<f64>) -> Result<(), String> {
+        let total = self.nx * self.ny * self.nz;
+        if porosity.len() != total {
+            return Err(format!(
+                "Porosity field vector must have length equal to nx*ny*nz ({})",
+                total
+            ));
+        }
+        for (id, &phi) in porosity.iter().enumerate() {
+            if !phi.is_finite() || phi <= 0.0 || phi > 1.0 {
+                return Err(format!(
+                    "Porosity for cell {} must be finite and within (0, 1], got {}",
+                    id, phi
+                ));
+            }
+        }
+        self.porosity = porosity;
+        self.inherit_appended_cell_rock();
+        Ok(())
+    }
+
+    /// Set the per-cell net-to-gross ratio (Eclipse `NTG`).
+    ///
+    /// NTG multiplies pore volume, the x/y face transmissibilities and the completion `kh` of
+    /// each cell; z transmissibility uses the gross cell and is unchanged. Pass an empty vector
+    /// to reset every cell to 1. Entries must be finite and within `(0, 1]`.
+    #[wasm_bindgen(js_name = setNetToGrossField)]
+    pub fn set_net_to_gross_field(&mut self, net_to_gross: Vec<f64>) -> Result<(), String> {
+        let total = self.nx * self.ny * self.nz;
+        if net_to_gross.is_empty() {
+            self.net_to_gross = vec![1.0; total];
+            self.inherit_appended_cell_rock();
+            return Ok(());
+        }
+        if net_to_gross.len() != total {
+            return Err(format!(
+                "Net-to-gross field vector must have length equal to nx*ny*nz ({})",
+                total
+            ));
+        }
+        for (id, &ntg) in net_to_gross.iter().enumerate() {
+            if !ntg.is_finite() || ntg <= 0.0 || ntg > 1.0 {
+                return Err(format!(
+                    "Net-to-gross for cell {} must be finite and within (0, 1], got {}",
+                    id, ntg
+                ));
+            }
+        }
+        self.net_to_gross = net_to_gross;
+        self.inherit_appended_cell_rock();
+        Ok(())
+    }
+
+    /// Set the active-cell mask (Eclipse `ACTNUM`), one entry per cell in the same order as
+    /// [`set_permeability_field`](Self::set_permeability_field); non-zero marks a cell active.
+    ///
+    /// Inactive cells drop out of the flow problem: they carry no pore volume, every face
+    /// touching them is closed, and neither solver gives them an unknown. Their state is frozen
+    /// and reported as NaN. Pass an empty vector to reactivate every cell. Local-grid children
+    /// follow the entry of the cell they refine.
+    #[wasm_bindgen(js_name = setActiveCells)]
+    pub fn set_active_cells(&mut self, actnum: Vec<u8>) -> Result<(), String> {
+        let total = self.nx * self.ny * self.nz;
+        if actnum.is_empty() {
+            self.cell_active = self.expand_active_cells(vec![true; total]);
+            return Ok(());
+        }
+        if actnum.len() != total {
+            return Err(format!(
+                "Active cell vector must have length equal to nx*ny*nz ({})",
+                total
+            ));
+        }
+        if actnum.iter().all(|&flag| flag == 0) {
+            return Err("At least one cell must be active".to_string());
+        }
+        let cell_active = self.expand_active_cells(actnum.iter().map(|&flag| flag != 0).collect());
+        for well in &self.wells {
+            if !cell_active[self.well_cell_index(well)] {
+                return Err(format!(
+                    "Well completion in inactive cell is not allowed: ({}, {}, {})",
+                    well.i, well.j, well.k
+                ));
+            }
+        }
+        self.cell_active = cell_active;
+        Ok(())
+    }
+
+    /// Set per-face transmissibility multipliers (Eclipse `MULTX`/`MULTY`/`MULTZ`), one entry
+    /// per cell in the same order as [`set_permeability_field`](Self::set_permeability_field).
+    ///
+    /// Each entry scales the face a cell shares with its `i + 1`, `j + 1` or `k + 1` neighbour;
+    /// `0` seals it. Pass an empty vector for a direction to reset it to 1. Fault multipliers
+    /// from [`set_fault`](Self::set_fault) apply on top.
+    #[wasm_bindgen(js_name = setTransmissibilityMultipliers)]
+    pub fn set_transmissibility_multipliers(
+        &mut self,
+        mult_x: Vec<f64>,
+        mult_y: Vec<f64>,
+        mult_z: Vec<f64>,
+    ) -> Result<(), String> {
+        let total = self.nx * self.ny * self.nz;
+        let resolve = |name: &str, values: Vec<f64>| -> Result<Vec<f64>, String> {
+            if values.is_empty() {
+                return Ok(vec![1.0; total]);
+            }
+            if values.len() != total {
+                return Err(format!(
+                    "{} must have length equal to nx*ny*nz ({}), got {}",
+                    name,
+                    total,
+                    values.len()
+                ));
+            }
+            if let Some((id, value)) = values
+                .iter()
+                .enumerate()
+                .find(|(_, value)| !value.is_finite() || **value < 0.0)
+            {
+                return Err(format!(
+                    "{} for cell {} must be finite and non-negative, got {}",
+                    name, id, value
+                ));
+            }
+            Ok(values)
+        };
+        let mult_x = resolve("MULTX", mult_x)?;
+        let mult_y = resolve("MULTY", mult_y)?;
+        let mult_z = resolve("MULTZ", mult_z)?;
+        self.mult_x = mult_x;
+        self.mult_y = mult_y;
+        self.mult_z = mult_z;
+        self.refresh_face_multipliers();
+        Ok(())
+    }
+
+    /// Define or replace a named fault (Eclipse `FAULTS` with `MULTFLT`).
+    ///
+    /// `cell_ids[n]` and the `n`-th character of `faces` name one fault face: `X`, `Y` or `Z`
+    /// selects the face the cell shares with its `i + 1`, `j + 1` or `k + 1` neighbour. Every
+    /// face of the fault has its transmissibility multiplied by `multiplier` (`0` seals it).
+    /// Redefining a name replaces the previous fault of that name.
+    #[wasm_bindgen(js_name = setFault)]
+    pub fn set_fault(
+        &mut self,
+        name: String,
+        cell_ids: Vec<u32>,
+        faces: String,
+        multiplier: f64,
+    ) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Fault name must not be empty".to_string());
+        }
+        if !multiplier.is_finite() || multiplier < 0.0 {
+            return Err(format!(
+                "Fault multiplier must be finite and non-negative, got {}",
+                multiplier
+            ));
+        }
+        let dims: Vec<char> = faces.chars().map(|c| c.to_ascii_lowercase()).collect();
+        if dims.len() != cell_ids.len() {
+            return Err(format!(
+                "Fault '{}' needs one face per cell, got {} cells and {} faces",
+                name,
+                cell_ids.len(),
+                dims.len()
+            ));
+        }
+        let total = self.nx * self.ny * self.nz;
+        let mut fault_faces = Vec::with_capacity(cell_ids.len());
+        for (&cell, &dim) in cell_ids.iter().zip(&dims) {
+            let cell = cell as usize;
+            if cell >= total {
+                return Err(format!(
+                    "Fault '{}' cell {} is out of bounds (nx*ny*nz = {})",
+                    name, cell, total
+                ));
+            }
+            if !matches!(dim, 'x' | 'y' | 'z') {
+                return Err(format!(
+                    "Fault '{}' face must be one of X, Y or Z, got '{}'",
+                    name, dim
+                ));
+            }
+            if !self.has_plus_neighbor(cell, dim) {
+                return Err(format!(
+                    "Fault '{}' cell {} has no neighbour across its +{} face",
+                    name,
+                    cell,
+                    dim.to_ascii_uppercase()
+                ));
+            }
+            fault_faces.push((cell, dim));
+        }
+
+        let fault = crate::grid::Fault {
+            name: name.to_string(),
+            faces: fault_faces,
+            multiplier,
+        };
+        match self
+            .faults
+            .iter_mut()
+            .find(|existing| existing.name == name)
+        {
+            Some(existing) => *existing = fault,
+            None => self.faults.push(fault),
+        }
+        self.refresh_face_multipliers();
+        Ok(())
+    }
+
+    /// Change the transmissibility multiplier of an existing fault (Eclipse `MULTFLT`), e.g.
+    /// to open or seal it between report steps.
+    #[wasm_bindgen(js_name = setFaultMultiplier)]
+    pub fn set_fault_multiplier(&mut self, name: String, multiplier: f64) -> Result<(), String> {
+        if !multiplier.is_finite() || multiplier < 0.0 {
+            return Err(format!(
+                "Fault multiplier must be finite and non-negative, got {}",
+                multiplier
+            ));
+        }
+        let name = name.trim();
+        let Some(fault) = self.faults.iter_mut().find(|fault| fault.name == name) else {
+            return Err(format!("No fault found with name '{}'", name));
+        };
+        fault.multiplier = multiplier;
+        self.refresh_face_multipliers();
+        Ok(())
+    }
+
+    /// Replace the non-neighbour connection list (Eclipse `NNC`).
+    ///
+    /// Entry `n` joins cells `cell_a[n]` and `cell_b[n]` (flat indices, same order as
+    /// [`set_permeability_field`](Self::set_permeability_field)) with geometric
+    /// transmissibility `transmissibility[n]` [mD·m²/m], the same units as a Cartesian face
+    /// before the Darcy factor and mobility are applied. Connections are added to the
+    /// Cartesian faces, so one joining two neighbours acts in parallel with their shared face.
+    /// Connections touching an inactive cell are ignored. Pass empty vectors to remove all.
+    #[wasm_bindgen(js_name = setNonNeighborConnections)]
+    pub fn set_non_neighbor_connections(
+        &mut self,
+        cell_a: Vec<u32>,
+        cell_b: Vec<u32>,
+        transmissibility: Vec<f64>,
+    ) -> Result<(), String> {
+        if cell_a.len() != cell_b.len() || cell_a.len() != transmissibility.len() {
+            return Err(format!(
+                "Non-neighbour connection vectors must have equal lengths, got {}, {} and {}",
+                cell_a.len(),
+                cell_b.len(),
+                transmissibility.len()
+            ));
+        }
+        let total = self.nx * self.ny * self.nz;
+        let mut connections = Vec::with_capacity(cell_a.len());
+        for (index, ((&a, &b), &t)) in cell_a
+            .iter()
+            .zip(&cell_b)
+            .zip(&transmissibility)
+            .enumerate()
+        {
+            let (a, b) = (a as usize, b as usize);
+            if a >= total || b >= total {
+                return Err(format!(
+                    "Non-neighbour connection {} cell is out of bounds (nx*ny*nz = {})",
+                    index, total
+                ));
+            }
+            if a == b {
+                return Err(format!(
+                    "Non-neighbour connection {} must join two different cells, got {} twice",
+                    index, a
+                ));
+            }
+            if !t.is_finite() || t < 0.0 {
+                return Err(format!(
+                    "Non-neighbour connection {} transmissibility must be finite and non-negative, got {}",
+                    index, t
+                ));
+            }
+            connections.push(crate::grid::NonNeighborConnection {
+                cell_a: a,
+                cell_b: b,
+                transmissibility: t,
+            });
+        }
+        self.non_neighbor_connections = connections;
+        self.rebuild_non_neighbor_index();
+        Ok(())
+    }
+
+    /// Hold an outer face of the grid at a constant pressure.
+    ///
+    /// `face` is one of `x-`, `x+`, `y-`, `y+`, `z-` or `z+`; on an `x` or `y` face, `layers`
+    /// (0-based `k`) restricts the condition to those layers, and an empty list covers them all.
+    /// Each face cell sees a ghost cell at its own depth holding `pressure_bar`, water saturation
+    /// `sat_water` and, in three-phase mode, gas saturation `sat_gas`; fluid flows in or out
+    /// across the half-cell transmissibility as the potentials dictate, and inflow carries the
+    /// ghost's phase mix.
+    #[wasm_bindgen(js_name = addPressureBoundary)]
+    pub fn add_pressure_boundary(
+        &mut self,
+        face: String,
+        layers: Vec<u32>,
+        pressure_bar: f64,
+        sat_water: f64,
+        sat_gas: f64,
+    ) -> Result<(), String> {
+        if !pressure_bar.is_finite() || pressure_bar <= 0.0 {
+            return Err(format!(
+                "Boundary pressure must be positive and finite, got {}",
+                pressure_bar
+            ));
+        }
+        self.add_boundary_condition(
+            &face,
+            layers,
+            BoundaryDrive::Pressure { pressure_bar },
+            sat_water,
+            sat_gas,
+        )
+    }
+
+    /// Prescribe the reservoir-volume flux [m³/day] across an outer face of the grid, positive
+    /// into the reservoir. `face` and `layers` select the faces as in
+    /// [`add_pressure_boundary`](Self::add_pressure_boundary), and the rate is split over them in
+    /// proportion to their half-cell transmissibility. Inflow carries the phase mix that
+    /// `sat_water` and `sat_gas` would flow; outflow carries each cell's own.
+    #[wasm_bindgen(js_name = addFluxBoundary)]
+    pub fn add_flux_boundary(
+        &mut self,
+        face: String,
+        layers: Vec<u32>,
+        rate_m3_day: f64,
+        sat_water: f64,
+        sat_gas: f64,
+    ) -> Result<(), String> {
+        if !rate_m3_day.is_finite() {
+            return Err(format!("Boundary rate must be finite, got {}", rate_m3_day));
+        }
+        self.add_boundary_condition(
+            &face,
+            layers,
+            BoundaryDrive::Rate { rate_m3_day },
+            sat_water,
+            sat_gas,
+        )
+    }
+
+    /// Attach a Fetkovich aquifer to a list of outer faces of the grid and return its index in
+    /// the reported `aquifers`.
+    ///
+    /// The aquifer is a pot of water at `initial_pressure_bar` with total compressibility ×
+    /// volume `compressibility_volume_m3_bar` [m³/bar], feeding the reservoir through
+    /// `productivity_index` [m³/(day·bar)] shared among all of its faces in proportion to their
+    /// half-cell transmissibility. `faces` and `layers` select the faces as in
+    /// [`add_pressure_boundary`](Self::add_pressure_boundary), with `layers` applied to every
+    /// `x` and `y` face in the list. With gravity on, the pressure is referenced to the depth of
+    /// the shallowest face cell. The influx is water only, and the pot loses what it lets in.
+    #[wasm_bindgen(js_name = addFetkovichAquifer)]
+    pub fn add_fetkovich_aquifer(
+        &mut self,
+        faces: Vec<String>,
+        layers: Vec<u32>,
+        initial_pressure_bar: f64,
+        productivity_index: f64,
+        compressibility_volume_m3_bar: f64,
+    ) -> Result<u32, String> {
+        for (name, value) in [
+            ("initial pressure", initial_pressure_bar),
+            ("productivity index", productivity_index),
+            ("compressibility × volume", compressibility_volume_m3_bar),
+        ] {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(format!(
+                    "Aquifer {} must be positive and finite, got {}",
+                    name, value
+                ));
+            }
+        }
+        if faces.is_empty() {
+            return Err("An aquifer needs at least one boundary face".to_string());
+        }
+
+        let aquifer = self.aquifers.len();
+        let conditions_before = self.boundary_conditions.len();
+        let drive = BoundaryDrive::Aquifer {
+            aquifer,
+            productivity_index,
+        };
+        for face in &faces {
+            let layers = if face.trim().starts_with(['z', 'Z']) {
+                Vec::new()
+            } else {
+                layers.clone()
+            };
+            if let Err(message) = self.add_boundary_condition(face, layers, drive, 1.0, 0.0) {
+                self.boundary_conditions.truncate(conditions_before);
+                return Err(message);
+            }
+        }
+        self.aquifers.push(FetkovichAquifer {
+            initial_pressure_bar,
+            pressure_bar: initial_pressure_bar,
+            productivity_index,
+            compressibility_volume_m3_bar,
+            datum_depth_m: 0.0,
+            cumulative_influx_m3: 0.0,
+        });
+        let datum_depth_m = self
+            .boundary_connections()
+            .iter()
+            .filter(|connection| {
+                matches!(connection.drive, BoundaryDrive::Aquifer { aquifer: a, .. } if a == aquifer)
+            })
+            .map(|connection| self.depth_at(connection.cell))
+            .reduce(f64::min);
+        let Some(datum_depth_m) = datum_depth_m else {
+            self.aquifers.pop();
+            self.boundary_conditions.truncate(conditions_before);
+            return Err("Aquifer faces have no active cells".to_string());
+        };
+        self.aquifers[aquifer].datum_depth_m = datum_depth_m;
+        Ok(aquifer as u32)
+    }
+
+    /// Remove every boundary condition and aquifer, closing all outer faces again.
+    #[wasm_bindgen(js_name = clearBoundaryConditions)]
+    pub fn clear_boundary_conditions(&mut self) {
+        self.boundary_conditions.clear();
+        self.aquifers.clear();
+    }
+
+    fn add_boundary_condition(
+        &mut self,
+        face: &str,
+        layers: Vec<u32>,
+        drive: BoundaryDrive,
+        sat_water: f64,
+        sat_gas: f64,
+    ) -> Result<(), String> {
+        let face = face.trim().to_ascii_lowercase();
+        let (dim, plus) = match face.as_str() {
+            "x-" => ('x', false),
+            "x+" => ('x', true),
+            "y-" => ('y', false),
+            "y+" => ('y', true),
+            "z-" => ('z', false),
+            "z+" => ('z', true),
+            _ => {
+                return Err(format!(
+                    "Boundary face must be one of x-, x+, y-, y+, z- or z+, got '{}'",
+                    face
+                ));
+            }
+        };
+        if dim == 'y' && self.radial_grid.is_some() {
+            return Err("A radial grid has no y boundary faces".to_string());
+        }
+        if dim == 'z' && !layers.is_empty() {
+            return Err(format!(
+                "A {} boundary covers a whole layer and takes no layer list",
+                face
+            ));
+        }
+        let layers: Vec<usize> = layers.into_iter().map(|k| k as usize).collect();
+        if let Some(k) = layers.iter().find(|&&k| k >= self.nz) {
+            return Err(format!(
+                "Boundary layer {} is out of bounds (nz = {})",
+                k, self.nz
+            ));
+        }
+        if !sat_water.is_finite()
+            || !sat_gas.is_finite()
+            || sat_water < 0.0
+            || sat_gas < 0.0
+            || sat_water + sat_gas > 1.0
+        {
+            return Err(format!(
+                "Boundary saturations must be non-negative with sat_water + sat_gas <= 1, got sat_water={}, sat_gas={}",
+                sat_water, sat_gas
+            ));
+        }
+        self.boundary_conditions.push(BoundaryCondition {
+            dim,
+            plus,
+            layers,
+            drive,
+            sat_water,
+            sat_gas,
+        });
         Ok(())
     }
 }
@@ -8,27 +8,220 @@ fn harmonic_mean(a: f64, b: f64) -> f64 {
     }
 }
 
+/// Harmonic mean of two half-cell transmissibilities `perm · area / (len / 2)`.
+fn half_cell_harmonic(perm1: f64, len1: f64, perm2: f64, len2: f64, area: f64) -> f64 {
+    let denom = perm2 * len1 + perm1 * len2;
+    if denom <= 0.0 {
+        return 0.0;
+    }
+    2.0 * perm1 * perm2 * area / denom
+}
+
+/// A named set of cell faces sharing one transmissibility multiplier (Eclipse `FAULTS` with
+/// `MULTFLT`). Each face is the `+` face of `cell` along `dim`, i.e. the face it shares with
+/// its `i + 1`, `j + 1` or `k + 1` neighbour.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Fault {
+    pub(crate) name: String,
+    pub(crate) faces: Vec<(usize, char)>,
+    pub(crate) multiplier: f64,
+}
+
+/// A user-supplied non-neighbour connection (Eclipse `NNC`): two cells joined by an explicit
+/// geometric transmissibility [mD·m²/m], independent of grid adjacency.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct NonNeighborConnection {
+    pub(crate) cell_a: usize,
+    pub(crate) cell_b: usize,
+    pub(crate) transmissibility: f64,
+}
+
+/// One open edge of the flow graph: a Cartesian face (`dim` is `'x'`, `'y'` or `'z'`, with
+/// `id_j` the `+` neighbour of `id_i`) or a non-neighbour connection (`dim` is `'n'`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct FlowConnection {
+    pub(crate) id_i: usize,
+    pub(crate) id_j: usize,
+    pub(crate) dim: char,
+    /// Geometric transmissibility [mD·m²/m], multipliers included.
+    pub(crate) transmissibility: f64,
+}
+
+/// Cylindrical `r`-`z` geometry (Eclipse `RADIAL`): column `i` is a ring about a well on the
+/// grid axis, layers stack by `dz`, and the single row spans `sector_angle_rad`. Pore volume and
+/// transmissibility come from the ring radii instead of `dx`/`dy`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RadialGrid {
+    /// Ring boundaries [m], `nx + 1` of them, from the inner radius outwards.
+    pub(crate) ring_edges_m: Vec<f64>,
+    pub(crate) sector_angle_rad: f64,
+}
+
+impl RadialGrid {
+    /// `nx` rings spaced logarithmically between `inner_radius_m` and `outer_radius_m`, so every
+    /// ring carries the same steady-state radial pressure drop.
+    pub(crate) fn logarithmic(
+        nx: usize,
+        inner_radius_m: f64,
+        outer_radius_m: f64,
+        sector_angle_rad: f64,
+    ) -> Self {
+        let ratio = outer_radius_m / inner_radius_m;
+        let mut ring_edges_m: Vec<f64> = (0..=nx)
+            .map(|i| inner_radius_m * ratio.powf(i as f64 / nx as f64))
+            .collect();
+        ring_edges_m[nx] = outer_radius_m;
+        Self {
+            ring_edges_m,
+            sector_angle_rad,
+        }
+    }
+
+    /// Pressure-equivalent centre of ring `i`: the geometric mean of its bounding radii.
+    pub(crate) fn ring_centre_m(&self, i: usize) -> f64 {
+        (self.ring_edges_m[i] * self.ring_edges_m[i + 1]).sqrt()
+    }
+
+    /// A completion in the innermost ring needs its wellbore inside the ring centre, which
+    /// stands in for Peaceman's equivalent radius.
+    pub(crate) fn check_well_radius(&self, well_radius: f64) -> Result<(), String> {
+        let centre = self.ring_centre_m(0);
+        if well_radius >= centre {
+            return Err(format!(
+                "Well radius {} must be smaller than the innermost ring centre {} of the radial grid",
+                well_radius, centre
+            ));
+        }
+        Ok(())
+    }
+
+    fn ring_area_m2(&self, i: usize) -> f64 {
+        0.5 * self.sector_angle_rad
+            * (self.ring_edges_m[i + 1].powi(2) - self.ring_edges_m[i].powi(2))
+    }
+}
+
+fn axis_index(dim: char) -> Option<usize> {
+    match dim {
+        'x' => Some(0),
+        'y' => Some(1),
+        'z' => Some(2),
+        _ => None,
+    }
+}
+
 impl ReservoirSimulator {
+    pub(crate) fn dx_at(&self, id: usize) -> f64 {
+        if let Some(fracture) = self.fracture_cell(id) {
+            return self.dx_at(fracture);
+        }
+        if let Some((parent, refinement)) = self.local_refinement(id) {
+            return self.dx_at(parent) / refinement[0] as f64;
+        }
+        self.dx[id % self.nx]
+    }
+
+    pub(crate) fn dy_at(&self, id: usize) -> f64 {
+        if let Some(fracture) = self.fracture_cell(id) {
+            return self.dy_at(fracture);
+        }
+        if let Some((parent, refinement)) = self.local_refinement(id) {
+            return self.dy_at(parent) / refinement[1] as f64;
+        }
+        self.dy[(id / self.nx) % self.ny]
+    }
+
     pub(crate) fn dz_at(&self, id: usize) -> f64 {
+        if let Some(fracture) = self.fracture_cell(id) {
+            return self.dz_at(fracture);
+        }
+        if let Some((parent, refinement)) = self.local_refinement(id) {
+            return self.dz_at(parent) / refinement[2] as f64;
+        }
         let k = id / (self.nx * self.ny);
         self.dz[k]
     }
 
+    /// Gross bulk volume [m³] of cell `id`.
+    pub(crate) fn bulk_volume_m3(&self, id: usize) -> f64 {
+        if let Some(radial) = &self.radial_grid {
+            return radial.ring_area_m2(id % self.nx) * self.dz_at(id);
+        }
+        self.dx_at(id) * self.dy_at(id) * self.dz_at(id)
+    }
+
     pub fn pore_volume_m3(&self, id: usize) -> f64 {
-        self.dx * self.dy * self.dz_at(id) * self.porosity[id]
+        if !self.cell_active[id] {
+            return 0.0;
+        }
+        self.bulk_volume_m3(id) * self.porosity[id] * self.net_to_gross[id]
+    }
+
+    pub(crate) fn is_active(&self, id: usize) -> bool {
+        self.cell_active[id]
+    }
+
+    pub(crate) fn has_inactive_cells(&self) -> bool {
+        self.cell_active.iter().any(|&active| !active)
+    }
+
+    /// Compact equation row of each cell, `None` for inactive cells. Active cells keep
+    /// their natural order, so a fully active grid maps every cell to its own index.
+    pub(crate) fn active_cell_rows(&self) -> Vec<Option<usize>> {
+        let mut next_row = 0;
+        self.cell_active
+            .iter()
+            .map(|&active| {
+                active.then(|| {
+                    next_row += 1;
+                    next_row - 1
+                })
+            })
+            .collect()
     }
 
     pub(crate) fn idx(&self, i: usize, j: usize, k: usize) -> usize {
         (k * self.nx * self.ny) + (j * self.nx) + i
     }
 
-    pub(crate) fn depth_at_k(&self, k: usize) -> f64 {
-        let mut depth = self.depth_reference_m;
-        for layer in 0..k {
-            depth += self.dz[layer];
+    /// Cell-centre depth [m TVDSS] of cell `id`.
+    ///
+    /// Without structural tops every layer is flat: the depth is
+    /// `depth_reference_m` plus the thickness of the layers above plus half this
+    /// layer. With [`Self::cell_top_depth_m`] set, the top comes from the tops
+    /// array instead — per column (layers then stack by `dz` beneath it) or per
+    /// cell — so dipping and domed structures carry their own gravity heads.
+    /// A local-grid child sits at its slice of the parent's thickness, and a matrix cell at
+    /// the depth of its fracture cell.
+    pub(crate) fn depth_at(&self, id: usize) -> f64 {
+        if let Some(fracture) = self.fracture_cell(id) {
+            return self.depth_at(fracture);
+        }
+        if let Some(cell) = self.local_cell(id) {
+            let parent_dz = self.dz_at(cell.parent);
+            return self.depth_at(cell.parent) - 0.5 * parent_dz
+                + (cell.offset[2] as f64 + 0.5) * self.dz_at(id);
+        }
+        let cells_per_layer = self.nx * self.ny;
+        let k = id / cells_per_layer;
+        match self.cell_top_depth_m.as_deref() {
+            Some(tops) if tops.len() == cells_per_layer => {
+                let mut depth = tops[id % cells_per_layer];
+                for layer in 0..k {
+                    depth += self.dz[layer];
+                }
+                depth + self.dz[k] * 0.5
+            }
+            Some(tops) => tops[id] + self.dz[k] * 0.5,
+            None => {
+                let mut depth = self.depth_reference_m;
+                for layer in 0..k {
+                    depth += self.dz[layer];
+                }
+                depth += self.dz[k] * 0.5;
+                depth
+            }
         }
-        depth += self.dz[k] * 0.5;
-        depth
     }
 
     /// Geometric transmissibility factor [mD·m²/m] - geometry only, no mobility.
@@ -36,46 +229,378 @@ impl ReservoirSimulator {
     /// Uses the standard two-point flux approximation (TPFA): the harmonic mean
     /// of half-cell transmissibilities.
     ///
-    /// For x/y directions (same layer, same dz):
-    ///   T = 2·k1·k2 / (k1 + k2) · A / L
+    /// For every direction the face area is shared by both cells (a tensor-product
+    /// grid keeps the transverse widths of a row/column/layer fixed), while the
+    /// cell lengths along the flow direction may differ:
+    ///   T = 2·k1·k2·A / (k2·L1 + k1·L2)
+    ///
+    /// This is the harmonic mean of half-cell transmissibilities
+    /// `T_half_i = k_i · A / (L_i / 2)`, then `T = 1/(1/T1 + 1/T2)`. With equal
+    /// lengths it reduces to `2·k1·k2/(k1 + k2) · A / L`.
     ///
-    /// For z direction (different layer thicknesses and permeabilities):
-    ///   T = 2·k1·k2·A / (k2·dz1 + k1·dz2)
+    /// Net-to-gross shrinks the open face area of each half cell in x and y, so
+    /// it is folded into that cell's permeability there; z flow crosses the gross
+    /// area.
     ///
-    /// The z formula is the harmonic mean of half-cell transmissibilities:
-    ///   T_half_i = k_i · A / (dz_i / 2), then T = 1/(1/T1 + 1/T2).
+    /// The result carries the face's `MULTX/Y/Z` and fault multipliers. A face touching an
+    /// inactive cell is closed.
     pub(crate) fn geometric_transmissibility(&self, id1: usize, id2: usize, dim: char) -> f64 {
-        let k1 = id1 / (self.nx * self.ny);
-        let k2 = id2 / (self.nx * self.ny);
-        let dz1 = self.dz[k1];
-        let dz2 = self.dz[k2];
+        if !self.cell_active[id1] || !self.cell_active[id2] {
+            return 0.0;
+        }
+        let Some(axis) = axis_index(dim) else {
+            return 0.0;
+        };
+        // Matrix faces of a dual-permeability model share their fracture face's multipliers.
+        let face = id1.min(id2);
+        let face = self.fracture_cell(face).unwrap_or(face);
+        self.unmodified_transmissibility(id1, id2, dim) * self.face_multipliers[axis][face]
+    }
 
+    fn unmodified_transmissibility(&self, id1: usize, id2: usize, dim: char) -> f64 {
+        if let Some(radial) = &self.radial_grid {
+            return self.radial_transmissibility(radial, id1, id2, dim);
+        }
         match dim {
             'x' => {
-                let perm1 = self.perm_x[id1];
-                let perm2 = self.perm_x[id2];
-                let area = self.dy * dz1;
-                let k_h = harmonic_mean(perm1, perm2);
-                k_h * area / self.dx
+                let area = self.dy_at(id1) * self.dz_at(id1);
+                let dx1 = self.dx_at(id1);
+                let dx2 = self.dx_at(id2);
+                // Equal widths keep the historical arithmetic bit-for-bit.
+                let perm1 = self.perm_x[id1] * self.net_to_gross[id1];
+                let perm2 = self.perm_x[id2] * self.net_to_gross[id2];
+                if dx1 == dx2 {
+                    harmonic_mean(perm1, perm2) * area / dx1
+                } else {
+                    half_cell_harmonic(perm1, dx1, perm2, dx2, area)
+                }
             }
             'y' => {
-                let perm1 = self.perm_y[id1];
-                let perm2 = self.perm_y[id2];
-                let area = self.dx * dz1;
-                let k_h = harmonic_mean(perm1, perm2);
-                k_h * area / self.dy
+                let area = self.dx_at(id1) * self.dz_at(id1);
+                let dy1 = self.dy_at(id1);
+                let dy2 = self.dy_at(id2);
+                let perm1 = self.perm_y[id1] * self.net_to_gross[id1];
+                let perm2 = self.perm_y[id2] * self.net_to_gross[id2];
+                if dy1 == dy2 {
+                    harmonic_mean(perm1, perm2) * area / dy1
+                } else {
+                    half_cell_harmonic(perm1, dy1, perm2, dy2, area)
+                }
             }
             'z' => {
-                let perm1 = self.perm_z[id1];
-                let perm2 = self.perm_z[id2];
-                let area = self.dx * self.dy;
-                let denom = perm2 * dz1 + perm1 * dz2;
-                if denom <= 0.0 {
-                    return 0.0;
+                let area = self.dx_at(id1) * self.dy_at(id1);
+                half_cell_harmonic(
+                    self.perm_z[id1],
+                    self.dz_at(id1),
+                    self.perm_z[id2],
+                    self.dz_at(id2),
+                    area,
+                )
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Radial-grid counterpart of the Cartesian TPFA. Between rings the half-cell terms are the
+    /// steady radial-flow conductances `θ·k·h / ln(r_face / r_centre)`, so a homogeneous pair
+    /// gives exactly `θ·k·h / ln(r₂ / r₁)`; between layers the shared face is the ring's annulus.
+    fn radial_transmissibility(
+        &self,
+        radial: &RadialGrid,
+        id1: usize,
+        id2: usize,
+        dim: char,
+    ) -> f64 {
+        let (i1, i2) = (id1 % self.nx, id2 % self.nx);
+        match dim {
+            'x' => {
+                let (inner, outer, i_inner) = if i1 < i2 {
+                    (id1, id2, i1)
+                } else {
+                    (id2, id1, i2)
+                };
+                let r_face = radial.ring_edges_m[i_inner + 1];
+                let theta_h = radial.sector_angle_rad * self.dz_at(id1);
+                let t_inner = theta_h * self.perm_x[inner] * self.net_to_gross[inner]
+                    / (r_face / radial.ring_centre_m(i_inner)).ln();
+                let t_outer = theta_h * self.perm_x[outer] * self.net_to_gross[outer]
+                    / (radial.ring_centre_m(i_inner + 1) / r_face).ln();
+                if t_inner + t_outer <= 0.0 {
+                    0.0
+                } else {
+                    t_inner * t_outer / (t_inner + t_outer)
                 }
-                2.0 * perm1 * perm2 * area / denom
             }
+            'z' => half_cell_harmonic(
+                self.perm_z[id1],
+                self.dz_at(id1),
+                self.perm_z[id2],
+                self.dz_at(id2),
+                radial.ring_area_m2(i1),
+            ),
+            _ => 0.0,
+        }
+    }
+
+    /// Half-cell transmissibility [mD·m²/m] from the centre of `id` to its outer `+` (`plus`)
+    /// or `-` face along `dim`, `k · A / (L / 2)` with the same net-to-gross and radial rules as
+    /// an interior face. Boundary faces carry no multipliers; an inactive cell has none.
+    pub(crate) fn boundary_transmissibility(&self, id: usize, dim: char, plus: bool) -> f64 {
+        if !self.cell_active[id] {
+            return 0.0;
+        }
+        if let Some(radial) = &self.radial_grid {
+            let i = id % self.nx;
+            return match dim {
+                'x' => {
+                    let r_face = radial.ring_edges_m[if plus { i + 1 } else { i }];
+                    let theta_h = radial.sector_angle_rad * self.dz_at(id);
+                    theta_h * self.perm_x[id] * self.net_to_gross[id]
+                        / (r_face / radial.ring_centre_m(i)).ln().abs()
+                }
+                'z' => 2.0 * self.perm_z[id] * radial.ring_area_m2(i) / self.dz_at(id),
+                _ => 0.0,
+            };
+        }
+        let (dx, dy, dz) = (self.dx_at(id), self.dy_at(id), self.dz_at(id));
+        match dim {
+            'x' => 2.0 * self.perm_x[id] * self.net_to_gross[id] * dy * dz / dx,
+            'y' => 2.0 * self.perm_y[id] * self.net_to_gross[id] * dx * dz / dy,
+            'z' => 2.0 * self.perm_z[id] * dx * dy / dz,
             _ => 0.0,
         }
     }
+
+    /// Equivalent radius and inflow angle of a completion in cell `id` on a radial grid: the
+    /// centre of its ring and the sector angle, in place of Peaceman's `r_eq` and `2π`.
+    pub(crate) fn radial_well_inflow(&self, id: usize) -> Option<(f64, f64)> {
+        self.radial_grid
+            .as_ref()
+            .map(|radial| (radial.ring_centre_m(id % self.nx), radial.sector_angle_rad))
+    }
+
+    /// Root-grid neighbour across the `+` face of `cell` along `dim`.
+    pub(crate) fn plus_neighbor(&self, cell: usize, dim: char) -> Option<usize> {
+        let stride = match dim {
+            'x' => 1,
+            'y' => self.nx,
+            _ => self.nx * self.ny,
+        };
+        self.has_plus_neighbor(cell, dim).then_some(cell + stride)
+    }
+
+    /// Root-grid neighbour across the `-` face of `cell` along `dim`.
+    pub(crate) fn minus_neighbor(&self, cell: usize, dim: char) -> Option<usize> {
+        let i = cell % self.nx;
+        let j = (cell / self.nx) % self.ny;
+        let k = cell / (self.nx * self.ny);
+        match dim {
+            'x' if i > 0 => Some(cell - 1),
+            'y' if j > 0 => Some(cell - self.nx),
+            'z' if k > 0 => Some(cell - self.nx * self.ny),
+            _ => None,
+        }
+    }
+
+    /// Whether `cell` has a neighbour across its `+` face along `dim`.
+    pub(crate) fn has_plus_neighbor(&self, cell: usize, dim: char) -> bool {
+        let i = cell % self.nx;
+        let j = (cell / self.nx) % self.ny;
+        let k = cell / (self.nx * self.ny);
+        match dim {
+            'x' => i + 1 < self.nx,
+            'y' => j + 1 < self.ny,
+            'z' => k + 1 < self.nz,
+            _ => false,
+        }
+    }
+
+    /// Rebuild `face_multipliers` from `MULTX/Y/Z` and the fault list. A face named by more
+    /// than one fault takes the product of their multipliers.
+    pub(crate) fn refresh_face_multipliers(&mut self) {
+        let mut multipliers = [
+            self.mult_x.clone(),
+            self.mult_y.clone(),
+            self.mult_z.clone(),
+        ];
+        for fault in &self.faults {
+            for &(cell, dim) in &fault.faces {
+                if let Some(axis) = axis_index(dim) {
+                    multipliers[axis][cell] *= fault.multiplier;
+                }
+            }
+        }
+        self.face_multipliers = multipliers;
+    }
+
+    /// Every open connection of the flow graph: the Cartesian faces in `k`, `j`, `i` order
+    /// (`+x`, `+y`, `+z` per cell), then the non-neighbour connections in input order. Closed
+    /// faces and connections touching an inactive cell are left out.
+    pub(crate) fn flow_connections(&self) -> Vec<FlowConnection> {
+        let mut connections = Vec::with_capacity(3 * self.nx * self.ny * self.nz);
+        for k in 0..self.nz {
+            for j in 0..self.ny {
+                for i in 0..self.nx {
+                    let id = self.idx(i, j, k);
+                    let mut push = |id_j: usize, dim: char| {
+                        let transmissibility = self.geometric_transmissibility(id, id_j, dim);
+                        if transmissibility > 0.0 {
+                            connections.push(FlowConnection {
+                                id_i: id,
+                                id_j,
+                                dim,
+                                transmissibility,
+                            });
+                        }
+                    };
+                    if i + 1 < self.nx {
+                        push(self.idx(i + 1, j, k), 'x');
+                    }
+                    if j + 1 < self.ny {
+                        push(self.idx(i, j + 1, k), 'y');
+                    }
+                    if k + 1 < self.nz {
+                        push(self.idx(i, j, k + 1), 'z');
+                    }
+                }
+            }
+        }
+        connections.extend(
+            self.open_non_neighbor_connections()
+                .map(|nnc| FlowConnection {
+                    id_i: nnc.cell_a,
+                    id_j: nnc.cell_b,
+                    dim: 'n',
+                    transmissibility: nnc.transmissibility,
+                }),
+        );
+        connections
+    }
+
+    /// Open connections of one cell as `(neighbour, geometric transmissibility)`: the
+    /// Cartesian neighbours in `-x`, `+x`, `-y`, `+y`, `-z`, `+z` order, then any
+    /// non-neighbour connections. A local-grid child has only the latter.
+    pub(crate) fn cell_flow_connections(&self, id: usize) -> Vec<(usize, f64)> {
+        if id >= self.root_cell_count() {
+            return self.cell_non_neighbor_connections(id);
+        }
+        let i = id % self.nx;
+        let j = (id / self.nx) % self.ny;
+        let k = id / (self.nx * self.ny);
+        let mut candidates: Vec<(usize, char)> = Vec::with_capacity(6);
+        if i > 0 {
+            candidates.push((self.idx(i - 1, j, k), 'x'));
+        }
+        if i + 1 < self.nx {
+            candidates.push((self.idx(i + 1, j, k), 'x'));
+        }
+        if j > 0 {
+            candidates.push((self.idx(i, j - 1, k), 'y'));
+        }
+        if j + 1 < self.ny {
+            candidates.push((self.idx(i, j + 1, k), 'y'));
+        }
+        if k > 0 {
+            candidates.push((self.idx(i, j, k - 1), 'z'));
+        }
+        if k + 1 < self.nz {
+            candidates.push((self.idx(i, j, k + 1), 'z'));
+        }
+        let mut connections: Vec<(usize, f64)> = candidates
+            .into_iter()
+            .map(|(neighbor, dim)| (neighbor, self.geometric_transmissibility(id, neighbor, dim)))
+            .filter(|&(_, transmissibility)| transmissibility > 0.0)
+            .collect();
+        connections.extend(self.cell_non_neighbor_connections(id));
+        connections
+    }
+
+    /// Open non-neighbour connections of one cell as `(neighbour, transmissibility)`: the
+    /// user list in input order, then the local-grid faces, then the matrix connections.
+    pub(crate) fn cell_non_neighbor_connections(&self, id: usize) -> Vec<(usize, f64)> {
+        let mut connections = Vec::new();
+        for &nnc_idx in self.non_neighbor_index.get(id).into_iter().flatten() {
+            let nnc = &self.non_neighbor_connections[nnc_idx];
+            if !self.user_non_neighbor_connection_open(nnc) {
+                continue;
+            }
+            let neighbor = if nnc.cell_a == id {
+                nnc.cell_b
+            } else {
+                nnc.cell_a
+            };
+            connections.push((neighbor, nnc.transmissibility));
+        }
+        connections.extend(self.cell_local_grid_connections(id));
+        connections.extend(self.cell_dual_porosity_connections(id));
+        connections
+    }
+
+    /// Every open non-neighbour connection: the user list, then the local-grid faces, then the
+    /// matrix connections of a dual-porosity model.
+    pub(crate) fn open_non_neighbor_connections(
+        &self,
+    ) -> impl Iterator<Item = NonNeighborConnection> + '_ {
+        self.open_user_non_neighbor_connections()
+            .copied()
+            .chain(self.local_grid_connections())
+            .chain(self.dual_porosity_connections())
+    }
+
+    fn open_user_non_neighbor_connections(&self) -> impl Iterator<Item = &NonNeighborConnection> {
+        self.non_neighbor_connections
+            .iter()
+            .filter(|nnc| self.user_non_neighbor_connection_open(nnc))
+    }
+
+    fn user_non_neighbor_connection_open(&self, nnc: &NonNeighborConnection) -> bool {
+        nnc.transmissibility > 0.0 && self.cell_active[nnc.cell_a] && self.cell_active[nnc.cell_b]
+    }
+
+    pub(crate) fn rebuild_non_neighbor_index(&mut self) {
+        let mut index = vec![Vec::new(); self.root_cell_count()];
+        for (nnc_idx, nnc) in self.non_neighbor_connections.iter().enumerate() {
+            index[nnc.cell_a].push(nnc_idx);
+            index[nnc.cell_b].push(nnc_idx);
+        }
+        self.non_neighbor_index = index;
+    }
+
+    /// Copy of a per-cell field on the root grid for reporting: inactive cells read NaN, and a
+    /// cell refined by a local grid reads the pore-volume-weighted mean of its children. A
+    /// dual-porosity model reports its fracture continuum here.
+    pub(crate) fn masked_cell_values(&self, values: &[f64]) -> Vec<f64> {
+        let root_cells = self.root_cell_count();
+        if self.local_cells.is_empty() {
+            return values[..root_cells]
+                .iter()
+                .zip(&self.cell_active)
+                .map(|(&value, &active)| if active { value } else { f64::NAN })
+                .collect();
+        }
+        let mut sums = vec![0.0; values.len()];
+        let mut weights = vec![0.0; values.len()];
+        // Children follow their parents, so walking backwards folds nested grids upwards.
+        for (offset, cell) in self.local_cells.iter().enumerate().rev() {
+            let id = root_cells + offset;
+            let (value, weight) = if weights[id] > 0.0 {
+                (sums[id] / weights[id], weights[id])
+            } else {
+                (values[id], self.pore_volume_m3(id))
+            };
+            sums[cell.parent] += value * weight;
+            weights[cell.parent] += weight;
+        }
+        (0..root_cells)
+            .map(|id| {
+                if self.cell_active[id] {
+                    values[id]
+                } else if weights[id] > 0.0 {
+                    sums[id] / weights[id]
+                } else {
+                    f64::NAN
+                }
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,343 @@
+//! Group and field rate controls (Eclipse `GCONPROD` / `GCONINJE` with `WGRUPCON` guide rates).
+//!
+//! A group gathers physical wells by id; the field is the implicit group of every well. Either
+//! may carry a production target (oil, water, gas, liquid or reservoir volume) and an injection
+//! target (water, gas or reservoir volume). At the start of every step the target is shared
+//! among the member wells that can flow the target phase, in proportion to their guide rates
+//! when every member has one and to their potentials — the rate at their BHP limit from the
+//! state entering the step — otherwise. A well whose share exceeds its potential is held at the
+//! potential, which leaves it on its BHP limit, and the rest is shared again among the others.
+//!
+//! Groups are allocated before the field, so a field target sees each grouped well at most at
+//! its group share and moves a group's shortfall to the other wells. Each share becomes the
+//! well's [`GroupAllocation`]: a surface target when the target phase is the well's native one
+//! (oil for a producer, the injected phase for an injector), and otherwise a reservoir rate
+//! converted with the well's phase split entering the step. Both solvers then honour it through
+//! the ordinary per-well rate control.
+//!
+//! An injection target may instead be a voidage replacement ratio (Eclipse `VREP`): the
+//! injectors then share, as reservoir volume, that multiple of the reservoir-volume offtake of
+//! the group's producers. The offtake is the producers' rate under their controls, group shares
+//! included, at the state entering the step — the rate the previous step ended at — so the
+//! injection lags production by one step rather than being solved for implicitly.
+
+use crate::economic_limits::EconomicLimits;
+use crate::well::GroupAllocation;
+use crate::{InjectedFluid, ReservoirSimulator};
+
+/// Name of the implicit group holding every well.
+pub(crate) const FIELD_GROUP: &str = "FIELD";
+
+/// Phase a group rate target is counted in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum GroupRatePhase {
+    Oil,
+    Water,
+    Gas,
+    Liquid,
+    /// Reservoir volume [m³/day].
+    Reservoir,
+}
+
+impl GroupRatePhase {
+    pub(crate) fn parse(phase: &str) -> Option<Self> {
+        match phase.trim().to_ascii_lowercase().as_str() {
+            "oil" => Some(Self::Oil),
+            "water" => Some(Self::Water),
+            "gas" => Some(Self::Gas),
+            "liquid" => Some(Self::Liquid),
+            "resv" => Some(Self::Reservoir),
+            _ => None,
+        }
+    }
+}
+
+/// A group target: `rate` [Sm³/day, or m³/day for reservoir volume] of `phase`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct GroupTarget {
+    pub(crate) phase: GroupRatePhase,
+    pub(crate) rate: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct WellGroup {
+    pub(crate) name: String,
+    /// Physical well ids of the members; unused for the field, which holds every well.
+    pub(crate) wells: Vec<String>,
+    pub(crate) production: Option<GroupTarget>,
+    pub(crate) injection: Option<GroupTarget>,
+    /// Voidage replacement ratio; replaces `injection` when set.
+    pub(crate) voidage_replacement: Option<f64>,
+    pub(crate) economic_limits: Option<EconomicLimits>,
+}
+
+impl WellGroup {
+    pub(crate) fn new(name: String) -> Self {
+        Self {
+            name,
+            wells: Vec::new(),
+            production: None,
+            injection: None,
+            voidage_replacement: None,
+            economic_limits: None,
+        }
+    }
+
+    fn is_field(&self) -> bool {
+        self.name == FIELD_GROUP
+    }
+
+    pub(crate) fn holds(&self, id: &str) -> bool {
+        self.is_field() || self.wells.iter().any(|well| well == id)
+    }
+}
+
+/// One physical well taking part in group allocation.
+struct GroupMember {
+    id: String,
+    /// Completion entries in `ReservoirSimulator::wells`.
+    completions: Vec<usize>,
+    guide_rate: Option<f64>,
+    /// Rate at the BHP limit: reservoir volume [m³/day] and each producer phase [Sm³/day] as
+    /// `[water, oil, gas]`, or for an injector the injected phase in slot 0.
+    potential_reservoir: f64,
+    potential_phases: [f64; 3],
+    injected_fluid: InjectedFluid,
+    /// Largest reservoir rate a group allocation left the well.
+    cap_reservoir: f64,
+    allocation: Option<GroupAllocation>,
+}
+
+impl GroupMember {
+    /// Potential in the units of `phase`; zero when the well cannot flow it.
+    fn potential(&self, phase: GroupRatePhase, injector: bool) -> f64 {
+        let [water, oil, gas] = self.potential_phases;
+        if injector {
+            return match (phase, self.injected_fluid) {
+                (GroupRatePhase::Reservoir, _) => self.potential_reservoir,
+                (GroupRatePhase::Water, InjectedFluid::Water)
+                | (GroupRatePhase::Gas, InjectedFluid::Gas) => water,
+                _ => 0.0,
+            };
+        }
+        match phase {
+            GroupRatePhase::Oil => oil,
+            GroupRatePhase::Water => water,
+            GroupRatePhase::Gas => gas,
+            GroupRatePhase::Liquid => water + oil,
+            GroupRatePhase::Reservoir => self.potential_reservoir,
+        }
+    }
+
+    /// Whether a share in `phase` is held as a surface target rather than a reservoir rate.
+    fn native_surface_phase(&self, phase: GroupRatePhase, injector: bool) -> bool {
+        if injector {
+            phase != GroupRatePhase::Reservoir
+        } else {
+            phase == GroupRatePhase::Oil
+        }
+    }
+
+    /// Record a share of a `phase` target and return its reservoir rate.
+    fn assign(&mut self, share: f64, phase: GroupRatePhase, injector: bool) -> f64 {
+        let reservoir = share * self.potential_reservoir / self.potential(phase, injector);
+        let surface = self.native_surface_phase(phase, injector);
+        self.allocation = Some(GroupAllocation {
+            target_rate_m3_day: if surface { share } else { reservoir },
+            surface,
+        });
+        reservoir
+    }
+}
+
+/// Share `target` by `guides`, holding any well whose share would pass its potential at that
+/// potential and sharing the remainder again among the rest.
+fn share_by_guide_rates(target: f64, potentials: &[f64], guides: &[f64]) -> Vec<f64> {
+    let mut shares = vec![0.0; potentials.len()];
+    let mut open: Vec<bool> = potentials
+        .iter()
+        .map(|&potential| potential > 0.0)
+        .collect();
+    let mut remaining = target.max(0.0);
+    loop {
+        let guide_total: f64 = (0..guides.len())
+            .filter(|&i| open[i])
+            .map(|i| guides[i])
+            .sum();
+        if guide_total <= 0.0 {
+            return shares;
+        }
+        let capped: Vec<usize> = (0..guides.len())
+            .filter(|&i| open[i] && remaining * guides[i] / guide_total >= potentials[i])
+            .collect();
+        if capped.is_empty() {
+            for i in (0..guides.len()).filter(|&i| open[i]) {
+                shares[i] = remaining * guides[i] / guide_total;
+            }
+            return shares;
+        }
+        for i in capped {
+            shares[i] = potentials[i];
+            remaining = (remaining - potentials[i]).max(0.0);
+            open[i] = false;
+        }
+    }
+}
+
+impl ReservoirSimulator {
+    /// Group owning physical well `id`, if any.
+    pub(crate) fn well_group_of(&self, id: &str) -> Option<&WellGroup> {
+        self.well_groups
+            .iter()
+            .find(|group| !group.is_field() && group.wells.iter().any(|well| well == id))
+    }
+
+    /// Rewrite every well's [`crate::Well::group_allocation`] from the current state.
+    pub(crate) fn allocate_group_targets(&mut self) {
+        for well in &mut self.wells {
+            well.group_allocation = None;
+        }
+        if self.well_groups.is_empty() {
+            return;
+        }
+        let production: Vec<Option<GroupTarget>> = self
+            .well_groups
+            .iter()
+            .map(|group| group.production)
+            .collect();
+        self.allocate_group_family(false, &production);
+        // Producers now run under their shares, so the offtake below honours them.
+        let injection: Vec<Option<GroupTarget>> = self
+            .well_groups
+            .iter()
+            .map(|group| match group.voidage_replacement {
+                Some(ratio) => Some(GroupTarget {
+                    phase: GroupRatePhase::Reservoir,
+                    rate: ratio * self.group_reservoir_offtake_m3_day(group),
+                }),
+                None => group.injection,
+            })
+            .collect();
+        self.allocate_group_family(true, &injection);
+    }
+
+    /// Reservoir-volume rate [m³/day] the group's producers flow at the current state.
+    fn group_reservoir_offtake_m3_day(&self, group: &WellGroup) -> f64 {
+        self.wells
+            .iter()
+            .filter(|well| !well.injector)
+            .filter(|well| {
+                well.physical_well_id
+                    .as_deref()
+                    .is_some_and(|id| group.holds(id))
+            })
+            .filter_map(|well| self.well_rate_m3_day_for_pressures(well, &self.pressure))
+            .map(|q_m3_day| q_m3_day.max(0.0))
+            .sum()
+    }
+
+    /// Share `targets`, one per entry of `well_groups`, among the wells of one kind: groups
+    /// first, then the field.
+    fn allocate_group_family(&mut self, injector: bool, targets: &[Option<GroupTarget>]) {
+        let mut members = self.group_members(injector);
+        let order = (0..self.well_groups.len())
+            .filter(|&g| !self.well_groups[g].is_field())
+            .chain((0..self.well_groups.len()).filter(|&g| self.well_groups[g].is_field()));
+        for g in order {
+            let Some(target) = targets[g] else {
+                continue;
+            };
+            let group = &self.well_groups[g];
+            let indices: Vec<usize> = (0..members.len())
+                .filter(|&m| group.holds(&members[m].id))
+                .filter(|&m| members[m].potential(target.phase, injector) > 0.0)
+                .collect();
+            // A grouped well can give the field no more than its group share.
+            let potentials: Vec<f64> = indices
+                .iter()
+                .map(|&m| {
+                    let member = &members[m];
+                    let potential = member.potential(target.phase, injector);
+                    potential.min(member.cap_reservoir * potential / member.potential_reservoir)
+                })
+                .collect();
+            let guides: Vec<f64> = if indices.iter().all(|&m| members[m].guide_rate.is_some()) {
+                indices
+                    .iter()
+                    .map(|&m| members[m].guide_rate.unwrap_or(0.0))
+                    .collect()
+            } else {
+                potentials.clone()
+            };
+            let shares = share_by_guide_rates(target.rate, &potentials, &guides);
+            for (&m, share) in indices.iter().zip(shares) {
+                let reservoir = members[m].assign(share, target.phase, injector);
+                members[m].cap_reservoir = members[m].cap_reservoir.min(reservoir);
+            }
+        }
+        for member in members {
+            if let Some(allocation) = member.allocation {
+                for &completion in &member.completions {
+                    self.wells[completion].group_allocation = Some(allocation);
+                }
+            }
+        }
+    }
+
+    /// Enabled physical wells of one kind with an id, with their potentials at the BHP limit.
+    fn group_members(&self, injector: bool) -> Vec<GroupMember> {
+        let mut members: Vec<GroupMember> = Vec::new();
+        for (entry, well) in self.wells.iter().enumerate() {
+            let Some(id) = well.physical_well_id.as_deref() else {
+                continue;
+            };
+            if well.injector != injector {
+                continue;
+            }
+            let config = self.well_control_config(well);
+            if !config.enabled {
+                continue;
+            }
+            let cell = self.well_cell_index(well);
+            let pressure_bar = self.pressure[cell];
+            let q_m3_day = self
+                .completion_rate_for_bhp(well, pressure_bar, config.bhp_limit)
+                .unwrap_or(0.0);
+            let (reservoir, phases) = if injector {
+                let injected = -q_m3_day;
+                let surface = match self.well_injected_fluid(well) {
+                    InjectedFluid::Water => injected * self.water_inverse_fvf(pressure_bar),
+                    InjectedFluid::Gas => injected / self.get_b_g(pressure_bar).max(1e-9),
+                };
+                (injected, [surface, 0.0, 0.0])
+            } else {
+                (
+                    q_m3_day,
+                    self.producer_surface_rates(well, &self.pressure, q_m3_day),
+                )
+            };
+            let member = match members.iter_mut().find(|member| member.id == id) {
+                Some(member) => member,
+                None => {
+                    members.push(GroupMember {
+                        id: id.to_string(),
+                        completions: Vec::new(),
+                        guide_rate: well.guide_rate,
+                        potential_reservoir: 0.0,
+                        potential_phases: [0.0; 3],
+                        injected_fluid: self.well_injected_fluid(well),
+                        cap_reservoir: f64::INFINITY,
+                        allocation: None,
+                    });
+                    members.last_mut().expect("member was just pushed")
+                }
+            };
+            member.completions.push(entry);
+            member.potential_reservoir += reservoir.max(0.0);
+            for (total, phase) in member.potential_phases.iter_mut().zip(phases) {
+                *total += phase.max(0.0);
+            }
+        }
+        members
+    }
+}
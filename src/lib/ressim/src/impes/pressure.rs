@@ -2,6 +2,7 @@ use nalgebra::DVector;
 use sprs::{CsMat, TriMatI};
 use std::f64;
 
+use crate::boundary::{BoundaryConnection, BoundaryDrive};
 use crate::solvers::{LinearSolveParams, solve_with_default};
 use crate::well_control::{ResolvedWellControl, WellControlDecision};
 use crate::{InjectedFluid, ReservoirSimulator};
@@ -9,6 +10,26 @@ use crate::{InjectedFluid, ReservoirSimulator};
 /// Conversion factor from mD·m²/(m·cP) to m³/day/bar.
 const DARCY_METRIC_FACTOR: f64 = 8.526_988_8e-3;
 
+/// Per-cell inflow through the boundary faces, positive into the cell.
+pub(crate) struct BoundaryInflow {
+    pub(crate) water_m3_day: Vec<f64>,
+    pub(crate) oil_sc_day: Vec<f64>,
+    pub(crate) free_gas_sc_day: Vec<f64>,
+    pub(crate) dissolved_gas_sc_day: Vec<f64>,
+}
+
+impl BoundaryInflow {
+    /// Grid totals `[water m³, oil Sm³, gas Sm³]` per day, free and dissolved gas together.
+    pub(crate) fn totals(&self) -> [f64; 3] {
+        let sum = |values: &[f64]| values.iter().sum::<f64>();
+        [
+            sum(&self.water_m3_day),
+            sum(&self.oil_sc_day),
+            sum(&self.free_gas_sc_day) + sum(&self.dissolved_gas_sc_day),
+        ]
+    }
+}
+
 impl ReservoirSimulator {
     pub(crate) fn calculate_fluxes(
         &self,
@@ -23,7 +44,7 @@ impl ReservoirSimulator {
         bool,
         usize,
     ) {
-        let n_cells = self.nx * self.ny * self.nz;
+        let n_cells = self.cell_count();
         if n_cells == 0 {
             return (
                 DVector::zeros(0),
@@ -38,195 +59,192 @@ impl ReservoirSimulator {
         }
         let dt_days = delta_t_days.max(1e-12);
 
-        let mut rows: Vec<usize> = Vec::with_capacity(n_cells * 7);
-        let mut cols: Vec<usize> = Vec::with_capacity(n_cells * 7);
-        let mut vals: Vec<f64> = Vec::with_capacity(n_cells * 7);
+        // Inactive cells get no pressure unknown; active cells are numbered in cell order.
+        let active_rows = self.active_cell_rows();
+        let n_active = active_rows.iter().flatten().count();
+
+        let mut rows: Vec<usize> = Vec::with_capacity(n_active * 7);
+        let mut cols: Vec<usize> = Vec::with_capacity(n_active * 7);
+        let mut vals: Vec<f64> = Vec::with_capacity(n_active * 7);
 
-        let mut b_rhs = DVector::<f64>::zeros(n_cells);
-        let mut diag_inv = DVector::<f64>::zeros(n_cells);
+        let mut b_rhs = DVector::<f64>::zeros(n_active);
+        let mut diag_inv = DVector::<f64>::zeros(n_active);
 
         let well_controls: Vec<Option<ResolvedWellControl>> = self
             .wells
             .iter()
             .map(|w| self.resolve_well_control_for_pressures(w, &self.pressure))
             .collect();
+        let boundary_connections = self.boundary_connections();
+        let mut boundary_diag = vec![0.0; n_cells];
+        let mut boundary_rhs = vec![0.0; n_cells];
+        for connection in &boundary_connections {
+            let (diag, rhs) = self.boundary_pressure_terms(connection, dt_days);
+            boundary_diag[connection.cell] += diag;
+            boundary_rhs[connection.cell] += rhs;
+        }
 
-        for k in 0..self.nz {
-            for j in 0..self.ny {
-                for i in 0..self.nx {
-                    let id = self.idx(i, j, k);
-                    let vp_m3 = self.pore_volume_m3(id);
+        for id in 0..n_cells {
+            let Some(row) = active_rows[id] else {
+                continue;
+            };
+            let vp_m3 = self.pore_volume_m3(id);
 
-                    let sg_id = self.sat_gas[id];
-                    let so_id = if self.three_phase_mode {
-                        (1.0 - self.sat_water[id] - sg_id).max(0.0)
-                    } else {
-                        self.sat_oil[id]
-                    };
-                    let c_o_term = if self.three_phase_mode {
-                        self.get_c_o_effective(self.pressure[id], self.rs[id])
-                    } else {
-                        self.get_c_o(self.pressure[id])
-                    };
-                    let c_t = (c_o_term * so_id
-                        + self.pvt.c_w * self.sat_water[id]
-                        + if self.three_phase_mode {
-                            self.get_c_g(self.pressure[id]) * sg_id
-                        } else {
-                            0.0
-                        })
-                        + self.rock_compressibility;
+            let sg_id = self.sat_gas[id];
+            let so_id = if self.three_phase_mode {
+                (1.0 - self.sat_water[id] - sg_id).max(0.0)
+            } else {
+                self.sat_oil[id]
+            };
+            let c_o_term = if self.three_phase_mode {
+                self.get_c_o_effective(self.pressure[id], self.rs[id])
+            } else {
+                self.get_c_o(self.pressure[id])
+            };
+            let c_t = (c_o_term * so_id
+                + self.pvt.c_w * self.sat_water[id]
+                + if self.three_phase_mode {
+                    self.get_c_g(self.pressure[id]) * sg_id
+                } else {
+                    0.0
+                })
+                + self.rock_compressibility;
 
-                    let accum = (vp_m3 * c_t) / dt_days;
-                    let mut diag = accum;
-                    b_rhs[id] += accum * self.pressure[id];
+            let accum = (vp_m3 * c_t) / dt_days;
+            let mut diag = accum;
+            b_rhs[row] += accum * self.pressure[id];
 
-                    let mut neighbors: Vec<(usize, char, usize)> = Vec::new();
-                    if i > 0 {
-                        neighbors.push((self.idx(i - 1, j, k), 'x', k));
-                    }
-                    if i < self.nx - 1 {
-                        neighbors.push((self.idx(i + 1, j, k), 'x', k));
-                    }
-                    if j > 0 {
-                        neighbors.push((self.idx(i, j - 1, k), 'y', k));
-                    }
-                    if j < self.ny - 1 {
-                        neighbors.push((self.idx(i, j + 1, k), 'y', k));
-                    }
-                    if k > 0 {
-                        neighbors.push((self.idx(i, j, k - 1), 'z', k - 1));
-                    }
-                    if k < self.nz - 1 {
-                        neighbors.push((self.idx(i, j, k + 1), 'z', k + 1));
-                    }
+            for (n_id, transmissibility) in &self.cell_flow_connections(id) {
+                let Some(col) = active_rows[*n_id] else {
+                    continue;
+                };
+                let depth_i = self.depth_at(id);
+                let depth_j = self.depth_at(*n_id);
+
+                let p_i = self.pressure[id];
+                let p_j = self.pressure[*n_id];
+                let pc_i = self.get_capillary_pressure(self.sat_water[id]);
+                let pc_j = self.get_capillary_pressure(self.sat_water[*n_id]);
+
+                let rho_w_i = self.get_rho_w(p_i);
+                let rho_w_j = self.get_rho_w(p_j);
+                let grav_w = self.gravity_head_bar(
+                    depth_i,
+                    depth_j,
+                    self.interface_density_barrier(rho_w_i, rho_w_j),
+                );
+                let rho_o_i = if self.three_phase_mode {
+                    self.get_rho_o_cell(id, p_i)
+                } else {
+                    self.get_rho_o(p_i)
+                };
+                let rho_o_j = if self.three_phase_mode {
+                    self.get_rho_o_cell(*n_id, p_j)
+                } else {
+                    self.get_rho_o(p_j)
+                };
+                let grav_o = self.gravity_head_bar(
+                    depth_i,
+                    depth_j,
+                    self.interface_density_barrier(rho_o_i, rho_o_j),
+                );
+
+                let dphi_o = (p_i - p_j) - grav_o;
+                let dphi_w = (p_i - p_j) - (pc_i - pc_j) - grav_w;
+
+                let geom_t = DARCY_METRIC_FACTOR * transmissibility;
+
+                let t_total;
+                let explicit_rhs;
+                if self.three_phase_mode {
+                    let (lam_w_i, lam_o_i, lam_g_i) = self.phase_mobilities_3p(id);
+                    let (lam_w_j, lam_o_j, lam_g_j) = self.phase_mobilities_3p(*n_id);
+
+                    let lam_o_up = if dphi_o >= 0.0 { lam_o_i } else { lam_o_j };
+                    let lam_w_up = if dphi_w >= 0.0 { lam_w_i } else { lam_w_j };
+
+                    let pc_og_i = self.get_gas_oil_capillary_pressure(self.sat_gas[id]);
+                    let pc_og_j = self.get_gas_oil_capillary_pressure(self.sat_gas[*n_id]);
+                    let rho_g_i = self.get_rho_g(p_i);
+                    let rho_g_j = self.get_rho_g(p_j);
+                    let grav_g = self.gravity_head_bar(
+                        depth_i,
+                        depth_j,
+                        self.interface_density_barrier(rho_g_i, rho_g_j),
+                    );
+                    let dphi_g = (p_i - p_j) + (pc_og_i - pc_og_j) - grav_g;
+                    let lam_g_up = if dphi_g >= 0.0 { lam_g_i } else { lam_g_j };
+
+                    let t_o = geom_t * lam_o_up;
+                    let t_w = geom_t * lam_w_up;
+                    let t_g = geom_t * lam_g_up;
+                    t_total = t_o + t_w + t_g;
+                    explicit_rhs = t_o * grav_o + t_w * (pc_i - pc_j + grav_w)
+                        - t_g * (pc_og_i - pc_og_j - grav_g);
+                } else {
+                    let (lam_w_i, lam_o_i) = self.phase_mobilities(id);
+                    let (lam_w_j, lam_o_j) = self.phase_mobilities(*n_id);
 
-                    for (n_id, dim, n_k) in &neighbors {
-                        let depth_i = self.depth_at_k(k);
-                        let depth_j = self.depth_at_k(*n_k);
-
-                        let p_i = self.pressure[id];
-                        let p_j = self.pressure[*n_id];
-                        let pc_i = self.get_capillary_pressure(self.sat_water[id]);
-                        let pc_j = self.get_capillary_pressure(self.sat_water[*n_id]);
-
-                        let rho_w_i = self.get_rho_w(p_i);
-                        let rho_w_j = self.get_rho_w(p_j);
-                        let grav_w = self.gravity_head_bar(
-                            depth_i,
-                            depth_j,
-                            self.interface_density_barrier(rho_w_i, rho_w_j),
-                        );
-                        let rho_o_i = if self.three_phase_mode {
-                            self.get_rho_o_cell(id, p_i)
-                        } else {
-                            self.get_rho_o(p_i)
-                        };
-                        let rho_o_j = if self.three_phase_mode {
-                            self.get_rho_o_cell(*n_id, p_j)
-                        } else {
-                            self.get_rho_o(p_j)
-                        };
-                        let grav_o = self.gravity_head_bar(
-                            depth_i,
-                            depth_j,
-                            self.interface_density_barrier(rho_o_i, rho_o_j),
-                        );
-
-                        let dphi_o = (p_i - p_j) - grav_o;
-                        let dphi_w = (p_i - p_j) - (pc_i - pc_j) - grav_w;
-
-                        let geom_t =
-                            DARCY_METRIC_FACTOR * self.geometric_transmissibility(id, *n_id, *dim);
-
-                        let t_total;
-                        let explicit_rhs;
-                        if self.three_phase_mode {
-                            let (lam_w_i, lam_o_i, lam_g_i) = self.phase_mobilities_3p(id);
-                            let (lam_w_j, lam_o_j, lam_g_j) = self.phase_mobilities_3p(*n_id);
-
-                            let lam_o_up = if dphi_o >= 0.0 { lam_o_i } else { lam_o_j };
-                            let lam_w_up = if dphi_w >= 0.0 { lam_w_i } else { lam_w_j };
-
-                            let pc_og_i = self.get_gas_oil_capillary_pressure(self.sat_gas[id]);
-                            let pc_og_j = self.get_gas_oil_capillary_pressure(self.sat_gas[*n_id]);
-                            let rho_g_i = self.get_rho_g(p_i);
-                            let rho_g_j = self.get_rho_g(p_j);
-                            let grav_g = self.gravity_head_bar(
-                                depth_i,
-                                depth_j,
-                                self.interface_density_barrier(rho_g_i, rho_g_j),
-                            );
-                            let dphi_g = (p_i - p_j) + (pc_og_i - pc_og_j) - grav_g;
-                            let lam_g_up = if dphi_g >= 0.0 { lam_g_i } else { lam_g_j };
-
-                            let t_o = geom_t * lam_o_up;
-                            let t_w = geom_t * lam_w_up;
-                            let t_g = geom_t * lam_g_up;
-                            t_total = t_o + t_w + t_g;
-                            explicit_rhs = t_o * grav_o + t_w * (pc_i - pc_j + grav_w)
-                                - t_g * (pc_og_i - pc_og_j - grav_g);
-                        } else {
-                            let (lam_w_i, lam_o_i) = self.phase_mobilities(id);
-                            let (lam_w_j, lam_o_j) = self.phase_mobilities(*n_id);
+                    let lam_o_up = if dphi_o >= 0.0 { lam_o_i } else { lam_o_j };
+                    let lam_w_up = if dphi_w >= 0.0 { lam_w_i } else { lam_w_j };
 
-                            let lam_o_up = if dphi_o >= 0.0 { lam_o_i } else { lam_o_j };
-                            let lam_w_up = if dphi_w >= 0.0 { lam_w_i } else { lam_w_j };
+                    let t_o = geom_t * lam_o_up;
+                    let t_w = geom_t * lam_w_up;
+                    t_total = t_o + t_w;
+                    explicit_rhs = t_o * grav_o + t_w * (pc_i - pc_j + grav_w);
+                }
 
-                            let t_o = geom_t * lam_o_up;
-                            let t_w = geom_t * lam_w_up;
-                            t_total = t_o + t_w;
-                            explicit_rhs = t_o * grav_o + t_w * (pc_i - pc_j + grav_w);
-                        }
+                diag += t_total;
+                rows.push(row);
+                cols.push(col);
+                vals.push(-t_total);
+                b_rhs[row] += explicit_rhs;
+            }
 
-                        diag += t_total;
-                        rows.push(id);
-                        cols.push(*n_id);
-                        vals.push(-t_total);
-                        b_rhs[id] += explicit_rhs;
-                    }
+            diag += boundary_diag[id];
+            b_rhs[row] += boundary_rhs[id];
 
-                    for (w_idx, w) in self.wells.iter().enumerate() {
-                        if w.i == i && w.j == j && w.k == k {
-                            if let Some(ref control) = well_controls[w_idx] {
-                                match &control.decision {
-                                    WellControlDecision::Disabled => {}
-                                    WellControlDecision::Rate { q_m3_day } => {
-                                        b_rhs[id] -= q_m3_day;
-                                    }
-                                    WellControlDecision::Bhp { bhp_bar } => {
-                                        if w.productivity_index.is_finite() && bhp_bar.is_finite() {
-                                            diag += w.productivity_index;
-                                            b_rhs[id] += w.productivity_index
-                                                * w.connection_pressure_bar(*bhp_bar);
-                                        }
-                                    }
+            for (w_idx, w) in self.wells.iter().enumerate() {
+                if self.well_cell_index(w) == id {
+                    if let Some(ref control) = well_controls[w_idx] {
+                        match &control.decision {
+                            WellControlDecision::Disabled => {}
+                            WellControlDecision::Rate { q_m3_day } => {
+                                b_rhs[row] -= q_m3_day;
+                            }
+                            WellControlDecision::Bhp { bhp_bar } => {
+                                if w.productivity_index.is_finite() && bhp_bar.is_finite() {
+                                    diag += w.productivity_index;
+                                    b_rhs[row] +=
+                                        w.productivity_index * w.connection_pressure_bar(*bhp_bar);
                                 }
                             }
                         }
                     }
-
-                    rows.push(id);
-                    cols.push(id);
-                    vals.push(diag);
-                    diag_inv[id] = if diag.abs() > f64::EPSILON {
-                        1.0 / diag
-                    } else {
-                        1.0
-                    };
                 }
             }
+
+            rows.push(row);
+            cols.push(row);
+            vals.push(diag);
+            diag_inv[row] = if diag.abs() > f64::EPSILON {
+                1.0 / diag
+            } else {
+                1.0
+            };
         }
 
-        let mut tri = TriMatI::<f64, usize>::new((n_cells, n_cells));
+        let mut tri = TriMatI::<f64, usize>::new((n_active, n_active));
         for idx in 0..vals.len() {
             tri.add_triplet(rows[idx], cols[idx], vals[idx]);
         }
         let a_mat: CsMat<f64> = tri.to_csr();
 
-        let mut x0 = DVector::<f64>::zeros(n_cells);
-        for i in 0..n_cells {
-            x0[i] = self.pressure[i];
+        let mut x0 = DVector::<f64>::zeros(n_active);
+        for (id, row) in active_rows.iter().enumerate() {
+            if let Some(row) = *row {
+                x0[row] = self.pressure[id];
+            }
         }
         let solver_result = solve_with_default(LinearSolveParams {
             matrix: &a_mat,
@@ -236,192 +254,162 @@ impl ReservoirSimulator {
             tolerance: 1e-7,
             max_iterations: 1000,
         });
-        let p_new = solver_result.solution;
+        // Inactive cells keep their frozen pressure.
+        let p_new = if n_active == n_cells {
+            solver_result.solution
+        } else {
+            DVector::from_iterator(
+                n_cells,
+                active_rows.iter().enumerate().map(|(id, row)| match row {
+                    Some(row) => solver_result.solution[*row],
+                    None => self.pressure[id],
+                }),
+            )
+        };
 
         let mut delta_water_m3 = vec![0.0f64; n_cells];
         let mut delta_free_gas_sc = vec![0.0f64; n_cells];
         let mut delta_dg_sc = vec![0.0f64; n_cells];
         let mut max_sat_change = 0.0;
 
-        for k in 0..self.nz {
-            for j in 0..self.ny {
-                for i in 0..self.nx {
-                    let id = self.idx(i, j, k);
-                    let mut check = Vec::new();
-                    if i < self.nx - 1 {
-                        check.push((self.idx(i + 1, j, k), 'x', k));
-                    }
-                    if j < self.ny - 1 {
-                        check.push((self.idx(i, j + 1, k), 'y', k));
-                    }
-                    if k < self.nz - 1 {
-                        check.push((self.idx(i, j, k + 1), 'z', k + 1));
-                    }
+        let connections = self.flow_connections();
+        for connection in &connections {
+            let (id, nid) = (connection.id_i, connection.id_j);
+            let depth_i = self.depth_at(id);
+            let depth_j = self.depth_at(nid);
+
+            let pc_i = self.get_capillary_pressure(self.sat_water[id]);
+            let pc_j = self.get_capillary_pressure(self.sat_water[nid]);
+
+            let rho_w_old_i = self.get_rho_w(self.pressure[id]);
+            let rho_w_old_j = self.get_rho_w(self.pressure[nid]);
+            let rho_w_new_i = self.get_rho_w(p_new[id]);
+            let rho_w_new_j = self.get_rho_w(p_new[nid]);
+            let grav_w_old = self.gravity_head_bar(
+                depth_i,
+                depth_j,
+                self.interface_density_barrier(rho_w_old_i, rho_w_old_j),
+            );
+            let grav_w_new = self.gravity_head_bar(
+                depth_i,
+                depth_j,
+                self.interface_density_barrier(rho_w_new_i, rho_w_new_j),
+            );
 
-                    for (nid, dim, n_k) in check {
-                        let depth_i = self.depth_at_k(k);
-                        let depth_j = self.depth_at_k(n_k);
-
-                        let pc_i = self.get_capillary_pressure(self.sat_water[id]);
-                        let pc_j = self.get_capillary_pressure(self.sat_water[nid]);
-
-                        let rho_w_old_i = self.get_rho_w(self.pressure[id]);
-                        let rho_w_old_j = self.get_rho_w(self.pressure[nid]);
-                        let rho_w_new_i = self.get_rho_w(p_new[id]);
-                        let rho_w_new_j = self.get_rho_w(p_new[nid]);
-                        let grav_w_old = self.gravity_head_bar(
-                            depth_i,
-                            depth_j,
-                            self.interface_density_barrier(rho_w_old_i, rho_w_old_j),
-                        );
-                        let grav_w_new = self.gravity_head_bar(
-                            depth_i,
-                            depth_j,
-                            self.interface_density_barrier(rho_w_new_i, rho_w_new_j),
-                        );
-
-                        let dphi_w_old =
-                            (self.pressure[id] - self.pressure[nid]) - (pc_i - pc_j) - grav_w_old;
-                        let dphi_w = (p_new[id] - p_new[nid]) - (pc_i - pc_j) - grav_w_new;
-
-                        let (lam_w_i, lam_w_j) = if self.three_phase_mode {
-                            let (w_i, _, _) = self.phase_mobilities_3p(id);
-                            let (w_j, _, _) = self.phase_mobilities_3p(nid);
-                            (w_i, w_j)
-                        } else {
-                            let (w_i, _) = self.phase_mobilities(id);
-                            let (w_j, _) = self.phase_mobilities(nid);
-                            (w_i, w_j)
-                        };
+            let dphi_w_old = (self.pressure[id] - self.pressure[nid]) - (pc_i - pc_j) - grav_w_old;
+            let dphi_w = (p_new[id] - p_new[nid]) - (pc_i - pc_j) - grav_w_new;
+
+            let (lam_w_i, lam_w_j) = if self.three_phase_mode {
+                let (w_i, _, _) = self.phase_mobilities_3p(id);
+                let (w_j, _, _) = self.phase_mobilities_3p(nid);
+                (w_i, w_j)
+            } else {
+                let (w_i, _) = self.phase_mobilities(id);
+                let (w_j, _) = self.phase_mobilities(nid);
+                (w_i, w_j)
+            };
 
-                        let lam_w_up = if dphi_w_old >= 0.0 { lam_w_i } else { lam_w_j };
-                        let geom_t =
-                            DARCY_METRIC_FACTOR * self.geometric_transmissibility(id, nid, dim);
-                        let t_w = geom_t * lam_w_up;
-                        let water_flux_m3_day = t_w * dphi_w;
-                        let dv_water = water_flux_m3_day * dt_days;
+            let lam_w_up = if dphi_w_old >= 0.0 { lam_w_i } else { lam_w_j };
+            let geom_t = DARCY_METRIC_FACTOR * connection.transmissibility;
+            let t_w = geom_t * lam_w_up;
+            let water_flux_m3_day = t_w * dphi_w;
+            let dv_water = water_flux_m3_day * dt_days;
 
-                        delta_water_m3[id] -= dv_water;
-                        delta_water_m3[nid] += dv_water;
-                    }
-                }
-            }
+            delta_water_m3[id] -= dv_water;
+            delta_water_m3[nid] += dv_water;
         }
 
         if self.three_phase_mode {
-            for k in 0..self.nz {
-                for j in 0..self.ny {
-                    for i in 0..self.nx {
-                        let id = self.idx(i, j, k);
-
-                        let mut check = Vec::new();
-                        if i < self.nx - 1 {
-                            check.push((self.idx(i + 1, j, k), 'x', k));
-                        }
-                        if j < self.ny - 1 {
-                            check.push((self.idx(i, j + 1, k), 'y', k));
-                        }
-                        if k < self.nz - 1 {
-                            check.push((self.idx(i, j, k + 1), 'z', k + 1));
-                        }
-
-                        for (nid, dim, n_k) in check {
-                            let depth_i = self.depth_at_k(k);
-                            let depth_j = self.depth_at_k(n_k);
-
-                            let pc_og_i = self.get_gas_oil_capillary_pressure(self.sat_gas[id]);
-                            let pc_og_j = self.get_gas_oil_capillary_pressure(self.sat_gas[nid]);
-                            let rho_g_old_i = self.get_rho_g(self.pressure[id]);
-                            let rho_g_old_j = self.get_rho_g(self.pressure[nid]);
-                            let rho_g_new_i = self.get_rho_g(p_new[id]);
-                            let rho_g_new_j = self.get_rho_g(p_new[nid]);
-                            let grav_g_old = self.gravity_head_bar(
-                                depth_i,
-                                depth_j,
-                                self.interface_density_barrier(rho_g_old_i, rho_g_old_j),
-                            );
-                            let grav_g_new = self.gravity_head_bar(
-                                depth_i,
-                                depth_j,
-                                self.interface_density_barrier(rho_g_new_i, rho_g_new_j),
-                            );
+            for connection in &connections {
+                let (id, nid) = (connection.id_i, connection.id_j);
+                let depth_i = self.depth_at(id);
+                let depth_j = self.depth_at(nid);
+
+                let pc_og_i = self.get_gas_oil_capillary_pressure(self.sat_gas[id]);
+                let pc_og_j = self.get_gas_oil_capillary_pressure(self.sat_gas[nid]);
+                let rho_g_old_i = self.get_rho_g(self.pressure[id]);
+                let rho_g_old_j = self.get_rho_g(self.pressure[nid]);
+                let rho_g_new_i = self.get_rho_g(p_new[id]);
+                let rho_g_new_j = self.get_rho_g(p_new[nid]);
+                let grav_g_old = self.gravity_head_bar(
+                    depth_i,
+                    depth_j,
+                    self.interface_density_barrier(rho_g_old_i, rho_g_old_j),
+                );
+                let grav_g_new = self.gravity_head_bar(
+                    depth_i,
+                    depth_j,
+                    self.interface_density_barrier(rho_g_new_i, rho_g_new_j),
+                );
+
+                let dphi_g_old =
+                    (self.pressure[id] - self.pressure[nid]) + (pc_og_i - pc_og_j) - grav_g_old;
+                let dphi_g = (p_new[id] - p_new[nid]) + (pc_og_i - pc_og_j) - grav_g_new;
+
+                let lam_g_up = if dphi_g_old >= 0.0 {
+                    self.gas_mobility(id)
+                } else {
+                    self.gas_mobility(nid)
+                };
+                let geom_t = DARCY_METRIC_FACTOR * connection.transmissibility;
+                let t_g = geom_t * lam_g_up;
+                let gas_flux_m3_day = t_g * dphi_g;
+                let up_id = if dphi_g_old >= 0.0 { id } else { nid };
+                let gas_flux_sc_day = gas_flux_m3_day / self.get_b_g(p_new[up_id]).max(1e-9);
+                let dv_gas_sc = gas_flux_sc_day * dt_days;
+
+                delta_free_gas_sc[id] -= dv_gas_sc;
+                delta_free_gas_sc[nid] += dv_gas_sc;
+
+                if self.pvt_table.is_some() {
+                    let rho_o_old_i = self.get_rho_o_cell(id, self.pressure[id]);
+                    let rho_o_old_j = self.get_rho_o_cell(nid, self.pressure[nid]);
+                    let rho_o_new_i = self.get_rho_o_cell(id, p_new[id]);
+                    let rho_o_new_j = self.get_rho_o_cell(nid, p_new[nid]);
+                    let grav_o_old = self.gravity_head_bar(
+                        depth_i,
+                        depth_j,
+                        self.interface_density_barrier(rho_o_old_i, rho_o_old_j),
+                    );
+                    let grav_o_new = self.gravity_head_bar(
+                        depth_i,
+                        depth_j,
+                        self.interface_density_barrier(rho_o_new_i, rho_o_new_j),
+                    );
+                    let dphi_o_old = (self.pressure[id] - self.pressure[nid]) - grav_o_old;
+                    let dphi_o = (p_new[id] - p_new[nid]) - grav_o_new;
+
+                    let (_, lam_o_i, _) = self.phase_mobilities_3p(id);
+                    let (_, lam_o_j, _) = self.phase_mobilities_3p(nid);
+                    let lam_o_up = if dphi_o_old >= 0.0 { lam_o_i } else { lam_o_j };
+                    let t_o = geom_t * lam_o_up;
+
+                    let oil_flux_res_day = t_o * dphi_o;
+                    let up_id = if dphi_o_old >= 0.0 { id } else { nid };
+                    let oil_flux_sc_day =
+                        oil_flux_res_day / self.get_b_o_cell(up_id, p_new[up_id]).max(1e-9);
+                    let rs_upwind = if dphi_o_old >= 0.0 {
+                        self.rs[id]
+                    } else {
+                        self.rs[nid]
+                    };
+                    let dg_flux_sc_day = oil_flux_sc_day * rs_upwind;
+                    let dv_dg_sc = dg_flux_sc_day * dt_days;
 
-                            let dphi_g_old = (self.pressure[id] - self.pressure[nid])
-                                + (pc_og_i - pc_og_j)
-                                - grav_g_old;
-                            let dphi_g =
-                                (p_new[id] - p_new[nid]) + (pc_og_i - pc_og_j) - grav_g_new;
-
-                            let lam_g_up = if dphi_g_old >= 0.0 {
-                                self.gas_mobility(id)
-                            } else {
-                                self.gas_mobility(nid)
-                            };
-                            let geom_t =
-                                DARCY_METRIC_FACTOR * self.geometric_transmissibility(id, nid, dim);
-                            let t_g = geom_t * lam_g_up;
-                            let gas_flux_m3_day = t_g * dphi_g;
-                            let up_id = if dphi_g_old >= 0.0 { id } else { nid };
-                            let gas_flux_sc_day =
-                                gas_flux_m3_day / self.get_b_g(p_new[up_id]).max(1e-9);
-                            let dv_gas_sc = gas_flux_sc_day * dt_days;
-
-                            delta_free_gas_sc[id] -= dv_gas_sc;
-                            delta_free_gas_sc[nid] += dv_gas_sc;
-
-                            if self.pvt_table.is_some() {
-                                let rho_o_old_i = self.get_rho_o_cell(id, self.pressure[id]);
-                                let rho_o_old_j = self.get_rho_o_cell(nid, self.pressure[nid]);
-                                let rho_o_new_i = self.get_rho_o_cell(id, p_new[id]);
-                                let rho_o_new_j = self.get_rho_o_cell(nid, p_new[nid]);
-                                let grav_o_old = self.gravity_head_bar(
-                                    depth_i,
-                                    depth_j,
-                                    self.interface_density_barrier(rho_o_old_i, rho_o_old_j),
-                                );
-                                let grav_o_new = self.gravity_head_bar(
-                                    depth_i,
-                                    depth_j,
-                                    self.interface_density_barrier(rho_o_new_i, rho_o_new_j),
-                                );
-                                let dphi_o_old =
-                                    (self.pressure[id] - self.pressure[nid]) - grav_o_old;
-                                let dphi_o = (p_new[id] - p_new[nid]) - grav_o_new;
-
-                                let (_, lam_o_i, _) = self.phase_mobilities_3p(id);
-                                let (_, lam_o_j, _) = self.phase_mobilities_3p(nid);
-                                let lam_o_up = if dphi_o_old >= 0.0 { lam_o_i } else { lam_o_j };
-                                let t_o = geom_t * lam_o_up;
-
-                                let oil_flux_res_day = t_o * dphi_o;
-                                let up_id = if dphi_o_old >= 0.0 { id } else { nid };
-                                let oil_flux_sc_day = oil_flux_res_day
-                                    / self.get_b_o_cell(up_id, p_new[up_id]).max(1e-9);
-                                let rs_upwind = if dphi_o_old >= 0.0 {
-                                    self.rs[id]
-                                } else {
-                                    self.rs[nid]
-                                };
-                                let dg_flux_sc_day = oil_flux_sc_day * rs_upwind;
-                                let dv_dg_sc = dg_flux_sc_day * dt_days;
-
-                                delta_dg_sc[id] -= dv_dg_sc;
-                                delta_dg_sc[nid] += dv_dg_sc;
-                            }
-                        }
-                    }
+                    delta_dg_sc[id] -= dv_dg_sc;
+                    delta_dg_sc[nid] += dv_dg_sc;
                 }
             }
         }
 
         for (w_idx, w) in self.wells.iter().enumerate() {
-            let id = self.idx(w.i, w.j, w.k);
+            let id = self.well_cell_index(w);
             if let Some(control) = well_controls[w_idx] {
                 if let Some(q_m3_day) = self.well_transport_rate_from_control(w, control, p_new[id])
                 {
                     if self.three_phase_mode {
                         let (fw, fg, fo) = if w.injector {
-                            match self.injected_fluid {
+                            match self.well_injected_fluid(w) {
                                 InjectedFluid::Water => (1.0, 0.0, 0.0),
                                 InjectedFluid::Gas => (0.0, 1.0, 0.0),
                             }
@@ -470,6 +458,15 @@ impl ReservoirSimulator {
             }
         }
 
+        if !boundary_connections.is_empty() {
+            let boundary = self.boundary_inflow(p_new.as_slice(), dt_days);
+            for idx in 0..n_cells {
+                delta_water_m3[idx] += boundary.water_m3_day[idx] * dt_days;
+                delta_free_gas_sc[idx] += boundary.free_gas_sc_day[idx] * dt_days;
+                delta_dg_sc[idx] += boundary.dissolved_gas_sc_day[idx] * dt_days;
+            }
+        }
+
         for idx in 0..n_cells {
             let vp_m3 = self.pore_volume_m3(idx);
             if vp_m3 > 0.0 {
@@ -508,7 +505,7 @@ impl ReservoirSimulator {
             let Some(control) = well_controls[w_idx] else {
                 continue;
             };
-            let id = self.idx(w.i, w.j, w.k);
+            let id = self.well_cell_index(w);
             let vp_m3 = self.pore_volume_m3(id);
             if vp_m3 <= 0.0 {
                 continue;
@@ -551,7 +548,7 @@ impl ReservoirSimulator {
                     self.well_transport_rate_from_control(
                         w,
                         control,
-                        self.pressure[self.idx(w.i, w.j, w.k)],
+                        self.pressure[self.well_cell_index(w)],
                     )
                 })
                 .unwrap_or(0.0);
@@ -560,7 +557,7 @@ impl ReservoirSimulator {
                     self.well_transport_rate_from_control(
                         w,
                         control,
-                        p_new[self.idx(w.i, w.j, w.k)],
+                        p_new[self.well_cell_index(w)],
                     )
                 })
                 .unwrap_or(0.0);
@@ -598,3 +595,171 @@ impl ReservoirSimulator {
         )
     }
 }
+
+impl ReservoirSimulator {
+    /// Mobilities `(λ_w, λ_o, λ_g)` [1/cP] of the fluid outside a boundary face.
+    fn boundary_mobilities(
+        &self,
+        connection: &BoundaryConnection,
+        pressure_bar: f64,
+    ) -> (f64, f64, f64) {
+        let sw = connection.sat_water;
+        if self.three_phase_mode {
+            let mobilities = self.phase_mobilities_for_state(
+                sw,
+                self.boundary_gas_saturation(connection),
+                pressure_bar,
+                self.boundary_rs(connection, pressure_bar),
+            );
+            (mobilities.water, mobilities.oil, mobilities.gas)
+        } else {
+            (
+                self.scal.k_rw(sw) / self.get_mu_w(pressure_bar),
+                self.scal.k_ro(sw) / self.get_mu_o(pressure_bar),
+                0.0,
+            )
+        }
+    }
+
+    /// Mobilities `(λ_w, λ_o, λ_g)` [1/cP] of cell `id` at its current state.
+    fn cell_mobilities(&self, id: usize) -> (f64, f64, f64) {
+        if self.three_phase_mode {
+            self.phase_mobilities_3p(id)
+        } else {
+            let (water, oil) = self.phase_mobilities(id);
+            (water, oil, 0.0)
+        }
+    }
+
+    /// Capillary pressure differences `(Pc_ow, Pc_go)` [bar] of a face cell over its boundary.
+    fn boundary_capillary_differences(&self, connection: &BoundaryConnection) -> (f64, f64) {
+        let id = connection.cell;
+        let pc_w = self.get_capillary_pressure(self.sat_water[id])
+            - self.get_capillary_pressure(connection.sat_water);
+        let pc_og = if self.three_phase_mode {
+            self.get_gas_oil_capillary_pressure(self.sat_gas[id])
+                - self.get_gas_oil_capillary_pressure(self.boundary_gas_saturation(connection))
+        } else {
+            0.0
+        };
+        (pc_w, pc_og)
+    }
+
+    /// Pressure-matrix diagonal and right-hand-side terms of one boundary face over a step of
+    /// `dt_days`. A pressure boundary couples the cell to a fixed ghost pressure like an
+    /// interior face with a known neighbour; a rate boundary is a fixed source; an aquifer face
+    /// couples it to the pot pressure through its influx coefficient.
+    fn boundary_pressure_terms(&self, connection: &BoundaryConnection, dt_days: f64) -> (f64, f64) {
+        let pressure_bar = match connection.drive {
+            BoundaryDrive::Rate { rate_m3_day } => return (0.0, rate_m3_day),
+            BoundaryDrive::Pressure { pressure_bar } => pressure_bar,
+            BoundaryDrive::Aquifer { .. } => {
+                return self.aquifer_face_terms(connection, dt_days).map_or(
+                    (0.0, 0.0),
+                    |(coefficient, aquifer_pressure_bar)| {
+                        (coefficient, coefficient * aquifer_pressure_bar)
+                    },
+                );
+            }
+        };
+        let id = connection.cell;
+        let (pc_w, pc_og) = self.boundary_capillary_differences(connection);
+        let dphi_o = self.pressure[id] - pressure_bar;
+        let dphi_w = dphi_o - pc_w;
+        let dphi_g = dphi_o + pc_og;
+
+        let (lam_w_i, lam_o_i, lam_g_i) = self.cell_mobilities(id);
+        let (lam_w_b, lam_o_b, lam_g_b) = self.boundary_mobilities(connection, pressure_bar);
+        let geom_t = DARCY_METRIC_FACTOR * connection.transmissibility;
+        let t_w = geom_t * if dphi_w >= 0.0 { lam_w_i } else { lam_w_b };
+        let t_o = geom_t * if dphi_o >= 0.0 { lam_o_i } else { lam_o_b };
+        let t_g = geom_t * if dphi_g >= 0.0 { lam_g_i } else { lam_g_b };
+        let t_total = t_w + t_o + t_g;
+        (t_total, t_total * pressure_bar + t_w * pc_w - t_g * pc_og)
+    }
+
+    /// Phase inflow through every boundary face over a step of `dt_days` ending at `p_new`.
+    /// Upwinding uses the potentials at the start of the step, as for the interior faces.
+    pub(crate) fn boundary_inflow(&self, p_new: &[f64], dt_days: f64) -> BoundaryInflow {
+        let n_cells = self.cell_count();
+        let mut inflow = BoundaryInflow {
+            water_m3_day: vec![0.0; n_cells],
+            oil_sc_day: vec![0.0; n_cells],
+            free_gas_sc_day: vec![0.0; n_cells],
+            dissolved_gas_sc_day: vec![0.0; n_cells],
+        };
+        let dissolved_gas = self.three_phase_mode && self.pvt_table.is_some();
+
+        for connection in self.boundary_connections() {
+            let id = connection.cell;
+            let p_cell = p_new[id];
+            // Reservoir-volume phase inflows and the upstream oil and gas FVF and Rs.
+            let (q_w, q_o, q_g, bo, bg, rs) = match connection.drive {
+                BoundaryDrive::Rate { rate_m3_day } => {
+                    let (lam_w, lam_o, lam_g, bo, rs) = if rate_m3_day >= 0.0 {
+                        let (w, o, g) = self.boundary_mobilities(&connection, p_cell);
+                        let rs = self.boundary_rs(&connection, p_cell);
+                        (w, o, g, self.get_b_o_for_rs(p_cell, rs), rs)
+                    } else {
+                        let (w, o, g) = self.cell_mobilities(id);
+                        (w, o, g, self.get_b_o_cell(id, p_cell), self.rs[id])
+                    };
+                    let lam_t = lam_w + lam_o + lam_g;
+                    if lam_t <= 0.0 {
+                        continue;
+                    }
+                    let q = rate_m3_day / lam_t;
+                    (
+                        q * lam_w,
+                        q * lam_o,
+                        q * lam_g,
+                        bo,
+                        self.get_b_g(p_cell),
+                        rs,
+                    )
+                }
+                BoundaryDrive::Pressure { pressure_bar } => {
+                    let (pc_w, pc_og) = self.boundary_capillary_differences(&connection);
+                    let dphi_o_old = self.pressure[id] - pressure_bar;
+                    let dphi_o = p_cell - pressure_bar;
+
+                    let (lam_w_i, lam_o_i, lam_g_i) = self.cell_mobilities(id);
+                    let (lam_w_b, lam_o_b, lam_g_b) =
+                        self.boundary_mobilities(&connection, pressure_bar);
+                    let geom_t = DARCY_METRIC_FACTOR * connection.transmissibility;
+                    let upwind = |dphi_old: f64, cell: f64, boundary: f64| {
+                        geom_t * if dphi_old >= 0.0 { cell } else { boundary }
+                    };
+                    let q_w = -upwind(dphi_o_old - pc_w, lam_w_i, lam_w_b) * (dphi_o - pc_w);
+                    let q_o = -upwind(dphi_o_old, lam_o_i, lam_o_b) * dphi_o;
+                    let q_g = -upwind(dphi_o_old + pc_og, lam_g_i, lam_g_b) * (dphi_o + pc_og);
+                    let (bo, rs) = if dphi_o_old >= 0.0 {
+                        (self.get_b_o_cell(id, p_cell), self.rs[id])
+                    } else {
+                        let rs = self.boundary_rs(&connection, pressure_bar);
+                        (self.get_b_o_for_rs(pressure_bar, rs), rs)
+                    };
+                    // Free gas converts at its upstream pressure, as across interior faces.
+                    let bg = if dphi_o_old + pc_og >= 0.0 {
+                        self.get_b_g(p_cell)
+                    } else {
+                        self.get_b_g(pressure_bar)
+                    };
+                    (q_w, q_o, q_g, bo, bg, rs)
+                }
+                BoundaryDrive::Aquifer { .. } => {
+                    let q_w = self.aquifer_face_influx(&connection, p_cell, dt_days);
+                    (q_w, 0.0, 0.0, 1.0, 1.0, 0.0)
+                }
+            };
+            let q_o_sc = q_o / bo.max(1e-9);
+            inflow.water_m3_day[id] += q_w;
+            inflow.oil_sc_day[id] += q_o_sc;
+            inflow.free_gas_sc_day[id] += q_g / bg.max(1e-9);
+            if dissolved_gas {
+                inflow.dissolved_gas_sc_day[id] += q_o_sc * rs;
+            }
+        }
+        inflow
+    }
+}
@@ -133,53 +133,60 @@ impl ReservoirSimulator {
         const DARCY_METRIC_FACTOR: f64 = 8.526_988_8e-3;
         let mut max_flux_over_pv = 0.0_f64;
 
-        for k in 0..self.nz {
-            for j in 0..self.ny {
-                for i in 0..self.nx {
-                    let id = self.idx(i, j, k);
-                    let vp = self.pore_volume_m3(id);
-                    if vp <= 0.0 {
+        let root_cells = self.root_cell_count();
+        for id in 0..self.cell_count() {
+            let vp = self.pore_volume_m3(id);
+            if vp <= 0.0 {
+                continue;
+            }
+            let lam_t = self.total_mobility(id);
+            let mut outflow = 0.0_f64;
+
+            if id < root_cells {
+                let i = id % self.nx;
+                let j = (id / self.nx) % self.ny;
+                let k = id / (self.nx * self.ny);
+                let neighbors: &[(isize, isize, isize, char)] = &[
+                    (1, 0, 0, 'x'),
+                    (-1, 0, 0, 'x'),
+                    (0, 1, 0, 'y'),
+                    (0, -1, 0, 'y'),
+                    (0, 0, 1, 'z'),
+                    (0, 0, -1, 'z'),
+                ];
+                for &(di, dj, dk, dim) in neighbors {
+                    let ni = i as isize + di;
+                    let nj = j as isize + dj;
+                    let nk = k as isize + dk;
+                    if ni < 0
+                        || nj < 0
+                        || nk < 0
+                        || ni >= self.nx as isize
+                        || nj >= self.ny as isize
+                        || nk >= self.nz as isize
+                    {
                         continue;
                     }
-                    let lam_t = self.total_mobility(id);
-                    let mut outflow = 0.0_f64;
-
-                    let neighbors: &[(isize, isize, isize, char)] = &[
-                        (1, 0, 0, 'x'),
-                        (-1, 0, 0, 'x'),
-                        (0, 1, 0, 'y'),
-                        (0, -1, 0, 'y'),
-                        (0, 0, 1, 'z'),
-                        (0, 0, -1, 'z'),
-                    ];
-                    for &(di, dj, dk, dim) in neighbors {
-                        let ni = i as isize + di;
-                        let nj = j as isize + dj;
-                        let nk = k as isize + dk;
-                        if ni < 0
-                            || nj < 0
-                            || nk < 0
-                            || ni >= self.nx as isize
-                            || nj >= self.ny as isize
-                            || nk >= self.nz as isize
-                        {
-                            continue;
-                        }
-                        let nid = self.idx(ni as usize, nj as usize, nk as usize);
-                        let dp = self.pressure[id] - self.pressure[nid];
-                        if dp > 0.0 {
-                            let geom_t =
-                                DARCY_METRIC_FACTOR * self.geometric_transmissibility(id, nid, dim);
-                            outflow += geom_t * lam_t * dp;
-                        }
-                    }
-
-                    let ratio = outflow / vp;
-                    if ratio > max_flux_over_pv {
-                        max_flux_over_pv = ratio;
+                    let nid = self.idx(ni as usize, nj as usize, nk as usize);
+                    let dp = self.pressure[id] - self.pressure[nid];
+                    if dp > 0.0 {
+                        let geom_t =
+                            DARCY_METRIC_FACTOR * self.geometric_transmissibility(id, nid, dim);
+                        outflow += geom_t * lam_t * dp;
                     }
                 }
             }
+            for (nid, transmissibility) in self.cell_non_neighbor_connections(id) {
+                let dp = self.pressure[id] - self.pressure[nid];
+                if dp > 0.0 {
+                    outflow += DARCY_METRIC_FACTOR * transmissibility * lam_t * dp;
+                }
+            }
+
+            let ratio = outflow / vp;
+            if ratio > max_flux_over_pv {
+                max_flux_over_pv = ratio;
+            }
         }
 
         if max_flux_over_pv > 0.0 {
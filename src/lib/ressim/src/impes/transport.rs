@@ -9,19 +9,19 @@ impl ReservoirSimulator {
         p_new: &DVector<f64>,
         well_controls: &[Option<ResolvedWellControl>],
     ) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
-        let n_cells = self.nx * self.ny * self.nz;
+        let n_cells = self.cell_count();
         let mut delta_water_m3 = vec![0.0; n_cells];
         let mut delta_free_gas_sc = vec![0.0; n_cells];
         let mut delta_dg_sc = vec![0.0; n_cells];
 
         for (w_idx, w) in self.wells.iter().enumerate() {
-            let id = self.idx(w.i, w.j, w.k);
+            let id = self.well_cell_index(w);
             if let Some(control) = well_controls[w_idx] {
                 if let Some(q_m3_day) = self.well_transport_rate_from_control(w, control, p_new[id])
                 {
                     if self.three_phase_mode {
                         let (fw, fg, fo) = if w.injector {
-                            match self.injected_fluid {
+                            match self.well_injected_fluid(w) {
                                 InjectedFluid::Water => (1.0, 0.0, 0.0),
                                 InjectedFluid::Gas => (0.0, 1.0, 0.0),
                             }
@@ -82,7 +82,7 @@ impl ReservoirSimulator {
                     .get(w_idx)
                     .and_then(|control| *control)
                     .is_some()
-                && self.idx(w.i, w.j, w.k) == cell_idx
+                && self.well_cell_index(w) == cell_idx
         })
     }
 
@@ -163,12 +163,13 @@ impl ReservoirSimulator {
         well_controls: &[Option<ResolvedWellControl>],
         dt_days: f64,
     ) {
-        let n_cells = self.nx * self.ny * self.nz;
+        let n_cells = self.cell_count();
         let (well_source_water_m3_day, well_source_free_gas_sc_day, well_source_dg_sc_day) =
             self.accumulate_well_source_deltas(p_new, well_controls);
         // Must be captured before the saturation update below overwrites the state the
         // transport was built from.
         let phase_splits = self.producer_transport_phase_splits(well_controls);
+        let boundary_inflow = self.boundary_inflow(p_new.as_slice(), dt_days).totals();
         let mut actual_change_m3 = 0.0;
         let mut actual_oil_removed_sc = 0.0;
         let mut actual_change_gas_sc = 0.0;
@@ -291,6 +292,7 @@ impl ReservoirSimulator {
             well_controls,
             &phase_splits,
             dt_days,
+            boundary_inflow,
             actual_change_m3,
             actual_oil_removed_sc,
             actual_change_gas_sc,
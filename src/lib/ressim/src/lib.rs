@@ -19,27 +19,44 @@ use serde::{Deserialize, Serialize};
 use std::f64;
 use wasm_bindgen::prelude::*;
 
+mod aquifer;
+mod boundary;
 mod capillary;
+mod dual_porosity;
+mod economic_limits;
 mod fim;
 mod frontend;
 mod grid;
+mod group_control;
 mod impes;
+mod local_grid;
 mod mobility;
 mod pvt;
 mod relperm;
 mod reporting;
+mod schedule;
 mod solvers;
 mod step;
 mod timing;
+mod vfp;
 mod well;
 mod well_control;
+mod well_fracture;
+mod well_segments;
+mod well_trajectory;
 
 pub use capillary::{CapillaryPressure, GasOilCapillaryPressure};
+pub use economic_limits::{EconomicAction, EconomicLimitEvent, EconomicLimits};
 pub use relperm::{
     RockFluidProps, RockFluidPropsThreePhase, SgofRow, SwofRow, ThreePhaseScalTables,
 };
-pub use reporting::{FimStepStats, SweepConfig, TimePointRates, WellRates};
-pub use well::Well;
+pub use reporting::{
+    AquiferTimePoint, CompletionTimePoint, FimStepStats, SweepConfig, TimePointRates, WellRates,
+    WellTimePoint,
+};
+pub use schedule::{ScheduleAction, ScheduleEvent};
+pub use vfp::VfpTable;
+pub use well::{CompletionConnection, Well};
 
 /// Which fluid the injector injects in three-phase mode.
 #[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
@@ -94,10 +111,50 @@ pub struct ReservoirSimulator {
     nx: usize,
     ny: usize,
     nz: usize,
-    dx: f64,
-    dy: f64,
+    /// Cell width per column `i` [m] (Eclipse `DXV`).
+    dx: Vec<f64>,
+    /// Cell width per row `j` [m] (Eclipse `DYV`).
+    dy: Vec<f64>,
     dz: Vec<f64>,
     porosity: Vec<f64>,
+    /// Net-to-gross ratio per cell (Eclipse `NTG`). Scales pore volume, horizontal
+    /// transmissibility and completion `kh`; vertical flow sees the gross thickness.
+    net_to_gross: Vec<f64>,
+    /// Active-cell mask (Eclipse `ACTNUM`). Inactive cells hold no pore volume, have no
+    /// connections, cannot be completed, and are left out of the IMPES pressure matrix and
+    /// the FIM linear solve.
+    cell_active: Vec<bool>,
+    /// Transmissibility multiplier on the `+x`, `+y` and `+z` face of each cell (Eclipse
+    /// `MULTX`/`MULTY`/`MULTZ`).
+    mult_x: Vec<f64>,
+    mult_y: Vec<f64>,
+    mult_z: Vec<f64>,
+    /// Named faults (Eclipse `FAULTS`/`MULTFLT`); their multipliers stack on `mult_x/y/z`.
+    faults: Vec<grid::Fault>,
+    /// Combined `MULT*` × fault multiplier of each cell's `+x`/`+y`/`+z` face, rebuilt by
+    /// `refresh_face_multipliers` whenever either input changes.
+    face_multipliers: [Vec<f64>; 3],
+    /// Non-neighbour connections (Eclipse `NNC`), added to the Cartesian faces.
+    non_neighbor_connections: Vec<grid::NonNeighborConnection>,
+    /// Indices into `non_neighbor_connections` touching each root cell.
+    non_neighbor_index: Vec<Vec<usize>>,
+    /// Cylindrical geometry replacing `dx`/`dy` when set by `setRadialGrid`.
+    radial_grid: Option<grid::RadialGrid>,
+    /// Local grid refinements (Eclipse `CARFIN`). Their child cells follow the root cells in
+    /// every per-cell vector; `local_faces` joins them to each other and to the coarse grid.
+    local_grids: Vec<local_grid::LocalGrid>,
+    local_cells: Vec<local_grid::LocalCell>,
+    local_faces: Vec<local_grid::LocalFace>,
+    /// Indices into `local_faces` touching each cell.
+    local_face_index: Vec<Vec<usize>>,
+    /// Matrix continuum of a dual-porosity model. Its cells follow every other cell in each
+    /// per-cell vector, one per root cell.
+    dual_porosity: Option<dual_porosity::DualPorosity>,
+    /// Constant-pressure and prescribed-flux conditions on the outer faces; every other outer
+    /// face is closed.
+    boundary_conditions: Vec<boundary::BoundaryCondition>,
+    /// Fetkovich aquifers, by the index their boundary conditions name.
+    aquifers: Vec<aquifer::FetkovichAquifer>,
     perm_x: Vec<f64>,
     perm_y: Vec<f64>,
     perm_z: Vec<f64>,
@@ -105,6 +162,10 @@ pub struct ReservoirSimulator {
     sat_water: Vec<f64>,
     sat_oil: Vec<f64>,
     wells: Vec<Well>,
+    /// Well groups with their rate targets, including `FIELD` once it has one.
+    well_groups: Vec<group_control::WellGroup>,
+    /// Lift curves of `thp`-controlled wells, by table number.
+    vfp_tables: Vec<VfpTable>,
     time_days: f64,
     pvt: FluidProperties,
     scal: RockFluidProps,
@@ -182,6 +243,10 @@ pub struct ReservoirSimulator {
     target_producer_surface_rate_m3_day: Option<f64>,
     rock_compressibility: f64,
     depth_reference_m: f64,
+    /// Structural top depths [m TVDSS] (Eclipse `TOPS`), either one per column
+    /// (`nx * ny`) or one per cell (`nx * ny * nz`). `None` keeps the flat
+    /// layer-cake derived from `depth_reference_m` and `dz`.
+    cell_top_depth_m: Option<Vec<f64>>,
     b_o: f64,
     b_w: f64,
     /// Pressure at which `b_w` is defined. `set_initial_pressure()` establishes the current
@@ -201,6 +266,10 @@ pub struct ReservoirSimulator {
     /// public input model.
     pub(crate) rock_reference_pressure_bar: f64,
     rate_history: Vec<TimePointRates>,
+    /// Set once an economic limit stops the run; every later step does nothing.
+    run_stopped: bool,
+    /// Well events not yet applied, sorted by time.
+    schedule_events: Vec<schedule::ScheduleEvent>,
     pub(crate) sat_gas: Vec<f64>,
     pub(crate) scal_3p: Option<RockFluidPropsThreePhase>,
     pub(crate) pc_og: Option<GasOilCapillaryPressure>,
@@ -214,6 +283,10 @@ pub struct ReservoirSimulator {
     pub(crate) gas_redissolution_enabled: bool,
     pub(crate) fim_enabled: bool,
     pub(crate) sweep_config: Option<SweepConfig>,
+    /// Record each completion's rates in the per-well rate history.
+    report_completion_rates: bool,
+    /// Report each well's rates in the rate history scaled up to the full well.
+    report_full_pattern_rates: bool,
 }
 
 #[cfg(test)]
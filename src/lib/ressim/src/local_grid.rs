@@ -0,0 +1,520 @@
+//! Local grid refinement (Eclipse `CARFIN`).
+//!
+//! A local grid subdivides a box of parent cells into `rx × ry × rz` children each. Child cells
+//! are appended after the `nx * ny * nz` root cells and after the children of every earlier
+//! local grid, so each per-cell vector simply grows and a child inherits its parent's rock and
+//! state. The parent cells stay in place but are deactivated like an `ACTNUM` hole, and the
+//! child-child and child-parent faces join the flow graph as computed non-neighbour connections.
+//! Both solvers therefore pick up a refinement through the connection lists alone.
+//!
+//! A local grid may itself be refined (a nested grid names it as its parent). Two grids may not
+//! share a face: every interface joins a child to an unrefined cell of the enclosing grid.
+
+use crate::ReservoirSimulator;
+use crate::grid::NonNeighborConnection;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct LocalGrid {
+    pub(crate) name: String,
+    /// Index of the enclosing local grid, `None` for a box in the root grid.
+    pub(crate) parent_grid: Option<usize>,
+    /// First parent-grid cell of the box along x, y and z.
+    pub(crate) box_lower: [usize; 3],
+    /// Children per parent cell along x, y and z.
+    pub(crate) refinement: [usize; 3],
+    /// Child cells along x, y and z.
+    pub(crate) dims: [usize; 3],
+    /// Global index of the first child cell.
+    pub(crate) first_cell: usize,
+}
+
+/// One child cell, at global index `nx * ny * nz + position in `local_cells``.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LocalCell {
+    pub(crate) grid: usize,
+    /// The cell this child subdivides.
+    pub(crate) parent: usize,
+    /// Position inside the parent along x, y and z.
+    pub(crate) offset: [usize; 3],
+}
+
+/// A face between two cells of which at least one is a child. `plus` is true when `cell_b`
+/// lies on the `+dim` side of `cell_a`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LocalFace {
+    pub(crate) cell_a: usize,
+    pub(crate) cell_b: usize,
+    pub(crate) dim: char,
+    pub(crate) plus: bool,
+    /// Root-grid face this face subdivides, as `(axis, lower cell)` into `face_multipliers`,
+    /// so `MULTX/Y/Z` and faults on the coarse face carry over. `None` inside a refinement.
+    pub(crate) root_face: Option<(usize, usize)>,
+}
+
+fn axis_dim(axis: usize) -> char {
+    ['x', 'y', 'z'][axis]
+}
+
+/// Re-derives every child's entry of a per-cell field from its parent, dropping any stale
+/// child entries first. Children are stored after their parents, so nested children see their
+/// parent's freshly derived value.
+pub(crate) fn inherit_local_cells<T: Copy>(
+    local_cells: &[LocalCell],
+    root_cells: usize,
+    values: &mut Vec<T>,
+) {
+    values.truncate(root_cells);
+    extend_local_cells(local_cells, root_cells, values);
+}
+
+/// Appends an entry from its parent for each child the field does not cover yet, leaving
+/// existing children untouched.
+fn extend_local_cells<T: Copy>(local_cells: &[LocalCell], root_cells: usize, values: &mut Vec<T>) {
+    for cell in &local_cells[values.len() - root_cells..] {
+        let value = values[cell.parent];
+        values.push(value);
+    }
+}
+
+impl ReservoirSimulator {
+    pub(crate) fn root_cell_count(&self) -> usize {
+        self.nx * self.ny * self.nz
+    }
+
+    /// Root cells plus every local-grid child cell and matrix cell.
+    pub(crate) fn cell_count(&self) -> usize {
+        let matrix_cells = if self.dual_porosity.is_some() {
+            self.root_cell_count()
+        } else {
+            0
+        };
+        self.root_cell_count() + self.local_cells.len() + matrix_cells
+    }
+
+    pub(crate) fn local_cell(&self, id: usize) -> Option<&LocalCell> {
+        id.checked_sub(self.root_cell_count())
+            .and_then(|offset| self.local_cells.get(offset))
+    }
+
+    pub(crate) fn local_refinement(&self, id: usize) -> Option<(usize, [usize; 3])> {
+        self.local_cell(id)
+            .map(|cell| (cell.parent, self.local_grids[cell.grid].refinement))
+    }
+
+    /// `(i, j, k)` of the root-grid cell that contains `id`, following a child up through
+    /// every refinement level. Root cells map to their own indices and matrix cells to their
+    /// fracture cell's.
+    pub(crate) fn root_cell_ijk(&self, id: usize) -> (usize, usize, usize) {
+        let mut root = self.fracture_cell(id).unwrap_or(id);
+        while let Some(cell) = self.local_cell(root) {
+            root = cell.parent;
+        }
+        let i = root % self.nx;
+        let j = (root / self.nx) % self.ny;
+        let k = root / (self.nx * self.ny);
+        (i, j, k)
+    }
+
+    pub(crate) fn local_grid_index(&self, name: &str) -> Option<usize> {
+        self.local_grids.iter().position(|grid| grid.name == name)
+    }
+
+    /// Whether some local grid subdivides cell `id`.
+    pub(crate) fn is_refined(&self, id: usize) -> bool {
+        self.local_cells.iter().any(|cell| cell.parent == id)
+    }
+
+    fn grid_dims(&self, grid: Option<usize>) -> [usize; 3] {
+        match grid {
+            Some(grid) => self.local_grids[grid].dims,
+            None => [self.nx, self.ny, self.nz],
+        }
+    }
+
+    fn grid_cell(&self, grid: Option<usize>, ijk: [usize; 3]) -> usize {
+        let dims = self.grid_dims(grid);
+        let first = grid.map_or(0, |grid| self.local_grids[grid].first_cell);
+        first + ijk[2] * dims[0] * dims[1] + ijk[1] * dims[0] + ijk[0]
+    }
+
+    fn grid_position(&self, grid: Option<usize>, id: usize) -> [usize; 3] {
+        let dims = self.grid_dims(grid);
+        let local = id - grid.map_or(0, |grid| self.local_grids[grid].first_cell);
+        [
+            local % dims[0],
+            (local / dims[0]) % dims[1],
+            local / (dims[0] * dims[1]),
+        ]
+    }
+
+    fn cell_grid(&self, id: usize) -> Option<usize> {
+        self.local_cell(id).map(|cell| cell.grid)
+    }
+
+    /// Global index of cell `(i, j, k)` of the named local grid.
+    pub(crate) fn local_grid_cell(
+        &self,
+        name: &str,
+        i: usize,
+        j: usize,
+        k: usize,
+    ) -> Result<usize, String> {
+        let grid = self
+            .local_grid_index(name)
+            .ok_or_else(|| format!("No local grid found with name '{}'", name))?;
+        let dims = self.local_grids[grid].dims;
+        if i >= dims[0] || j >= dims[1] || k >= dims[2] {
+            return Err(format!(
+                "Local grid '{}' cell ({}, {}, {}) is out of bounds ({}, {}, {})",
+                name, i, j, k, dims[0], dims[1], dims[2]
+            ));
+        }
+        Ok(self.grid_cell(Some(grid), [i, j, k]))
+    }
+
+    /// Cells across the `+`/`-` face of `id` along `axis` in its own grid: the Cartesian
+    /// neighbour inside the grid, or — on a local grid's boundary — the coarse cells its
+    /// interface faces already reach. Each comes with the root face it subdivides, if any.
+    fn cells_across(
+        &self,
+        id: usize,
+        axis: usize,
+        plus: bool,
+    ) -> Vec<(usize, Option<(usize, usize)>)> {
+        let grid = self.cell_grid(id);
+        let dims = self.grid_dims(grid);
+        let mut ijk = self.grid_position(grid, id);
+        let inside = if plus {
+            ijk[axis] + 1 < dims[axis]
+        } else {
+            ijk[axis] > 0
+        };
+        if inside {
+            ijk[axis] = if plus { ijk[axis] + 1 } else { ijk[axis] - 1 };
+            let neighbor = self.grid_cell(grid, ijk);
+            let root_face = grid.is_none().then(|| (axis, id.min(neighbor)));
+            return vec![(neighbor, root_face)];
+        }
+        let dim = axis_dim(axis);
+        self.local_faces
+            .iter()
+            .filter(|face| face.dim == dim && face.cell_a == id && face.plus == plus)
+            .map(|face| (face.cell_b, face.root_face))
+            .collect()
+    }
+
+    /// Refine the box `lower..=upper` of `parent_grid` (`None` for the root grid) into
+    /// `refinement` children per cell, appending the child cells and their faces.
+    pub(crate) fn add_local_grid(
+        &mut self,
+        name: &str,
+        parent_grid: Option<usize>,
+        lower: [usize; 3],
+        upper: [usize; 3],
+        refinement: [usize; 3],
+    ) -> Result<(), String> {
+        let parent_dims = self.grid_dims(parent_grid);
+        for axis in 0..3 {
+            if lower[axis] > upper[axis] || upper[axis] >= parent_dims[axis] {
+                return Err(format!(
+                    "Local grid '{}' box {}..={} along {} is outside its parent grid (size {})",
+                    name,
+                    lower[axis],
+                    upper[axis],
+                    axis_dim(axis),
+                    parent_dims[axis]
+                ));
+            }
+            if refinement[axis] == 0 {
+                return Err(format!(
+                    "Local grid '{}' refinement along {} must be at least 1",
+                    name,
+                    axis_dim(axis)
+                ));
+            }
+        }
+
+        let box_cells: Vec<usize> = (lower[2]..=upper[2])
+            .flat_map(|k| {
+                (lower[1]..=upper[1])
+                    .flat_map(move |j| (lower[0]..=upper[0]).map(move |i| [i, j, k]))
+            })
+            .map(|ijk| self.grid_cell(parent_grid, ijk))
+            .collect();
+        for &cell in &box_cells {
+            if !self.is_active(cell) {
+                return Err(format!(
+                    "Local grid '{}' box contains an inactive or already refined cell ({})",
+                    name, cell
+                ));
+            }
+            if self
+                .wells
+                .iter()
+                .any(|well| self.well_cell_index(well) == cell)
+            {
+                return Err(format!(
+                    "Local grid '{}' box contains a well completion (cell {}); add wells after \
+                     the refinement",
+                    name, cell
+                ));
+            }
+        }
+
+        // Collect each parent cell's coarse neighbours outside the box before anything moves;
+        // a refined neighbour would need a fine-fine interface, which is not supported.
+        let mut interfaces = Vec::new();
+        for &cell in &box_cells {
+            let ijk = self.grid_position(parent_grid, cell);
+            for axis in 0..3 {
+                for plus in [false, true] {
+                    let leaves_box = if plus {
+                        ijk[axis] == upper[axis]
+                    } else {
+                        ijk[axis] == lower[axis]
+                    };
+                    if !leaves_box {
+                        continue;
+                    }
+                    for (neighbor, root_face) in self.cells_across(cell, axis, plus) {
+                        if self.is_refined(neighbor) {
+                            return Err(format!(
+                                "Local grid '{}' shares a face with another local grid (cell {})",
+                                name, neighbor
+                            ));
+                        }
+                        interfaces.push((cell, axis, plus, neighbor, root_face));
+                    }
+                }
+            }
+        }
+
+        let grid_index = self.local_grids.len();
+        let dims = [
+            (upper[0] - lower[0] + 1) * refinement[0],
+            (upper[1] - lower[1] + 1) * refinement[1],
+            (upper[2] - lower[2] + 1) * refinement[2],
+        ];
+        let first_cell = self.cell_count();
+        self.local_grids.push(LocalGrid {
+            name: name.to_string(),
+            parent_grid,
+            box_lower: lower,
+            refinement,
+            dims,
+            first_cell,
+        });
+        for k in 0..dims[2] {
+            for j in 0..dims[1] {
+                for i in 0..dims[0] {
+                    let child = [i, j, k];
+                    let parent_ijk = [
+                        lower[0] + i / refinement[0],
+                        lower[1] + j / refinement[1],
+                        lower[2] + k / refinement[2],
+                    ];
+                    self.local_cells.push(LocalCell {
+                        grid: grid_index,
+                        parent: self.grid_cell(parent_grid, parent_ijk),
+                        offset: [
+                            child[0] % refinement[0],
+                            child[1] % refinement[1],
+                            child[2] % refinement[2],
+                        ],
+                    });
+                }
+            }
+        }
+
+        // Faces between children, in cell order with +x, +y, +z per cell.
+        for k in 0..dims[2] {
+            for j in 0..dims[1] {
+                for i in 0..dims[0] {
+                    let id = self.grid_cell(Some(grid_index), [i, j, k]);
+                    for axis in 0..3 {
+                        let mut next = [i, j, k];
+                        next[axis] += 1;
+                        if next[axis] < dims[axis] {
+                            self.local_faces.push(LocalFace {
+                                cell_a: id,
+                                cell_b: self.grid_cell(Some(grid_index), next),
+                                dim: axis_dim(axis),
+                                plus: true,
+                                root_face: None,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        // Each coarse interface splits into one face per child on that side of its parent.
+        let root_cells = self.root_cell_count();
+        for (parent, axis, plus, neighbor, root_face) in interfaces {
+            let edge = if plus { refinement[axis] - 1 } else { 0 };
+            for (offset, cell) in self.local_cells.iter().enumerate() {
+                if cell.grid != grid_index || cell.parent != parent || cell.offset[axis] != edge {
+                    continue;
+                }
+                self.local_faces.push(LocalFace {
+                    cell_a: root_cells + offset,
+                    cell_b: neighbor,
+                    dim: axis_dim(axis),
+                    plus,
+                    root_face,
+                });
+            }
+        }
+
+        // Only the new children take their parents' values: existing children keep the state
+        // they have evolved, and the box cells are still open at this point.
+        let cells = &self.local_cells;
+        extend_local_cells(cells, root_cells, &mut self.porosity);
+        extend_local_cells(cells, root_cells, &mut self.net_to_gross);
+        extend_local_cells(cells, root_cells, &mut self.perm_x);
+        extend_local_cells(cells, root_cells, &mut self.perm_y);
+        extend_local_cells(cells, root_cells, &mut self.perm_z);
+        extend_local_cells(cells, root_cells, &mut self.pressure);
+        extend_local_cells(cells, root_cells, &mut self.sat_water);
+        extend_local_cells(cells, root_cells, &mut self.sat_oil);
+        extend_local_cells(cells, root_cells, &mut self.sat_gas);
+        extend_local_cells(cells, root_cells, &mut self.rs);
+        extend_local_cells(cells, root_cells, &mut self.cell_active);
+        for cell in box_cells {
+            self.cell_active[cell] = false;
+        }
+        self.rebuild_local_face_index();
+        Ok(())
+    }
+
+    /// Re-derive the pressure, saturations and `Rs` of the cells appended after the root grid:
+    /// local-grid children from their parents, matrix cells from their fracture cells.
+    pub(crate) fn inherit_appended_cell_state(&mut self) {
+        let root_cells = self.root_cell_count();
+        inherit_local_cells(&self.local_cells, root_cells, &mut self.pressure);
+        inherit_local_cells(&self.local_cells, root_cells, &mut self.sat_water);
+        inherit_local_cells(&self.local_cells, root_cells, &mut self.sat_oil);
+        inherit_local_cells(&self.local_cells, root_cells, &mut self.sat_gas);
+        inherit_local_cells(&self.local_cells, root_cells, &mut self.rs);
+        if self.dual_porosity.is_some() {
+            self.pressure.extend_from_within(..root_cells);
+            self.sat_water.extend_from_within(..root_cells);
+            self.sat_oil.extend_from_within(..root_cells);
+            self.sat_gas.extend_from_within(..root_cells);
+            self.rs.extend_from_within(..root_cells);
+        }
+    }
+
+    /// Re-derive the porosity, net-to-gross and permeability of the cells appended after the
+    /// root grid: local-grid children from their parents, matrix cells from the matrix rock.
+    pub(crate) fn inherit_appended_cell_rock(&mut self) {
+        let root_cells = self.root_cell_count();
+        inherit_local_cells(&self.local_cells, root_cells, &mut self.porosity);
+        inherit_local_cells(&self.local_cells, root_cells, &mut self.net_to_gross);
+        inherit_local_cells(&self.local_cells, root_cells, &mut self.perm_x);
+        inherit_local_cells(&self.local_cells, root_cells, &mut self.perm_y);
+        inherit_local_cells(&self.local_cells, root_cells, &mut self.perm_z);
+        if let Some(dual) = &self.dual_porosity {
+            self.porosity.extend_from_slice(&dual.matrix_porosity);
+            self.net_to_gross.extend_from_within(..root_cells);
+            self.perm_x.extend_from_slice(&dual.matrix_perm[0]);
+            self.perm_y.extend_from_slice(&dual.matrix_perm[1]);
+            self.perm_z.extend_from_slice(&dual.matrix_perm[2]);
+        }
+    }
+
+    /// Expand a root-grid `ACTNUM` mask over the appended cells and close every refined parent.
+    /// A matrix cell follows its fracture cell.
+    pub(crate) fn expand_active_cells(&self, mut active: Vec<bool>) -> Vec<bool> {
+        let root_cells = self.root_cell_count();
+        inherit_local_cells(&self.local_cells, root_cells, &mut active);
+        for cell in &self.local_cells {
+            active[cell.parent] = false;
+        }
+        if self.dual_porosity.is_some() {
+            active.extend_from_within(..root_cells);
+        }
+        active
+    }
+
+    fn rebuild_local_face_index(&mut self) {
+        let mut index = vec![Vec::new(); self.cell_count()];
+        for (face_idx, face) in self.local_faces.iter().enumerate() {
+            index[face.cell_a].push(face_idx);
+            index[face.cell_b].push(face_idx);
+        }
+        self.local_face_index = index;
+    }
+
+    /// Length of cell `id` along `dim` [m].
+    fn cell_length(&self, id: usize, dim: char) -> f64 {
+        match dim {
+            'x' => self.dx_at(id),
+            'y' => self.dy_at(id),
+            _ => self.dz_at(id),
+        }
+    }
+
+    /// TPFA transmissibility [mD·m²/m] of a face with at least one child cell. The face area
+    /// is the smaller of the two cells' faces, so a coarse cell sees each child through the
+    /// child's own face. Net-to-gross enters horizontally exactly as on the root grid.
+    pub(crate) fn local_face_transmissibility(&self, face: &LocalFace) -> f64 {
+        let (a, b) = (face.cell_a, face.cell_b);
+        if !self.cell_active[a] || !self.cell_active[b] {
+            return 0.0;
+        }
+        let area = |id: usize| match face.dim {
+            'x' => self.dy_at(id) * self.dz_at(id),
+            'y' => self.dx_at(id) * self.dz_at(id),
+            _ => self.dx_at(id) * self.dy_at(id),
+        };
+        let perm = |id: usize| match face.dim {
+            'x' => self.perm_x[id] * self.net_to_gross[id],
+            'y' => self.perm_y[id] * self.net_to_gross[id],
+            _ => self.perm_z[id],
+        };
+        let denom =
+            perm(b) * self.cell_length(a, face.dim) + perm(a) * self.cell_length(b, face.dim);
+        if denom <= 0.0 {
+            return 0.0;
+        }
+        let multiplier = face
+            .root_face
+            .map_or(1.0, |(axis, cell)| self.face_multipliers[axis][cell]);
+        2.0 * perm(a) * perm(b) * area(a).min(area(b)) / denom * multiplier
+    }
+
+    /// Open local-grid faces as connections, in face order.
+    pub(crate) fn local_grid_connections(
+        &self,
+    ) -> impl Iterator<Item = NonNeighborConnection> + '_ {
+        self.local_faces.iter().filter_map(|face| {
+            let transmissibility = self.local_face_transmissibility(face);
+            (transmissibility > 0.0).then_some(NonNeighborConnection {
+                cell_a: face.cell_a,
+                cell_b: face.cell_b,
+                transmissibility,
+            })
+        })
+    }
+
+    /// Open local-grid faces touching `id` as `(neighbour, transmissibility)`.
+    pub(crate) fn cell_local_grid_connections(&self, id: usize) -> Vec<(usize, f64)> {
+        let Some(faces) = self.local_face_index.get(id) else {
+            return Vec::new();
+        };
+        faces
+            .iter()
+            .filter_map(|&face_idx| {
+                let face = &self.local_faces[face_idx];
+                let transmissibility = self.local_face_transmissibility(face);
+                let neighbor = if face.cell_a == id {
+                    face.cell_b
+                } else {
+                    face.cell_a
+                };
+                (transmissibility > 0.0).then_some((neighbor, transmissibility))
+            })
+            .collect()
+    }
+}
@@ -645,7 +645,6 @@ impl ReservoirSimulator {
         }
     }
 
-    #[allow(dead_code)]
     pub(crate) fn get_mu_o_for_rs(&self, p: f64, rs_sm3_sm3: f64) -> f64 {
         if let Some(table) = &self.pvt_table {
             if self.three_phase_mode {
@@ -2,13 +2,16 @@ use std::collections::HashSet;
 
 use serde::{Deserialize, Serialize};
 
+use crate::economic_limits::EconomicLimitEvent;
+use crate::fim::assembly_ad::boundary_inflow_sc_day;
 use crate::fim::state::FimState;
 use crate::fim::wells::{
     build_well_topology, current_reservoir_connection_rate, perforation_component_rates_sc_day,
-    physical_well_control, producer_control_state,
+    perforation_producer_state, physical_well_control,
 };
-use crate::well_control::{ProducerControlState, ResolvedWellControl};
-use crate::{InjectedFluid, ReservoirSimulator};
+use crate::well::{WellScheduleControl, default_well_fraction};
+use crate::well_control::{ProducerControlState, ResolvedWellControl, WellControlGroupKey};
+use crate::{InjectedFluid, ReservoirSimulator, Well};
 
 /// Divide-by-zero guard for producing GOR [Sm³/day of surface oil].
 ///
@@ -51,34 +54,48 @@ pub struct SweepMetrics {
 
 fn compute_sweep_metrics(sim: &ReservoirSimulator, config: &SweepConfig) -> SweepMetrics {
     let nx = sim.nx;
-    let ny = sim.ny;
-    let nz = sim.nz;
     let threshold = config.swept_threshold;
 
-    let mut swept_cells = 0.0_f64;
-    let mut swept_columns = 0.0_f64;
-
-    for j in 0..ny {
-        for i in 0..nx {
-            let mut column_weight = 0.0_f64;
-            for k in 0..nz {
-                let sw = sim.sat_water[k * nx * ny + j * nx + i];
-                let weight = if sw > threshold { 1.0 } else { 0.0 };
-                swept_cells += weight;
-                column_weight = column_weight.max(weight);
-            }
-            swept_columns += column_weight;
+    // Every fraction below is pore-volume weighted, so graded grids and
+    // heterogeneous porosity/NTG fields report swept *volume*, not swept cell
+    // count. A uniform grid reduces to the cell-count fractions. The sums run
+    // over every cell that holds pore volume, the same set the recovered oil
+    // below is summed over: a local-grid child stands in for its deactivated
+    // parent and counts toward the root column it lies in, and a matrix cell
+    // counts toward its fracture cell's column.
+    let mut swept_pv = 0.0_f64;
+    let mut total_pv = 0.0_f64;
+    let mut column_pv = vec![0.0_f64; nx * sim.ny];
+    let mut column_swept = vec![false; nx * sim.ny];
+
+    for id in 0..sim.cell_count() {
+        let pv = sim.pore_volume_m3(id);
+        if pv <= 0.0 {
+            continue;
+        }
+        let (i, j, _) = sim.root_cell_ijk(id);
+        let column = j * nx + i;
+        total_pv += pv;
+        column_pv[column] += pv;
+        if sim.sat_water[id] > threshold {
+            swept_pv += pv;
+            column_swept[column] = true;
         }
     }
-
-    let total = (nx * ny * nz) as f64;
-    let e_vol = if total > 0.0 {
-        swept_cells / total
+    let swept_column_pv: f64 = column_pv
+        .iter()
+        .zip(&column_swept)
+        .filter(|&(_, &swept)| swept)
+        .map(|(&pv, _)| pv)
+        .sum();
+
+    let e_vol = if total_pv > 0.0 {
+        swept_pv / total_pv
     } else {
         0.0
     };
-    let e_a_raw = if (nx * ny) > 0 {
-        swept_columns / (nx * ny) as f64
+    let e_a_raw = if total_pv > 0.0 {
+        swept_column_pv / total_pv
     } else {
         0.0
     };
@@ -100,13 +117,13 @@ fn compute_sweep_metrics(sim: &ReservoirSimulator, config: &SweepConfig) -> Swee
             // "both" or unknown: eA/eV are null, compute mobile oil recovered
             let initial_mobile_per_cell =
                 (config.initial_oil_saturation - config.residual_oil_saturation).max(0.0);
-            let initial_mobile = total * initial_mobile_per_cell;
+            let initial_mobile = total_pv * initial_mobile_per_cell;
             let mobile_oil_recovered = if initial_mobile > 1e-12 {
-                let remaining: f64 = sim
-                    .sat_oil
-                    .iter()
-                    .take(nx * ny * nz)
-                    .map(|&so| (so - config.residual_oil_saturation).max(0.0))
+                let remaining: f64 = (0..sim.cell_count())
+                    .map(|id| {
+                        sim.pore_volume_m3(id)
+                            * (sim.sat_oil[id] - config.residual_oil_saturation).max(0.0)
+                    })
                     .sum();
                 (1.0 - remaining / initial_mobile).clamp(0.0, 1.0)
             } else {
@@ -122,14 +139,6 @@ fn compute_sweep_metrics(sim: &ReservoirSimulator, config: &SweepConfig) -> Swee
     }
 }
 
-fn effective_injected_fluid(sim: &ReservoirSimulator) -> InjectedFluid {
-    if sim.three_phase_mode {
-        sim.injected_fluid
-    } else {
-        InjectedFluid::Water
-    }
-}
-
 #[derive(Clone, Serialize, Deserialize)]
 pub struct WellRates {
     pub oil_rate: f64,
@@ -137,6 +146,63 @@ pub struct WellRates {
     pub total_liquid_rate: f64,
 }
 
+/// One physical well's rates over a step, recorded on every `TimePointRates` entry.
+///
+/// Rates are positive for injectors too: an injector's rates are what it injects. Surface
+/// rates are [Sm³/day], the reservoir rate [m³/day], and cumulatives integrate the rates
+/// over every recorded step.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WellTimePoint {
+    /// `None` for an unnamed well, which is then known by `injector` and its head `i`, `j`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub physical_well_id: Option<String>,
+    pub injector: bool,
+    pub i: usize,
+    pub j: usize,
+    pub oil_rate: f64,
+    pub water_rate: f64,
+    /// Free and dissolved gas.
+    pub gas_rate: f64,
+    pub reservoir_rate: f64,
+    pub cumulative_oil: f64,
+    pub cumulative_water: f64,
+    pub cumulative_gas: f64,
+    pub cumulative_reservoir: f64,
+    /// Surface water / (water + oil); 0 for an injector or a well without liquid rate.
+    pub water_cut: f64,
+    /// Surface gas / oil [Sm³/Sm³]; 0 without surface oil rate.
+    pub gor: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flowing_bhp: Option<f64>,
+    /// Control the well actually flowed under: `"shut"`, `"bhp"`, `"rate"` (a reservoir-volume
+    /// or injector surface target), `"orat"`, `"wrat"`, `"grat"` or `"lrat"` (a producer
+    /// surface target), `"resv"`, `"thp"` or `"group"`. A rate-controlled well held at its BHP
+    /// limit reports `"bhp"`.
+    pub control_mode: String,
+    /// Fraction of the well inside the model (see `Well::well_fraction`).
+    #[serde(default = "default_well_fraction")]
+    pub well_fraction: f64,
+    /// Whether the rates, cumulatives and completion splits are the full well's, the share
+    /// inside the model divided by `well_fraction`, rather than the share itself.
+    #[serde(default)]
+    pub full_pattern: bool,
+    /// Per-completion split of the rates, recorded once completion reporting is enabled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub completions: Vec<CompletionTimePoint>,
+}
+
+/// One completion's share of its well's rates in a `WellTimePoint`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CompletionTimePoint {
+    pub i: usize,
+    pub j: usize,
+    pub k: usize,
+    pub oil_rate: f64,
+    pub water_rate: f64,
+    pub gas_rate: f64,
+    pub reservoir_rate: f64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FimAcceptedRungStats {
     pub substep: u32,
@@ -242,6 +308,16 @@ pub struct FimStepStats {
     pub state_update_ms: Option<f64>,
 }
 
+/// A Fetkovich aquifer at the end of a recorded step, in the order the aquifers were added.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AquiferTimePoint {
+    /// Pot pressure [bar].
+    pub pressure: f64,
+    /// Water let into the reservoir so far [m³ at reservoir conditions]; negative once the
+    /// reservoir has pushed more out than it took in.
+    pub cumulative_influx: f64,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TimePointRates {
     pub time: f64,
@@ -250,6 +326,13 @@ pub struct TimePointRates {
     pub total_production_liquid_reservoir: f64,
     pub total_injection: f64,
     pub total_injection_reservoir: f64,
+    /// Water injected [Sm³/day]; the water part of `total_injection`.
+    #[serde(default)]
+    pub total_injection_water: f64,
+    /// Gas injected [Sm³/day]; the gas part of `total_injection`, non-zero only in three-phase
+    /// mode.
+    #[serde(default)]
+    pub total_injection_gas: f64,
     /// Material balance error [m³]: cumulative (injection - production) vs actual in-place change
     pub material_balance_error_m3: f64,
     /// Oil reporting/material-balance diagnostic [Sm³]: cumulative reported oil production vs
@@ -261,12 +344,12 @@ pub struct TimePointRates {
     pub material_balance_error_oil_m3: f64,
     /// Average reservoir pressure [bar]
     pub avg_reservoir_pressure: f64,
-    /// Average water saturation
+    /// Pore-volume-weighted average water saturation
     pub avg_water_saturation: f64,
     /// Total gas produced [m³/day] (non-zero only in three-phase mode)
     #[serde(default)]
     pub total_production_gas: f64,
-    /// Average gas saturation (non-zero only in three-phase mode)
+    /// Pore-volume-weighted average gas saturation (non-zero only in three-phase mode)
     #[serde(default)]
     pub avg_gas_saturation: f64,
     /// Gas material balance error [Sm³]: cumulative (surface gas injection − surface gas production)
@@ -288,6 +371,19 @@ pub struct TimePointRates {
     /// Sweep efficiency diagnostics (present when sweep config is set).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sweep: Option<SweepMetrics>,
+    /// Economic-limit actions taken at the end of this step.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<EconomicLimitEvent>,
+    /// Rates of every physical well, in order of first completion.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wells: Vec<WellTimePoint>,
+    /// Water every Fetkovich aquifer has let in so far [m³ at reservoir conditions], for the
+    /// water-drive index of a material-balance plot.
+    #[serde(default)]
+    pub cumulative_aquifer_influx: f64,
+    /// State of every Fetkovich aquifer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aquifers: Vec<AquiferTimePoint>,
 }
 
 impl ReservoirSimulator {
@@ -302,25 +398,172 @@ impl ReservoirSimulator {
     }
 
     pub(crate) fn average_reservoir_pressure_pv_weighted(&self) -> f64 {
-        let mut weighted_pressure_sum = 0.0;
+        self.pore_volume_weighted_average(&self.pressure)
+    }
+
+    /// Pore-volume-weighted mean of a per-cell field; cells with a non-positive or
+    /// non-finite pore volume carry no weight.
+    pub(crate) fn pore_volume_weighted_average(&self, values: &[f64]) -> f64 {
+        let mut weighted_sum = 0.0;
         let mut pore_volume_sum = 0.0;
 
-        for id in 0..self.nx * self.ny * self.nz {
+        for (id, value) in values.iter().enumerate().take(self.cell_count()) {
             let pore_volume = self.pore_volume_m3(id);
             if pore_volume <= 0.0 || !pore_volume.is_finite() {
                 continue;
             }
-            weighted_pressure_sum += self.pressure[id] * pore_volume;
+            weighted_sum += value * pore_volume;
             pore_volume_sum += pore_volume;
         }
 
         if pore_volume_sum > 0.0 {
-            weighted_pressure_sum / pore_volume_sum
+            weighted_sum / pore_volume_sum
         } else {
             0.0
         }
     }
 
+    /// Control `well` flowed under this step, as reported in `WellTimePoint::control_mode`.
+    fn active_control_mode(&self, well: &Well, bhp_limited: bool) -> &'static str {
+        let config = self.well_control_config(well);
+        if !config.enabled {
+            "shut"
+        } else if config.thp.is_some() {
+            "thp"
+        } else if config.rate_controlled && !bhp_limited {
+            if well.group_allocation.is_some() {
+                "group"
+            } else if !well.injector && config.target_surface_rate_m3_day.is_some() {
+                config.surface_phase.control_mode()
+            } else {
+                "rate"
+            }
+        } else if !config.rate_controlled
+            && well.schedule.control_kind() == Some(WellScheduleControl::Resv)
+        {
+            "resv"
+        } else {
+            "bhp"
+        }
+    }
+
+    /// Per-physical-well report of a step of `dt_days`, from each completion entry's
+    /// `[water, oil, gas, reservoir]` rates and whether its well sits at its BHP limit.
+    /// Cumulatives carry on from the well's entry in the last recorded point.
+    fn well_time_points(
+        &self,
+        entry_rates: &[[f64; 4]],
+        entry_bhp_limited: &[bool],
+        dt_days: f64,
+    ) -> Vec<WellTimePoint> {
+        let mut keys: Vec<WellControlGroupKey> = Vec::new();
+        let mut points: Vec<WellTimePoint> = Vec::new();
+        for (entry, well) in self.wells.iter().enumerate() {
+            let key = self.well_control_group_key(well);
+            let index = match keys.iter().position(|known| *known == key) {
+                Some(index) => index,
+                None => {
+                    keys.push(key);
+                    let bhp_limited = entry_bhp_limited.get(entry).copied().unwrap_or(false);
+                    points.push(WellTimePoint {
+                        physical_well_id: well.physical_well_id.clone(),
+                        injector: well.injector,
+                        i: well.i,
+                        j: well.j,
+                        flowing_bhp: well.flowing_bhp,
+                        control_mode: self.active_control_mode(well, bhp_limited).to_string(),
+                        well_fraction: well.well_fraction,
+                        full_pattern: self.report_full_pattern_rates,
+                        ..WellTimePoint::default()
+                    });
+                    points.len() - 1
+                }
+            };
+            let [water, oil, gas, reservoir] = entry_rates.get(entry).copied().unwrap_or_default();
+            let point = &mut points[index];
+            point.water_rate += water;
+            point.oil_rate += oil;
+            point.gas_rate += gas;
+            point.reservoir_rate += reservoir;
+            if self.report_completion_rates {
+                point.completions.push(CompletionTimePoint {
+                    i: well.i,
+                    j: well.j,
+                    k: well.k,
+                    oil_rate: oil,
+                    water_rate: water,
+                    gas_rate: gas,
+                    reservoir_rate: reservoir,
+                });
+            }
+        }
+
+        let previous = self
+            .rate_history
+            .last()
+            .map(|point| point.wells.as_slice())
+            .unwrap_or_default();
+        for point in &mut points {
+            let earlier = previous.iter().find(|earlier| {
+                earlier.physical_well_id == point.physical_well_id
+                    && earlier.injector == point.injector
+                    && (point.physical_well_id.is_some()
+                        || (earlier.i == point.i && earlier.j == point.j))
+            });
+            // Cumulatives carry on at the element's share, whichever way the last point was
+            // reported, so switching full-pattern reporting mid-run keeps them consistent.
+            if let Some(earlier) = earlier {
+                let to_element = if earlier.full_pattern {
+                    earlier.well_fraction
+                } else {
+                    1.0
+                };
+                point.cumulative_oil = earlier.cumulative_oil * to_element;
+                point.cumulative_water = earlier.cumulative_water * to_element;
+                point.cumulative_gas = earlier.cumulative_gas * to_element;
+                point.cumulative_reservoir = earlier.cumulative_reservoir * to_element;
+            }
+            point.cumulative_oil += point.oil_rate * dt_days;
+            point.cumulative_water += point.water_rate * dt_days;
+            point.cumulative_gas += point.gas_rate * dt_days;
+            point.cumulative_reservoir += point.reservoir_rate * dt_days;
+            if point.full_pattern {
+                let scale = 1.0 / point.well_fraction;
+                for value in [
+                    &mut point.oil_rate,
+                    &mut point.water_rate,
+                    &mut point.gas_rate,
+                    &mut point.reservoir_rate,
+                    &mut point.cumulative_oil,
+                    &mut point.cumulative_water,
+                    &mut point.cumulative_gas,
+                    &mut point.cumulative_reservoir,
+                ] {
+                    *value *= scale;
+                }
+                for completion in &mut point.completions {
+                    completion.oil_rate *= scale;
+                    completion.water_rate *= scale;
+                    completion.gas_rate *= scale;
+                    completion.reservoir_rate *= scale;
+                }
+            }
+
+            let liquid = point.water_rate + point.oil_rate;
+            point.water_cut = if !point.injector && liquid > 0.0 {
+                point.water_rate / liquid
+            } else {
+                0.0
+            };
+            point.gor = if point.oil_rate > MIN_GOR_OIL_RATE_SC_DAY {
+                point.gas_rate / point.oil_rate
+            } else {
+                0.0
+            };
+        }
+        points
+    }
+
     /// Producer phase split as the transport step used it, captured before saturations are
     /// updated.
     ///
@@ -345,17 +588,19 @@ impl ReservoirSimulator {
                 let control = well_controls.get(w_idx).and_then(|control| *control)?;
                 let state =
                     self.producer_control_state_from_resolved_control(w, control, &self.pressure);
-                let frac_water = self.frac_flow_water(self.idx(w.i, w.j, w.k));
+                let frac_water = self.frac_flow_water(self.well_cell_index(w));
                 Some((state, frac_water))
             })
             .collect()
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn record_step_report(
         &mut self,
         well_controls: &[Option<ResolvedWellControl>],
         phase_splits: &[Option<(ProducerControlState, f64)>],
         dt_days: f64,
+        boundary_inflow: [f64; 3],
         actual_change_m3: f64,
         actual_oil_removed_sc: f64,
         actual_change_gas_sc: f64,
@@ -377,13 +622,13 @@ impl ReservoirSimulator {
             well.flowing_bhp = bhp;
         }
 
-        let n_cells = self.nx * self.ny * self.nz;
         let mut total_prod_oil = 0.0;
         let mut total_prod_liquid = 0.0;
         let mut total_prod_liquid_reservoir = 0.0;
         let mut total_injection = 0.0;
         let mut total_injection_reservoir = 0.0;
         let mut total_water_injection_reservoir = 0.0;
+        let mut total_water_injection_sc = 0.0;
         let mut total_prod_water_reservoir = 0.0;
         let mut total_prod_gas = 0.0;
         let mut total_prod_dissolved_gas = 0.0;
@@ -393,10 +638,13 @@ impl ReservoirSimulator {
         let mut producer_bhp_limited_wells = 0usize;
         let mut injector_bhp_limited_wells = 0usize;
         let mut counted_control_groups = HashSet::new();
+        let mut entry_rates = vec![[0.0; 4]; self.wells.len()];
+        let mut entry_bhp_limited = vec![false; self.wells.len()];
 
         for (w_idx, w) in self.wells.iter().enumerate() {
-            let id = self.idx(w.i, w.j, w.k);
+            let id = self.well_cell_index(w);
             if let Some(control) = well_controls.get(w_idx).and_then(|control| *control) {
+                entry_bhp_limited[w_idx] = control.bhp_limited;
                 let control_config = self.well_control_config(w);
                 let group_key = self.well_control_group_key(w);
                 if control_config.rate_controlled && counted_control_groups.insert(group_key) {
@@ -421,22 +669,21 @@ impl ReservoirSimulator {
 
                 if w.injector {
                     total_injection_reservoir += -q_m3_day;
-                    if self.three_phase_mode {
-                        match self.injected_fluid {
-                            InjectedFluid::Water => {
-                                total_injection +=
-                                    -q_m3_day * self.water_inverse_fvf(self.pressure[id]);
-                                total_water_injection_reservoir += -q_m3_day;
-                            }
-                            InjectedFluid::Gas => {
-                                let bg = self.get_b_g(self.pressure[id]).max(1e-9);
-                                total_injection += -q_m3_day / bg;
-                                total_gas_injection_sc += -q_m3_day / bg;
-                            }
+                    entry_rates[w_idx][3] = -q_m3_day;
+                    match self.well_injected_fluid(w) {
+                        InjectedFluid::Water => {
+                            let water_sc = -q_m3_day * self.water_inverse_fvf(self.pressure[id]);
+                            total_injection += water_sc;
+                            total_water_injection_sc += water_sc;
+                            total_water_injection_reservoir += -q_m3_day;
+                            entry_rates[w_idx][0] = water_sc;
+                        }
+                        InjectedFluid::Gas => {
+                            let bg = self.get_b_g(self.pressure[id]).max(1e-9);
+                            total_injection += -q_m3_day / bg;
+                            total_gas_injection_sc += -q_m3_day / bg;
+                            entry_rates[w_idx][2] = -q_m3_day / bg;
                         }
-                    } else {
-                        total_injection += -q_m3_day * self.water_inverse_fvf(self.pressure[id]);
-                        total_water_injection_reservoir += -q_m3_day;
                     }
                 } else {
                     total_prod_liquid_reservoir += q_m3_day;
@@ -462,10 +709,14 @@ impl ReservoirSimulator {
                     total_prod_liquid += oil_rate_sc + water_rate_sc;
 
                     let bg = producer_state.gas_fvf.max(1e-9);
-                    total_prod_gas += q_m3_day * fg / bg;
+                    let mut gas_rate_sc = q_m3_day * fg / bg;
+                    total_prod_gas += gas_rate_sc;
                     if self.pvt_table.is_some() && self.three_phase_mode {
-                        total_prod_dissolved_gas += oil_rate_sc * producer_state.rs_sm3_sm3;
+                        let dissolved_gas_sc = oil_rate_sc * producer_state.rs_sm3_sm3;
+                        total_prod_dissolved_gas += dissolved_gas_sc;
+                        gas_rate_sc += dissolved_gas_sc;
                     }
+                    entry_rates[w_idx] = [water_rate_sc, oil_rate_sc, gas_rate_sc, q_m3_day];
                 }
             }
         }
@@ -473,39 +724,28 @@ impl ReservoirSimulator {
         self.cumulative_injection_m3 += total_water_injection_reservoir * dt_days;
         self.cumulative_production_m3 += total_prod_water_reservoir * dt_days;
 
-        let net_water_added_m3 =
-            (total_water_injection_reservoir - total_prod_water_reservoir) * dt_days;
+        // Boundary faces add `[water m³, oil Sm³, gas Sm³]` per day to the balance.
+        let [boundary_water, boundary_oil, boundary_gas] = boundary_inflow;
+        let net_water_added_m3 = (total_water_injection_reservoir - total_prod_water_reservoir
+            + boundary_water)
+            * dt_days;
         self.cumulative_mb_error_m3 += net_water_added_m3 - actual_change_m3;
 
-        let produced_oil_sc = total_prod_oil * dt_days;
-        self.cumulative_mb_oil_error_m3 += produced_oil_sc - actual_oil_removed_sc;
+        let net_oil_removed_sc = (total_prod_oil - boundary_oil) * dt_days;
+        self.cumulative_mb_oil_error_m3 += net_oil_removed_sc - actual_oil_removed_sc;
 
         if self.three_phase_mode {
             let total_gas_prod_sc = total_prod_gas + total_prod_dissolved_gas;
-            let net_gas_added_sc = (total_gas_injection_sc - total_gas_prod_sc) * dt_days;
+            let net_gas_added_sc =
+                (total_gas_injection_sc - total_gas_prod_sc + boundary_gas) * dt_days;
             self.cumulative_mb_gas_error_m3 += net_gas_added_sc - actual_change_gas_sc;
         }
 
         let mb_error = self.cumulative_mb_error_m3.abs();
 
-        let mut sum_sat_water = 0.0;
-        let mut sum_sat_gas = 0.0;
-        for i in 0..n_cells {
-            sum_sat_water += self.sat_water[i];
-            sum_sat_gas += self.sat_gas[i];
-        }
-
         let avg_reservoir_pressure = self.average_reservoir_pressure_pv_weighted();
-        let avg_water_saturation = if n_cells > 0 {
-            sum_sat_water / n_cells as f64
-        } else {
-            0.0
-        };
-        let avg_gas_saturation = if n_cells > 0 {
-            sum_sat_gas / n_cells as f64
-        } else {
-            0.0
-        };
+        let avg_water_saturation = self.pore_volume_weighted_average(&self.sat_water);
+        let avg_gas_saturation = self.pore_volume_weighted_average(&self.sat_gas);
 
         let total_gas_sc = total_prod_gas + total_prod_dissolved_gas;
         let producing_gor = if total_prod_oil > MIN_GOR_OIL_RATE_SC_DAY {
@@ -528,6 +768,9 @@ impl ReservoirSimulator {
             .sweep_config
             .as_ref()
             .map(|cfg| compute_sweep_metrics(self, cfg));
+        let wells = self.well_time_points(&entry_rates, &entry_bhp_limited, dt_days);
+        self.advance_aquifers(dt_days);
+        let aquifers = self.aquifer_time_points();
 
         self.rate_history.push(TimePointRates {
             time: self.time_days + dt_days,
@@ -536,6 +779,8 @@ impl ReservoirSimulator {
             total_production_liquid_reservoir: total_prod_liquid_reservoir,
             total_injection,
             total_injection_reservoir,
+            total_injection_water: total_water_injection_sc,
+            total_injection_gas: total_gas_injection_sc,
             material_balance_error_m3: mb_error,
             material_balance_error_oil_m3: self.cumulative_mb_oil_error_m3.abs(),
             material_balance_error_gas_m3: self.cumulative_mb_gas_error_m3.abs(),
@@ -547,6 +792,13 @@ impl ReservoirSimulator {
             producer_bhp_limited_fraction,
             injector_bhp_limited_fraction,
             sweep,
+            events: Vec::new(),
+            wells,
+            cumulative_aquifer_influx: aquifers
+                .iter()
+                .map(|aquifer| aquifer.cumulative_influx)
+                .sum(),
+            aquifers,
         });
     }
 
@@ -558,7 +810,6 @@ impl ReservoirSimulator {
         actual_oil_removed_sc: f64,
         actual_change_gas_sc: f64,
     ) {
-        let n_cells = self.nx * self.ny * self.nz;
         let topology = build_well_topology(self);
 
         // Same reporting-only publication as the IMPES path. Here the flowing
@@ -597,6 +848,8 @@ impl ReservoirSimulator {
         let mut injector_rate_controlled_wells = 0usize;
         let mut producer_bhp_limited_wells = 0usize;
         let mut injector_bhp_limited_wells = 0usize;
+        let mut entry_rates = vec![[0.0; 4]; self.wells.len()];
+        let mut entry_bhp_limited = vec![false; self.wells.len()];
 
         for (well_idx, physical_well) in topology.wells.iter().enumerate() {
             let control = physical_well_control(self, &topology, well_idx);
@@ -607,6 +860,10 @@ impl ReservoirSimulator {
                 } else {
                     bhp_bar <= control.bhp_limit + 1e-6
                 };
+                for &perf_idx in &physical_well.perforation_indices {
+                    entry_bhp_limited[topology.perforations[perf_idx].well_entry_index] =
+                        bhp_limited;
+                }
 
                 if physical_well.injector {
                     injector_rate_controlled_wells += 1;
@@ -628,29 +885,48 @@ impl ReservoirSimulator {
             let components_sc_day =
                 perforation_component_rates_sc_day(self, state, &topology, perf_idx);
 
+            let entry = perforation.well_entry_index;
             if perforation.injector {
                 total_injection_reservoir += (-q_m3_day).max(0.0);
-                match effective_injected_fluid(self) {
+                entry_rates[entry][3] = (-q_m3_day).max(0.0);
+                match self.well_injected_fluid(&self.wells[entry]) {
                     InjectedFluid::Water => {
                         total_injection += (-components_sc_day[0]).max(0.0);
                         total_water_injection_reservoir += (-q_m3_day).max(0.0);
                         total_water_injection_sc += (-components_sc_day[0]).max(0.0);
+                        entry_rates[entry][0] = (-components_sc_day[0]).max(0.0);
                     }
                     InjectedFluid::Gas => {
                         total_injection += (-components_sc_day[2]).max(0.0);
                         total_gas_injection_sc += (-components_sc_day[2]).max(0.0);
+                        entry_rates[entry][2] = (-components_sc_day[2]).max(0.0);
                     }
                 }
                 continue;
             }
 
-            total_prod_liquid_reservoir += q_m3_day.max(0.0);
-            let producer = producer_control_state(self, state, perforation);
-            total_prod_water_reservoir += q_m3_day.max(0.0) * producer.water_fraction;
-            total_prod_water_sc += components_sc_day[0].max(0.0);
-            total_prod_oil += components_sc_day[1].max(0.0);
-            total_prod_liquid += components_sc_day[0].max(0.0) + components_sc_day[1].max(0.0);
-            total_prod_gas += components_sc_day[2].max(0.0);
+            // A multi-segment producer's completion can put wellbore fluid back into its
+            // layer; that crossflow nets against what the other completions produce.
+            let produced = |rate: f64| {
+                if perforation.takes_crossflow() {
+                    rate
+                } else {
+                    rate.max(0.0)
+                }
+            };
+            total_prod_liquid_reservoir += produced(q_m3_day);
+            let producer = perforation_producer_state(self, state, &topology, perf_idx, q_m3_day);
+            total_prod_water_reservoir += produced(q_m3_day) * producer.water_fraction;
+            total_prod_water_sc += produced(components_sc_day[0]);
+            total_prod_oil += produced(components_sc_day[1]);
+            total_prod_liquid += produced(components_sc_day[0]) + produced(components_sc_day[1]);
+            total_prod_gas += produced(components_sc_day[2]);
+            entry_rates[entry] = [
+                produced(components_sc_day[0]),
+                produced(components_sc_day[1]),
+                produced(components_sc_day[2]),
+                produced(q_m3_day),
+            ];
         }
 
         self.cumulative_injection_m3 += total_water_injection_reservoir * dt_days;
@@ -659,37 +935,26 @@ impl ReservoirSimulator {
         // FIM conserves surface-condition water component (`PV * Sw / Bw`),
         // not reservoir-condition water volume. Use the matching component
         // well rates so pressure-dependent Bw cannot appear as false drift.
-        let net_water_added_m3 = (total_water_injection_sc - total_prod_water_sc) * dt_days;
+        let [boundary_water, boundary_oil, boundary_gas] =
+            boundary_inflow_sc_day(self, state, dt_days);
+        let net_water_added_m3 =
+            (total_water_injection_sc - total_prod_water_sc + boundary_water) * dt_days;
         self.cumulative_mb_error_m3 += net_water_added_m3 - actual_change_m3;
 
-        let produced_oil_sc = total_prod_oil * dt_days;
-        self.cumulative_mb_oil_error_m3 += produced_oil_sc - actual_oil_removed_sc;
+        let net_oil_removed_sc = (total_prod_oil - boundary_oil) * dt_days;
+        self.cumulative_mb_oil_error_m3 += net_oil_removed_sc - actual_oil_removed_sc;
 
         if self.three_phase_mode {
-            let net_gas_added_sc = (total_gas_injection_sc - total_prod_gas) * dt_days;
+            let net_gas_added_sc =
+                (total_gas_injection_sc - total_prod_gas + boundary_gas) * dt_days;
             self.cumulative_mb_gas_error_m3 += net_gas_added_sc - actual_change_gas_sc;
         }
 
         let mb_error = self.cumulative_mb_error_m3.abs();
 
-        let mut sum_sat_water = 0.0;
-        let mut sum_sat_gas = 0.0;
-        for i in 0..n_cells {
-            sum_sat_water += self.sat_water[i];
-            sum_sat_gas += self.sat_gas[i];
-        }
-
         let avg_reservoir_pressure = self.average_reservoir_pressure_pv_weighted();
-        let avg_water_saturation = if n_cells > 0 {
-            sum_sat_water / n_cells as f64
-        } else {
-            0.0
-        };
-        let avg_gas_saturation = if n_cells > 0 {
-            sum_sat_gas / n_cells as f64
-        } else {
-            0.0
-        };
+        let avg_water_saturation = self.pore_volume_weighted_average(&self.sat_water);
+        let avg_gas_saturation = self.pore_volume_weighted_average(&self.sat_gas);
 
         let producing_gor = if total_prod_oil > MIN_GOR_OIL_RATE_SC_DAY {
             total_prod_gas / total_prod_oil
@@ -711,6 +976,9 @@ impl ReservoirSimulator {
             .sweep_config
             .as_ref()
             .map(|cfg| compute_sweep_metrics(self, cfg));
+        let wells = self.well_time_points(&entry_rates, &entry_bhp_limited, dt_days);
+        self.advance_aquifers(dt_days);
+        let aquifers = self.aquifer_time_points();
 
         self.rate_history.push(TimePointRates {
             time: self.time_days + dt_days,
@@ -719,6 +987,8 @@ impl ReservoirSimulator {
             total_production_liquid_reservoir: total_prod_liquid_reservoir,
             total_injection,
             total_injection_reservoir,
+            total_injection_water: total_water_injection_sc,
+            total_injection_gas: total_gas_injection_sc,
             material_balance_error_m3: mb_error,
             material_balance_error_oil_m3: self.cumulative_mb_oil_error_m3.abs(),
             avg_reservoir_pressure,
@@ -730,6 +1000,13 @@ impl ReservoirSimulator {
             producer_bhp_limited_fraction,
             injector_bhp_limited_fraction,
             sweep,
+            events: Vec::new(),
+            wells,
+            cumulative_aquifer_influx: aquifers
+                .iter()
+                .map(|aquifer| aquifer.cumulative_influx)
+                .sum(),
+            aquifers,
         });
     }
 }
@@ -0,0 +1,292 @@
+//! Dated well events applied by the engine as simulation time reaches them (Eclipse `SCHEDULE`
+//! with `DATES` / `TSTEP`).
+//!
+//! Each event names a physical well and what happens to it: it opens or shuts, takes a new
+//! control and targets, switches THP control or injected phase, gains a completion or opens or
+//! shuts one. Events are kept sorted by time, and those sharing a time apply in the order given.
+//!
+//! [`ReservoirSimulator::step_internal`] cuts every requested step at the pending event times,
+//! so both timestep controllers land exactly on each event, and applies the events due before
+//! stepping on. A WAG cycle or a field history is then a single `runUntil` call.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ReservoirSimulator;
+use crate::well_segments::WellSegment;
+
+/// Two times closer than this [days] are the same event time.
+pub(crate) const SCHEDULE_TIME_TOLERANCE_DAYS: f64 = 1e-9;
+
+/// What a schedule event does to its well.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScheduleAction {
+    /// Reopen the well.
+    Open,
+    /// Shut the well in; it keeps its completions and control.
+    Shut,
+    /// Change the well's control as `setWellSchedule` does, keeping its open or shut status.
+    /// `bhp_bar` also moves the well's BHP target.
+    Control {
+        control_mode: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target_rate_m3_day: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target_surface_rate_m3_day: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bhp_limit: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bhp_bar: Option<f64>,
+    },
+    /// Put the well on tubing-head pressure control, as `setWellThpControl` does.
+    Thp { vfp_table: u32, thp_bar: f64 },
+    /// Switch the injected phase, as `setWellInjectedFluid` does; empty follows the
+    /// simulator-wide fluid.
+    InjectedFluid { fluid: String },
+    /// Add a completion in cell `(i, j, k)`, vertical or along `direction` (`"x"`, `"y"` or
+    /// `"z"`). A completion added to an existing well takes on its control, guide rate, limits,
+    /// datum and D-factor, and extends a multi-segment wellbore at its toe.
+    Completion {
+        i: usize,
+        j: usize,
+        k: usize,
+        bhp: f64,
+        well_radius: f64,
+        skin: f64,
+        injector: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        direction: Option<String>,
+    },
+    /// Open or shut the well's completion in root cell `(i, j, k)`, as `setCompletionStatus`
+    /// does.
+    CompletionStatus {
+        i: usize,
+        j: usize,
+        k: usize,
+        open: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleEvent {
+    /// Simulation time the event applies at [days].
+    pub time_days: f64,
+    pub physical_well_id: String,
+    #[serde(flatten)]
+    pub action: ScheduleAction,
+}
+
+impl ScheduleEvent {
+    fn validate(&self) -> Result<(), String> {
+        if !self.time_days.is_finite() || self.time_days < 0.0 {
+            return Err(format!(
+                "Schedule event time must be finite and non-negative, got {}",
+                self.time_days
+            ));
+        }
+        if self.physical_well_id.trim().is_empty() {
+            return Err("Schedule event physical well id must not be empty".to_string());
+        }
+        let finite = |name: &str, value: f64| {
+            if value.is_finite() {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Schedule event {} must be finite, got {}",
+                    name, value
+                ))
+            }
+        };
+        match &self.action {
+            ScheduleAction::Open
+            | ScheduleAction::Shut
+            | ScheduleAction::InjectedFluid { .. }
+            | ScheduleAction::CompletionStatus { .. } => {}
+            ScheduleAction::Control { bhp_bar, .. } => {
+                if let Some(bhp_bar) = bhp_bar {
+                    finite("BHP", *bhp_bar)?;
+                }
+            }
+            ScheduleAction::Thp { thp_bar, .. } => finite("THP", *thp_bar)?,
+            ScheduleAction::Completion {
+                bhp,
+                well_radius,
+                skin,
+                ..
+            } => {
+                finite("completion BHP", *bhp)?;
+                finite("completion well radius", *well_radius)?;
+                finite("completion skin", *skin)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ReservoirSimulator {
+    /// Replace the pending schedule with `events`. Events at or before the current time apply
+    /// at the start of the next step.
+    pub(crate) fn set_schedule_events(
+        &mut self,
+        mut events: Vec<ScheduleEvent>,
+    ) -> Result<(), String> {
+        for event in &events {
+            event.validate()?;
+        }
+        events.sort_by(|a, b| a.time_days.total_cmp(&b.time_days));
+        self.schedule_events = events;
+        Ok(())
+    }
+
+    /// Time [days] of the earliest pending event.
+    pub(crate) fn next_schedule_event_time(&self) -> Option<f64> {
+        self.schedule_events.first().map(|event| event.time_days)
+    }
+
+    /// Apply every pending event due at the current time, in order. Returns a message for the
+    /// first event that could not be applied; the others still apply.
+    pub(crate) fn apply_due_schedule_events(&mut self) -> Option<String> {
+        let due = self.schedule_events.partition_point(|event| {
+            event.time_days <= self.time_days + SCHEDULE_TIME_TOLERANCE_DAYS
+        });
+        let mut failure = None;
+        for event in self.schedule_events.drain(..due).collect::<Vec<_>>() {
+            if let Err(message) = self.apply_schedule_event(&event) {
+                failure.get_or_insert_with(|| {
+                    format!(
+                        "Schedule event at t={:.6} days for well '{}' failed: {}",
+                        event.time_days, event.physical_well_id, message
+                    )
+                });
+            }
+        }
+        failure
+    }
+
+    fn apply_schedule_event(&mut self, event: &ScheduleEvent) -> Result<(), String> {
+        let well_id = event.physical_well_id.trim();
+        let template = self
+            .wells
+            .iter()
+            .position(|well| well.physical_well_id.as_deref() == Some(well_id));
+        if template.is_none() && !matches!(event.action, ScheduleAction::Completion { .. }) {
+            return Err(format!("No well found for physical well id '{}'", well_id));
+        }
+
+        match &event.action {
+            ScheduleAction::Open | ScheduleAction::Shut => {
+                let enabled = event.action == ScheduleAction::Open;
+                for well in self.wells.iter_mut() {
+                    if well.physical_well_id.as_deref() == Some(well_id) {
+                        well.schedule.enabled = enabled;
+                    }
+                }
+            }
+            ScheduleAction::Control {
+                control_mode,
+                target_rate_m3_day,
+                target_surface_rate_m3_day,
+                bhp_limit,
+                bhp_bar,
+            } => {
+                let enabled = template.is_some_and(|entry| self.wells[entry].schedule.enabled);
+                self.set_well_schedule(
+                    well_id.to_string(),
+                    control_mode.clone(),
+                    target_rate_m3_day.unwrap_or(f64::NAN),
+                    target_surface_rate_m3_day.unwrap_or(f64::NAN),
+                    bhp_limit.unwrap_or(f64::NAN),
+                    enabled,
+                )?;
+                if let Some(bhp_bar) = *bhp_bar {
+                    for well in self.wells.iter_mut() {
+                        if well.physical_well_id.as_deref() == Some(well_id) {
+                            well.bhp = bhp_bar;
+                        }
+                    }
+                }
+            }
+            ScheduleAction::Thp { vfp_table, thp_bar } => {
+                self.set_well_thp_control(well_id.to_string(), *vfp_table, *thp_bar)?;
+            }
+            ScheduleAction::InjectedFluid { fluid } => {
+                self.set_well_injected_fluid(well_id.to_string(), fluid)?;
+            }
+            ScheduleAction::CompletionStatus { i, j, k, open } => {
+                self.set_completion_status(well_id.to_string(), *i, *j, *k, *open)?;
+            }
+            ScheduleAction::Completion {
+                i,
+                j,
+                k,
+                bhp,
+                well_radius,
+                skin,
+                injector,
+                direction,
+            } => {
+                if let Some(entry) = template
+                    && self.wells[entry].injector != *injector
+                {
+                    return Err(format!(
+                        "Completion must be {} like the rest of well '{}'",
+                        if *injector {
+                            "a producer"
+                        } else {
+                            "an injector"
+                        },
+                        well_id
+                    ));
+                }
+                let wells_before = self.wells.len();
+                match direction {
+                    Some(direction) => self.add_directional_well(
+                        *i,
+                        *j,
+                        *k,
+                        direction.clone(),
+                        *bhp,
+                        *well_radius,
+                        *skin,
+                        *injector,
+                        well_id.to_string(),
+                    )?,
+                    None => self.add_well_with_id(
+                        *i,
+                        *j,
+                        *k,
+                        *bhp,
+                        *well_radius,
+                        *skin,
+                        *injector,
+                        well_id.to_string(),
+                    )?,
+                }
+                if let Some(template) = template.map(|entry| self.wells[entry].clone()) {
+                    for well in &mut self.wells[wells_before..] {
+                        well.schedule = template.schedule.clone();
+                        well.guide_rate = template.guide_rate;
+                        well.economic_limits = template.economic_limits;
+                        well.datum_depth_m = template.datum_depth_m;
+                        well.wellbore_density_kg_m3 = template.wellbore_density_kg_m3;
+                        well.well_fraction = template.well_fraction;
+                        well.d_factor = template.d_factor;
+                    }
+                    if let Some(segment) = template.segment {
+                        let group = self.well_control_group_indices(&template);
+                        let added = self.wells.len() - wells_before;
+                        for position in group.len() - added..group.len() {
+                            self.wells[group[position]].segment = Some(WellSegment {
+                                length_m: self.derived_segment_length_m(&group, position),
+                                ..segment
+                            });
+                        }
+                    }
+                    self.update_dynamic_well_productivity_indices();
+                    self.refresh_well_head_offsets();
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::schedule::SCHEDULE_TIME_TOLERANCE_DAYS;
 use crate::{InjectedFluid, ReservoirSimulator};
 
 /// Fraction of total pore volume the cumulative volumetric material-balance
@@ -10,11 +11,43 @@ use crate::{InjectedFluid, ReservoirSimulator};
 const MATERIAL_BALANCE_WARNING_FRACTION: f64 = 1e-3;
 
 impl ReservoirSimulator {
+    /// Advance `target_dt_days`, cut at every pending schedule event so the timestep
+    /// controllers land exactly on each event time, where its events apply before stepping on.
     pub(crate) fn step_internal(&mut self, target_dt_days: f64) {
+        let end_time_days = self.time_days + target_dt_days;
+        let mut warning = String::new();
+        while !self.run_stopped {
+            if let Some(failure) = self.apply_due_schedule_events()
+                && warning.is_empty()
+            {
+                warning = failure;
+            }
+            if end_time_days - self.time_days <= SCHEDULE_TIME_TOLERANCE_DAYS {
+                break;
+            }
+            let segment_end_days = self
+                .next_schedule_event_time()
+                .filter(|&event_time| event_time < end_time_days)
+                .unwrap_or(end_time_days);
+            self.step_segment(segment_end_days - self.time_days);
+            if warning.is_empty() {
+                warning = std::mem::take(&mut self.last_solver_warning);
+            }
+            // A controller that gave up short of the event must not have the event applied.
+            if segment_end_days - self.time_days > SCHEDULE_TIME_TOLERANCE_DAYS {
+                break;
+            }
+        }
+        self.last_solver_warning = warning;
+    }
+
+    fn step_segment(&mut self, target_dt_days: f64) {
         // Lag the wellbore column on the state entering the step, so every
         // completion's datum offset is a constant for the whole step (and every
         // FIM Newton iteration inside it).
         self.refresh_well_head_offsets();
+        // Group shares are likewise fixed from the state entering the step.
+        self.allocate_group_targets();
 
         if self.fim_enabled {
             crate::fim::timestep::step_internal(self, target_dt_days);
@@ -23,6 +56,7 @@ impl ReservoirSimulator {
         }
 
         self.warn_on_material_balance_drift();
+        self.apply_economic_limits();
     }
 
     /// Raise a solver-independent warning when the shared reporting ledger says
@@ -38,12 +72,17 @@ impl ReservoirSimulator {
         // injection/depletion runs use the gas-component ledger instead; their
         // connate-water `PV * Sw` can move under rock compaction without being a
         // valid water-conservation oracle.
-        if (self.three_phase_mode && self.injected_fluid == InjectedFluid::Gas)
+        let injects_gas = self.three_phase_mode
+            && (self.injected_fluid == InjectedFluid::Gas
+                || self.wells.iter().any(|well| {
+                    well.injector && self.well_injected_fluid(well) == InjectedFluid::Gas
+                }));
+        if injects_gas
             || self.cumulative_injection_m3.abs() + self.cumulative_production_m3.abs() <= 1e-12
         {
             return;
         }
-        let pore_volume_m3: f64 = (0..self.nx * self.ny * self.nz)
+        let pore_volume_m3: f64 = (0..self.cell_count())
             .map(|idx| self.pore_volume_m3(idx))
             .sum();
         if !(pore_volume_m3 > 0.0) {
@@ -18,9 +18,9 @@ fn per_layer_dz_affects_pore_volume_and_depth() {
     assert!((pv1 - 100.0 * 100.0 * 9.0 * 0.25).abs() < 1e-10);
     assert!((pv2 - 100.0 * 100.0 * 15.0 * 0.25).abs() < 1e-10);
 
-    let d0 = sim.depth_at_k(0);
-    let d1 = sim.depth_at_k(1);
-    let d2 = sim.depth_at_k(2);
+    let d0 = sim.depth_at(sim.idx(0, 0, 0));
+    let d1 = sim.depth_at(sim.idx(0, 0, 1));
+    let d2 = sim.depth_at(sim.idx(0, 0, 2));
 
     assert!(
         (d0 - 3.0).abs() < 1e-10,
@@ -95,3 +95,1595 @@ fn average_reservoir_pressure_is_pv_weighted() {
     assert!((sim.average_reservoir_pressure_pv_weighted() - expected).abs() < 1e-12);
     assert!((sim.average_reservoir_pressure_pv_weighted() - 150.0).abs() > 1e-6);
 }
+
+#[test]
+fn tensor_grid_spacing_sets_per_cell_pore_volume() {
+    let mut sim = ReservoirSimulator::new(3, 2, 1, 0.2);
+    sim.set_cell_dimensions_tensor(vec![1.0, 4.0, 16.0], vec![5.0, 10.0], vec![2.0])
+        .unwrap();
+
+    for (i, dx) in [1.0, 4.0, 16.0].iter().enumerate() {
+        for (j, dy) in [5.0, 10.0].iter().enumerate() {
+            let id = sim.idx(i, j, 0);
+            let expected = dx * dy * 2.0 * 0.2;
+            assert!(
+                (sim.pore_volume_m3(id) - expected).abs() < 1e-12,
+                "cell ({}, {}) pore volume should be {}, got {}",
+                i,
+                j,
+                expected,
+                sim.pore_volume_m3(id)
+            );
+        }
+    }
+}
+
+#[test]
+fn tensor_grid_transmissibility_uses_half_cell_lengths() {
+    let mut sim = ReservoirSimulator::new(2, 2, 1, 0.2);
+    sim.set_cell_dimensions_tensor(vec![2.0, 8.0], vec![3.0, 12.0], vec![4.0])
+        .unwrap();
+    sim.set_permeability_field(
+        vec![100.0, 400.0, 100.0, 400.0],
+        vec![50.0, 50.0, 200.0, 200.0],
+        vec![10.0; 4],
+    )
+    .unwrap();
+
+    // x-face between (0,0) and (1,0): area dy[0]*dz, half-lengths dx/2.
+    let t_x = sim.geometric_transmissibility(sim.idx(0, 0, 0), sim.idx(1, 0, 0), 'x');
+    let expected_x = 1.0 / (1.0 / (100.0 * 12.0 / 1.0) + 1.0 / (400.0 * 12.0 / 4.0));
+    assert!((t_x - expected_x).abs() / expected_x < 1e-12);
+
+    // y-face between (1,0) and (1,1): area dx[1]*dz, half-lengths dy/2.
+    let t_y = sim.geometric_transmissibility(sim.idx(1, 0, 0), sim.idx(1, 1, 0), 'y');
+    let expected_y = 1.0 / (1.0 / (50.0 * 32.0 / 1.5) + 1.0 / (200.0 * 32.0 / 6.0));
+    assert!((t_y - expected_y).abs() / expected_y < 1e-12);
+}
+
+#[test]
+fn tensor_grid_peaceman_radius_uses_the_completed_cell() {
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+    sim.set_cell_dimensions_tensor(vec![50.0, 2.0, 50.0], vec![2.0], vec![10.0])
+        .unwrap();
+
+    sim.add_well(1, 0, 0, 100.0, 0.1, 0.0, false).unwrap();
+    sim.add_well(0, 0, 0, 100.0, 0.1, 0.0, false).unwrap();
+
+    let fine_pi = sim.well_productivity_index(&sim.wells[0]).unwrap();
+    let coarse_pi = sim.well_productivity_index(&sim.wells[1]).unwrap();
+
+    let r_eq_fine = 0.28 * (2.0_f64 * 2.0 + 2.0 * 2.0).sqrt() / 2.0;
+    let r_eq_coarse = 0.28 * (50.0_f64 * 50.0 + 2.0 * 2.0).sqrt() / 2.0;
+    let expected_ratio = (r_eq_coarse / 0.1).ln() / (r_eq_fine / 0.1).ln();
+    assert!(
+        (fine_pi / coarse_pi - expected_ratio).abs() < 1e-12,
+        "PI ratio should follow ln(r_eq) of each cell, got {}",
+        fine_pi / coarse_pi
+    );
+}
+
+#[test]
+fn uniform_tensor_grid_matches_scalar_cell_dimensions() {
+    let build = |tensor: bool| {
+        let mut sim = ReservoirSimulator::new(4, 1, 2, 0.2);
+        if tensor {
+            sim.set_cell_dimensions_tensor(vec![20.0; 4], vec![15.0], vec![3.0, 5.0])
+                .unwrap();
+        } else {
+            sim.set_cell_dimensions_per_layer(20.0, 15.0, vec![3.0, 5.0])
+                .unwrap();
+        }
+        sim.set_permeability_random_seeded(50.0, 500.0, 7).unwrap();
+        sim.add_well(0, 0, 0, 400.0, 0.1, 0.0, true).unwrap();
+        sim.add_well(3, 0, 1, 150.0, 0.1, 0.0, false).unwrap();
+        sim.step(2.0);
+        sim
+    };
+
+    let scalar = build(false);
+    let tensor = build(true);
+    assert_eq!(scalar.pressure, tensor.pressure);
+    assert_eq!(scalar.sat_water, tensor.sat_water);
+}
+
+#[test]
+fn tensor_grid_validation_rejects_invalid_inputs() {
+    let mut sim = ReservoirSimulator::new(2, 3, 1, 0.2);
+
+    err_contains(
+        sim.set_cell_dimensions_tensor(vec![1.0], vec![1.0, 1.0, 1.0], vec![1.0]),
+        "dx_per_column must have length equal to nx",
+    );
+    err_contains(
+        sim.set_cell_dimensions_tensor(vec![1.0, 1.0], vec![1.0, 1.0], vec![1.0]),
+        "dy_per_row must have length equal to ny",
+    );
+    err_contains(
+        sim.set_cell_dimensions_tensor(vec![1.0, 1.0], vec![1.0, f64::NAN, 1.0], vec![1.0]),
+        "positive and finite",
+    );
+    err_contains(
+        sim.set_cell_dimensions_tensor(vec![1.0, 1.0], vec![1.0, 1.0, 1.0], vec![0.0]),
+        "dz_per_layer entry 0 must be positive and finite",
+    );
+}
+
+#[test]
+fn porosity_field_sets_per_cell_pore_volume() {
+    let mut sim = ReservoirSimulator::new(2, 1, 2, 0.2);
+    sim.set_cell_dimensions(10.0, 10.0, 2.0).unwrap();
+    sim.set_porosity_field(vec![0.1, 0.2, 0.3, 0.4]).unwrap();
+
+    for (id, phi) in [0.1, 0.2, 0.3, 0.4].iter().enumerate() {
+        assert!((sim.pore_volume_m3(id) - 200.0 * phi).abs() < 1e-12);
+    }
+
+    sim.sat_water = vec![1.0, 0.0, 0.0, 0.0];
+    let expected = 0.1 / (0.1 + 0.2 + 0.3 + 0.4);
+    assert!((sim.pore_volume_weighted_average(&sim.sat_water) - expected).abs() < 1e-12);
+}
+
+#[test]
+fn net_to_gross_scales_pore_volume_horizontal_transmissibility_and_pi() {
+    let build = |ntg: Option<Vec<f64>>| {
+        let mut sim = ReservoirSimulator::new(2, 2, 2, 0.25);
+        sim.set_cell_dimensions(10.0, 10.0, 4.0).unwrap();
+        if let Some(ntg) = ntg {
+            sim.set_net_to_gross_field(ntg).unwrap();
+        }
+        sim.add_well(0, 0, 0, 100.0, 0.1, 0.0, false).unwrap();
+        sim
+    };
+    let gross = build(None);
+    let net = build(Some(vec![0.5; 8]));
+
+    let (a, b) = (net.idx(0, 0, 0), net.idx(1, 0, 0));
+    assert!((net.pore_volume_m3(a) - 0.5 * gross.pore_volume_m3(a)).abs() < 1e-12);
+    assert!(
+        (net.geometric_transmissibility(a, b, 'x')
+            - 0.5 * gross.geometric_transmissibility(a, b, 'x'))
+        .abs()
+            < 1e-12
+    );
+    let c = net.idx(0, 1, 0);
+    assert!(
+        (net.geometric_transmissibility(a, c, 'y')
+            - 0.5 * gross.geometric_transmissibility(a, c, 'y'))
+        .abs()
+            < 1e-12
+    );
+    let d = net.idx(0, 0, 1);
+    assert_eq!(
+        net.geometric_transmissibility(a, d, 'z'),
+        gross.geometric_transmissibility(a, d, 'z')
+    );
+
+    let pi_net = net.well_productivity_index(&net.wells[0]).unwrap();
+    let pi_gross = gross.well_productivity_index(&gross.wells[0]).unwrap();
+    assert!((pi_net - 0.5 * pi_gross).abs() / pi_gross < 1e-12);
+}
+
+#[test]
+fn net_to_gross_contrast_uses_half_cell_areas() {
+    let mut sim = ReservoirSimulator::new(2, 1, 1, 0.2);
+    sim.set_cell_dimensions(10.0, 10.0, 5.0).unwrap();
+    sim.set_net_to_gross_field(vec![1.0, 0.25]).unwrap();
+
+    let t_x = sim.geometric_transmissibility(0, 1, 'x');
+    let area = 10.0 * 5.0;
+    let expected = 1.0 / (1.0 / (100.0 * area / 5.0) + 1.0 / (25.0 * area / 5.0));
+    assert!((t_x - expected).abs() / expected < 1e-12);
+
+    sim.set_net_to_gross_field(Vec::new()).unwrap();
+    assert_eq!(sim.net_to_gross, vec![1.0, 1.0]);
+}
+
+#[test]
+fn porosity_and_net_to_gross_validation_rejects_invalid_inputs() {
+    let mut sim = ReservoirSimulator::new(2, 1, 1, 0.2);
+
+    err_contains(
+        sim.set_porosity_field(vec![0.2]),
+        "length equal to nx*ny*nz",
+    );
+    err_contains(
+        sim.set_porosity_field(vec![0.2, 0.0]),
+        "Porosity for cell 1 must be finite and within (0, 1]",
+    );
+    err_contains(
+        sim.set_net_to_gross_field(vec![0.5, 1.5]),
+        "Net-to-gross for cell 1 must be finite and within (0, 1]",
+    );
+    err_contains(
+        sim.set_net_to_gross_field(vec![0.5, 0.5, 0.5]),
+        "length equal to nx*ny*nz",
+    );
+}
+
+#[test]
+fn column_tops_stack_layers_beneath_each_column() {
+    let mut sim = ReservoirSimulator::new(2, 1, 2, 0.2);
+    sim.set_cell_dimensions_per_layer(10.0, 10.0, vec![4.0, 6.0])
+        .unwrap();
+    sim.set_rock_properties(0.0, 500.0, 1.0, 1.0).unwrap();
+    sim.set_cell_tops(vec![1000.0, 1020.0]).unwrap();
+
+    assert!((sim.depth_at(sim.idx(0, 0, 0)) - 1002.0).abs() < 1e-12);
+    assert!((sim.depth_at(sim.idx(0, 0, 1)) - 1007.0).abs() < 1e-12);
+    assert!((sim.depth_at(sim.idx(1, 0, 0)) - 1022.0).abs() < 1e-12);
+    assert!((sim.depth_at(sim.idx(1, 0, 1)) - 1027.0).abs() < 1e-12);
+
+    sim.set_cell_tops(Vec::new()).unwrap();
+    assert!((sim.depth_at(sim.idx(1, 0, 1)) - 507.0).abs() < 1e-12);
+}
+
+#[test]
+fn per_cell_tops_set_each_cell_independently() {
+    let mut sim = ReservoirSimulator::new(2, 1, 2, 0.2);
+    sim.set_cell_dimensions_per_layer(10.0, 10.0, vec![4.0, 6.0])
+        .unwrap();
+    sim.set_cell_tops(vec![1000.0, 1010.0, 1004.0, 1016.0])
+        .unwrap();
+
+    assert!((sim.depth_at(sim.idx(0, 0, 0)) - 1002.0).abs() < 1e-12);
+    assert!((sim.depth_at(sim.idx(1, 0, 0)) - 1012.0).abs() < 1e-12);
+    assert!((sim.depth_at(sim.idx(0, 0, 1)) - 1007.0).abs() < 1e-12);
+    assert!((sim.depth_at(sim.idx(1, 0, 1)) - 1019.0).abs() < 1e-12);
+
+    err_contains(
+        sim.set_cell_tops(vec![1000.0, 1010.0, 1004.0]),
+        "Cell tops must have length nx*ny (2) or nx*ny*nz (4)",
+    );
+    err_contains(
+        sim.set_cell_tops(vec![1000.0, f64::INFINITY]),
+        "Cell top 1 must be finite",
+    );
+}
+
+#[test]
+fn cell_tops_move_the_well_datum_offset_with_structure() {
+    let mut sim = ReservoirSimulator::new(2, 1, 1, 0.2);
+    sim.set_cell_dimensions(10.0, 10.0, 4.0).unwrap();
+    sim.set_gravity_enabled(true);
+    sim.add_well_with_id(0, 0, 0, 200.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    sim.add_well_with_id(1, 0, 0, 200.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    sim.set_well_datum("P1".to_string(), f64::NAN, 1000.0)
+        .unwrap();
+    assert_eq!(sim.wells[1].head_offset_bar, 0.0);
+
+    sim.set_cell_tops(vec![2000.0, 2010.0]).unwrap();
+    assert_eq!(sim.wells[0].head_offset_bar, 0.0);
+    let expected = 1000.0 * 9.80665 * 10.0 * 1e-5;
+    assert!((sim.wells[1].head_offset_bar - expected).abs() < 1e-12);
+}
+
+#[test]
+fn inactive_cells_hold_no_pore_volume_or_connections() {
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+    sim.set_active_cells(vec![1, 0, 1]).unwrap();
+
+    assert_eq!(sim.pore_volume_m3(1), 0.0);
+    assert!(sim.pore_volume_m3(0) > 0.0);
+    assert_eq!(sim.geometric_transmissibility(0, 1, 'x'), 0.0);
+    assert_eq!(sim.geometric_transmissibility(1, 2, 'x'), 0.0);
+    assert_eq!(sim.active_cell_rows(), vec![Some(0), None, Some(1)]);
+
+    let pressures = sim.get_pressures();
+    assert!(pressures[1].is_nan());
+    assert_eq!(pressures[0], 300.0);
+    assert!(sim.get_sat_water()[1].is_nan());
+    assert_eq!(sim.average_reservoir_pressure_pv_weighted(), 300.0);
+
+    sim.set_active_cells(Vec::new()).unwrap();
+    assert!(sim.pore_volume_m3(1) > 0.0);
+    assert!(!sim.get_pressures()[1].is_nan());
+}
+
+#[test]
+fn inactive_cell_validation_rejects_invalid_inputs() {
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+    err_contains(sim.set_active_cells(vec![1, 0]), "length equal to nx*ny*nz");
+    err_contains(
+        sim.set_active_cells(vec![0, 0, 0]),
+        "At least one cell must be active",
+    );
+
+    sim.add_well(1, 0, 0, 200.0, 0.1, 0.0, false).unwrap();
+    err_contains(
+        sim.set_active_cells(vec![1, 0, 1]),
+        "Well completion in inactive cell is not allowed: (1, 0, 0)",
+    );
+
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+    sim.set_active_cells(vec![1, 1, 0]).unwrap();
+    err_contains(
+        sim.add_well(2, 0, 0, 200.0, 0.1, 0.0, true),
+        "Well completion in inactive cell is not allowed: (2, 0, 0)",
+    );
+}
+
+#[test]
+fn inactive_cells_drop_out_of_both_solvers() {
+    for fim in [true, false] {
+        let build = |nx: usize| {
+            let mut sim = ReservoirSimulator::new(nx, 1, 1, 0.2);
+            sim.set_fim_enabled(fim);
+            sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+            if nx == 4 {
+                sim.set_active_cells(vec![1, 1, 1, 0]).unwrap();
+            }
+            sim.add_well(0, 0, 0, 400.0, 0.1, 0.0, true).unwrap();
+            sim.add_well(2, 0, 0, 200.0, 0.1, 0.0, false).unwrap();
+            sim.step(5.0);
+            sim
+        };
+
+        let reference = build(3);
+        let masked = build(4);
+        for id in 0..3 {
+            assert!(
+                (reference.pressure[id] - masked.pressure[id]).abs() < 1e-6,
+                "fim={fim}: cell {id} pressure {} vs {}",
+                reference.pressure[id],
+                masked.pressure[id]
+            );
+            assert!((reference.sat_water[id] - masked.sat_water[id]).abs() < 1e-8);
+        }
+        assert_eq!(masked.pressure[3], 300.0);
+        assert_eq!(masked.sat_water[3], 0.3);
+        assert!(
+            (reference.average_reservoir_pressure_pv_weighted()
+                - masked.average_reservoir_pressure_pv_weighted())
+            .abs()
+                < 1e-6
+        );
+    }
+}
+
+#[test]
+fn transmissibility_multipliers_scale_the_plus_face_of_each_cell() {
+    let mut sim = ReservoirSimulator::new(3, 2, 2, 0.2);
+    let base_x = sim.geometric_transmissibility(0, 1, 'x');
+    let base_y = sim.geometric_transmissibility(0, 3, 'y');
+    let base_z = sim.geometric_transmissibility(0, 6, 'z');
+
+    let mut mult_x = vec![1.0; 12];
+    mult_x[0] = 0.25;
+    let mut mult_z = vec![1.0; 12];
+    mult_z[0] = 0.0;
+    sim.set_transmissibility_multipliers(mult_x, Vec::new(), mult_z)
+        .unwrap();
+
+    assert_eq!(sim.geometric_transmissibility(0, 1, 'x'), base_x * 0.25);
+    assert_eq!(sim.geometric_transmissibility(1, 0, 'x'), base_x * 0.25);
+    assert_eq!(
+        sim.geometric_transmissibility(1, 2, 'x'),
+        sim.geometric_transmissibility(4, 5, 'x')
+    );
+    assert_eq!(sim.geometric_transmissibility(0, 3, 'y'), base_y);
+    assert_eq!(sim.geometric_transmissibility(6, 0, 'z'), 0.0);
+    assert!(base_z > 0.0);
+
+    err_contains(
+        sim.set_transmissibility_multipliers(vec![1.0; 3], Vec::new(), Vec::new()),
+        "MULTX must have length equal to nx*ny*nz (12), got 3",
+    );
+    let mut negative = vec![1.0; 12];
+    negative[5] = -1.0;
+    err_contains(
+        sim.set_transmissibility_multipliers(Vec::new(), negative, Vec::new()),
+        "MULTY for cell 5 must be finite and non-negative",
+    );
+}
+
+#[test]
+fn fault_multipliers_stack_and_can_be_edited() {
+    let mut sim = ReservoirSimulator::new(3, 2, 1, 0.2);
+    let base = sim.geometric_transmissibility(0, 1, 'x');
+
+    let mut mult_x = vec![1.0; 6];
+    mult_x[0] = 0.5;
+    sim.set_transmissibility_multipliers(mult_x, Vec::new(), Vec::new())
+        .unwrap();
+    sim.set_fault("F1".to_string(), vec![0, 3], "Xx".to_string(), 0.1)
+        .unwrap();
+    assert!((sim.geometric_transmissibility(0, 1, 'x') - base * 0.05).abs() < 1e-12 * base);
+    assert!((sim.geometric_transmissibility(3, 4, 'x') - base * 0.1).abs() < 1e-12 * base);
+
+    sim.set_fault_multiplier("F1".to_string(), 0.0).unwrap();
+    assert_eq!(sim.geometric_transmissibility(0, 1, 'x'), 0.0);
+    assert_eq!(sim.geometric_transmissibility(4, 3, 'x'), 0.0);
+
+    sim.set_fault("F1".to_string(), vec![1], "Y".to_string(), 0.0)
+        .unwrap();
+    assert_eq!(sim.geometric_transmissibility(0, 1, 'x'), base * 0.5);
+    assert_eq!(sim.geometric_transmissibility(1, 4, 'y'), 0.0);
+
+    err_contains(
+        sim.set_fault("F2".to_string(), vec![2], "X".to_string(), 0.0),
+        "Fault 'F2' cell 2 has no neighbour across its +X face",
+    );
+    err_contains(
+        sim.set_fault("F2".to_string(), vec![0, 1], "X".to_string(), 0.0),
+        "needs one face per cell",
+    );
+    err_contains(
+        sim.set_fault("F2".to_string(), vec![0], "Q".to_string(), 0.0),
+        "face must be one of X, Y or Z",
+    );
+    err_contains(
+        sim.set_fault("F2".to_string(), vec![9], "X".to_string(), 0.0),
+        "cell 9 is out of bounds",
+    );
+    err_contains(
+        sim.set_fault_multiplier("missing".to_string(), 0.0),
+        "No fault found with name 'missing'",
+    );
+}
+
+#[test]
+fn sealing_fault_isolates_a_compartment_in_both_solvers() {
+    for fim in [true, false] {
+        let mut sim = ReservoirSimulator::new(4, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+        sim.add_well(0, 0, 0, 150.0, 0.1, 0.0, false).unwrap();
+        sim.set_fault("BARRIER".to_string(), vec![1], "X".to_string(), 0.0)
+            .unwrap();
+        sim.step(5.0);
+
+        assert!(
+            sim.pressure[0] < 300.0 && sim.pressure[1] < 300.0,
+            "fim={fim}"
+        );
+        assert!((sim.pressure[2] - 300.0).abs() < 1e-6, "fim={fim}");
+        assert!((sim.pressure[3] - 300.0).abs() < 1e-6, "fim={fim}");
+
+        // Opening the fault between report steps lets the depletion through.
+        sim.set_fault_multiplier("BARRIER".to_string(), 1.0)
+            .unwrap();
+        sim.step(5.0);
+        assert!(sim.pressure[3] < 300.0, "fim={fim}");
+    }
+}
+
+#[test]
+fn non_neighbor_connection_validation_rejects_invalid_inputs() {
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+
+    err_contains(
+        sim.set_non_neighbor_connections(vec![0], vec![2, 1], vec![1.0]),
+        "equal lengths",
+    );
+    err_contains(
+        sim.set_non_neighbor_connections(vec![0], vec![3], vec![1.0]),
+        "out of bounds",
+    );
+    err_contains(
+        sim.set_non_neighbor_connections(vec![1], vec![1], vec![1.0]),
+        "two different cells",
+    );
+    err_contains(
+        sim.set_non_neighbor_connections(vec![0], vec![2], vec![f64::NAN]),
+        "finite and non-negative",
+    );
+
+    sim.set_non_neighbor_connections(vec![0], vec![2], vec![1.0])
+        .unwrap();
+    assert_eq!(
+        sim.cell_flow_connections(0),
+        vec![(1, sim.geometric_transmissibility(0, 1, 'x')), (2, 1.0)]
+    );
+    assert_eq!(
+        sim.cell_flow_connections(2),
+        vec![(1, sim.geometric_transmissibility(2, 1, 'x')), (0, 1.0)]
+    );
+    assert_eq!(sim.cell_flow_connections(1).len(), 2);
+    sim.set_non_neighbor_connections(Vec::new(), Vec::new(), Vec::new())
+        .unwrap();
+    assert_eq!(sim.cell_flow_connections(0).len(), 1);
+}
+
+#[test]
+fn non_neighbor_connection_replacing_a_face_matches_the_cartesian_grid() {
+    for fim in [true, false] {
+        let build = |replace_face: bool| {
+            let mut sim = ReservoirSimulator::new(4, 1, 1, 0.2);
+            sim.set_fim_enabled(fim);
+            sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+            sim.add_well(0, 0, 0, 150.0, 0.1, 0.0, false).unwrap();
+            if replace_face {
+                let face_t = sim.geometric_transmissibility(1, 2, 'x');
+                let mut mult_x = vec![1.0; 4];
+                mult_x[1] = 0.0;
+                sim.set_transmissibility_multipliers(mult_x, Vec::new(), Vec::new())
+                    .unwrap();
+                sim.set_non_neighbor_connections(vec![1], vec![2], vec![face_t])
+                    .unwrap();
+            }
+            sim
+        };
+        let mut cartesian = build(false);
+        let mut connected = build(true);
+        for _ in 0..3 {
+            cartesian.step(2.0);
+            connected.step(2.0);
+        }
+
+        for id in 0..4 {
+            assert!(
+                (cartesian.pressure[id] - connected.pressure[id]).abs() < 1e-6,
+                "fim={fim} cell={id}: {} vs {}",
+                cartesian.pressure[id],
+                connected.pressure[id]
+            );
+        }
+    }
+}
+
+#[test]
+fn non_neighbor_connection_carries_flow_between_distant_cells() {
+    for fim in [true, false] {
+        let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+        sim.add_well(0, 0, 0, 150.0, 0.1, 0.0, false).unwrap();
+        // Seal every Cartesian face, then join the two end cells directly.
+        let face_t = sim.geometric_transmissibility(0, 1, 'x');
+        sim.set_transmissibility_multipliers(vec![0.0; 3], Vec::new(), Vec::new())
+            .unwrap();
+        sim.set_non_neighbor_connections(vec![0], vec![2], vec![face_t])
+            .unwrap();
+        sim.step(5.0);
+
+        assert!(sim.pressure[0] < 300.0, "fim={fim}");
+        assert!(sim.pressure[2] < 300.0, "fim={fim}");
+        assert!((sim.pressure[1] - 300.0).abs() < 1e-6, "fim={fim}");
+    }
+}
+
+#[test]
+fn radial_grid_geometry_matches_cylindrical_formulas() {
+    let mut sim = ReservoirSimulator::new(3, 1, 2, 0.2);
+    sim.set_radial_grid(0.1, 100.0, 360.0, vec![4.0, 6.0])
+        .unwrap();
+    sim.add_well(0, 0, 0, 150.0, 0.1, 0.0, false).unwrap();
+
+    let two_pi = 2.0 * std::f64::consts::PI;
+    let total_pv: f64 = (0..6).map(|id| sim.pore_volume_m3(id)).sum();
+    let expected_pv = std::f64::consts::PI * (100.0_f64.powi(2) - 0.1_f64.powi(2)) * 10.0 * 0.2;
+    assert!((total_pv - expected_pv).abs() < 1e-9 * expected_pv);
+
+    // Rings are logarithmically spaced: each spans a factor of 10 in radius.
+    let dx: f64 = sim.dx.iter().sum();
+    assert!((dx - 99.9).abs() < 1e-9);
+    assert!((sim.dx[1] / sim.dx[0] - 10.0).abs() < 1e-9);
+
+    // A homogeneous ring pair carries exactly the steady radial conductance between centres.
+    let k = sim.perm_x[0];
+    let expected_t = two_pi * k * 4.0 / 10.0_f64.ln();
+    let t = sim.geometric_transmissibility(1, 2, 'x');
+    assert!((t - expected_t).abs() < 1e-9 * expected_t);
+    let annulus = 0.5 * two_pi * (10.0_f64.powi(2) - 1.0);
+    let expected_tz = sim.perm_z[1] * annulus / 5.0;
+    assert!((sim.geometric_transmissibility(1, 4, 'z') - expected_tz).abs() < 1e-9 * expected_tz);
+
+    // The completion index uses the innermost ring centre in place of Peaceman's radius.
+    let r0 = (0.1_f64 * 1.0).sqrt();
+    let expected_wi = 8.526_988_8e-3 * two_pi * k * 4.0 / (r0 / 0.1).ln();
+    let wi = sim.wells[0].productivity_index / sim.total_mobility(0);
+    assert!((wi - expected_wi).abs() < 1e-9 * expected_wi);
+}
+
+#[test]
+fn radial_grid_validation_rejects_invalid_inputs() {
+    let mut sim = ReservoirSimulator::new(4, 2, 1, 0.2);
+    err_contains(sim.set_radial_grid(0.1, 100.0, 360.0, vec![5.0]), "ny = 1");
+
+    let mut sim = ReservoirSimulator::new(4, 1, 1, 0.2);
+    err_contains(
+        sim.set_radial_grid(0.0, 100.0, 360.0, vec![5.0]),
+        "0 < inner < outer",
+    );
+    err_contains(
+        sim.set_radial_grid(10.0, 1.0, 360.0, vec![5.0]),
+        "0 < inner < outer",
+    );
+    err_contains(
+        sim.set_radial_grid(0.1, 100.0, 400.0, vec![5.0]),
+        "(0, 360]",
+    );
+    err_contains(
+        sim.set_radial_grid(0.1, 100.0, 360.0, vec![]),
+        "dz_per_layer",
+    );
+
+    sim.add_well(2, 0, 0, 150.0, 0.1, 0.0, false).unwrap();
+    err_contains(
+        sim.set_radial_grid(0.1, 100.0, 360.0, vec![5.0]),
+        "innermost ring",
+    );
+
+    // The wellbore has to sit inside the innermost ring centre, here √(0.1·1) ≈ 0.316 m.
+    let mut sim = ReservoirSimulator::new(4, 1, 1, 0.2);
+    sim.add_well(0, 0, 0, 150.0, 0.5, 0.0, false).unwrap();
+    err_contains(
+        sim.set_radial_grid(0.1, 1000.0, 360.0, vec![5.0]),
+        "innermost ring centre",
+    );
+    assert!(sim.radial_grid.is_none());
+
+    let mut sim = ReservoirSimulator::new(4, 1, 1, 0.2);
+    sim.set_radial_grid(0.1, 1000.0, 90.0, vec![5.0]).unwrap();
+    assert!((sim.dy[0] - 0.5 * std::f64::consts::PI * 1000.0).abs() < 1e-9);
+    err_contains(
+        sim.add_well(0, 0, 0, 150.0, 0.5, 0.0, false),
+        "innermost ring centre",
+    );
+    assert!(sim.wells.is_empty());
+    err_contains(
+        sim.add_well(1, 0, 0, 150.0, 0.1, 0.0, false),
+        "innermost ring",
+    );
+
+    // Any Cartesian setter returns to Cartesian geometry.
+    sim.set_cell_dimensions(10.0, 10.0, 5.0).unwrap();
+    assert!((sim.pore_volume_m3(0) - 10.0 * 10.0 * 5.0 * 0.2).abs() < 1e-12);
+}
+
+#[test]
+fn radial_sector_model_matches_the_full_cylinder_in_both_solvers() {
+    for fim in [true, false] {
+        let build = |angle: f64| {
+            let mut sim = ReservoirSimulator::new(6, 1, 1, 0.2);
+            sim.set_fim_enabled(fim);
+            sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+            sim.set_radial_grid(0.1, 300.0, angle, vec![10.0]).unwrap();
+            sim.add_well(0, 0, 0, 150.0, 0.1, 0.0, false).unwrap();
+            sim
+        };
+        let mut full = build(360.0);
+        let mut sector = build(90.0);
+        for _ in 0..3 {
+            full.step(1.0);
+            sector.step(1.0);
+        }
+
+        // Equal up to the Newton/linear tolerances, which see rates a quarter the size.
+        for id in 0..6 {
+            assert!(
+                (full.pressure[id] - sector.pressure[id]).abs() < 1e-4,
+                "fim={fim} ring={id}: {} vs {}",
+                full.pressure[id],
+                sector.pressure[id]
+            );
+        }
+        // Drawdown decays outwards from the axis well.
+        for id in 1..6 {
+            assert!(
+                full.pressure[id] >= full.pressure[id - 1],
+                "fim={fim} ring={id}"
+            );
+        }
+        assert!(full.pressure[0] < 300.0, "fim={fim}");
+    }
+}
+
+#[test]
+fn local_grid_validation_rejects_invalid_inputs() {
+    let mut sim = ReservoirSimulator::new(6, 6, 1, 0.2);
+    let refine = |sim: &mut ReservoirSimulator, name: &str, parent: &str, lo: usize, hi: usize| {
+        sim.add_local_grid_refinement(
+            name.to_string(),
+            parent.to_string(),
+            lo,
+            hi,
+            lo,
+            hi,
+            0,
+            0,
+            3,
+            3,
+            1,
+        )
+    };
+    err_contains(refine(&mut sim, " ", "", 1, 2), "must not be empty");
+    err_contains(refine(&mut sim, "L1", "", 4, 6), "outside its parent grid");
+    err_contains(
+        refine(&mut sim, "L1", "missing", 1, 2),
+        "No local grid found",
+    );
+    err_contains(
+        sim.add_local_grid_refinement("L1".into(), String::new(), 1, 2, 1, 2, 0, 0, 0, 3, 1),
+        "at least 1",
+    );
+
+    sim.add_well(1, 1, 0, 150.0, 0.1, 0.0, false).unwrap();
+    err_contains(refine(&mut sim, "L1", "", 1, 2), "well completion");
+    let mut sim = ReservoirSimulator::new(6, 6, 1, 0.2);
+    refine(&mut sim, "L1", "", 1, 2).unwrap();
+    err_contains(refine(&mut sim, "L1", "", 4, 4), "already exists");
+    err_contains(
+        sim.add_local_grid_refinement("L2".into(), String::new(), 3, 4, 1, 2, 0, 0, 2, 2, 1),
+        "shares a face",
+    );
+    err_contains(refine(&mut sim, "L2", "", 2, 4), "already refined");
+    err_contains(
+        sim.add_well(1, 1, 0, 150.0, 0.1, 0.0, false),
+        "refined by a local grid",
+    );
+    err_contains(
+        sim.add_local_grid_well("L1".into(), 6, 0, 0, 150.0, 0.1, 0.0, false, String::new()),
+        "out of bounds",
+    );
+
+    let mut sim = ReservoirSimulator::new(4, 1, 1, 0.2);
+    sim.set_radial_grid(0.1, 100.0, 360.0, vec![5.0]).unwrap();
+    err_contains(refine_radial(&mut sim), "radial grid");
+
+    fn refine_radial(sim: &mut ReservoirSimulator) -> Result<(), String> {
+        sim.add_local_grid_refinement("L1".into(), String::new(), 1, 2, 0, 0, 0, 0, 2, 1, 1)
+    }
+}
+
+#[test]
+fn local_grid_children_partition_their_parent() {
+    let mut sim = ReservoirSimulator::new(4, 3, 2, 0.2);
+    sim.set_cell_dimensions_per_layer(10.0, 10.0, vec![4.0, 6.0])
+        .unwrap();
+    let parent = sim.idx(1, 1, 1);
+    let parent_pv = sim.pore_volume_m3(parent);
+    let parent_depth = sim.depth_at(parent);
+    sim.add_local_grid_refinement("L1".into(), String::new(), 1, 2, 1, 1, 1, 1, 3, 3, 2)
+        .unwrap();
+
+    assert_eq!(sim.cell_count(), 24 + 2 * 18);
+    assert!(!sim.is_active(parent));
+    assert_eq!(sim.pore_volume_m3(parent), 0.0);
+    let children: Vec<usize> = (0..3)
+        .flat_map(|i| (0..3).flat_map(move |j| (0..2).map(move |k| (i, j, k))))
+        .map(|(i, j, k)| sim.local_grid_cell("L1", i, j, k).unwrap())
+        .collect();
+    let child_pv: f64 = children.iter().map(|&id| sim.pore_volume_m3(id)).sum();
+    assert!((child_pv - parent_pv).abs() < 1e-9 * parent_pv);
+    let mean_depth: f64 =
+        children.iter().map(|&id| sim.depth_at(id)).sum::<f64>() / children.len() as f64;
+    assert!((mean_depth - parent_depth).abs() < 1e-9);
+
+    // The interface to the coarse neighbour carries the same total conductance as the
+    // unrefined face would for a homogeneous medium with matching half-cell lengths.
+    let coarse = sim.idx(0, 1, 1);
+    let interface_t: f64 = sim
+        .cell_flow_connections(coarse)
+        .iter()
+        .filter(|&&(neighbor, _)| sim.local_cell(neighbor).is_some())
+        .map(|&(_, transmissibility)| transmissibility)
+        .sum();
+    let k = sim.perm_x[coarse];
+    let area = 10.0 * 6.0;
+    let expected = k * area / (5.0 + 10.0 / 6.0);
+    assert!((interface_t - expected).abs() < 1e-9 * expected);
+}
+
+#[test]
+fn unit_local_grid_matches_the_unrefined_grid_in_both_solvers() {
+    for fim in [true, false] {
+        let build = |refine: bool| {
+            let mut sim = ReservoirSimulator::new(5, 2, 1, 0.2);
+            sim.set_fim_enabled(fim);
+            sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+            sim.add_well(0, 0, 0, 150.0, 0.1, 0.0, false).unwrap();
+            if refine {
+                sim.add_local_grid_refinement(
+                    "L1".into(),
+                    String::new(),
+                    2,
+                    3,
+                    0,
+                    1,
+                    0,
+                    0,
+                    1,
+                    1,
+                    1,
+                )
+                .unwrap();
+            }
+            sim
+        };
+        let mut coarse = build(false);
+        let mut refined = build(true);
+        for _ in 0..3 {
+            coarse.step(2.0);
+            refined.step(2.0);
+        }
+
+        let tolerance = if fim { 1e-4 } else { 1e-6 };
+        let reported = refined.get_pressures();
+        assert_eq!(refined.pressure.len(), 14);
+        assert_eq!(reported.len(), 10);
+        for (id, (&expected, &actual)) in coarse.pressure.iter().zip(&reported).enumerate() {
+            assert!(
+                (expected - actual).abs() < tolerance,
+                "fim={fim} cell={id}: {expected} vs {actual}"
+            );
+        }
+    }
+}
+
+#[test]
+fn nested_local_grid_well_drains_through_the_interfaces() {
+    for fim in [true, false] {
+        let mut sim = ReservoirSimulator::new(5, 5, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+        sim.add_local_grid_refinement("L1".into(), String::new(), 1, 3, 1, 3, 0, 0, 3, 3, 1)
+            .unwrap();
+        sim.add_local_grid_refinement("L2".into(), "L1".into(), 4, 4, 4, 4, 0, 0, 3, 3, 1)
+            .unwrap();
+        sim.add_local_grid_well("L2".into(), 1, 1, 0, 150.0, 0.1, 0.0, false, "P1".into())
+            .unwrap();
+        assert_eq!(sim.wells[0].i, 2);
+        assert_eq!(sim.wells[0].j, 2);
+        let well_cell = sim.local_grid_cell("L2", 1, 1, 0).unwrap();
+        let well_dx = sim.dx_at(well_cell);
+        assert!((well_dx - 10.0 / 9.0).abs() < 1e-12);
+        sim.step(0.01);
+
+        let well_pressure = sim.pressure[well_cell];
+        assert!(well_pressure < 300.0, "fim={fim}");
+        // Drawdown decays away from the well: fine well cell, refined root cell, coarse corner.
+        let reported = sim.get_pressures();
+        assert_eq!(reported.len(), 25, "fim={fim}");
+        let centre = reported[sim.idx(2, 2, 0)];
+        let corner = reported[sim.idx(0, 0, 0)];
+        assert!(well_pressure < centre, "fim={fim}");
+        assert!(
+            centre < corner,
+            "fim={fim} {well_pressure} {centre} {corner} {reported:?}"
+        );
+        assert!(corner < 300.0, "fim={fim}");
+
+        // A refined root cell reports the pore-volume-weighted mean of its children.
+        let children: Vec<usize> = (0..3)
+            .flat_map(|i| (0..3).map(move |j| (i, j)))
+            .map(|(i, j)| sim.local_grid_cell("L1", i, j, 0).unwrap())
+            .collect();
+        let pv: f64 = children.iter().map(|&id| sim.pore_volume_m3(id)).sum();
+        let mean = children
+            .iter()
+            .map(|&id| sim.pore_volume_m3(id) * sim.pressure[id])
+            .sum::<f64>()
+            / pv;
+        assert!(
+            (reported[sim.idx(1, 1, 0)] - mean).abs() < 1e-9,
+            "fim={fim}"
+        );
+    }
+}
+
+#[test]
+fn sweep_metrics_count_local_grid_children_in_their_root_column() {
+    let sweep_after_step = |geometry: &str| {
+        let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+        sim.set_initial_saturation(0.1);
+        sim.add_local_grid_refinement("L1".into(), String::new(), 1, 1, 0, 0, 0, 0, 2, 1, 1)
+            .unwrap();
+        for i in 0..2 {
+            let child = sim.local_grid_cell("L1", i, 0, 0).unwrap();
+            sim.sat_water[child] = 0.9;
+            sim.sat_oil[child] = 0.1;
+        }
+        sim.sweep_config = Some(SweepConfig {
+            geometry: geometry.to_string(),
+            swept_threshold: 0.5,
+            initial_oil_saturation: 0.9,
+            residual_oil_saturation: 0.1,
+        });
+        sim.step(1e-3);
+        sim.rate_history.last().unwrap().sweep.clone().unwrap()
+    };
+
+    // The refined middle column holds a third of the pore volume, all of it swept.
+    let areal = sweep_after_step("areal");
+    assert!(
+        (areal.e_vol - 1.0 / 3.0).abs() < 1e-9,
+        "E_vol {}",
+        areal.e_vol
+    );
+    let e_a = areal.e_a.unwrap();
+    assert!((e_a - 1.0 / 3.0).abs() < 1e-9, "E_A {e_a}");
+    let recovered = sweep_after_step("both").mobile_oil_recovered.unwrap();
+    assert!(
+        (recovered - 1.0 / 3.0).abs() < 1e-3,
+        "the children's oil is a third of the mobile oil, got {recovered}"
+    );
+}
+
+#[test]
+fn dual_porosity_validation_rejects_invalid_inputs() {
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+    let n = 3;
+    err_contains(
+        sim.set_dual_porosity(
+            vec![0.1; n],
+            vec![1.0; n],
+            vec![1.0; n],
+            vec![1.0; n],
+            0.0,
+            false,
+        ),
+        "Shape factor",
+    );
+    err_contains(
+        sim.set_dual_porosity(
+            vec![0.1; 2],
+            vec![1.0; n],
+            vec![1.0; n],
+            vec![1.0; n],
+            1.0,
+            false,
+        ),
+        "nx*ny*nz",
+    );
+    err_contains(
+        sim.set_dual_porosity(
+            vec![1.5; n],
+            vec![1.0; n],
+            vec![1.0; n],
+            vec![1.0; n],
+            1.0,
+            false,
+        ),
+        "Matrix porosity for cell 0",
+    );
+    err_contains(
+        sim.set_dual_porosity(
+            vec![0.1; n],
+            vec![1.0; n],
+            vec![0.0; n],
+            vec![1.0; n],
+            1.0,
+            false,
+        ),
+        "Matrix permeability for cell 0",
+    );
+    err_contains(sim.get_matrix_state().map(|_| ()), "no matrix continuum");
+
+    sim.add_local_grid_refinement("L1".into(), String::new(), 1, 1, 0, 0, 0, 0, 2, 1, 1)
+        .unwrap();
+    err_contains(
+        sim.set_dual_porosity(
+            vec![0.1; n],
+            vec![1.0; n],
+            vec![1.0; n],
+            vec![1.0; n],
+            1.0,
+            false,
+        ),
+        "local grid",
+    );
+
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+    sim.set_dual_porosity(
+        vec![0.1; n],
+        vec![1.0; n],
+        vec![1.0; n],
+        vec![1.0; n],
+        1.0,
+        false,
+    )
+    .unwrap();
+    err_contains(
+        sim.add_local_grid_refinement("L1".into(), String::new(), 1, 1, 0, 0, 0, 0, 2, 1, 1),
+        "dual-porosity",
+    );
+}
+
+#[test]
+fn dual_porosity_appends_a_matrix_cell_per_cell() {
+    let mut sim = ReservoirSimulator::new(3, 2, 1, 0.2);
+    sim.set_cell_dimensions(10.0, 20.0, 5.0).unwrap();
+    let n = 6;
+    sim.set_dual_porosity(
+        vec![0.25; n],
+        vec![0.5; n],
+        vec![0.5; n],
+        vec![0.1; n],
+        0.04,
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(sim.cell_count(), 2 * n);
+    assert_eq!(sim.pressure.len(), 2 * n);
+    assert_eq!(sim.get_pressures().len(), n);
+    let matrix = n + 4;
+    assert_eq!(sim.depth_at(matrix), sim.depth_at(4));
+    let pv = 10.0 * 20.0 * 5.0 * 0.25;
+    assert!((sim.pore_volume_m3(matrix) - pv).abs() < 1e-9 * pv);
+
+    // Dual porosity: the matrix cell sees only its fracture cell, through σ·k·V.
+    let expected_t = 0.04 * 0.5 * 1000.0;
+    assert_eq!(sim.cell_flow_connections(matrix), vec![(4, expected_t)]);
+    assert!(sim.cell_flow_connections(4).contains(&(matrix, expected_t)));
+
+    // Dual permeability adds the Cartesian matrix faces.
+    sim.set_dual_porosity(
+        vec![0.25; n],
+        vec![0.5; n],
+        vec![0.5; n],
+        vec![0.1; n],
+        0.04,
+        true,
+    )
+    .unwrap();
+    let neighbors: Vec<usize> = sim
+        .cell_flow_connections(matrix)
+        .iter()
+        .map(|&(neighbor, _)| neighbor)
+        .collect();
+    assert_eq!(neighbors, vec![4, n + 3, n + 5, n + 1]);
+
+    // An empty matrix porosity returns to a single continuum.
+    sim.set_dual_porosity(Vec::new(), Vec::new(), Vec::new(), Vec::new(), 0.0, false)
+        .unwrap();
+    assert_eq!(sim.cell_count(), n);
+    assert_eq!(sim.pressure.len(), n);
+    assert_eq!(sim.porosity.len(), n);
+}
+
+#[test]
+fn sweep_metrics_count_matrix_pore_volume() {
+    let n = 2;
+    let mut sim = ReservoirSimulator::new(n, 1, 1, 0.05);
+    sim.set_initial_saturation(0.9);
+    sim.set_dual_porosity(
+        vec![0.2; n],
+        vec![0.01; n],
+        vec![0.01; n],
+        vec![0.01; n],
+        0.04,
+        false,
+    )
+    .unwrap();
+    for matrix in n..2 * n {
+        sim.sat_water[matrix] = 0.1;
+        sim.sat_oil[matrix] = 0.9;
+    }
+    sim.sweep_config = Some(SweepConfig {
+        geometry: "both".to_string(),
+        swept_threshold: 0.5,
+        initial_oil_saturation: 0.9,
+        residual_oil_saturation: 0.1,
+    });
+    sim.step(1e-3);
+
+    // The flooded fractures hold a fifth of the pore volume; the matrix still holds its oil.
+    let sweep = sim.rate_history.last().unwrap().sweep.clone().unwrap();
+    assert!((sweep.e_vol - 0.2).abs() < 1e-9, "E_vol {}", sweep.e_vol);
+    let recovered = sweep.mobile_oil_recovered.unwrap();
+    assert!(
+        (recovered - 0.2).abs() < 1e-3,
+        "only the fracture oil is recovered, got {recovered}"
+    );
+}
+
+#[test]
+fn dual_permeability_with_fast_transfer_matches_a_single_continuum() {
+    for fim in [true, false] {
+        let build = |dual: bool| {
+            let mut sim = ReservoirSimulator::new(4, 1, 1, 0.2);
+            sim.set_fim_enabled(fim);
+            sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+            if dual {
+                // Two identical half-continua in near-instant equilibrium hold the same pore
+                // volume and conductance as the single one.
+                let half_perm: Vec<f64> = sim.perm_x.iter().map(|k| 0.5 * k).collect();
+                let half_perm_z: Vec<f64> = sim.perm_z.iter().map(|k| 0.5 * k).collect();
+                sim.set_porosity_field(vec![0.1; 4]).unwrap();
+                sim.set_permeability_field(
+                    half_perm.clone(),
+                    half_perm.clone(),
+                    half_perm_z.clone(),
+                )
+                .unwrap();
+                sim.set_dual_porosity(
+                    vec![0.1; 4],
+                    half_perm.clone(),
+                    half_perm,
+                    half_perm_z,
+                    100.0,
+                    true,
+                )
+                .unwrap();
+            }
+            // No wells: a completion would see only the fracture half of the conductance.
+            sim.pressure[0] = 200.0;
+            sim.inherit_appended_cell_state();
+            sim
+        };
+        let mut single = build(false);
+        let mut dual = build(true);
+        for _ in 0..3 {
+            single.step(0.005);
+            dual.step(0.005);
+        }
+
+        assert!(single.pressure[0] > 200.0 && single.pressure[3] < 300.0);
+        for id in 0..4 {
+            for continuum in [id, 4 + id] {
+                assert!(
+                    (single.pressure[id] - dual.pressure[continuum]).abs() < 1e-2,
+                    "fim={fim} cell={id}: {} vs {}",
+                    single.pressure[id],
+                    dual.pressure[continuum]
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn dual_porosity_matrix_drains_through_its_fracture() {
+    for fim in [true, false] {
+        let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+        sim.set_porosity_field(vec![0.01; 3]).unwrap();
+        sim.set_dual_porosity(
+            vec![0.2; 3],
+            vec![0.1; 3],
+            vec![0.1; 3],
+            vec![0.1; 3],
+            0.01,
+            false,
+        )
+        .unwrap();
+        sim.add_well(0, 0, 0, 150.0, 0.1, 0.0, false).unwrap();
+        sim.step(1.0);
+
+        // The small fracture volume drains first; the tight matrix lags behind it.
+        for id in 0..3 {
+            assert!(sim.pressure[id] < 300.0, "fim={fim} cell={id}");
+            assert!(sim.pressure[3 + id] < 300.0, "fim={fim} cell={id}");
+            assert!(
+                sim.pressure[3 + id] > sim.pressure[id],
+                "fim={fim} cell={id}"
+            );
+        }
+        assert_eq!(sim.get_pressures(), sim.pressure[..3].to_vec());
+    }
+}
+
+#[test]
+fn boundary_condition_validation_rejects_invalid_inputs() {
+    let mut sim = ReservoirSimulator::new(3, 1, 2, 0.2);
+
+    err_contains(
+        sim.add_pressure_boundary("w-".to_string(), vec![], 250.0, 0.3, 0.0),
+        "Boundary face must be one of x-, x+, y-, y+, z- or z+, got 'w-'",
+    );
+    err_contains(
+        sim.add_pressure_boundary("z+".to_string(), vec![1], 250.0, 0.3, 0.0),
+        "A z+ boundary covers a whole layer and takes no layer list",
+    );
+    err_contains(
+        sim.add_pressure_boundary("x-".to_string(), vec![2], 250.0, 0.3, 0.0),
+        "Boundary layer 2 is out of bounds (nz = 2)",
+    );
+    err_contains(
+        sim.add_pressure_boundary("x-".to_string(), vec![], 0.0, 0.3, 0.0),
+        "Boundary pressure must be positive and finite",
+    );
+    err_contains(
+        sim.add_pressure_boundary("x-".to_string(), vec![], 250.0, 0.8, 0.3),
+        "Boundary saturations must be non-negative with sat_water + sat_gas <= 1",
+    );
+    err_contains(
+        sim.add_flux_boundary("x+".to_string(), vec![], f64::NAN, 0.3, 0.0),
+        "Boundary rate must be finite",
+    );
+    assert!(sim.boundary_conditions.is_empty());
+
+    sim.set_radial_grid(0.1, 100.0, 360.0, vec![1.0, 1.0])
+        .unwrap();
+    err_contains(
+        sim.add_flux_boundary("y-".to_string(), vec![], 1.0, 0.3, 0.0),
+        "A radial grid has no y boundary faces",
+    );
+}
+
+#[test]
+fn boundary_faces_use_the_half_cell_transmissibility() {
+    let mut sim = ReservoirSimulator::new(3, 2, 2, 0.2);
+    sim.set_cell_dimensions_per_layer(20.0, 10.0, vec![2.0, 6.0])
+        .unwrap();
+    sim.add_flux_boundary("x-".to_string(), vec![1], 8.0, 0.3, 0.0)
+        .unwrap();
+    sim.add_pressure_boundary("z+".to_string(), vec![], 250.0, 0.3, 0.0)
+        .unwrap();
+
+    let connections = sim.boundary_connections();
+    assert_eq!(connections.len(), 2 + 6);
+    let x_cells: Vec<usize> = connections[..2].iter().map(|c| c.cell).collect();
+    assert_eq!(x_cells, vec![sim.idx(0, 0, 1), sim.idx(0, 1, 1)]);
+    let half_cell_x = 2.0 * 100.0 * 10.0 * 6.0 / 20.0;
+    for connection in &connections[..2] {
+        assert!((connection.transmissibility - half_cell_x).abs() < 1e-9);
+        assert_eq!(
+            connection.drive,
+            crate::boundary::BoundaryDrive::Rate { rate_m3_day: 4.0 }
+        );
+    }
+    let half_cell_z = 2.0 * sim.perm_z[0] * 20.0 * 10.0 / 6.0;
+    for connection in &connections[2..] {
+        assert!(connection.cell >= sim.idx(0, 0, 1));
+        assert!((connection.transmissibility - half_cell_z).abs() < 1e-9);
+    }
+
+    sim.clear_boundary_conditions();
+    assert!(sim.boundary_connections().is_empty());
+}
+
+#[test]
+fn pressure_boundaries_reach_a_linear_steady_state_in_both_solvers() {
+    for fim in [true, false] {
+        // A core flood: fluid of the initial composition enters at 350 bar and leaves at 250.
+        let mut sim = ReservoirSimulator::new(5, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+        sim.add_pressure_boundary("x-".to_string(), vec![], 350.0, 0.3, 0.0)
+            .unwrap();
+        sim.add_pressure_boundary("x+".to_string(), vec![], 250.0, 0.3, 0.0)
+            .unwrap();
+        for _ in 0..20 {
+            sim.step(1.0);
+        }
+
+        // The half-cell faces carry half the resistance of a full cell, so cell centres sit
+        // on the straight line between the boundary pressures.
+        for (i, pressure) in sim.get_pressures().into_iter().enumerate() {
+            let expected = 350.0 - 100.0 * (i as f64 + 0.5) / 5.0;
+            assert!(
+                (pressure - expected).abs() < 1.0,
+                "fim={fim} cell={i}: {pressure} vs {expected}"
+            );
+        }
+        let report = sim.rate_history.last().unwrap();
+        assert!(report.material_balance_error_m3.abs() < 1e-3, "fim={fim}");
+        // IMPES carries oil as the volume remainder, so only FIM balances oil exactly.
+        if fim {
+            assert!(report.material_balance_error_oil_m3.abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn flux_boundary_injects_its_prescribed_rate_in_both_solvers() {
+    for fim in [true, false] {
+        // Incompressible water and rock, so injected water volume is also its surface volume.
+        let mut sim = ReservoirSimulator::new(3, 1, 2, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_rock_properties(0.0, 0.0, 1.0, 1.0).unwrap();
+        sim.set_fluid_compressibilities(1e-4, 0.0).unwrap();
+        sim.add_flux_boundary("x-".to_string(), vec![1], 0.01, 1.0, 0.0)
+            .unwrap();
+        let water_in_place = |sim: &ReservoirSimulator| -> f64 {
+            (0..6)
+                .map(|id| sim.sat_water[id] * sim.pore_volume_m3(id))
+                .sum()
+        };
+        let water_before = water_in_place(&sim);
+        for _ in 0..5 {
+            sim.step(1.0);
+        }
+
+        // Five days at 0.01 m³/day of water into the lower layer of a closed box.
+        let added = water_in_place(&sim) - water_before;
+        assert!((added - 0.05).abs() < 1e-6, "fim={fim}: added {added}");
+        assert!(sim.sat_water[sim.idx(0, 0, 1)] > sim.sat_water[sim.idx(2, 0, 1)]);
+        assert!(sim.pressure.iter().all(|&p| p > 300.0), "fim={fim}");
+        let report = sim.rate_history.last().unwrap();
+        assert!(report.material_balance_error_m3.abs() < 1e-6, "fim={fim}");
+    }
+}
+
+#[test]
+fn directional_completion_validation_rejects_invalid_inputs() {
+    let mut sim = ReservoirSimulator::new(3, 1, 2, 0.2);
+    err_contains(
+        sim.add_directional_well(0, 0, 0, "w".into(), 100.0, 0.1, 0.0, false, "P".into()),
+        "'x', 'y' or 'z'",
+    );
+    err_contains(
+        sim.add_directional_well(3, 0, 0, "x".into(), 100.0, 0.1, 0.0, false, "P".into()),
+        "out of bounds",
+    );
+    err_contains(
+        sim.add_deviated_well(vec![0.0, 0.5, 0.0], 100.0, 0.1, 0.0, false, "P".into()),
+        "at least two",
+    );
+    err_contains(
+        sim.add_deviated_well(
+            vec![0.0, 0.5, 0.5, 3.0, 0.5, 0.5],
+            100.0,
+            0.1,
+            0.0,
+            false,
+            " ".into(),
+        ),
+        "physical well id",
+    );
+    err_contains(
+        sim.add_deviated_well(
+            vec![-5.0, 0.5, 0.5, -1.0, 0.5, 0.5],
+            100.0,
+            0.1,
+            0.0,
+            false,
+            "P".into(),
+        ),
+        "any active cell",
+    );
+
+    // A trajectory through an already completed cell adds none of its completions.
+    sim.add_well(1, 0, 1, 100.0, 0.1, 0.0, false).unwrap();
+    err_contains(
+        sim.add_deviated_well(
+            vec![0.0, 5.0, 1.5, 30.0, 5.0, 1.5],
+            100.0,
+            0.1,
+            0.0,
+            false,
+            "P".into(),
+        ),
+        "same cell",
+    );
+    assert_eq!(sim.wells.len(), 1);
+
+    let mut radial = ReservoirSimulator::new(4, 1, 1, 0.2);
+    radial
+        .set_radial_grid(0.1, 100.0, 360.0, vec![5.0])
+        .unwrap();
+    err_contains(
+        radial.add_directional_well(0, 0, 0, "x".into(), 100.0, 0.1, 0.0, false, "P".into()),
+        "radial grid",
+    );
+}
+
+#[test]
+fn directional_completion_uses_the_permeabilities_across_its_axis() {
+    let mut sim = ReservoirSimulator::new(1, 1, 1, 0.2);
+    sim.set_cell_dimensions(40.0, 20.0, 4.0).unwrap();
+    sim.set_permeability_per_layer(vec![100.0], vec![50.0], vec![10.0])
+        .unwrap();
+    sim.set_net_to_gross_field(vec![0.5]).unwrap();
+    sim.add_directional_well(0, 0, 0, "X".into(), 100.0, 0.1, 0.0, false, String::new())
+        .unwrap();
+    let peaceman = |ka: f64, kb: f64, da: f64, db: f64| {
+        let r_eq = 0.28 * ((kb / ka).sqrt() * da * da + (ka / kb).sqrt() * db * db).sqrt()
+            / ((kb / ka).powf(0.25) + (ka / kb).powf(0.25));
+        (ka * kb).sqrt() / (r_eq / 0.1).ln()
+    };
+
+    let pi = |penetration_m| {
+        let mut well = sim.wells[0].clone();
+        well.penetration_m = penetration_m;
+        sim.well_productivity_index(&well).unwrap()
+    };
+    let vertical = pi(None);
+    let along_x = pi(Some([40.0, 0.0, 0.0]));
+    let along_y = pi(Some([0.0, 20.0, 0.0]));
+    // Net-to-gross thins only the vertical completion.
+    let expected_vertical = peaceman(100.0, 50.0, 40.0, 20.0) * 4.0 * 0.5;
+    let expected_x = peaceman(50.0, 10.0, 20.0, 4.0) * 40.0;
+    let expected_y = peaceman(100.0, 10.0, 40.0, 4.0) * 20.0;
+    assert!((along_x / vertical - expected_x / expected_vertical).abs() < 1e-12);
+    assert!((along_y / vertical - expected_y / expected_vertical).abs() < 1e-12);
+
+    assert_eq!(sim.wells[0].penetration_m, Some([40.0, 0.0, 0.0]));
+    assert!((sim.wells[0].productivity_index - along_x).abs() < 1e-12 * along_x);
+}
+
+#[test]
+fn deviated_trajectory_completes_each_cell_it_crosses() {
+    let mut sim = ReservoirSimulator::new(3, 1, 2, 0.2);
+    sim.set_cell_dimensions(10.0, 10.0, 5.0).unwrap();
+    // A straight path dropping one layer over the grid's length: it leaves the top layer
+    // halfway across the middle column.
+    sim.add_deviated_well(
+        vec![0.0, 5.0, 0.0, 30.0, 5.0, 10.0],
+        100.0,
+        0.1,
+        0.0,
+        false,
+        "P1".into(),
+    )
+    .unwrap();
+
+    let expected = [
+        ((0, 0), [10.0, 0.0, 10.0 / 3.0]),
+        ((1, 0), [5.0, 0.0, 5.0 / 3.0]),
+        ((1, 1), [5.0, 0.0, 5.0 / 3.0]),
+        ((2, 1), [10.0, 0.0, 10.0 / 3.0]),
+    ];
+    assert_eq!(sim.wells.len(), expected.len());
+    for (well, ((i, k), lengths)) in sim.wells.iter().zip(expected) {
+        assert_eq!((well.i, well.j, well.k), (i, 0, k));
+        assert_eq!(well.physical_well_id.as_deref(), Some("P1"));
+        let penetration = well.penetration_m.unwrap();
+        for axis in 0..3 {
+            assert!((penetration[axis] - lengths[axis]).abs() < 1e-9);
+        }
+    }
+
+    // Each projection adds its own Peaceman inflow in quadrature.
+    let pi = |penetration| {
+        let mut well = sim.wells[1].clone();
+        well.penetration_m = Some(penetration);
+        sim.well_productivity_index(&well).unwrap()
+    };
+    let combined = pi(sim.wells[1].penetration_m.unwrap());
+    let expected_combined = pi([5.0, 0.0, 0.0]).hypot(pi([0.0, 0.0, 5.0 / 3.0]));
+    assert!((combined - expected_combined).abs() < 1e-9 * combined);
+
+    // Inactive cells on the path are passed over.
+    let mut with_hole = ReservoirSimulator::new(3, 1, 1, 0.2);
+    with_hole.set_active_cells(vec![1, 0, 1]).unwrap();
+    with_hole
+        .add_deviated_well(
+            vec![0.0, 5.0, 0.5, 30.0, 5.0, 0.5],
+            100.0,
+            0.1,
+            0.0,
+            false,
+            "P1".into(),
+        )
+        .unwrap();
+    let completed: Vec<usize> = with_hole.wells.iter().map(|well| well.i).collect();
+    assert_eq!(completed, vec![0, 2]);
+}
+
+#[test]
+fn fractured_well_connects_every_cell_its_fracture_crosses() {
+    let mut sim = ReservoirSimulator::new(5, 3, 1, 0.2);
+    sim.set_cell_dimensions(10.0, 10.0, 5.0).unwrap();
+    sim.set_permeability_per_layer(vec![100.0], vec![40.0], vec![10.0])
+        .unwrap();
+    // Half-length 20 m along x from the centre of column (2, 1): the tips fall halfway
+    // across the end columns.
+    sim.add_fractured_well(2, 1, 0, 20.0, 0.0, 100.0, 0.1, false, "F1".into())
+        .unwrap();
+
+    let expected = [(2, 10.0), (0, 5.0), (1, 10.0), (3, 10.0), (4, 5.0)];
+    assert_eq!(sim.wells.len(), expected.len());
+    for (well, (i, length)) in sim.wells.iter().zip(expected) {
+        assert_eq!((well.i, well.j, well.k), (i, 1, 0));
+        let fracture = well.fracture.unwrap();
+        assert!((fracture.length_m - length).abs() < 1e-9);
+        // The plane runs through the middle of the row: ⟨d⟩ = dy/4.
+        assert!((fracture.mean_normal_distance_m - 2.5).abs() < 1e-12);
+        // Linear flow onto both faces sees ky, the permeability across the fracture.
+        let expected_factor = 8.526_988_8e-3 * 2.0 * 40.0 * 5.0 * length / 2.5;
+        let factor = sim.well_connection_factor(well).unwrap();
+        assert!((factor - expected_factor).abs() < 1e-12 * expected_factor);
+    }
+
+    // A diagonal fracture reaching past the grid stops at its edge.
+    let mut diagonal = ReservoirSimulator::new(3, 3, 1, 0.2);
+    diagonal.set_cell_dimensions(10.0, 10.0, 5.0).unwrap();
+    diagonal
+        .add_fractured_well(1, 1, 0, 100.0, 45.0, 100.0, 0.1, false, "F1".into())
+        .unwrap();
+    let cells: Vec<(usize, usize)> = diagonal.wells.iter().map(|w| (w.i, w.j)).collect();
+    assert_eq!(cells, vec![(1, 1), (0, 0), (2, 2)]);
+    let total: f64 = diagonal
+        .wells
+        .iter()
+        .map(|well| well.fracture.unwrap().length_m)
+        .sum();
+    assert!((total - 30.0 * 2f64.sqrt()).abs() < 1e-9);
+    // ⟨d⟩ over a square cut along its diagonal is side/(3√2).
+    let centre = diagonal.wells[0].fracture.unwrap();
+    assert!((centre.mean_normal_distance_m - 10.0 / (3.0 * 2f64.sqrt())).abs() < 1e-9);
+
+    // Inactive cells are passed over.
+    let mut with_hole = ReservoirSimulator::new(3, 1, 1, 0.2);
+    with_hole.set_active_cells(vec![0, 1, 1]).unwrap();
+    with_hole
+        .add_fractured_well(1, 0, 0, 15.0, 0.0, 100.0, 0.1, false, "F1".into())
+        .unwrap();
+    let completed: Vec<usize> = with_hole.wells.iter().map(|well| well.i).collect();
+    assert_eq!(completed, vec![1, 2]);
+}
+
+#[test]
+fn fractured_well_validation_rejects_invalid_inputs() {
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+    err_contains(
+        sim.add_fractured_well(1, 0, 0, 0.0, 0.0, 100.0, 0.1, false, "F1".into()),
+        "half-length",
+    );
+    err_contains(
+        sim.add_fractured_well(1, 0, 0, 10.0, f64::NAN, 100.0, 0.1, false, "F1".into()),
+        "azimuth",
+    );
+    err_contains(
+        sim.add_fractured_well(3, 0, 0, 10.0, 0.0, 100.0, 0.1, false, "F1".into()),
+        "out of bounds",
+    );
+    err_contains(
+        sim.add_fractured_well(1, 0, 0, 10.0, 0.0, 100.0, 0.1, false, " ".into()),
+        "physical well id",
+    );
+
+    // A fracture into an already completed cell adds none of its completions.
+    sim.add_well(2, 0, 0, 100.0, 0.1, 0.0, false).unwrap();
+    err_contains(
+        sim.add_fractured_well(1, 0, 0, 10.0, 0.0, 100.0, 0.1, false, "F1".into()),
+        "same cell",
+    );
+    assert_eq!(sim.wells.len(), 1);
+
+    let mut radial = ReservoirSimulator::new(4, 1, 1, 0.2);
+    radial
+        .set_radial_grid(0.1, 100.0, 360.0, vec![5.0])
+        .unwrap();
+    err_contains(
+        radial.add_fractured_well(0, 0, 0, 10.0, 0.0, 100.0, 0.1, false, "F1".into()),
+        "radial grid",
+    );
+}
+
+#[test]
+fn horizontal_completions_along_x_and_y_match_in_both_solvers() {
+    for fim in [true, false] {
+        // The same row of cells laid out along x or along y, drained by a well along the row.
+        let run = |along_x: bool| {
+            let (nx, ny) = if along_x { (3, 1) } else { (1, 3) };
+            let mut sim = ReservoirSimulator::new(nx, ny, 1, 0.2);
+            sim.set_fim_enabled(fim);
+            sim.set_rock_properties(1e-5, 0.0, 1.0, 1.0).unwrap();
+            if along_x {
+                sim.set_cell_dimensions(20.0, 4.0, 4.0).unwrap();
+            } else {
+                sim.set_cell_dimensions(4.0, 20.0, 4.0).unwrap();
+            }
+            for n in 0..2 {
+                let (i, j, direction) = if along_x { (n, 0, "x") } else { (0, n, "y") };
+                sim.add_directional_well(
+                    i,
+                    j,
+                    0,
+                    direction.into(),
+                    150.0,
+                    0.1,
+                    0.0,
+                    false,
+                    "H".into(),
+                )
+                .unwrap();
+            }
+            for _ in 0..3 {
+                sim.step(0.01);
+            }
+            sim
+        };
+        let x_row = run(true);
+        let y_row = run(false);
+        assert!(x_row.pressure[2] < 300.0, "fim={fim}");
+        for (id, (&px, &py)) in x_row.pressure.iter().zip(&y_row.pressure).enumerate() {
+            assert!((px - py).abs() < 1e-9, "fim={fim} cell={id}: {px} vs {py}");
+        }
+        let x_oil = x_row.rate_history.last().unwrap().total_production_oil;
+        let y_oil = y_row.rate_history.last().unwrap().total_production_oil;
+        assert!(
+            x_oil > 0.0 && (x_oil - y_oil).abs() < 1e-9 * x_oil,
+            "fim={fim}"
+        );
+    }
+}
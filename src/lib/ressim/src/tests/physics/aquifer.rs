@@ -0,0 +1,190 @@
+//! Fetkovich aquifers: pressure support under depletion and the pot's own material balance.
+//!
+//! A pot of compressibility × volume `c_t·V` that has let in `W_e` of water has fallen by
+//! `W_e/(c_t·V)`, and left to itself it settles at the reservoir pressure. Every cubic metre it
+//! lets in has to turn up in the reservoir.
+
+use crate::ReservoirSimulator;
+
+const INITIAL_PRESSURE_BAR: f64 = 300.0;
+const AQUIFER_PI: f64 = 5.0;
+const AQUIFER_CT_V: f64 = 2000.0;
+
+/// 5x1x1 oil row with a producer at `x+` and, if `aquifer`, a Fetkovich aquifer on `x-`.
+fn depletion_row(fim_enabled: bool, aquifer: bool) -> ReservoirSimulator {
+    let mut sim = ReservoirSimulator::new(5, 1, 1, 0.2);
+    sim.set_fim_enabled(fim_enabled);
+    sim.set_cell_dimensions(20.0, 20.0, 10.0).unwrap();
+    sim.set_initial_pressure(INITIAL_PRESSURE_BAR);
+    sim.set_initial_saturation(0.2);
+    sim.add_well_with_id(4, 0, 0, 200.0, 0.1, 0.0, false, "P".to_string())
+        .unwrap();
+    if aquifer {
+        let index = sim
+            .add_fetkovich_aquifer(
+                vec!["x-".to_string()],
+                Vec::new(),
+                INITIAL_PRESSURE_BAR,
+                AQUIFER_PI,
+                AQUIFER_CT_V,
+            )
+            .unwrap();
+        assert_eq!(index, 0);
+    }
+    sim
+}
+
+fn water_in_place(sim: &ReservoirSimulator) -> f64 {
+    (0..sim.cell_count())
+        .map(|id| sim.sat_water[id] * sim.pore_volume_m3(id))
+        .sum()
+}
+
+#[test]
+fn physics_aquifer_supports_pressure_under_depletion() {
+    let mut influx = Vec::new();
+    for fim in [false, true] {
+        let mut closed = depletion_row(fim, false);
+        let mut supported = depletion_row(fim, true);
+        for _ in 0..20 {
+            closed.step(1.0);
+            supported.step(1.0);
+        }
+        let closed_point = closed.rate_history.last().unwrap();
+        let point = supported.rate_history.last().unwrap();
+        assert_eq!(closed_point.cumulative_aquifer_influx, 0.0);
+        assert!(closed_point.aquifers.is_empty());
+        assert!(
+            point.avg_reservoir_pressure > closed_point.avg_reservoir_pressure + 5.0,
+            "fim={fim}: the aquifer should hold the pressure up, closed {} supported {}",
+            closed_point.avg_reservoir_pressure,
+            point.avg_reservoir_pressure
+        );
+        assert!(point.cumulative_aquifer_influx > 0.0, "fim={fim}");
+        assert_eq!(point.aquifers.len(), 1);
+        assert_eq!(
+            point.aquifers[0].cumulative_influx,
+            point.cumulative_aquifer_influx
+        );
+        assert!(point.aquifers[0].pressure < INITIAL_PRESSURE_BAR);
+        assert!(
+            point.material_balance_error_m3 < 1e-3 * point.cumulative_aquifer_influx,
+            "fim={fim}: material balance error {} against influx {}",
+            point.material_balance_error_m3,
+            point.cumulative_aquifer_influx
+        );
+        influx.push(point.cumulative_aquifer_influx);
+    }
+    assert!(
+        (influx[0] / influx[1] - 1.0).abs() < 0.05,
+        "IMPES and FIM should let in the same water: IMPES {} FIM {}",
+        influx[0],
+        influx[1]
+    );
+}
+
+#[test]
+fn physics_aquifer_pot_balances_the_water_it_lets_in() {
+    for fim in [false, true] {
+        // No wells: an overpressured aquifer pushes water into the row until the two settle.
+        let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_cell_dimensions(20.0, 20.0, 10.0).unwrap();
+        sim.set_initial_pressure(250.0);
+        sim.set_initial_saturation(0.2);
+        sim.add_fetkovich_aquifer(
+            vec!["x-".to_string()],
+            Vec::new(),
+            INITIAL_PRESSURE_BAR,
+            AQUIFER_PI,
+            AQUIFER_CT_V,
+        )
+        .unwrap();
+        let water_before = water_in_place(&sim);
+        for _ in 0..40 {
+            sim.step(5.0);
+        }
+
+        let point = sim.rate_history.last().unwrap();
+        let aquifer = &point.aquifers[0];
+        let influx = point.cumulative_aquifer_influx;
+        assert!(
+            (influx - AQUIFER_CT_V * (INITIAL_PRESSURE_BAR - aquifer.pressure)).abs()
+                < 1e-9 * influx,
+            "fim={fim}: the pot should fall by its influx over c_t·V"
+        );
+        assert!(
+            (aquifer.pressure - point.avg_reservoir_pressure).abs() < 0.1,
+            "fim={fim}: pot {} and reservoir {} should have settled",
+            aquifer.pressure,
+            point.avg_reservoir_pressure
+        );
+        assert!(
+            point.material_balance_error_m3 < 1e-3 * influx,
+            "fim={fim}: material balance error {} against influx {influx}",
+            point.material_balance_error_m3
+        );
+        if !fim {
+            // IMPES keeps water volumes at reservoir conditions.
+            let added = water_in_place(&sim) - water_before;
+            assert!(
+                (added / influx - 1.0).abs() < 0.01,
+                "the reservoir gained {added} m³ for {influx} m³ of influx"
+            );
+        }
+    }
+}
+
+#[test]
+fn physics_aquifer_setup_validates_and_clears() {
+    let mut sim = ReservoirSimulator::new(2, 1, 2, 0.2);
+    assert!(
+        sim.add_fetkovich_aquifer(vec!["x-".to_string()], Vec::new(), 300.0, 0.0, 100.0)
+            .is_err()
+    );
+    assert!(
+        sim.add_fetkovich_aquifer(vec!["x-".to_string()], Vec::new(), 300.0, 1.0, f64::NAN)
+            .is_err()
+    );
+    assert!(
+        sim.add_fetkovich_aquifer(Vec::new(), Vec::new(), 300.0, 1.0, 100.0)
+            .is_err()
+    );
+    // A bad face rolls back the faces already added.
+    assert!(
+        sim.add_fetkovich_aquifer(
+            vec!["x-".to_string(), "w+".to_string()],
+            Vec::new(),
+            300.0,
+            1.0,
+            100.0
+        )
+        .is_err()
+    );
+    assert!(sim.boundary_conditions.is_empty() && sim.aquifers.is_empty());
+
+    // Layers apply to the x face only; the shared index splits by transmissibility.
+    sim.add_fetkovich_aquifer(
+        vec!["x-".to_string(), "z+".to_string()],
+        vec![1],
+        300.0,
+        6.0,
+        100.0,
+    )
+    .unwrap();
+    let shares: f64 = sim
+        .boundary_connections()
+        .iter()
+        .map(|connection| match connection.drive {
+            crate::boundary::BoundaryDrive::Aquifer {
+                productivity_index, ..
+            } => productivity_index,
+            _ => 0.0,
+        })
+        .sum();
+    assert_eq!(sim.boundary_connections().len(), 3);
+    assert!((shares - 6.0).abs() < 1e-12);
+
+    sim.clear_boundary_conditions();
+    assert!(sim.boundary_conditions.is_empty() && sim.aquifers.is_empty());
+}
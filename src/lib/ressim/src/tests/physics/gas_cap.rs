@@ -508,3 +508,45 @@ fn physics_gas_cap_gravity_column_timestep_refinement_keeps_profile_stable() {
         top_sw_abs_diff
     );
 }
+
+#[test]
+fn physics_gas_cap_dipping_layer_holds_hydrostatic_head_along_structure() {
+    // One layer dipping 5 m between two columns: the horizontal x-face now
+    // carries a gravity head, so a hydrostatic start must stay at rest.
+    let build = |fim_enabled: bool| {
+        let mut sim = ReservoirSimulator::new(2, 1, 1, 0.2);
+        sim.set_fim_enabled(fim_enabled);
+        sim.set_permeability_random_seeded(80_000.0, 80_000.0, 7)
+            .unwrap();
+        sim.set_initial_saturation(0.9);
+        sim.pc.p_entry = 0.0;
+        sim.set_fluid_densities(800.0, 1000.0).unwrap();
+        sim.set_gravity_enabled(true);
+        sim.set_cell_tops(vec![1000.0, 1005.0]).unwrap();
+        sim
+    };
+
+    for fim_enabled in [true, false] {
+        let mut sim = build(fim_enabled);
+        let expected_dp_bar = sim.pvt.rho_w * 9.80665 * 5.0 * 1e-5;
+        sim.pressure[0] = 300.0;
+        sim.pressure[1] = 300.0 + expected_dp_bar;
+
+        sim.step(5.0);
+
+        let measured_dp_bar = sim.pressure[1] - sim.pressure[0];
+        assert!(
+            (measured_dp_bar - expected_dp_bar).abs() < 1e-3,
+            "dipping layer should hold the hydrostatic head (fim={}): expected={:.6}, measured={:.6}",
+            fim_enabled,
+            expected_dp_bar,
+            measured_dp_bar
+        );
+        assert!(
+            (sim.sat_water[0] - 0.9).abs() < 1e-6 && (sim.sat_water[1] - 0.9).abs() < 1e-6,
+            "hydrostatic dipping layer should not segregate (fim={}): sw={:?}",
+            fim_enabled,
+            sim.sat_water
+        );
+    }
+}
@@ -1,3 +1,4 @@
+mod aquifer;
 mod depletion_gas;
 mod depletion_grid_convergence;
 mod depletion_liberation;
@@ -6,7 +7,9 @@ pub(crate) mod fixtures;
 mod gas_cap;
 mod gas_flood;
 mod geometry_anisotropy;
+mod non_darcy;
 mod pvt_flash;
 mod waterflood;
 mod wellbore_datum;
+mod wellbore_segments;
 mod wells_sources;
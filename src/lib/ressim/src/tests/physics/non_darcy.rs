@@ -0,0 +1,88 @@
+//! Non-Darcy gas inflow: a multi-rate test on a well with a D-factor.
+//!
+//! Turbulence near the wellbore adds `D·q_g` to the skin, so at a fixed reservoir state the
+//! drawdown per unit rate grows linearly with rate, `Δp/q_g = a + b·q_g`, where a Darcy well
+//! keeps `Δp/q_g = a`. The slope over the intercept is the well's turbulence coefficient, the
+//! D-factor over the Darcy skin term.
+
+use super::fixtures::make_closed_gas_depletion_single_cell_sim;
+use crate::ReservoirSimulator;
+
+const INITIAL_PRESSURE_BAR: f64 = 220.0;
+const D_FACTOR_DAY_PER_SM3: f64 = 2e-7;
+
+fn gas_well(fim_enabled: bool, d_factor: f64, bhp_bar: f64) -> ReservoirSimulator {
+    let mut sim = make_closed_gas_depletion_single_cell_sim();
+    sim.set_fim_enabled(fim_enabled);
+    sim.wells[0].bhp = bhp_bar;
+    sim.wells[0].physical_well_id = Some("G".to_string());
+    sim.set_well_d_factor("G".to_string(), d_factor).unwrap();
+    sim
+}
+
+/// Gas rate [Sm³/day] over a step short enough to leave the cell at its initial state.
+fn flow_period_gas_rate(fim_enabled: bool, d_factor: f64, bhp_bar: f64) -> f64 {
+    let mut sim = gas_well(fim_enabled, d_factor, bhp_bar);
+    sim.step(1e-5);
+    let point = sim.rate_history.last().unwrap();
+    assert!(point.total_production_gas > 0.0);
+    point.total_production_gas
+}
+
+#[test]
+fn physics_non_darcy_multi_rate_test_shows_rate_dependent_skin() {
+    let coefficient = {
+        let sim = gas_well(false, D_FACTOR_DAY_PER_SM3, 100.0);
+        sim.well_non_darcy_coefficient(&sim.wells[0])
+    };
+    assert!(coefficient > 0.0);
+
+    for fim in [false, true] {
+        let darcy_rate = flow_period_gas_rate(fim, 0.0, 180.0);
+        let intercept = (INITIAL_PRESSURE_BAR - 180.0) / darcy_rate;
+
+        let points: Vec<(f64, f64)> = [180.0, 160.0, 140.0, 120.0]
+            .iter()
+            .map(|&bhp_bar| {
+                let rate = flow_period_gas_rate(fim, D_FACTOR_DAY_PER_SM3, bhp_bar);
+                (rate, (INITIAL_PRESSURE_BAR - bhp_bar) / rate)
+            })
+            .collect();
+        assert!(
+            points[0].0 < 0.8 * darcy_rate,
+            "fim={fim}: turbulence should throttle the well, Darcy {darcy_rate} non-Darcy {}",
+            points[0].0
+        );
+        for &(rate, drawdown_per_rate) in &points {
+            let line = intercept * (1.0 + coefficient * rate);
+            assert!(
+                (drawdown_per_rate / line - 1.0).abs() < 0.01,
+                "fim={fim}: Δp/q at {rate} Sm³/day is {drawdown_per_rate}, the line gives {line}"
+            );
+        }
+    }
+}
+
+#[test]
+fn physics_non_darcy_leaves_water_injectors_and_unset_wells_darcy() {
+    let darcy = gas_well(true, 0.0, 120.0);
+    assert_eq!(darcy.well_non_darcy_coefficient(&darcy.wells[0]), 0.0);
+    assert!(darcy.wells[0].d_factor.is_none());
+
+    let mut sim = ReservoirSimulator::new(2, 1, 1, 0.2);
+    sim.add_well_with_id(0, 0, 0, 400.0, 0.1, 0.0, true, "W".to_string())
+        .unwrap();
+    sim.set_well_d_factor("W".to_string(), 1e-4).unwrap();
+    assert_eq!(sim.wells[0].d_factor, Some(1e-4));
+    assert_eq!(sim.well_non_darcy_coefficient(&sim.wells[0]), 0.0);
+
+    assert!(sim.set_well_d_factor("W".to_string(), -1.0).is_err());
+    assert!(sim.set_well_d_factor("missing".to_string(), 1e-4).is_err());
+    for invalid in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let err = sim.set_well_d_factor("W".to_string(), invalid).unwrap_err();
+        assert!(err.contains("finite and non-negative"), "{err}");
+    }
+    assert_eq!(sim.wells[0].d_factor, Some(1e-4));
+    sim.set_well_d_factor("W".to_string(), 0.0).unwrap();
+    assert!(sim.wells[0].d_factor.is_none());
+}
@@ -37,7 +37,7 @@ fn hydrostatic_water_column() -> ReservoirSimulator {
     // the reservoir gradient is the analytic ρ·g·Δz to machine precision.
     for k in 0..LAYERS {
         let id = sim.idx(0, 0, k);
-        let depth_offset_m = sim.depth_at_k(k) - sim.depth_at_k(0);
+        let depth_offset_m = sim.depth_at(sim.idx(0, 0, k)) - sim.depth_at(sim.idx(0, 0, 0));
         sim.pressure[id] = DATUM_PRESSURE_BAR
             + sim.water_density_generic(DATUM_PRESSURE_BAR) * GRAVITY_M_S2 * depth_offset_m * 1e-5;
     }
@@ -155,7 +155,7 @@ fn physics_wellbore_datum_honours_explicit_datum_and_density() {
     let mut sim = hydrostatic_water_column();
     // Quote the BHP at the *bottom* completion instead, with a light wellbore
     // column that no longer matches the reservoir fluid.
-    let bottom_depth_m = sim.depth_at_k(LAYERS - 1);
+    let bottom_depth_m = sim.depth_at(sim.idx(0, 0, LAYERS - 1));
     for well in sim.wells.iter_mut() {
         well.datum_depth_m = Some(bottom_depth_m);
         well.wellbore_density_kg_m3 = Some(500.0);
@@ -168,7 +168,8 @@ fn physics_wellbore_datum_honours_explicit_datum_and_density() {
         "the completion at the datum carries no head"
     );
     for k in 0..LAYERS {
-        let expected = 500.0 * GRAVITY_M_S2 * (sim.depth_at_k(k) - bottom_depth_m) * 1e-5;
+        let expected =
+            500.0 * GRAVITY_M_S2 * (sim.depth_at(sim.idx(0, 0, k)) - bottom_depth_m) * 1e-5;
         assert!(
             (sim.wells[k].head_offset_bar - expected).abs() < 1e-9,
             "completion {} should use the explicit datum and density: expected {}, got {}",
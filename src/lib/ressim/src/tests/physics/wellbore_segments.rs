@@ -0,0 +1,195 @@
+//! Multi-segment wellbores: friction along a horizontal well and the mixture head of a vertical
+//! one.
+//!
+//! A horizontal producer through a row of identical cells drains every cell alike while its
+//! wellbore is a single pressure. With wall friction the toe sits above the heel by the drop
+//! the flow needs to reach the heel, so the toe sees less drawdown and the inflow leans toward
+//! the heel: the heel–toe effect.
+//!
+//! The implicit solver also lets a segmented wellbore carry fluid between layers: a depleted
+//! layer below the wellbore pressure takes back what a charged layer produces.
+
+use crate::ReservoirSimulator;
+
+const CELLS: usize = 10;
+
+/// `CELLS`x1x1 row drained by a horizontal producer along x through every cell, heel at `i = 0`.
+fn horizontal_producer(fim_enabled: bool, diameter_m: Option<f64>) -> ReservoirSimulator {
+    let mut sim = ReservoirSimulator::new(CELLS, 1, 1, 0.2);
+    sim.set_fim_enabled(fim_enabled);
+    sim.set_cell_dimensions(100.0, 1000.0, 10.0).unwrap();
+    sim.set_permeability_per_layer(vec![5.0], vec![5.0], vec![5.0])
+        .unwrap();
+    sim.set_initial_pressure(300.0);
+    sim.set_initial_saturation(0.2);
+    for i in 0..CELLS {
+        sim.add_directional_well(
+            i,
+            0,
+            0,
+            "x".to_string(),
+            250.0,
+            0.1,
+            0.0,
+            false,
+            "H".to_string(),
+        )
+        .unwrap();
+    }
+    if let Some(diameter_m) = diameter_m {
+        sim.set_multi_segment_well("H".to_string(), diameter_m, 1e-5, Vec::new())
+            .unwrap();
+    }
+    sim.set_completion_rate_reporting(true);
+    sim
+}
+
+/// Heel and toe completions' reservoir rates after a short step.
+fn heel_and_toe_rates(sim: &mut ReservoirSimulator) -> (f64, f64) {
+    sim.step(0.05);
+    let well = &sim.rate_history.last().unwrap().wells[0];
+    assert_eq!(well.completions.len(), CELLS);
+    (
+        well.completions[0].reservoir_rate,
+        well.completions[CELLS - 1].reservoir_rate,
+    )
+}
+
+#[test]
+fn physics_wellbore_segments_friction_leans_inflow_toward_the_heel() {
+    for fim in [false, true] {
+        let (heel, toe) = heel_and_toe_rates(&mut horizontal_producer(fim, None));
+        assert!(
+            (heel / toe - 1.0).abs() < 0.02,
+            "fim={fim}: a frictionless wellbore drains the row evenly, got heel {heel} toe {toe}"
+        );
+
+        let (heel, toe) = heel_and_toe_rates(&mut horizontal_producer(fim, Some(0.06)));
+        assert!(
+            heel > 1.1 * toe && toe > 0.0,
+            "fim={fim}: friction should favour the heel, got heel {heel} toe {toe}"
+        );
+
+        // A wide bore carries the same flow with next to no loss.
+        let (heel, toe) = heel_and_toe_rates(&mut horizontal_producer(fim, Some(0.5)));
+        assert!(
+            (heel / toe - 1.0).abs() < 0.02,
+            "fim={fim}: a wide bore should barely skew the inflow, got heel {heel} toe {toe}"
+        );
+    }
+}
+
+#[test]
+fn physics_wellbore_segments_friction_agrees_between_solvers() {
+    let (impes_heel, impes_toe) = heel_and_toe_rates(&mut horizontal_producer(false, Some(0.06)));
+    let (fim_heel, fim_toe) = heel_and_toe_rates(&mut horizontal_producer(true, Some(0.06)));
+    let impes_skew = impes_heel / impes_toe;
+    let fim_skew = fim_heel / fim_toe;
+    assert!(
+        (impes_skew / fim_skew - 1.0).abs() < 0.05,
+        "lagged and implicit friction should agree on a short step: IMPES {impes_skew}, FIM {fim_skew}"
+    );
+}
+
+#[test]
+fn physics_wellbore_segments_hold_the_datum_head_in_a_still_vertical_well() {
+    let mut sim = ReservoirSimulator::new(1, 1, 4, 0.2);
+    sim.set_cell_dimensions_per_layer(20.0, 20.0, vec![10.0; 4])
+        .unwrap();
+    sim.set_initial_pressure(300.0);
+    sim.set_initial_saturation(1.0);
+    sim.set_gravity_enabled(true);
+    for k in 0..4 {
+        sim.add_well_with_id(0, 0, k, 300.0, 0.1, 0.0, false, "V".to_string())
+            .unwrap();
+    }
+    sim.set_well_datum("V".to_string(), f64::NAN, 1000.0)
+        .unwrap();
+    let column: Vec<f64> = sim.wells.iter().map(|well| well.head_offset_bar).collect();
+
+    // Shut in, the segmented wellbore holds the same column as the single-density datum head.
+    sim.set_well_schedule(
+        "V".to_string(),
+        "pressure".to_string(),
+        f64::NAN,
+        f64::NAN,
+        f64::NAN,
+        false,
+    )
+    .unwrap();
+    sim.set_multi_segment_well("V".to_string(), 0.1, 1e-5, Vec::new())
+        .unwrap();
+    assert!(
+        sim.wells[1]
+            .segment
+            .is_some_and(|segment| (segment.length_m - 10.0).abs() < 1e-9)
+    );
+    for (well, expected) in sim.wells.iter().zip(column) {
+        assert!(
+            expected >= 0.0 && (well.head_offset_bar - expected).abs() < 1e-9,
+            "segment head {} should match the datum head {expected}",
+            well.head_offset_bar
+        );
+    }
+
+    sim.set_multi_segment_well("V".to_string(), f64::NAN, 0.0, Vec::new())
+        .unwrap();
+    assert!(sim.wells.iter().all(|well| well.segment.is_none()));
+    assert!(
+        sim.set_multi_segment_well("V".to_string(), 0.1, 1e-5, vec![10.0])
+            .is_err(),
+        "one length per completion"
+    );
+}
+
+/// Two stacked layers, the upper charged and the lower depleted, completed by one producer whose
+/// BHP sits between them.
+fn crossflow_column(segmented: bool) -> ReservoirSimulator {
+    let mut sim = ReservoirSimulator::new(1, 1, 2, 0.2);
+    sim.set_fim_enabled(true);
+    sim.set_cell_dimensions_per_layer(100.0, 100.0, vec![10.0; 2])
+        .unwrap();
+    // A shale break between the layers leaves the wellbore as their only connection.
+    sim.set_permeability_per_layer(vec![100.0; 2], vec![100.0; 2], vec![1e-9; 2])
+        .unwrap();
+    sim.set_initial_saturation(0.2);
+    sim.pressure[0] = 300.0;
+    sim.pressure[1] = 100.0;
+    for k in 0..2 {
+        sim.add_well_with_id(0, 0, k, 200.0, 0.1, 0.0, false, "X".to_string())
+            .unwrap();
+    }
+    if segmented {
+        sim.set_multi_segment_well("X".to_string(), 0.1, 1e-5, Vec::new())
+            .unwrap();
+    }
+    sim.set_completion_rate_reporting(true);
+    sim
+}
+
+#[test]
+fn physics_wellbore_segments_carry_crossflow_between_layers() {
+    let mut plain = crossflow_column(false);
+    plain.step(0.01);
+    let completions = &plain.rate_history.last().unwrap().wells[0].completions;
+    assert!(
+        completions[1].reservoir_rate.abs() < 1e-9,
+        "an ordinary producer's completion never injects, got {}",
+        completions[1].reservoir_rate
+    );
+
+    let mut segmented = crossflow_column(true);
+    segmented.step(0.01);
+    let completions = &segmented.rate_history.last().unwrap().wells[0].completions;
+    let (upper, lower) = (completions[0].reservoir_rate, completions[1].reservoir_rate);
+    assert!(
+        upper > 0.0 && lower < 0.0,
+        "the charged layer should feed the depleted one, got upper {upper} lower {lower}"
+    );
+    assert!(
+        segmented.pressure[1] > plain.pressure[1] + 1.0,
+        "the depleted layer should recharge through the wellbore: {} vs {}",
+        segmented.pressure[1],
+        plain.pressure[1]
+    );
+}
@@ -57,3 +57,62 @@ fn physics_wells_sources_gas_injection_surface_totals_match_target_on_both_solve
         );
     }
 }
+
+#[test]
+fn physics_wells_sources_fractured_well_shows_early_linear_flow() {
+    // Log-log slope of the drawdown of a well producing at constant rate, from the first
+    // report step to the one at ~0.3 days.
+    fn early_drawdown_slope(fractured: bool) -> f64 {
+        let (nx, ny) = (31, 25);
+        let mut sim = ReservoirSimulator::new(nx, ny, 1, 0.2);
+        // Rows refine toward the fracture plane so the linear-flow region is resolved.
+        let mut dy = vec![0.0; ny];
+        let mut width = 1.0;
+        for offset in 0..=ny / 2 {
+            dy[ny / 2 + offset] = width;
+            dy[ny / 2 - offset] = width;
+            width *= 1.35;
+        }
+        sim.set_cell_dimensions_tensor(vec![10.0; nx], dy, vec![10.0])
+            .unwrap();
+        sim.set_permeability_per_layer(vec![1.0], vec![1.0], vec![1.0])
+            .unwrap();
+        sim.set_rel_perm_props(0.1, 0.1, 2.0, 2.0, 1.0, 1.0)
+            .unwrap();
+        sim.set_initial_pressure(300.0);
+        sim.set_initial_saturation(0.1);
+        sim.set_well_bhp_limits(10.0, 500.0).unwrap();
+        if fractured {
+            sim.add_fractured_well(nx / 2, ny / 2, 0, 100.0, 0.0, 10.0, 0.1, false, "P".into())
+                .unwrap();
+        } else {
+            sim.add_well_with_id(nx / 2, ny / 2, 0, 10.0, 0.1, 0.0, false, "P".into())
+                .unwrap();
+        }
+        sim.set_well_schedule("P".into(), "rate".into(), 5.0, f64::NAN, f64::NAN, true)
+            .unwrap();
+
+        let mut drawdowns = Vec::new();
+        let mut dt = 0.01;
+        while sim.time_days < 0.3 {
+            sim.step(dt);
+            dt *= 1.5;
+            let well = &sim.rate_history.last().unwrap().wells[0];
+            drawdowns.push((sim.time_days, 300.0 - well.flowing_bhp.unwrap()));
+        }
+        let ((t0, dp0), (t1, dp1)) = (drawdowns[0], drawdowns[drawdowns.len() - 1]);
+        (dp1 / dp0).ln() / (t1 / t0).ln()
+    }
+
+    // Infinite-conductivity linear flow draws down as √t; radial flow only logarithmically.
+    let fractured = early_drawdown_slope(true);
+    let radial = early_drawdown_slope(false);
+    assert!(
+        (fractured - 0.5).abs() < 0.08,
+        "fractured well should show a half slope, got {fractured}"
+    );
+    assert!(
+        radial < 0.2,
+        "radial flow slope should stay small, got {radial}"
+    );
+}
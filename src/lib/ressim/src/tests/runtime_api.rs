@@ -591,3 +591,161 @@ fn set_initial_saturation_per_layer_applies_uniformly_by_k() {
         }
     }
 }
+
+/// Five cells between a pressure-supporting injector `I1` and producer `P1`.
+fn scheduled_line_drive_sim(fim: bool) -> ReservoirSimulator {
+    let mut sim = ReservoirSimulator::new(5, 1, 1, 0.2);
+    sim.set_fim_enabled(fim);
+    sim.set_cell_dimensions(200.0, 200.0, 10.0).unwrap();
+    sim.set_initial_pressure(300.0);
+    sim.set_initial_saturation(0.2);
+    sim.add_well_with_id(0, 0, 0, 350.0, 0.1, 0.0, true, "I1".to_string())
+        .unwrap();
+    sim.add_well_with_id(4, 0, 0, 250.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    sim
+}
+
+fn schedule_event(time_days: f64, well: &str, action: ScheduleAction) -> ScheduleEvent {
+    ScheduleEvent {
+        time_days,
+        physical_well_id: well.to_string(),
+        action,
+    }
+}
+
+#[test]
+fn schedule_events_land_on_report_times_and_shut_and_reopen_a_well_on_both_solvers() {
+    for fim in [false, true] {
+        let mut sim = scheduled_line_drive_sim(fim);
+        sim.set_schedule_events(vec![
+            schedule_event(0.4, "P1", ScheduleAction::Open),
+            schedule_event(0.25, "P1", ScheduleAction::Shut),
+        ])
+        .unwrap();
+
+        sim.run_until(0.6);
+
+        assert!((sim.time_days - 0.6).abs() < 1e-9, "fim={fim}");
+        assert!(sim.last_solver_warning.is_empty(), "fim={fim}");
+        for event_time in [0.25, 0.4] {
+            assert!(
+                sim.rate_history
+                    .iter()
+                    .any(|point| (point.time - event_time).abs() < 1e-9),
+                "fim={fim}: no report lands on t={event_time}"
+            );
+        }
+        for point in &sim.rate_history {
+            let shut = point.time > 0.25 + 1e-9 && point.time < 0.4 + 1e-9;
+            assert_eq!(
+                point.total_production_liquid > 0.0,
+                !shut,
+                "fim={fim}: t={} liquid {}",
+                point.time,
+                point.total_production_liquid
+            );
+        }
+        assert!(sim.schedule_events.is_empty());
+    }
+}
+
+#[test]
+fn schedule_control_change_and_new_completion_take_effect_at_their_time() {
+    let mut sim = scheduled_line_drive_sim(false);
+    sim.set_well_schedule(
+        "P1".to_string(),
+        "rate".to_string(),
+        20.0,
+        f64::NAN,
+        100.0,
+        true,
+    )
+    .unwrap();
+    sim.set_schedule_events(vec![
+        schedule_event(
+            1.0,
+            "P1",
+            ScheduleAction::Control {
+                control_mode: "rate".to_string(),
+                target_rate_m3_day: Some(5.0),
+                target_surface_rate_m3_day: None,
+                bhp_limit: Some(120.0),
+                bhp_bar: Some(120.0),
+            },
+        ),
+        schedule_event(
+            1.5,
+            "P1",
+            ScheduleAction::Completion {
+                i: 3,
+                j: 0,
+                k: 0,
+                bhp: 120.0,
+                well_radius: 0.1,
+                skin: 0.0,
+                injector: false,
+                direction: None,
+            },
+        ),
+    ])
+    .unwrap();
+
+    sim.run_until(0.9);
+    assert_eq!(sim.wells[1].schedule.target_rate_m3_day, Some(20.0));
+    sim.run_until(1.2);
+    assert_eq!(sim.wells[1].schedule.target_rate_m3_day, Some(5.0));
+    assert_eq!(sim.wells[1].bhp, 120.0);
+    let rate = sim
+        .rate_history
+        .last()
+        .unwrap()
+        .total_production_liquid_reservoir;
+    assert!((rate - 5.0).abs() < 1e-6, "rate {rate}");
+
+    sim.run_until(2.0);
+    assert_eq!(sim.wells.len(), 3);
+    assert_eq!(sim.wells[2].physical_well_id.as_deref(), Some("P1"));
+    assert_eq!(sim.wells[2].schedule, sim.wells[1].schedule);
+}
+
+#[test]
+fn schedule_rejects_invalid_events_and_reports_failed_ones() {
+    let mut sim = ReservoirSimulator::new(2, 1, 1, 0.2);
+    sim.add_well_with_id(1, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+
+    let err = sim
+        .set_schedule_events(vec![schedule_event(-1.0, "P1", ScheduleAction::Shut)])
+        .unwrap_err();
+    assert!(err.contains("non-negative"), "{err}");
+    let err = sim
+        .set_schedule_events(vec![schedule_event(1.0, " ", ScheduleAction::Shut)])
+        .unwrap_err();
+    assert!(err.contains("must not be empty"), "{err}");
+    let err = sim
+        .set_schedule_events(vec![schedule_event(
+            1.0,
+            "P1",
+            ScheduleAction::Thp {
+                vfp_table: 1,
+                thp_bar: f64::NAN,
+            },
+        )])
+        .unwrap_err();
+    assert!(err.contains("THP must be finite"), "{err}");
+
+    sim.set_schedule_events(vec![
+        schedule_event(0.5, "P9", ScheduleAction::Shut),
+        schedule_event(0.5, "P1", ScheduleAction::Shut),
+    ])
+    .unwrap();
+    sim.step(1.0);
+    assert!(
+        sim.last_solver_warning
+            .contains("Schedule event at t=0.500000 days for well 'P9' failed"),
+        "{}",
+        sim.last_solver_warning
+    );
+    assert!(!sim.wells[0].schedule.enabled);
+}
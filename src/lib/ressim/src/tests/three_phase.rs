@@ -260,6 +260,33 @@ fn api_contract_rejects_invalid_injected_fluid_string() {
     assert!(sim.set_injected_fluid("gas").is_ok());
 }
 
+#[test]
+fn api_contract_rejects_invalid_well_injected_fluid() {
+    let mut sim = ReservoirSimulator::new(2, 1, 1, 0.2);
+    sim.add_well_with_id(0, 0, 0, 250.0, 0.1, 0.0, true, "I1".into())
+        .unwrap();
+
+    err_contains(
+        sim.set_well_injected_fluid("I1".into(), "steam"),
+        "Unknown injected fluid",
+    );
+    err_contains(
+        sim.set_well_injected_fluid(" ".into(), "gas"),
+        "must not be empty",
+    );
+    err_contains(
+        sim.set_well_injected_fluid("I2".into(), "gas"),
+        "No well found",
+    );
+    sim.set_well_injected_fluid("I1".into(), "Gas").unwrap();
+    assert_eq!(
+        sim.wells[0].schedule.injected_fluid,
+        Some(InjectedFluid::Gas)
+    );
+    sim.set_well_injected_fluid("I1".into(), "").unwrap();
+    assert_eq!(sim.wells[0].schedule.injected_fluid, None);
+}
+
 #[test]
 fn api_contract_rejects_invalid_gas_oil_capillary_params() {
     let mut sim = ReservoirSimulator::new(2, 2, 1, 0.2);
@@ -321,3 +348,72 @@ fn set_initial_gas_saturation_per_layer_validation() {
         "within [0, 1]",
     );
 }
+
+fn water_and_gas_injector_sim(fim: bool) -> ReservoirSimulator {
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+    sim.set_fim_enabled(fim);
+    sim.set_rock_properties(0.0, 0.0, 1.0, 1.0).unwrap();
+    sim.set_three_phase_rel_perm_props(0.1, 0.1, 0.05, 0.05, 0.15, 2.0, 2.0, 2.0, 1.0, 1.0, 1.0)
+        .unwrap();
+    sim.set_three_phase_mode_enabled(true);
+    sim.set_initial_pressure(200.0);
+    sim.set_initial_saturation(0.1);
+    sim.set_injected_fluid("water").unwrap();
+    sim.add_well_with_id(0, 0, 0, 250.0, 0.1, 0.0, true, "WI".into())
+        .unwrap();
+    sim.add_well_with_id(2, 0, 0, 250.0, 0.1, 0.0, true, "GI".into())
+        .unwrap();
+    sim.add_well_with_id(1, 0, 0, 150.0, 0.1, 0.0, false, "P".into())
+        .unwrap();
+    sim.set_well_injected_fluid("GI".into(), "gas").unwrap();
+    sim
+}
+
+#[test]
+fn water_and_gas_injectors_coexist_in_both_solvers() {
+    for fim in [true, false] {
+        let mut sim = water_and_gas_injector_sim(fim);
+        for _ in 0..5 {
+            sim.step(0.1);
+        }
+
+        // Each injector floods its own cell with its own phase.
+        assert!(sim.sat_water[0] > 0.15, "fim={fim}");
+        assert!(sim.sat_gas[0] < 1e-6, "fim={fim}");
+        assert!(sim.sat_gas[2] > 0.05, "fim={fim}");
+        assert!(sim.sat_water[2] < 0.12, "fim={fim}");
+
+        let last = sim.rate_history.last().unwrap();
+        assert!(last.total_injection_water > 0.0, "fim={fim}");
+        assert!(last.total_injection_gas > 0.0, "fim={fim}");
+        if fim {
+            // Each component balance books each injector's own phase.
+            assert!(last.material_balance_error_m3 < 1e-6);
+            assert!(last.material_balance_error_gas_m3 < 1e-6);
+        }
+        assert!(
+            (last.total_injection - last.total_injection_water - last.total_injection_gas).abs()
+                < 1e-9 * last.total_injection,
+            "fim={fim}"
+        );
+    }
+}
+
+#[test]
+fn well_injected_fluid_switches_between_report_steps() {
+    for fim in [true, false] {
+        let mut sim = water_and_gas_injector_sim(fim);
+        sim.step(0.1);
+        let gas_before = sim.rate_history.last().unwrap().total_injection_gas;
+        assert!(gas_before > 0.0, "fim={fim}");
+
+        sim.set_well_injected_fluid("GI".into(), "water").unwrap();
+        sim.step(0.1);
+        let last = sim.rate_history.last().unwrap();
+        assert_eq!(last.total_injection_gas, 0.0, "fim={fim}");
+        assert!(
+            (last.total_injection - last.total_injection_water).abs() < 1e-12,
+            "fim={fim}"
+        );
+    }
+}
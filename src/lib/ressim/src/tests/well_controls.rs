@@ -198,8 +198,10 @@ fn well_productivity_index_matches_metric_unit_conversion() {
     let well_radius = 0.1;
     let skin = 0.0;
 
+    sim.add_well(0, 0, 0, 100.0, well_radius, skin, false)
+        .unwrap();
     let pi = sim
-        .calculate_well_productivity_index(id, well_radius, skin)
+        .well_productivity_index(&sim.wells[0])
         .expect("PI should calculate for a valid isotropic cell");
 
     let kx = sim.perm_x[id];
@@ -207,7 +209,7 @@ fn well_productivity_index_matches_metric_unit_conversion() {
     let k_avg = (kx * ky).sqrt();
     let ratio = kx / ky;
     let r_eq = 0.28
-        * ((ratio.sqrt() * sim.dx.powi(2) + (1.0 / ratio).sqrt() * sim.dy.powi(2)).sqrt())
+        * ((ratio.sqrt() * sim.dx[0].powi(2) + (1.0 / ratio).sqrt() * sim.dy[0].powi(2)).sqrt())
         / (ratio.powf(0.25) + (1.0 / ratio).powf(0.25));
     let denom = (r_eq / well_radius).ln() + skin;
     let total_mobility = 1.0 / sim.pvt.mu_o;
@@ -294,3 +296,1031 @@ fn multi_completion_producer_rate_control_uses_shared_bhp() {
         bhp1
     );
 }
+
+fn two_producer_group_sim(fim: bool) -> ReservoirSimulator {
+    let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+    sim.set_fim_enabled(fim);
+    sim.set_initial_pressure(300.0);
+    sim.set_initial_saturation(0.2);
+    sim.set_well_bhp_limits(50.0, 500.0).unwrap();
+    sim.add_well_with_id(0, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    sim.add_well_with_id(2, 0, 0, 100.0, 0.1, 0.0, false, "P2".to_string())
+        .unwrap();
+    sim
+}
+
+#[test]
+fn api_contract_rejects_invalid_group_controls() {
+    let mut sim = two_producer_group_sim(false);
+    sim.add_well_with_id(1, 0, 0, 400.0, 0.1, 0.0, true, "I1".to_string())
+        .unwrap();
+
+    err_contains(
+        sim.set_well_group("field".to_string(), vec!["P1".to_string()]),
+        "cannot be set",
+    );
+    err_contains(
+        sim.set_well_group("NORTH".to_string(), vec!["P9".to_string()]),
+        "No well found",
+    );
+    sim.set_well_group("NORTH".to_string(), vec!["P1".to_string()])
+        .unwrap();
+    err_contains(
+        sim.set_well_group("SOUTH".to_string(), vec!["P1".to_string()]),
+        "already belongs to group 'NORTH'",
+    );
+    err_contains(
+        sim.set_group_production_target("SOUTH".to_string(), "oil", 10.0),
+        "No well group",
+    );
+    err_contains(
+        sim.set_group_production_target("NORTH".to_string(), "condensate", 10.0),
+        "Unknown group production phase",
+    );
+    err_contains(
+        sim.set_group_injection_target("NORTH".to_string(), "oil", 10.0),
+        "Unknown group injection phase",
+    );
+    err_contains(
+        sim.set_group_production_target("FIELD".to_string(), "oil", -1.0),
+        "finite and non-negative",
+    );
+    err_contains(sim.set_well_guide_rate("P1".to_string(), 0.0), "positive");
+    err_contains(
+        sim.set_well_guide_rate("P9".to_string(), 1.0),
+        "No well found",
+    );
+
+    sim.set_group_production_target("FIELD".to_string(), "liquid", 10.0)
+        .unwrap();
+    sim.set_group_injection_target("NORTH".to_string(), "water", 10.0)
+        .unwrap();
+    sim.clear_group_targets("FIELD".to_string()).unwrap();
+    sim.allocate_group_targets();
+    assert!(sim.wells.iter().all(|well| well.group_allocation.is_none()));
+}
+
+#[test]
+fn field_reservoir_target_is_shared_by_guide_rate_in_both_solvers() {
+    for fim in [false, true] {
+        let mut sim = two_producer_group_sim(fim);
+        sim.set_well_guide_rate("P1".to_string(), 1.0).unwrap();
+        sim.set_well_guide_rate("P2".to_string(), 3.0).unwrap();
+        sim.set_group_production_target("FIELD".to_string(), "resv", 0.04)
+            .unwrap();
+
+        sim.allocate_group_targets();
+        let pressures = sim.pressure.clone();
+        let q1 = sim
+            .well_rate_m3_day_for_pressures(&sim.wells[0], &pressures)
+            .unwrap();
+        let q2 = sim
+            .well_rate_m3_day_for_pressures(&sim.wells[1], &pressures)
+            .unwrap();
+        assert!((q1 - 0.01).abs() < 1e-9, "fim={fim}: P1 rate {q1}");
+        assert!((q2 - 0.03).abs() < 1e-9, "fim={fim}: P2 rate {q2}");
+
+        sim.step(0.1);
+        let point = sim.rate_history.last().unwrap();
+        assert!(
+            (point.total_production_liquid_reservoir - 0.04).abs() < 1e-4,
+            "fim={fim}: field reservoir rate {}",
+            point.total_production_liquid_reservoir
+        );
+    }
+}
+
+#[test]
+fn group_share_beyond_well_potential_falls_back_to_bhp_limit() {
+    let mut sim = two_producer_group_sim(false);
+    sim.set_well_schedule(
+        "P1".to_string(),
+        "pressure".to_string(),
+        f64::NAN,
+        f64::NAN,
+        295.0,
+        true,
+    )
+    .unwrap();
+    sim.set_well_guide_rate("P1".to_string(), 1.0).unwrap();
+    sim.set_well_guide_rate("P2".to_string(), 1.0).unwrap();
+    let potential_p1 = sim
+        .completion_rate_for_bhp(&sim.wells[0], 300.0, 295.0)
+        .unwrap();
+    sim.set_well_group("PAIR".to_string(), vec!["P1".to_string(), "P2".to_string()])
+        .unwrap();
+    sim.set_group_production_target("PAIR".to_string(), "resv", 4.0 * potential_p1)
+        .unwrap();
+
+    sim.allocate_group_targets();
+    let pressures = sim.pressure.clone();
+    let control = sim
+        .resolve_well_control_for_pressures(&sim.wells[0], &pressures)
+        .unwrap();
+    assert!(control.bhp_limited);
+    let q1 = sim
+        .well_rate_m3_day_for_pressures(&sim.wells[0], &pressures)
+        .unwrap();
+    let q2 = sim
+        .well_rate_m3_day_for_pressures(&sim.wells[1], &pressures)
+        .unwrap();
+    assert!((q1 - potential_p1).abs() < 1e-9, "P1 rate {q1}");
+    assert!(
+        (q2 - 3.0 * potential_p1).abs() < 1e-6,
+        "P2 should take the remainder, got {q2}"
+    );
+}
+
+#[test]
+fn field_target_sees_grouped_wells_at_most_at_their_group_share() {
+    let mut sim = two_producer_group_sim(false);
+    sim.set_well_group("NORTH".to_string(), vec!["P1".to_string()])
+        .unwrap();
+    sim.set_group_production_target("NORTH".to_string(), "resv", 1.0)
+        .unwrap();
+    sim.set_group_production_target("FIELD".to_string(), "resv", 5.0)
+        .unwrap();
+    sim.set_well_guide_rate("P1".to_string(), 1.0).unwrap();
+    sim.set_well_guide_rate("P2".to_string(), 1.0).unwrap();
+
+    sim.allocate_group_targets();
+    let pressures = sim.pressure.clone();
+    let q1 = sim
+        .well_rate_m3_day_for_pressures(&sim.wells[0], &pressures)
+        .unwrap();
+    let q2 = sim
+        .well_rate_m3_day_for_pressures(&sim.wells[1], &pressures)
+        .unwrap();
+    assert!((q1 - 1.0).abs() < 1e-6, "P1 rate {q1}");
+    assert!((q2 - 4.0).abs() < 1e-6, "P2 rate {q2}");
+}
+
+#[test]
+fn group_water_injection_target_is_shared_by_potential_in_both_solvers() {
+    for fim in [false, true] {
+        let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_initial_pressure(300.0);
+        sim.set_initial_saturation(0.2);
+        sim.set_well_bhp_limits(50.0, 500.0).unwrap();
+        sim.add_well_with_id(0, 0, 0, 400.0, 0.1, 0.0, true, "I1".to_string())
+            .unwrap();
+        sim.add_well_with_id(2, 0, 0, 400.0, 0.1, 0.0, true, "I2".to_string())
+            .unwrap();
+        sim.add_well_with_id(1, 0, 0, 250.0, 0.1, 0.0, false, "P1".to_string())
+            .unwrap();
+        sim.set_well_group("INJ".to_string(), vec!["I1".to_string(), "I2".to_string()])
+            .unwrap();
+        sim.set_group_injection_target("INJ".to_string(), "water", 2.0)
+            .unwrap();
+
+        sim.allocate_group_targets();
+        for well in &sim.wells[..2] {
+            let allocation = well.group_allocation.unwrap();
+            assert!(allocation.surface);
+            assert!((allocation.target_rate_m3_day - 1.0).abs() < 1e-9);
+        }
+
+        sim.step(0.1);
+        let point = sim.rate_history.last().unwrap();
+        assert!(
+            (point.total_injection - 2.0).abs() < 0.02,
+            "fim={fim}: group injection rate {}",
+            point.total_injection
+        );
+    }
+}
+
+#[test]
+fn voidage_replacement_injects_a_multiple_of_group_offtake_in_both_solvers() {
+    for fim in [false, true] {
+        let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_initial_pressure(300.0);
+        sim.set_initial_saturation(0.2);
+        sim.set_well_bhp_limits(50.0, 500.0).unwrap();
+        sim.add_well_with_id(0, 0, 0, 400.0, 0.1, 0.0, true, "I1".to_string())
+            .unwrap();
+        sim.add_well_with_id(2, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+            .unwrap();
+        sim.set_well_schedule(
+            "P1".to_string(),
+            "rate".to_string(),
+            0.04,
+            f64::NAN,
+            50.0,
+            true,
+        )
+        .unwrap();
+        err_contains(
+            sim.set_group_voidage_replacement("FIELD".to_string(), -1.0),
+            "finite and non-negative",
+        );
+        sim.set_well_group(
+            "PATTERN".to_string(),
+            vec!["I1".to_string(), "P1".to_string()],
+        )
+        .unwrap();
+        sim.set_group_voidage_replacement("PATTERN".to_string(), 1.5)
+            .unwrap();
+
+        sim.allocate_group_targets();
+        let allocation = sim.wells[0].group_allocation.unwrap();
+        assert!(!allocation.surface);
+        assert!(
+            (allocation.target_rate_m3_day - 0.06).abs() < 1e-9,
+            "fim={fim}: injector share {}",
+            allocation.target_rate_m3_day
+        );
+
+        sim.step(0.1);
+        let point = sim.rate_history.last().unwrap();
+        assert!(
+            (point.total_production_liquid_reservoir - 0.04).abs() < 1e-4,
+            "fim={fim}: offtake {}",
+            point.total_production_liquid_reservoir
+        );
+        assert!(
+            (point.total_injection_reservoir - 0.06).abs() < 1e-4,
+            "fim={fim}: injection {}",
+            point.total_injection_reservoir
+        );
+
+        // An explicit injection target replaces the ratio.
+        sim.set_group_injection_target("PATTERN".to_string(), "resv", 0.01)
+            .unwrap();
+        sim.allocate_group_targets();
+        let allocation = sim.wells[0].group_allocation.unwrap();
+        assert!((allocation.target_rate_m3_day - 0.01).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn api_contract_rejects_invalid_economic_limits() {
+    let mut sim = two_producer_group_sim(false);
+    sim.add_well_with_id(1, 0, 0, 400.0, 0.1, 0.0, true, "I1".to_string())
+        .unwrap();
+
+    err_contains(
+        sim.set_well_economic_limits("P1".to_string(), 1.0, 0.9, f64::NAN, f64::NAN, "abandon"),
+        "Unknown economic limit action",
+    );
+    err_contains(
+        sim.set_well_economic_limits("P1".to_string(), -1.0, 0.9, f64::NAN, f64::NAN, "shut"),
+        "Minimum oil rate must be non-negative",
+    );
+    err_contains(
+        sim.set_well_economic_limits("P1".to_string(), f64::NAN, 1.5, f64::NAN, f64::NAN, "shut"),
+        "must not exceed 1",
+    );
+    err_contains(
+        sim.set_well_economic_limits("I1".to_string(), 1.0, f64::NAN, f64::NAN, f64::NAN, "shut"),
+        "is an injector",
+    );
+    err_contains(
+        sim.set_group_economic_limits(
+            "NORTH".to_string(),
+            1.0,
+            f64::NAN,
+            f64::NAN,
+            f64::NAN,
+            "stop",
+        ),
+        "No well group",
+    );
+
+    sim.set_well_economic_limits("P1".to_string(), 1.0, f64::NAN, f64::NAN, f64::NAN, "shut")
+        .unwrap();
+    assert!(sim.wells[0].economic_limits.is_some());
+    sim.set_well_economic_limits(
+        "P1".to_string(),
+        f64::NAN,
+        f64::NAN,
+        f64::NAN,
+        f64::NAN,
+        "shut",
+    )
+    .unwrap();
+    assert!(sim.wells[0].economic_limits.is_none());
+}
+
+#[test]
+fn water_cut_limit_shuts_a_watered_out_producer_in_both_solvers() {
+    for fim in [false, true] {
+        let mut sim = ReservoirSimulator::new(2, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_cell_dimensions(500.0, 500.0, 10.0).unwrap();
+        sim.set_initial_pressure(300.0);
+        sim.set_initial_saturation(0.2);
+        sim.add_well_with_id(0, 0, 0, 200.0, 0.1, 0.0, false, "P1".to_string())
+            .unwrap();
+        sim.add_well_with_id(1, 0, 0, 200.0, 0.1, 0.0, false, "P2".to_string())
+            .unwrap();
+        sim.sat_water[1] = 0.8;
+        sim.sat_oil[1] = 0.2;
+        for id in ["P1", "P2"] {
+            sim.set_well_economic_limits(id.to_string(), f64::NAN, 0.5, f64::NAN, f64::NAN, "shut")
+                .unwrap();
+        }
+
+        sim.step(0.01);
+        let events = &sim.rate_history.last().unwrap().events;
+        assert_eq!(events.len(), 1, "fim={fim}");
+        assert_eq!(events[0].source, "P2");
+        assert_eq!(events[0].action, "shut well P2");
+        assert!(
+            events[0].reason.starts_with("water cut"),
+            "{}",
+            events[0].reason
+        );
+        assert!((events[0].time - 0.01).abs() < 1e-12);
+        assert!(sim.wells[0].schedule.enabled);
+        assert!(!sim.wells[1].schedule.enabled);
+
+        sim.step(0.01);
+        let point = sim.rate_history.last().unwrap();
+        assert!(point.events.is_empty());
+        let water_cut = 1.0 - point.total_production_oil / point.total_production_liquid;
+        assert!(
+            water_cut < 0.05,
+            "fim={fim}: only the dry producer should still flow, water cut {water_cut}"
+        );
+    }
+}
+
+#[test]
+fn close_completion_action_closes_the_wettest_completion_first() {
+    let mut sim = ReservoirSimulator::new(1, 1, 2, 0.2);
+    sim.set_cell_dimensions(500.0, 500.0, 10.0).unwrap();
+    sim.set_initial_pressure(300.0);
+    sim.set_initial_saturation(0.2);
+    sim.add_well_with_id(0, 0, 0, 200.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    sim.add_well_with_id(0, 0, 1, 200.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    sim.sat_water[0] = 0.8;
+    sim.sat_oil[0] = 0.2;
+    sim.set_well_economic_limits(
+        "P1".to_string(),
+        f64::NAN,
+        0.3,
+        f64::NAN,
+        f64::NAN,
+        "close_completion",
+    )
+    .unwrap();
+
+    sim.step(0.01);
+    let events = &sim.rate_history.last().unwrap().events;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].action, "close completion (0, 0, 0) of well P1");
+    assert_eq!(sim.wells.len(), 2);
+    assert!(!sim.wells[0].open);
+    assert!(sim.wells[1].open);
+    assert!(sim.wells[0].schedule.enabled);
+}
+
+#[test]
+fn field_limit_with_stop_action_halts_the_run() {
+    let mut sim = two_producer_group_sim(false);
+    sim.set_group_economic_limits(
+        "FIELD".to_string(),
+        1e6,
+        f64::NAN,
+        f64::NAN,
+        f64::NAN,
+        "stop",
+    )
+    .unwrap();
+
+    sim.step(0.01);
+    assert!(sim.is_run_stopped());
+    let point = sim.rate_history.last().unwrap();
+    assert_eq!(point.events.len(), 1);
+    assert_eq!(point.events[0].source, "FIELD");
+    assert_eq!(point.events[0].action, "stop run");
+
+    let (time, points) = (sim.time_days, sim.rate_history.len());
+    sim.step(0.01);
+    assert_eq!(sim.time_days, time);
+    assert_eq!(sim.rate_history.len(), points);
+}
+
+/// Production lift curve `BHP = 100 + THP + 0.05 * liquid rate`, flat in water cut and GOR.
+fn linear_production_vfp(sim: &mut ReservoirSimulator) {
+    let rates = vec![0.0, 2000.0];
+    let thps = vec![0.0, 100.0];
+    let mut bhps = Vec::new();
+    for thp in &thps {
+        for _water_cut in 0..2 {
+            for rate in &rates {
+                bhps.push(100.0 + thp + 0.05 * rate);
+            }
+        }
+    }
+    sim.set_vfp_production_table(1, rates, thps, vec![0.0, 1.0], vec![0.0], bhps)
+        .unwrap();
+}
+
+#[test]
+fn api_contract_rejects_invalid_vfp_tables_and_thp_controls() {
+    let mut sim = two_producer_group_sim(false);
+    sim.add_well_with_id(1, 0, 0, 400.0, 0.1, 0.0, true, "I1".to_string())
+        .unwrap();
+
+    err_contains(
+        sim.set_vfp_production_table(
+            1,
+            vec![0.0, 100.0],
+            vec![10.0],
+            vec![0.0],
+            vec![0.0],
+            vec![150.0],
+        ),
+        "needs 2 BHP values",
+    );
+    err_contains(
+        sim.set_vfp_production_table(
+            1,
+            vec![100.0, 0.0],
+            vec![10.0],
+            vec![0.0],
+            vec![0.0],
+            vec![150.0, 160.0],
+        ),
+        "strictly increasing",
+    );
+    err_contains(
+        sim.set_vfp_production_table(1, vec![0.0], vec![10.0], vec![1.5], vec![0.0], vec![150.0]),
+        "water cut",
+    );
+    err_contains(
+        sim.set_well_thp_control("P1".to_string(), 1, 20.0),
+        "No VFP table 1",
+    );
+    linear_production_vfp(&mut sim);
+    err_contains(
+        sim.set_vfp_injection_table(1, vec![0.0], vec![10.0], vec![150.0]),
+        "already exists as a production table",
+    );
+    err_contains(
+        sim.set_well_thp_control("I1".to_string(), 1, 20.0),
+        "is a production table but 'I1' is an injector",
+    );
+    err_contains(
+        sim.set_well_thp_control("P1".to_string(), 1, f64::NAN),
+        "THP target must be finite",
+    );
+    err_contains(
+        sim.set_well_thp_control("P9".to_string(), 1, 20.0),
+        "No well found",
+    );
+    sim.set_well_thp_control("P1".to_string(), 1, 20.0).unwrap();
+    assert_eq!(sim.wells[0].schedule.control_mode.as_deref(), Some("thp"));
+    assert!(sim.wells[0].validate(3, 1, 1).is_ok());
+    sim.wells[0].schedule.vfp_table = None;
+    assert!(sim.wells[0].validate(3, 1, 1).is_err());
+}
+
+#[test]
+fn thp_controlled_producer_flows_at_its_lift_curve_bhp_in_both_solvers() {
+    for fim in [false, true] {
+        let mut sim = ReservoirSimulator::new(1, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_cell_dimensions(500.0, 500.0, 10.0).unwrap();
+        sim.set_initial_pressure(300.0);
+        sim.set_initial_saturation(0.2);
+        sim.set_well_bhp_limits(50.0, 500.0).unwrap();
+        sim.add_well_with_id(0, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+            .unwrap();
+        linear_production_vfp(&mut sim);
+        sim.set_well_thp_control("P1".to_string(), 1, 20.0).unwrap();
+
+        sim.step(0.001);
+        let point = sim.rate_history.last().unwrap();
+        let liquid = point.total_production_liquid;
+        let bhp = sim.wells[0].flowing_bhp.unwrap();
+        assert!(
+            liquid > 1.0,
+            "fim={fim}: the well should flow, liquid {liquid}"
+        );
+        let lifted = 120.0 + 0.05 * liquid;
+        assert!(
+            (bhp - lifted).abs() < 0.5,
+            "fim={fim}: bhp {bhp} should sit on the lift curve {lifted}"
+        );
+
+        // A higher THP lifts less.
+        sim.set_well_thp_control("P1".to_string(), 1, 80.0).unwrap();
+        sim.step(0.001);
+        let throttled = sim.rate_history.last().unwrap().total_production_liquid;
+        assert!(
+            throttled < liquid,
+            "fim={fim}: THP 80 rate {throttled} should fall below THP 20 rate {liquid}"
+        );
+    }
+}
+
+#[test]
+fn thp_controlled_producer_is_held_at_its_bhp_limit() {
+    for fim in [false, true] {
+        let mut sim = ReservoirSimulator::new(1, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_cell_dimensions(500.0, 500.0, 10.0).unwrap();
+        sim.set_initial_pressure(300.0);
+        sim.set_initial_saturation(0.2);
+        sim.add_well_with_id(0, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+            .unwrap();
+        linear_production_vfp(&mut sim);
+        sim.set_well_thp_control("P1".to_string(), 1, 0.0).unwrap();
+        sim.wells[0].schedule.bhp_limit = Some(250.0);
+
+        sim.step(0.001);
+        let bhp = sim.wells[0].flowing_bhp.unwrap();
+        assert!((bhp - 250.0).abs() < 1e-6, "fim={fim}: bhp {bhp}");
+    }
+}
+
+#[test]
+fn thp_controlled_water_injector_follows_its_injection_table_in_both_solvers() {
+    for fim in [false, true] {
+        let mut sim = ReservoirSimulator::new(1, 1, 1, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_cell_dimensions(500.0, 500.0, 10.0).unwrap();
+        sim.set_initial_pressure(200.0);
+        sim.set_initial_saturation(0.2);
+        sim.set_well_bhp_limits(50.0, 1000.0).unwrap();
+        sim.add_well_with_id(0, 0, 0, 300.0, 0.1, 0.0, true, "I1".to_string())
+            .unwrap();
+        // BHP = 100 + THP - 0.02 * rate: friction eats into the hydrostatic head.
+        sim.set_vfp_injection_table(
+            2,
+            vec![0.0, 2000.0],
+            vec![0.0, 200.0],
+            vec![100.0, 60.0, 300.0, 260.0],
+        )
+        .unwrap();
+        sim.set_well_thp_control("I1".to_string(), 2, 150.0)
+            .unwrap();
+
+        sim.step(0.001);
+        let injected = sim.rate_history.last().unwrap().total_injection;
+        let bhp = sim.wells[0].flowing_bhp.unwrap();
+        assert!(
+            injected > 1.0,
+            "fim={fim}: the well should inject, rate {injected}"
+        );
+        let lifted = 250.0 - 0.02 * injected;
+        assert!(
+            (bhp - lifted).abs() < 0.5,
+            "fim={fim}: bhp {bhp} should sit on the injection curve {lifted}"
+        );
+    }
+}
+
+#[test]
+fn completion_connection_overrides_replace_the_computed_inflow() {
+    let mut sim = ReservoirSimulator::new(1, 1, 2, 0.2);
+    sim.set_cell_dimensions(100.0, 100.0, 10.0).unwrap();
+    sim.add_well_with_id(0, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    let mobility = sim.total_mobility(0);
+    let computed_pi = sim.wells[0].productivity_index;
+
+    // Doubling kh doubles the Peaceman factor.
+    let grid_kh = sim.perm_x[0] * 10.0;
+    sim.set_completion_connection(
+        "P1".to_string(),
+        0,
+        0,
+        0,
+        CompletionConnection::new(f64::NAN, 2.0 * grid_kh, f64::NAN),
+    )
+    .unwrap();
+    assert!((sim.wells[0].productivity_index - 2.0 * computed_pi).abs() < 1e-9 * computed_pi);
+
+    // A connection factor wins over kh and skin.
+    sim.set_completion_connection(
+        "P1".to_string(),
+        0,
+        0,
+        0,
+        CompletionConnection::new(3.0, 2.0 * grid_kh, 5.0),
+    )
+    .unwrap();
+    assert_eq!(sim.wells[0].skin, 5.0);
+    assert!((sim.wells[0].productivity_index - 3.0 * mobility).abs() < 1e-12);
+    sim.step(0.01);
+    assert!((sim.wells[0].productivity_index - 3.0 * sim.total_mobility(0)).abs() < 1e-12);
+
+    // Non-finite values return to the computed factor with the new skin kept.
+    sim.set_completion_connection(
+        "P1".to_string(),
+        0,
+        0,
+        0,
+        CompletionConnection::new(f64::NAN, f64::NAN, f64::NAN),
+    )
+    .unwrap();
+    assert_eq!(sim.wells[0].connection_factor, None);
+    assert_eq!(sim.wells[0].skin, 5.0);
+
+    err_contains(
+        sim.set_completion_connection(
+            "P1".to_string(),
+            0,
+            0,
+            0,
+            CompletionConnection::new(-1.0, f64::NAN, f64::NAN),
+        ),
+        "Connection factor must be finite and non-negative",
+    );
+    assert_eq!(sim.wells[0].connection_factor, None);
+    err_contains(
+        sim.set_completion_connection(
+            "P1".to_string(),
+            0,
+            0,
+            1,
+            CompletionConnection::new(1.0, f64::NAN, f64::NAN),
+        ),
+        "No completion of well 'P1' in cell (0, 0, 1)",
+    );
+}
+
+#[test]
+fn shut_completion_carries_no_flow_in_both_solvers() {
+    fn liquid_after_step(fim: bool, two_completions: bool) -> f64 {
+        let mut sim = ReservoirSimulator::new(1, 1, 2, 0.2);
+        sim.set_fim_enabled(fim);
+        sim.set_cell_dimensions(500.0, 500.0, 10.0).unwrap();
+        sim.set_initial_pressure(300.0);
+        sim.set_initial_saturation(0.2);
+        if two_completions {
+            sim.add_well_with_id(0, 0, 0, 200.0, 0.1, 0.0, false, "P1".to_string())
+                .unwrap();
+        }
+        sim.add_well_with_id(0, 0, 1, 200.0, 0.1, 0.0, false, "P1".to_string())
+            .unwrap();
+        if two_completions {
+            sim.set_completion_status("P1".to_string(), 0, 0, 0, false)
+                .unwrap();
+            assert_eq!(sim.wells[0].productivity_index, 0.0);
+        }
+        sim.step(0.01);
+        sim.rate_history.last().unwrap().total_production_liquid
+    }
+
+    for fim in [false, true] {
+        let shut = liquid_after_step(fim, true);
+        let single = liquid_after_step(fim, false);
+        assert!(single > 1.0, "fim={fim}: the open completion should flow");
+        assert!(
+            (shut - single).abs() < 1e-6 * single,
+            "fim={fim}: shut completion changed the rate, {shut} vs {single}"
+        );
+    }
+}
+
+#[test]
+fn shutting_the_last_open_completion_shuts_the_well_and_reopening_waits_for_the_well() {
+    let mut sim = ReservoirSimulator::new(1, 1, 2, 0.2);
+    sim.add_well_with_id(0, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    sim.add_well_with_id(0, 0, 1, 100.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    sim.set_schedule_events(vec![
+        ScheduleEvent {
+            time_days: 0.0,
+            physical_well_id: "P1".to_string(),
+            action: ScheduleAction::CompletionStatus {
+                i: 0,
+                j: 0,
+                k: 0,
+                open: false,
+            },
+        },
+        ScheduleEvent {
+            time_days: 0.0,
+            physical_well_id: "P1".to_string(),
+            action: ScheduleAction::CompletionStatus {
+                i: 0,
+                j: 0,
+                k: 1,
+                open: false,
+            },
+        },
+    ])
+    .unwrap();
+    sim.step(0.01);
+    assert!(
+        sim.wells
+            .iter()
+            .all(|well| !well.open && !well.schedule.enabled)
+    );
+
+    sim.set_completion_status("P1".to_string(), 0, 0, 1, true)
+        .unwrap();
+    assert!(sim.wells[1].open);
+    assert!(!sim.wells[1].schedule.enabled);
+    assert!(sim.wells[1].productivity_index > 0.0);
+}
+
+#[test]
+fn per_well_rate_history_adds_up_to_field_totals_in_both_solvers() {
+    for fim in [false, true] {
+        let mut sim = two_producer_group_sim(fim);
+        sim.add_well_with_id(1, 0, 0, 400.0, 0.1, 0.0, true, "I1".to_string())
+            .unwrap();
+        sim.set_well_schedule(
+            "P2".to_string(),
+            "rate".to_string(),
+            5.0,
+            f64::NAN,
+            f64::NAN,
+            true,
+        )
+        .unwrap();
+        sim.step(0.01);
+        sim.step(0.01);
+
+        let mut previous_time = 0.0;
+        let mut cumulative_oil = [0.0; 2];
+        for point in &sim.rate_history {
+            let ids: Vec<_> = point
+                .wells
+                .iter()
+                .map(|well| well.physical_well_id.as_deref().unwrap())
+                .collect();
+            assert_eq!(ids, ["P1", "P2", "I1"], "fim={fim}");
+            let [p1, p2, i1] = [&point.wells[0], &point.wells[1], &point.wells[2]];
+            let tolerance = 1e-9 * point.total_production_oil.max(1.0);
+            assert!(
+                (p1.oil_rate + p2.oil_rate - point.total_production_oil).abs() < tolerance,
+                "fim={fim}"
+            );
+            assert!((i1.water_rate - point.total_injection_water).abs() < tolerance);
+            assert!(i1.injector && i1.water_rate > 0.0 && i1.oil_rate == 0.0);
+
+            let dt = point.time - previous_time;
+            previous_time = point.time;
+            for (cumulative, well) in cumulative_oil.iter_mut().zip([p1, p2]) {
+                *cumulative += well.oil_rate * dt;
+                assert!((well.cumulative_oil - *cumulative).abs() < 1e-9 * cumulative.max(1.0));
+                assert!(well.water_cut >= 0.0 && well.water_cut < 1.0);
+            }
+
+            assert_eq!(p1.control_mode, "bhp", "fim={fim}");
+            assert_eq!(p1.flowing_bhp, Some(100.0));
+            assert_eq!(p2.control_mode, "rate", "fim={fim}");
+            assert!((p2.reservoir_rate - 5.0).abs() < 1e-6, "fim={fim}");
+            assert!(p2.flowing_bhp.is_some_and(|bhp| bhp > 100.0));
+            assert!(p1.completions.is_empty());
+        }
+    }
+}
+
+#[test]
+fn completion_rate_reporting_splits_a_well_and_a_shut_well_reports_no_rate() {
+    let mut sim = ReservoirSimulator::new(1, 1, 2, 0.2);
+    sim.set_initial_pressure(300.0);
+    sim.set_initial_saturation(0.2);
+    sim.add_well_with_id(0, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    sim.add_well_with_id(0, 0, 1, 100.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    sim.set_completion_rate_reporting(true);
+    sim.step(0.01);
+
+    let well = &sim.rate_history.last().unwrap().wells[0];
+    assert_eq!(well.completions.len(), 2);
+    assert_eq!(
+        (
+            well.completions[1].i,
+            well.completions[1].j,
+            well.completions[1].k
+        ),
+        (0, 0, 1)
+    );
+    let completion_oil: f64 = well.completions.iter().map(|c| c.oil_rate).sum();
+    assert!(well.oil_rate > 0.0);
+    assert!((completion_oil - well.oil_rate).abs() < 1e-9 * well.oil_rate);
+    let produced_oil = well.cumulative_oil;
+
+    sim.set_completion_rate_reporting(false);
+    sim.set_well_schedule(
+        "P1".to_string(),
+        "pressure".to_string(),
+        f64::NAN,
+        f64::NAN,
+        f64::NAN,
+        false,
+    )
+    .unwrap();
+    sim.step(0.01);
+    let well = &sim.rate_history.last().unwrap().wells[0];
+    assert_eq!(well.control_mode, "shut");
+    assert_eq!(well.oil_rate, 0.0);
+    assert_eq!(well.cumulative_oil, produced_oil);
+    assert!(well.completions.is_empty());
+}
+
+#[test]
+fn api_contract_rejects_invalid_phase_rate_controls() {
+    let mut sim = two_producer_group_sim(false);
+    sim.add_well_with_id(1, 0, 0, 400.0, 0.1, 0.0, true, "I1".to_string())
+        .unwrap();
+
+    err_contains(
+        sim.set_well_schedule(
+            "I1".to_string(),
+            "wrat".to_string(),
+            f64::NAN,
+            10.0,
+            f64::NAN,
+            true,
+        ),
+        "applies to producers only",
+    );
+    err_contains(
+        sim.set_well_schedule(
+            "P1".to_string(),
+            "lrat".to_string(),
+            10.0,
+            f64::NAN,
+            f64::NAN,
+            true,
+        ),
+        "needs a target surface rate",
+    );
+    err_contains(
+        sim.set_well_schedule(
+            "P1".to_string(),
+            "crat".to_string(),
+            f64::NAN,
+            10.0,
+            f64::NAN,
+            true,
+        ),
+        "'orat', 'wrat', 'grat' or 'lrat'",
+    );
+    assert!(sim.wells[0].schedule.control_mode.is_none());
+
+    sim.set_well_schedule(
+        "P1".to_string(),
+        "GRAT".to_string(),
+        f64::NAN,
+        10.0,
+        f64::NAN,
+        true,
+    )
+    .unwrap();
+    assert_eq!(sim.wells[0].schedule.control_mode.as_deref(), Some("grat"));
+    assert_eq!(
+        sim.well_control_config(&sim.wells[0]).surface_phase,
+        crate::well::SurfaceRatePhase::Gas
+    );
+}
+
+#[test]
+fn phase_surface_rate_targets_meter_their_phase_in_both_solvers() {
+    for fim in [false, true] {
+        for (mode, target, metered) in [("orat", 4.0, 0), ("wrat", 4.0, 1), ("lrat", 6.0, 2)] {
+            let mut sim = ReservoirSimulator::new(3, 1, 1, 0.2);
+            sim.set_fim_enabled(fim);
+            sim.set_cell_dimensions(200.0, 200.0, 10.0).unwrap();
+            sim.set_initial_pressure(300.0);
+            sim.set_initial_saturation(0.5);
+            sim.set_well_bhp_limits(50.0, 500.0).unwrap();
+            sim.add_well_with_id(0, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+                .unwrap();
+            sim.set_well_schedule(
+                "P1".to_string(),
+                mode.to_string(),
+                f64::NAN,
+                target,
+                f64::NAN,
+                true,
+            )
+            .unwrap();
+            sim.step(0.01);
+
+            let well = &sim.rate_history.last().unwrap().wells[0];
+            assert!(
+                well.oil_rate > 0.0 && well.water_rate > 0.0,
+                "fim={fim} {mode}"
+            );
+            let rate = [
+                well.oil_rate,
+                well.water_rate,
+                well.oil_rate + well.water_rate,
+            ][metered];
+            assert!(
+                (rate - target).abs() < 0.02 * target,
+                "fim={fim} {mode}: metered rate {rate} vs target {target}"
+            );
+            assert_eq!(well.control_mode, mode, "fim={fim}");
+
+            // A target the well cannot reach leaves it on its BHP limit.
+            sim.set_well_schedule(
+                "P1".to_string(),
+                mode.to_string(),
+                f64::NAN,
+                1e6,
+                f64::NAN,
+                true,
+            )
+            .unwrap();
+            sim.step(0.01);
+            let point = sim.rate_history.last().unwrap();
+            assert_eq!(point.wells[0].control_mode, "bhp", "fim={fim} {mode}");
+            assert_eq!(point.producer_bhp_limited_fraction, 1.0);
+            assert!(
+                point.wells[0]
+                    .flowing_bhp
+                    .is_some_and(|bhp| (bhp - 50.0).abs() < 1e-6)
+            );
+        }
+    }
+}
+
+#[test]
+fn quarter_well_inflow_is_a_quarter_of_the_full_well_block() {
+    let mut full = ReservoirSimulator::new(1, 1, 1, 0.2);
+    full.set_cell_dimensions(200.0, 200.0, 10.0).unwrap();
+    full.add_well_with_id(0, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+
+    // The corner cell of a quarter five-spot holds a quarter of the block around the well.
+    let mut quarter = ReservoirSimulator::new(1, 1, 1, 0.2);
+    quarter.set_cell_dimensions(100.0, 100.0, 10.0).unwrap();
+    quarter
+        .add_well_with_id(0, 0, 0, 100.0, 0.1, 0.0, false, "P1".to_string())
+        .unwrap();
+    let interior_pi = quarter.wells[0].productivity_index;
+    quarter.set_well_fraction("P1".to_string(), 0.25).unwrap();
+
+    let full_pi = full.wells[0].productivity_index;
+    assert_eq!(quarter.wells[0].well_fraction, 0.25);
+    assert!((quarter.wells[0].productivity_index - 0.25 * full_pi).abs() < 1e-12 * full_pi);
+
+    for fraction in [0.0, 1.5, f64::NAN] {
+        err_contains(
+            quarter.set_well_fraction("P1".to_string(), fraction),
+            "Well fraction must be in (0, 1]",
+        );
+    }
+    err_contains(
+        quarter.set_well_fraction("P9".to_string(), 0.5),
+        "No well found",
+    );
+    assert_eq!(quarter.wells[0].well_fraction, 0.25);
+
+    quarter.set_well_fraction("P1".to_string(), 1.0).unwrap();
+    assert!((quarter.wells[0].productivity_index - interior_pi).abs() < 1e-12 * interior_pi);
+}
+
+#[test]
+fn full_pattern_reporting_scales_well_rates_but_not_field_totals() {
+    for fim in [false, true] {
+        let mut sim = two_producer_group_sim(fim);
+        sim.set_well_fraction("P1".to_string(), 0.25).unwrap();
+        sim.step(0.01);
+        let full_pattern_start = sim.rate_history.len();
+        sim.set_full_pattern_rate_reporting(true);
+        sim.step(0.01);
+        let full_pattern_end = sim.rate_history.len();
+        sim.set_full_pattern_rate_reporting(false);
+        sim.step(0.01);
+
+        let mut previous_time = 0.0;
+        let mut element_cumulative_oil = 0.0;
+        for (step, point) in sim.rate_history.iter().enumerate() {
+            let [p1, p2] = [&point.wells[0], &point.wells[1]];
+            let full_pattern = (full_pattern_start..full_pattern_end).contains(&step);
+            assert_eq!(p1.full_pattern, full_pattern, "fim={fim}");
+            assert_eq!(p1.well_fraction, 0.25);
+            let to_element = if full_pattern { 0.25 } else { 1.0 };
+            let element_oil = p1.oil_rate * to_element;
+            let tolerance = 1e-9 * point.total_production_oil;
+            assert!(
+                (element_oil + p2.oil_rate - point.total_production_oil).abs() < tolerance,
+                "fim={fim} step {step}"
+            );
+
+            let dt = point.time - previous_time;
+            previous_time = point.time;
+            element_cumulative_oil += element_oil * dt;
+            assert!(
+                (p1.cumulative_oil * to_element - element_cumulative_oil).abs()
+                    < 1e-9 * element_cumulative_oil,
+                "fim={fim} step {step}"
+            );
+            assert!(
+                p2.oil_rate > element_oil,
+                "fim={fim}: the quarter well flows less"
+            );
+        }
+    }
+}
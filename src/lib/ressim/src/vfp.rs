@@ -0,0 +1,397 @@
+//! Vertical flow performance tables (Eclipse `VFPPROD` / `VFPINJ`) and tubing-head pressure
+//! control.
+//!
+//! A VFP table tabulates the bottomhole pressure a well needs to lift its flow to a given
+//! tubing-head pressure. A production table is indexed by surface liquid rate, THP, water cut
+//! and gas-oil ratio; an injection table by the surface rate of the injected phase and THP.
+//! The BHP is interpolated multilinearly, and extrapolated linearly beyond the end points,
+//! over [`Scalar`] so the FIM well equation carries its derivatives with respect to the
+//! well's rates.
+//!
+//! A `thp`-controlled well flows at the BHP its table gives for the rates it delivers, held
+//! at its BHP limit when the table asks for more drawdown (or, for an injector, more
+//! pressure) than the limit allows.
+
+use serde::{Deserialize, Serialize};
+
+use crate::fim::ad::Scalar;
+use crate::well::{Well, WellScheduleControl};
+use crate::{InjectedFluid, ReservoirSimulator};
+
+/// Rates below this [Sm³/day] leave the water cut and gas-oil ratio at zero.
+const VFP_RATIO_RATE_FLOOR: f64 = 1e-12;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VfpTable {
+    pub table_number: u32,
+    pub injector: bool,
+    /// Flow axis: surface liquid rate for a production table, surface rate of the injected
+    /// phase for an injection table [Sm³/day].
+    pub rates_sm3_day: Vec<f64>,
+    pub thps_bar: Vec<f64>,
+    /// Water cut axis; `[0]` for an injection table.
+    pub water_cuts: Vec<f64>,
+    /// Gas-oil ratio axis [Sm³/Sm³]; `[0]` for an injection table.
+    pub gors: Vec<f64>,
+    /// BHP [bar] at every axis point, the rate varying fastest, then GOR, water cut and THP
+    /// — one run of rates per `(thp, water cut, gor)` record as in Eclipse.
+    pub bhps_bar: Vec<f64>,
+}
+
+/// Tubing-head pressure target of a `thp`-controlled well.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ThpControl {
+    pub(crate) vfp_table: u32,
+    pub(crate) thp_bar: f64,
+}
+
+fn validate_axis(name: &str, axis: &[f64], min: f64, max: f64) -> Result<(), String> {
+    if axis.is_empty() {
+        return Err(format!("VFP {} axis must not be empty", name));
+    }
+    if let Some(value) = axis
+        .iter()
+        .find(|value| !value.is_finite() || **value < min || **value > max)
+    {
+        return Err(format!(
+            "VFP {} values must be finite and within [{}, {}], got {}",
+            name, min, max, value
+        ));
+    }
+    if axis.windows(2).any(|pair| pair[1] <= pair[0]) {
+        return Err(format!("VFP {} axis must be strictly increasing", name));
+    }
+    Ok(())
+}
+
+/// Interval of `axis` holding `x` (the end interval beyond the end points) and the position
+/// of `x` along it; a single-point axis has the one point and weight zero.
+fn bracket<S: Scalar>(axis: &[f64], x: S) -> (usize, S) {
+    if axis.len() == 1 {
+        return (0, S::from_f64(0.0));
+    }
+    let lower = axis
+        .partition_point(|&point| point <= x.value())
+        .saturating_sub(1)
+        .min(axis.len() - 2);
+    (lower, (x - axis[lower]) / (axis[lower + 1] - axis[lower]))
+}
+
+fn ratio<S: Scalar>(numerator: S, denominator: S) -> S {
+    if denominator.value() > VFP_RATIO_RATE_FLOOR {
+        numerator / denominator
+    } else {
+        S::from_f64(0.0)
+    }
+}
+
+impl VfpTable {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        validate_axis("rate", &self.rates_sm3_day, 0.0, f64::INFINITY)?;
+        validate_axis("THP", &self.thps_bar, f64::NEG_INFINITY, f64::INFINITY)?;
+        validate_axis("water cut", &self.water_cuts, 0.0, 1.0)?;
+        validate_axis("GOR", &self.gors, 0.0, f64::INFINITY)?;
+        let expected = self.rates_sm3_day.len()
+            * self.thps_bar.len()
+            * self.water_cuts.len()
+            * self.gors.len();
+        if self.bhps_bar.len() != expected {
+            return Err(format!(
+                "VFP table {} needs {} BHP values, got {}",
+                self.table_number,
+                expected,
+                self.bhps_bar.len()
+            ));
+        }
+        if let Some(bhp) = self.bhps_bar.iter().find(|bhp| !bhp.is_finite()) {
+            return Err(format!("VFP BHP values must be finite, got {}", bhp));
+        }
+        Ok(())
+    }
+
+    fn bhp_at(&self, [rate, thp, water_cut, gor]: [usize; 4]) -> f64 {
+        let index = ((thp * self.water_cuts.len() + water_cut) * self.gors.len() + gor)
+            * self.rates_sm3_day.len()
+            + rate;
+        self.bhps_bar[index]
+    }
+
+    /// BHP [bar] lifting surface rates `[water, oil, gas]` [Sm³/day] to `thp_bar`.
+    pub(crate) fn bhp_generic<S: Scalar>(&self, [water, oil, gas]: [S; 3], thp_bar: f64) -> S {
+        let zero = S::from_f64(0.0);
+        let (rate, water_cut, gor) = if self.injector {
+            (water + oil + gas, zero, zero)
+        } else {
+            let liquid = water + oil;
+            (liquid, ratio(water, liquid), ratio(gas, oil))
+        };
+        let axes: [(&[f64], (usize, S)); 4] = [
+            (&self.rates_sm3_day, bracket(&self.rates_sm3_day, rate)),
+            (
+                &self.thps_bar,
+                bracket(&self.thps_bar, S::from_f64(thp_bar)),
+            ),
+            (&self.water_cuts, bracket(&self.water_cuts, water_cut)),
+            (&self.gors, bracket(&self.gors, gor)),
+        ];
+
+        let mut bhp = zero;
+        'corners: for corner in 0..16 {
+            let mut weight = S::from_f64(1.0);
+            let mut index = [0; 4];
+            for (axis, (points, (lower, t))) in axes.iter().enumerate() {
+                let upper = corner >> axis & 1 == 1;
+                if upper && points.len() == 1 {
+                    continue 'corners;
+                }
+                index[axis] = lower + usize::from(upper);
+                weight = weight * if upper { *t } else { S::from_f64(1.0) - *t };
+            }
+            bhp = bhp + weight * self.bhp_at(index);
+        }
+        bhp
+    }
+
+    /// [`Self::bhp_generic`] held at `bhp_limit`: no lower for a producer, no higher for an
+    /// injector.
+    pub(crate) fn limited_bhp_generic<S: Scalar>(
+        &self,
+        rates: [S; 3],
+        thp_bar: f64,
+        bhp_limit: f64,
+    ) -> S {
+        let bhp = self.bhp_generic(rates, thp_bar);
+        if self.injector {
+            bhp.min_ceil(bhp_limit)
+        } else {
+            bhp.max_floor(bhp_limit)
+        }
+    }
+}
+
+impl ReservoirSimulator {
+    pub(crate) fn vfp_table(&self, table_number: u32) -> Option<&VfpTable> {
+        self.vfp_tables
+            .iter()
+            .find(|table| table.table_number == table_number)
+    }
+
+    /// THP target of an enabled `thp`-controlled well. A group share replaces it, like any
+    /// other well control.
+    pub(crate) fn well_thp_control(&self, well: &Well) -> Option<ThpControl> {
+        if !well.schedule.enabled
+            || well.group_allocation.is_some()
+            || well.schedule.control_kind() != Some(WellScheduleControl::Thp)
+        {
+            return None;
+        }
+        Some(ThpControl {
+            vfp_table: well.schedule.vfp_table?,
+            thp_bar: well.schedule.thp_target_bar?,
+        })
+    }
+
+    /// Surface rates `[water, oil, gas]` [Sm³/day] the completions of `well`'s physical well
+    /// deliver, or inject as positive rates, while flowing at `bhp_bar`.
+    fn well_surface_rates_for_bhp(&self, well: &Well, pressures: &[f64], bhp_bar: f64) -> [f64; 3] {
+        let mut rates = [0.0; 3];
+        for well_idx in self.well_control_group_indices(well) {
+            let completion = &self.wells[well_idx];
+            let id = self.well_cell_index(completion);
+            let Some(q_m3_day) = self.completion_rate_for_bhp(completion, pressures[id], bhp_bar)
+            else {
+                continue;
+            };
+            let completion_rates = if completion.injector {
+                match self.well_injected_fluid(completion) {
+                    InjectedFluid::Water => {
+                        [-q_m3_day * self.water_inverse_fvf(pressures[id]), 0.0, 0.0]
+                    }
+                    InjectedFluid::Gas => {
+                        [0.0, 0.0, -q_m3_day / self.get_b_g(pressures[id]).max(1e-9)]
+                    }
+                }
+            } else {
+                self.producer_surface_rates(completion, pressures, q_m3_day)
+            };
+            for (total, rate) in rates.iter_mut().zip(completion_rates) {
+                *total += rate;
+            }
+        }
+        rates
+    }
+
+    /// BHP [bar] at which a `thp`-controlled well flows at `pressures`, and whether its BHP
+    /// limit holds it there. `None` when its VFP table is missing.
+    ///
+    /// The BHP the table asks for falls (producer) or rises (injector) no faster than the
+    /// BHP itself as the rate it delivers grows, so `bhp - vfp(rates(bhp))` is monotone and
+    /// bisected between a zero-rate BHP and the limit.
+    pub(crate) fn solve_well_thp_bhp(
+        &self,
+        well: &Well,
+        pressures: &[f64],
+        thp: ThpControl,
+        bhp_limit: f64,
+    ) -> Option<(f64, bool)> {
+        let table = self.vfp_table(thp.vfp_table)?;
+        let datum_pressures: Vec<f64> = self
+            .well_control_group_indices(well)
+            .into_iter()
+            .map(|well_idx| {
+                let completion = &self.wells[well_idx];
+                pressures[self.well_cell_index(completion)] - completion.head_offset_bar
+            })
+            .collect();
+        let min_pressure = datum_pressures
+            .iter()
+            .copied()
+            .fold(f64::INFINITY, f64::min);
+        let max_pressure = datum_pressures
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if !min_pressure.is_finite() || !max_pressure.is_finite() {
+            return None;
+        }
+
+        let lifted_bhp = |bhp_bar: f64| {
+            table.limited_bhp_generic(
+                self.well_surface_rates_for_bhp(well, pressures, bhp_bar),
+                thp.thp_bar,
+                bhp_limit,
+            )
+        };
+        let shut_in_bhp = table.limited_bhp_generic([0.0; 3], thp.thp_bar, bhp_limit);
+        let (mut low, mut high) = if well.injector {
+            (min_pressure.min(shut_in_bhp), bhp_limit.max(max_pressure))
+        } else {
+            (bhp_limit.min(min_pressure), max_pressure.max(shut_in_bhp))
+        };
+        for _ in 0..64 {
+            let mid = 0.5 * (low + high);
+            if mid < lifted_bhp(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        let bhp_bar = 0.5 * (low + high);
+        let unlimited = table.bhp_generic(
+            self.well_surface_rates_for_bhp(well, pressures, bhp_bar),
+            thp.thp_bar,
+        );
+        let bhp_limited = if well.injector {
+            unlimited > bhp_limit
+        } else {
+            unlimited < bhp_limit
+        };
+        Some((bhp_bar, bhp_limited))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fim::ad::Ad;
+
+    fn production_table() -> VfpTable {
+        // BHP = 20 + thp + 0.5 * rate + 40 * water_cut - 0.01 * gor, linear in every axis.
+        let rates = vec![0.0, 100.0];
+        let thps = vec![10.0, 30.0];
+        let water_cuts = vec![0.0, 1.0];
+        let gors = vec![0.0, 1000.0];
+        let mut bhps = Vec::new();
+        for &thp in &thps {
+            for &water_cut in &water_cuts {
+                for &gor in &gors {
+                    for &rate in &rates {
+                        bhps.push(20.0 + thp + 0.5 * rate + 40.0 * water_cut - 0.01 * gor);
+                    }
+                }
+            }
+        }
+        VfpTable {
+            table_number: 1,
+            injector: false,
+            rates_sm3_day: rates,
+            thps_bar: thps,
+            water_cuts,
+            gors,
+            bhps_bar: bhps,
+        }
+    }
+
+    #[test]
+    fn production_table_reproduces_a_multilinear_lift_curve() {
+        let table = production_table();
+        table.validate().unwrap();
+        // 30 water + 50 oil + 10000 gas: liquid 80, water cut 0.375, GOR 200.
+        let bhp = table.bhp_generic([30.0, 50.0, 10_000.0], 15.0);
+        let expected = 20.0 + 15.0 + 0.5 * 80.0 + 40.0 * 0.375 - 0.01 * 200.0;
+        assert!(
+            (bhp - expected).abs() < 1e-9,
+            "bhp {bhp}, expected {expected}"
+        );
+        // Linear beyond the last rate point.
+        let beyond = table.bhp_generic([0.0, 150.0, 0.0], 10.0);
+        assert!((beyond - (30.0 + 75.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn production_table_derivatives_follow_the_rates() {
+        let table = production_table();
+        let rates = [
+            Ad::<3>::variable(30.0, 0),
+            Ad::<3>::variable(50.0, 1),
+            Ad::<3>::variable(10_000.0, 2),
+        ];
+        let bhp = table.bhp_generic(rates, 15.0);
+        let step = 1e-4;
+        for phase in 0..3 {
+            let mut plus = [30.0, 50.0, 10_000.0];
+            let mut minus = plus;
+            plus[phase] += step;
+            minus[phase] -= step;
+            let fd =
+                (table.bhp_generic(plus, 15.0) - table.bhp_generic(minus, 15.0)) / (2.0 * step);
+            assert!(
+                (bhp.deriv()[phase] - fd).abs() < 1e-6,
+                "phase {phase}: ad {}, fd {fd}",
+                bhp.deriv()[phase]
+            );
+        }
+    }
+
+    #[test]
+    fn limit_holds_the_lifted_bhp() {
+        let table = production_table();
+        let bhp = table.limited_bhp_generic([0.0, 10.0, 0.0], 10.0, 100.0);
+        assert_eq!(bhp, 100.0);
+    }
+
+    #[test]
+    fn validation_rejects_malformed_tables() {
+        let mut table = production_table();
+        table.bhps_bar.pop();
+        assert!(
+            table
+                .validate()
+                .unwrap_err()
+                .contains("needs 16 BHP values")
+        );
+
+        let mut table = production_table();
+        table.rates_sm3_day = vec![100.0, 0.0];
+        assert!(
+            table
+                .validate()
+                .unwrap_err()
+                .contains("strictly increasing")
+        );
+
+        let mut table = production_table();
+        table.water_cuts = vec![0.0, 1.5];
+        assert!(table.validate().unwrap_err().contains("water cut"));
+    }
+}
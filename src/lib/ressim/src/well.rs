@@ -1,9 +1,44 @@
 use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::InjectedFluid;
+use crate::economic_limits::EconomicLimits;
+use crate::well_fracture::FractureConnection;
+use crate::well_segments::{SegmentFluid, WellSegment};
 
 fn default_well_schedule_enabled() -> bool {
     true
 }
 
+fn default_completion_open() -> bool {
+    true
+}
+
+pub(crate) fn default_well_fraction() -> f64 {
+    1.0
+}
+
+/// A phase surface-rate control mode needs a producer and a surface-rate target.
+pub(crate) fn validate_surface_rate_control(
+    injector: bool,
+    control_mode: &str,
+    target_surface_rate_m3_day: Option<f64>,
+) -> Result<(), String> {
+    if injector {
+        return Err(format!(
+            "Well control mode '{}' applies to producers only",
+            control_mode
+        ));
+    }
+    if target_surface_rate_m3_day.is_none() {
+        return Err(format!(
+            "Well control mode '{}' needs a target surface rate",
+            control_mode
+        ));
+    }
+    Ok(())
+}
+
 /// Recognized schedule control kinds. The serialized schedule keeps its historical string field
 /// so existing scenario payloads remain compatible.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -11,6 +46,67 @@ pub enum WellScheduleControl {
     Pressure,
     Rate,
     Resv,
+    Thp,
+    /// Producer surface-rate targets on oil, water, gas or liquid (Eclipse `ORAT`, `WRAT`,
+    /// `GRAT`, `LRAT`), each falling back to the BHP limit like `Rate`.
+    Orat,
+    Wrat,
+    Grat,
+    Lrat,
+}
+
+impl WellScheduleControl {
+    /// Whether the well is held to a rate target, switching to its BHP limit when the target
+    /// cannot be met.
+    pub fn is_rate_control(self) -> bool {
+        self == Self::Rate || self.surface_rate_phase().is_some()
+    }
+
+    /// Phase metered by a phase-specific surface-rate control.
+    pub fn surface_rate_phase(self) -> Option<SurfaceRatePhase> {
+        match self {
+            Self::Orat => Some(SurfaceRatePhase::Oil),
+            Self::Wrat => Some(SurfaceRatePhase::Water),
+            Self::Grat => Some(SurfaceRatePhase::Gas),
+            Self::Lrat => Some(SurfaceRatePhase::Liquid),
+            _ => None,
+        }
+    }
+}
+
+/// Surface phase a producer's surface-rate target meters. A plain `rate` control with a surface
+/// target, and a group's oil share, meter oil.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SurfaceRatePhase {
+    #[default]
+    Oil,
+    Water,
+    /// Free and dissolved gas.
+    Gas,
+    /// Oil plus water.
+    Liquid,
+}
+
+impl SurfaceRatePhase {
+    /// This phase's share of producer surface rates `[water, oil, gas]`.
+    pub(crate) fn rate<S: Copy + std::ops::Add<Output = S>>(self, [water, oil, gas]: [S; 3]) -> S {
+        match self {
+            Self::Oil => oil,
+            Self::Water => water,
+            Self::Gas => gas,
+            Self::Liquid => water + oil,
+        }
+    }
+
+    /// Control-mode name reported for a producer held to this phase's surface rate.
+    pub(crate) fn control_mode(self) -> &'static str {
+        match self {
+            Self::Oil => "orat",
+            Self::Water => "wrat",
+            Self::Gas => "grat",
+            Self::Liquid => "lrat",
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -25,6 +121,16 @@ pub struct WellSchedule {
     pub bhp_limit: Option<f64>,
     #[serde(default = "default_well_schedule_enabled")]
     pub enabled: bool,
+    /// Phase this well injects in three-phase mode. `None` defers to the simulator-wide
+    /// `injected_fluid`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub injected_fluid: Option<InjectedFluid>,
+    /// Tubing-head pressure target [bar] of a `thp`-controlled well.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thp_target_bar: Option<f64>,
+    /// Number of the VFP table lifting a `thp`-controlled well's flow to its BHP.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vfp_table: Option<u32>,
 }
 
 impl Default for WellSchedule {
@@ -35,6 +141,9 @@ impl Default for WellSchedule {
             target_surface_rate_m3_day: None,
             bhp_limit: None,
             enabled: true,
+            injected_fluid: None,
+            thp_target_bar: None,
+            vfp_table: None,
         }
     }
 }
@@ -51,6 +160,11 @@ impl WellSchedule {
             "pressure" => Some(WellScheduleControl::Pressure),
             "rate" => Some(WellScheduleControl::Rate),
             "resv" => Some(WellScheduleControl::Resv),
+            "thp" => Some(WellScheduleControl::Thp),
+            "orat" => Some(WellScheduleControl::Orat),
+            "wrat" => Some(WellScheduleControl::Wrat),
+            "grat" => Some(WellScheduleControl::Grat),
+            "lrat" => Some(WellScheduleControl::Lrat),
             _ => None,
         }
     }
@@ -64,6 +178,42 @@ impl WellSchedule {
     }
 }
 
+/// Rate target a group control hands one physical well for the current step.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GroupAllocation {
+    /// Allocated rate: a surface rate [Sm³/day] of the oil (producer) or injected phase
+    /// (injector) when `surface` is set, otherwise a reservoir rate [m³/day].
+    pub target_rate_m3_day: f64,
+    pub surface: bool,
+}
+
+/// Inflow overrides for one completion as Eclipse `COMPDAT` gives them, applied by
+/// `setCompletionConnection`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompletionConnection {
+    /// Connection factor [m³·cP/(day·bar)] replacing the computed Peaceman factor outright;
+    /// non-finite returns to the computed value.
+    pub connection_factor: f64,
+    /// Permeability-thickness [mD·m] replacing the grid's in the Peaceman factor; non-finite
+    /// returns to the grid's.
+    pub kh_md_m: f64,
+    /// Skin replacing the completion's; non-finite keeps the current one.
+    pub skin: f64,
+}
+
+#[wasm_bindgen]
+impl CompletionConnection {
+    #[wasm_bindgen(constructor)]
+    pub fn new(connection_factor: f64, kh_md_m: f64, skin: f64) -> Self {
+        Self {
+            connection_factor,
+            kh_md_m,
+            skin,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Well {
     /// Stable physical-well identifier shared by all completions of the same well.
@@ -77,6 +227,17 @@ pub struct Well {
     pub j: usize,
     /// Cell index k (z-direction)
     pub k: usize,
+    /// Global index of the local-grid child cell this completion sits in. `i`, `j`, `k` then
+    /// name the root-grid cell that the local grid refines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_cell_index: Option<usize>,
+    /// Wellbore length inside the cell projected on the x, y and z axes [m].
+    ///
+    /// `None` is a vertical penetration of the full cell thickness. A horizontal completion
+    /// along x is `[dx, 0, 0]`; a deviated trajectory carries the projections of its path
+    /// through the cell, and each non-zero axis adds its own Peaceman inflow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub penetration_m: Option<[f64; 3]>,
     /// Bottom hole pressure [bar]
     pub bhp: f64,
     /// Productivity index [m³/(day·bar)]
@@ -88,6 +249,31 @@ pub struct Well {
     pub well_radius: f64,
     /// Skin factor [dimensionless]
     pub skin: f64,
+    /// Connection factor [m³·cP/(day·bar)] replacing the computed Peaceman factor, e.g. one
+    /// backed out of a measured PI (Eclipse `COMPDAT` item 8).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_factor: Option<f64>,
+    /// Permeability-thickness [mD·m] replacing the grid's in the Peaceman factor (Eclipse
+    /// `COMPDAT` item 10).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kh_md_m: Option<f64>,
+    /// Non-Darcy D-factor [day/Sm³] (Eclipse `COMPDAT` item 12): turbulence near the wellbore
+    /// adds `D·|q_g|` to the completion's skin, with `q_g` the free-gas surface rate it flows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub d_factor: Option<f64>,
+    /// Whether the completion is open. A shut completion keeps its entry but carries no flow.
+    #[serde(default = "default_completion_open")]
+    pub open: bool,
+    /// Fraction of the well inside the model, shared by every completion of the physical
+    /// well: `1` for an interior well, `1/2` on an edge and `1/4` in a corner of a
+    /// pattern-element model such as a quarter five-spot. It scales the Peaceman inflow angle
+    /// and equivalent radius, and full-pattern reporting divides the well's rates by it.
+    #[serde(default = "default_well_fraction")]
+    pub well_fraction: f64,
+    /// This completion's share of a hydraulic fracture (see `addFracturedWell`). It then
+    /// connects by linear flow onto the fracture faces in place of Peaceman radial inflow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fracture: Option<FractureConnection>,
     /// Depth the well's `bhp` is referenced to [m TVDSS], shared by every
     /// completion of the same physical well.
     ///
@@ -103,6 +289,16 @@ pub struct Well {
     /// mixture for a producer. Only consulted when gravity is enabled.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub wellbore_density_kg_m3: Option<f64>,
+    /// Wellbore segment from the completion above (or the datum) down to this one, set on
+    /// every completion of a multi-segment well (see `setMultiSegmentWell`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segment: Option<WellSegment>,
+    /// Fluid this completion passes into a multi-segment wellbore.
+    ///
+    /// Derived, not input: [`ReservoirSimulator::refresh_well_head_offsets`] rewrites it
+    /// from the state entering every step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segment_fluid: Option<SegmentFluid>,
     /// Hydrostatic head from the datum down to *this* completion [bar], so the
     /// pressure the connection law sees is `bhp + head_offset_bar`.
     ///
@@ -110,6 +306,10 @@ pub struct Well {
     /// rewrites it whenever the well set or the fluid state moves, and it is
     /// identically zero while gravity is disabled. It is held fixed across a
     /// step so the FIM Jacobian keeps `∂q/∂bhp` unchanged.
+    ///
+    /// On a multi-segment well it is the completion's node pressure below the
+    /// datum, friction included, at the rates entering the step; FIM instead
+    /// re-evaluates the node pressures from its current connection rates.
     #[serde(default)]
     pub head_offset_bar: f64,
     /// Bottomhole pressure the well actually flowed at on the last recorded
@@ -122,6 +322,22 @@ pub struct Well {
     /// needs. Written by the reporting pass only; no solver reads it back.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub flowing_bhp: Option<f64>,
+    /// Guide rate the well's group shares its target by, shared by every completion of the
+    /// physical well. `None` lets the group share by well potentials.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guide_rate: Option<f64>,
+    /// Rate target the well's group or the field assigned it for the current step.
+    ///
+    /// Derived, not input: [`ReservoirSimulator::allocate_group_targets`] rewrites it from
+    /// the state entering every step. While set it replaces the well's own rate target; the
+    /// well's BHP limit still applies, so a well that cannot deliver its share flows at that
+    /// limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_allocation: Option<GroupAllocation>,
+    /// Producer limits checked after every step, shared by every completion of the physical
+    /// well.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub economic_limits: Option<EconomicLimits>,
 }
 
 impl Well {
@@ -166,6 +382,61 @@ impl Well {
             return Err(format!("Skin factor must be finite, got: {}", self.skin));
         }
 
+        if let Some(connection_factor) = self.connection_factor
+            && (!connection_factor.is_finite() || connection_factor < 0.0)
+        {
+            return Err(format!(
+                "Connection factor must be finite and non-negative, got: {}",
+                connection_factor
+            ));
+        }
+
+        if let Some(kh) = self.kh_md_m
+            && (!kh.is_finite() || kh < 0.0)
+        {
+            return Err(format!(
+                "Completion kh must be finite and non-negative, got: {}",
+                kh
+            ));
+        }
+
+        if let Some(d_factor) = self.d_factor
+            && (!d_factor.is_finite() || d_factor < 0.0)
+        {
+            return Err(format!(
+                "D-factor must be finite and non-negative, got: {}",
+                d_factor
+            ));
+        }
+
+        if !self.well_fraction.is_finite() || self.well_fraction <= 0.0 || self.well_fraction > 1.0
+        {
+            return Err(format!(
+                "Well fraction must be in (0, 1], got: {}",
+                self.well_fraction
+            ));
+        }
+
+        if let Some(fracture) = &self.fracture {
+            fracture.validate()?;
+        }
+
+        if let Some(segment) = &self.segment {
+            segment.validate()?;
+        }
+
+        if let Some(penetration) = self.penetration_m
+            && (penetration
+                .iter()
+                .any(|length| !length.is_finite() || *length < 0.0)
+                || penetration.iter().all(|length| *length <= 0.0))
+        {
+            return Err(format!(
+                "Completion penetration must be finite, non-negative and not all zero, got: {:?}",
+                penetration
+            ));
+        }
+
         // Check productivity index is non-negative (PI = 0 means no well, PI < 0 is unphysical)
         if self.productivity_index < 0.0 {
             return Err(format!(
@@ -209,6 +480,15 @@ impl Well {
             }
         }
 
+        if let Some(guide_rate) = self.guide_rate
+            && (!guide_rate.is_finite() || guide_rate <= 0.0)
+        {
+            return Err(format!(
+                "Well guide rate must be positive and finite, got: {}",
+                guide_rate
+            ));
+        }
+
         if let Some(well_id) = &self.physical_well_id {
             if well_id.trim().is_empty() {
                 return Err("Physical well id must not be empty when provided".to_string());
@@ -218,11 +498,34 @@ impl Well {
         if let Some(control_mode) = &self.schedule.control_mode {
             if self.schedule.control_kind().is_none() {
                 return Err(format!(
-                    "Well control mode must be 'pressure', 'rate', or 'resv', got: {}",
+                    "Well control mode must be 'pressure', 'rate', 'orat', 'wrat', 'grat', 'lrat', \
+                     'resv', or 'thp', got: {}",
                     control_mode
                 ));
             }
         }
+        if self
+            .schedule
+            .control_kind()
+            .and_then(WellScheduleControl::surface_rate_phase)
+            .is_some()
+        {
+            validate_surface_rate_control(
+                self.injector,
+                self.schedule.control_mode.as_deref().unwrap_or_default(),
+                self.schedule.target_surface_rate_m3_day,
+            )?;
+        }
+        if let Some(thp) = self.schedule.thp_target_bar
+            && !thp.is_finite()
+        {
+            return Err(format!("Well THP target must be finite, got: {}", thp));
+        }
+        if self.schedule.control_kind() == Some(WellScheduleControl::Thp)
+            && (self.schedule.thp_target_bar.is_none() || self.schedule.vfp_table.is_none())
+        {
+            return Err("THP-controlled well needs a THP target and a VFP table".to_string());
+        }
         if let Some(target_rate) = self.schedule.target_rate_m3_day {
             if !target_rate.is_finite() || target_rate < 0.0 {
                 return Err(format!(
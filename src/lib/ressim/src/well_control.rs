@@ -1,10 +1,35 @@
+use crate::fim::ad::Scalar;
+use crate::vfp::ThpControl;
+use crate::well::{SurfaceRatePhase, WellScheduleControl};
+use crate::well_fracture::FractureConnection;
+use crate::well_segments::SegmentFluid;
 use crate::{InjectedFluid, ReservoirSimulator, Well};
 
 /// Conversion factor from mD·m²/(m·cP) to m³/day/bar.
 const DARCY_METRIC_FACTOR: f64 = 8.526_988_8e-3;
 
 /// Standard gravity [m/s²], as used by the flux gravity term.
-const GRAVITY_M_S2: f64 = 9.806_65;
+pub(crate) const GRAVITY_M_S2: f64 = 9.806_65;
+
+/// Peaceman inflow along one axis of a completion.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct CompletionInflow {
+    /// Peaceman equivalent radius [m] from the cell widths across the axis.
+    pub(crate) equivalent_radius_m: f64,
+    /// Permeability-thickness [mD·m]: the geometric mean of the permeabilities across the
+    /// axis times the wellbore length along it.
+    pub(crate) kh_md_m: f64,
+    /// Angle the cell opens around the wellbore [rad]; `2π` off a radial grid.
+    pub(crate) inflow_angle: f64,
+}
+
+/// Share `2/(1 + √(1 + 4x))` of its Darcy rate a non-Darcy connection flows, for `x` its
+/// turbulence coefficient times its Darcy free-gas surface rate: the root of
+/// `q = q_darcy/(1 + coefficient·q_g)` with `q_g` in proportion to `q`. Solving the
+/// connection law in closed form keeps the rate explicit in the cell state and BHP.
+pub(crate) fn non_darcy_rate_factor<S: Scalar>(x: S) -> S {
+    ((x.max_floor(0.0) * 4.0 + 1.0).sqrt() + 1.0).recip() * 2.0
+}
 
 #[derive(Clone, Copy)]
 pub(crate) enum WellControlDecision {
@@ -60,6 +85,11 @@ pub(crate) struct WellControlConfig {
     pub(crate) target_surface_rate_m3_day: Option<f64>,
     pub(crate) bhp_limit: f64,
     pub(crate) bhp_target: f64,
+    /// THP target of a `thp`-controlled well, which then flows at the BHP its VFP table
+    /// gives rather than at `bhp_target`.
+    pub(crate) thp: Option<ThpControl>,
+    /// Phase a producer's `target_surface_rate_m3_day` meters.
+    pub(crate) surface_phase: SurfaceRatePhase,
 }
 
 impl ReservoirSimulator {
@@ -88,82 +118,303 @@ impl ReservoirSimulator {
         }
     }
 
-    pub(crate) fn calculate_well_productivity_index(
+    /// Productivity index [m³/(day·bar)] of `well`'s completion, overrides and status included.
+    pub(crate) fn well_productivity_index(&self, well: &Well) -> Result<f64, String> {
+        let connection_factor = self.well_connection_factor(well)?;
+        self.productivity_index_for_connection_factor(self.well_cell_index(well), connection_factor)
+    }
+
+    /// Turbulence coefficient [day/Sm³] of a completion: its D-factor over the Darcy skin term
+    /// `ln(r_eq/r_w) + skin` of its connection, so that at free-gas surface rate `q_g` the
+    /// connection keeps `1/(1 + coefficient·|q_g|)` of its Darcy factor.
+    ///
+    /// The skin term is recovered from the connection factor as `C·θ·kh/CF`, which also holds
+    /// for a given connection factor and, as the equivalent radial term, for a fracture. Zero
+    /// without a D-factor, on a shut completion and on a water injector.
+    pub(crate) fn well_non_darcy_coefficient(&self, well: &Well) -> f64 {
+        let Some(d_factor) = well.d_factor.filter(|d_factor| *d_factor > 0.0) else {
+            return 0.0;
+        };
+        if well.injector && self.well_injected_fluid(well) == InjectedFluid::Water {
+            return 0.0;
+        }
+        let Ok(connection_factor) = self.well_connection_factor(well) else {
+            return 0.0;
+        };
+        let Ok(inflows) = self.completion_inflows(
+            self.well_cell_index(well),
+            well.penetration_m,
+            well.well_fraction,
+        ) else {
+            return 0.0;
+        };
+        let mut angle_kh: f64 = inflows
+            .iter()
+            .map(|inflow| inflow.inflow_angle * inflow.kh_md_m)
+            .sum();
+        if let Some(kh_md_m) = well.kh_md_m {
+            let grid_kh: f64 = inflows.iter().map(|inflow| inflow.kh_md_m).sum();
+            angle_kh = if grid_kh > 0.0 {
+                angle_kh / grid_kh * kh_md_m
+            } else {
+                inflows
+                    .first()
+                    .map_or(0.0, |inflow| inflow.inflow_angle * kh_md_m)
+            };
+        }
+        let skin_term = DARCY_METRIC_FACTOR * angle_kh / connection_factor;
+        if !skin_term.is_finite() || skin_term <= 0.0 {
+            return 0.0;
+        }
+        d_factor / skin_term
+    }
+
+    /// Share of its Darcy rate a completion flows this step under its D-factor, evaluated at
+    /// the cell pressures and flowing BHP the step starts from.
+    fn lagged_non_darcy_factor(&self, well: &Well, darcy_pi: f64) -> f64 {
+        let coefficient = self.well_non_darcy_coefficient(well);
+        if coefficient <= 0.0 {
+            return 1.0;
+        }
+        let id = self.well_cell_index(well);
+        let pressure_bar = self.pressure[id];
+        let bhp_bar = well.flowing_bhp.unwrap_or(well.bhp);
+        let darcy_rate = darcy_pi * (pressure_bar - well.connection_pressure_bar(bhp_bar));
+        let bg = self.get_b_g(pressure_bar).max(1e-9);
+        let darcy_gas_rate_sc = if well.injector {
+            (-darcy_rate).max(0.0) / bg
+        } else {
+            darcy_rate.max(0.0) * self.frac_flow_gas(id) / bg
+        };
+        let factor = non_darcy_rate_factor(coefficient * darcy_gas_rate_sc);
+        if factor.is_finite() { factor } else { 1.0 }
+    }
+
+    fn productivity_index_for_connection_factor(
         &self,
         id: usize,
-        well_radius: f64,
-        skin: f64,
+        connection_factor: f64,
     ) -> Result<f64, String> {
-        // Calculate equivalent radius (Peaceman's model)
-        let kx = self.perm_x[id];
-        let ky = self.perm_y[id];
-        if !kx.is_finite() || !ky.is_finite() || kx <= 0.0 || ky <= 0.0 {
+        let total_mobility = self.total_mobility(id);
+        if !total_mobility.is_finite() || total_mobility < 0.0 {
             return Err(format!(
-                "Cell permeability must be positive and finite for well PI calculation, got kx={}, ky={}",
-                kx, ky
+                "Total mobility must be finite and non-negative, got: {}",
+                total_mobility
             ));
         }
+        Ok(connection_factor * total_mobility)
+    }
 
-        let r_eq =
-            0.28 * f64::sqrt(
-                f64::sqrt(kx / ky) * self.dx.powi(2) + f64::sqrt(ky / kx) * self.dy.powi(2),
-            ) / ((kx / ky).powf(0.25) + (ky / kx).powf(0.25));
-        if !r_eq.is_finite() || r_eq <= 0.0 {
-            return Err(format!(
-                "Equivalent radius must be positive and finite, got: {}",
-                r_eq
-            ));
+    /// Connection factor [m³·cP/(day·bar)] of a completion: zero while it is shut, its
+    /// `connection_factor` when one is given, otherwise the Peaceman factor of its cell with
+    /// its `kh_md_m` in place of the grid's and scaled to its `well_fraction`, or its fracture
+    /// factor on a fractured well. A given connection factor is already the model's share of
+    /// the well.
+    pub(crate) fn well_connection_factor(&self, well: &Well) -> Result<f64, String> {
+        if !well.open {
+            return Ok(0.0);
+        }
+        if let Some(connection_factor) = well.connection_factor {
+            return Ok(connection_factor);
         }
+        if let Some(fracture) = &well.fracture {
+            return self.fracture_connection_factor(
+                self.well_cell_index(well),
+                fracture,
+                well.kh_md_m,
+            );
+        }
+        self.completion_connection_factor(
+            self.well_cell_index(well),
+            well.penetration_m,
+            well.kh_md_m,
+            well.well_radius,
+            well.skin,
+            well.well_fraction,
+        )
+    }
 
-        if r_eq <= well_radius {
-            return Err(format!(
-                "Equivalent radius must be greater than well radius for valid PI. r_eq={}, rw={}",
-                r_eq, well_radius
-            ));
+    /// Peaceman inflow of each axis a completion in cell `id` runs along, in x, y, z order.
+    ///
+    /// An axis sees the permeabilities and widths of the two axes across it: a z penetration
+    /// uses `kx`, `ky`, `dx`, `dy` and an x penetration `ky`, `kz`, `dy`, `dz`. Net-to-gross
+    /// thins only a vertical penetration, matching Eclipse `COMPDAT`. On a radial grid the
+    /// completion is vertical along the axis and takes the ring centre and sector angle.
+    ///
+    /// A fractional well (see [`Well::well_fraction`]) sees `well_fraction` of the full
+    /// `2π`, and its cell is that fraction of the block the full well would sit in, so the
+    /// equivalent radius is taken on widths scaled by `1/√well_fraction`: exact for a
+    /// quarter well in a corner cell, area-equivalent for a half well on an edge.
+    pub(crate) fn completion_inflows(
+        &self,
+        id: usize,
+        penetration_m: Option<[f64; 3]>,
+        well_fraction: f64,
+    ) -> Result<Vec<CompletionInflow>, String> {
+        let perm = [self.perm_x[id], self.perm_y[id], self.perm_z[id]];
+        let width = [self.dx_at(id), self.dy_at(id), self.dz_at(id)];
+        let penetration = penetration_m.unwrap_or([0.0, 0.0, width[2]]);
+        let mut inflows = Vec::new();
+        for (axis, &length_m) in penetration.iter().enumerate() {
+            if length_m <= 0.0 {
+                continue;
+            }
+            let (a, b) = match axis {
+                0 => (1, 2),
+                1 => (0, 2),
+                _ => (0, 1),
+            };
+            let (ka, kb) = (perm[a], perm[b]);
+            if !ka.is_finite() || !kb.is_finite() || ka <= 0.0 || kb <= 0.0 {
+                return Err(format!(
+                    "Cell permeability must be positive and finite for well PI calculation, got k{}={}, k{}={}",
+                    ['x', 'y', 'z'][a],
+                    ka,
+                    ['x', 'y', 'z'][b],
+                    kb
+                ));
+            }
+            let (equivalent_radius_m, inflow_angle) = match self.radial_well_inflow(id) {
+                Some(radial) => radial,
+                None => {
+                    let (da, db) = (width[a], width[b]);
+                    let r_eq =
+                        0.28 * f64::sqrt(
+                            f64::sqrt(kb / ka) * da.powi(2) + f64::sqrt(ka / kb) * db.powi(2),
+                        ) / ((kb / ka).powf(0.25) + (ka / kb).powf(0.25));
+                    (
+                        r_eq / well_fraction.sqrt(),
+                        2.0 * std::f64::consts::PI * well_fraction,
+                    )
+                }
+            };
+            let net_to_gross = if axis == 2 {
+                self.net_to_gross[id]
+            } else {
+                1.0
+            };
+            inflows.push(CompletionInflow {
+                equivalent_radius_m,
+                kh_md_m: f64::sqrt(ka * kb) * length_m * net_to_gross,
+                inflow_angle,
+            });
         }
+        Ok(inflows)
+    }
 
-        // Calculate productivity index (PI)
-        let k_avg = f64::sqrt(kx * ky); // Geometric mean of horizontal permeabilities
-        let total_mobility = self.total_mobility(id);
-        if !k_avg.is_finite() || k_avg <= 0.0 {
-            return Err(format!(
-                "Average permeability must be positive and finite, got: {}",
-                k_avg
-            ));
+    /// Geometric connection factor [m³·cP/(day·bar)] of a completion, before mobility.
+    ///
+    /// Each penetrated axis contributes its own Peaceman factor and a deviated completion
+    /// combines them as `√(Σ T_axis²)`, so an axis-aligned completion keeps its single term.
+    /// A `kh_md_m` override scales every axis's kh alike so that they sum to it.
+    pub(crate) fn completion_connection_factor(
+        &self,
+        id: usize,
+        penetration_m: Option<[f64; 3]>,
+        kh_md_m: Option<f64>,
+        well_radius: f64,
+        skin: f64,
+        well_fraction: f64,
+    ) -> Result<f64, String> {
+        let mut inflows = self.completion_inflows(id, penetration_m, well_fraction)?;
+        if let Some(kh_md_m) = kh_md_m {
+            let grid_kh: f64 = inflows.iter().map(|inflow| inflow.kh_md_m).sum();
+            let axes = inflows.len() as f64;
+            for inflow in &mut inflows {
+                inflow.kh_md_m = if grid_kh > 0.0 {
+                    inflow.kh_md_m / grid_kh * kh_md_m
+                } else {
+                    kh_md_m / axes
+                };
+            }
         }
-        if !total_mobility.is_finite() || total_mobility < 0.0 {
-            return Err(format!(
-                "Total mobility must be finite and non-negative, got: {}",
-                total_mobility
-            ));
+        let mut sum_of_squares = 0.0;
+        for inflow in inflows {
+            let r_eq = inflow.equivalent_radius_m;
+            if !r_eq.is_finite() || r_eq <= 0.0 {
+                return Err(format!(
+                    "Equivalent radius must be positive and finite, got: {}",
+                    r_eq
+                ));
+            }
+            if r_eq <= well_radius {
+                return Err(format!(
+                    "Equivalent radius must be greater than well radius for valid PI. r_eq={}, rw={}",
+                    r_eq, well_radius
+                ));
+            }
+            if !inflow.kh_md_m.is_finite() || inflow.kh_md_m < 0.0 {
+                return Err(format!(
+                    "Completion kh must be finite and non-negative, got: {}",
+                    inflow.kh_md_m
+                ));
+            }
+            let denom = f64::ln(r_eq / well_radius) + skin;
+            if !denom.is_finite() || denom.abs() <= f64::EPSILON {
+                return Err(format!(
+                    "Invalid PI denominator ln(r_eq/r_w)+skin = {}. Check well radius and skin.",
+                    denom
+                ));
+            }
+            let factor = DARCY_METRIC_FACTOR * inflow.inflow_angle * inflow.kh_md_m / denom;
+            sum_of_squares += factor * factor;
         }
+        Ok(sum_of_squares.sqrt())
+    }
 
-        let denom = f64::ln(r_eq / well_radius) + skin;
-        if !denom.is_finite() || denom.abs() <= f64::EPSILON {
+    /// Connection factor [m³·cP/(day·bar)] of a completion feeding a hydraulic fracture:
+    /// linear flow onto both fracture faces, `2·k_n·L·h / ⟨d⟩` with `k_n` the permeability
+    /// normal to the fracture plane. A `kh_md_m` override replaces `k_n·h`.
+    fn fracture_connection_factor(
+        &self,
+        id: usize,
+        fracture: &FractureConnection,
+        kh_md_m: Option<f64>,
+    ) -> Result<f64, String> {
+        let [nx, ny] = fracture.normal();
+        let normal_perm = self.perm_x[id] * nx * nx + self.perm_y[id] * ny * ny;
+        if !normal_perm.is_finite() || normal_perm <= 0.0 {
             return Err(format!(
-                "Invalid PI denominator ln(r_eq/r_w)+skin = {}. Check well radius and skin.",
-                denom
+                "Permeability normal to the fracture must be positive and finite, got: {}",
+                normal_perm
             ));
         }
+        let kh_md_m =
+            kh_md_m.unwrap_or_else(|| normal_perm * self.dz_at(id) * self.net_to_gross[id]);
+        Ok(DARCY_METRIC_FACTOR * 2.0 * kh_md_m * fracture.length_m
+            / fracture.mean_normal_distance_m)
+    }
 
-        Ok((DARCY_METRIC_FACTOR
-            * 2.0
-            * std::f64::consts::PI
-            * k_avg
-            * self.dz_at(id)
-            * total_mobility)
-            / denom)
+    /// Cell a completion sits in: its local-grid child cell if it has one, otherwise the root
+    /// cell `(i, j, k)`.
+    pub(crate) fn well_cell_index(&self, well: &Well) -> usize {
+        well.local_cell_index
+            .unwrap_or_else(|| self.idx(well.i, well.j, well.k))
+    }
+
+    /// Phase an injector completion injects: its schedule's fluid, else the simulator-wide
+    /// `injected_fluid`. A two-phase model always injects water.
+    pub(crate) fn well_injected_fluid(&self, well: &Well) -> InjectedFluid {
+        if self.three_phase_mode {
+            well.schedule.injected_fluid.unwrap_or(self.injected_fluid)
+        } else {
+            InjectedFluid::Water
+        }
     }
 
+    /// Recompute every completion's productivity index from the current mobilities.
+    ///
+    /// A completion with a D-factor takes the non-Darcy share of its Darcy index at the rate
+    /// it would flow against its last flowing BHP, so IMPES holds the turbulence skin fixed
+    /// across a step; FIM instead solves it with the connection rate.
     pub(crate) fn update_dynamic_well_productivity_indices(&mut self) {
         let mut updated_pi: Vec<Option<f64>> = Vec::with_capacity(self.wells.len());
 
         for well in self.wells.iter() {
-            let id = self.idx(well.i, well.j, well.k);
-
             let maybe_pi = self
-                .calculate_well_productivity_index(id, well.well_radius, well.skin)
+                .well_productivity_index(well)
                 .ok()
+                .map(|pi| pi * self.lagged_non_darcy_factor(well, pi))
                 .filter(|pi| pi.is_finite() && *pi >= 0.0);
             updated_pi.push(maybe_pi);
         }
@@ -186,15 +437,25 @@ impl ReservoirSimulator {
     /// dominant in exactly the gravity-dominated regime where the drawdown is a
     /// bar or two.
     ///
-    /// Recomputed once per step rather than per Newton iteration, so the offset
-    /// is a constant of the step: `∂q/∂bhp` is untouched and the FIM Jacobian
-    /// keeps its current entries.
+    /// A multi-segment well instead takes the node pressures of its wellbore
+    /// (see `well_segments`), friction included, at the rates its completions
+    /// flow against the last flowing BHP through those same node pressures.
+    ///
+    /// Recomputed once per step rather than per Newton iteration. For an
+    /// ordinary well the offset is a constant of the step, so `∂q/∂bhp` is
+    /// untouched. FIM solves a multi-segment well's node pressures as unknowns
+    /// and uses this profile only as their starting point; IMPES holds it.
     pub(crate) fn refresh_well_head_offsets(&mut self) {
-        if !self.gravity_enabled {
-            for well in self.wells.iter_mut() {
-                well.head_offset_bar = 0.0;
-            }
-            return;
+        let fluids: Vec<Option<SegmentFluid>> = (0..self.wells.len())
+            .map(|idx| {
+                self.wells[idx].segment.map(|_| {
+                    let group = self.well_control_group_indices(&self.wells[idx]);
+                    self.completion_segment_fluid(idx, self.explicit_wellbore_density(&group))
+                })
+            })
+            .collect();
+        for (well, fluid) in self.wells.iter_mut().zip(fluids) {
+            well.segment_fluid = fluid;
         }
 
         let offsets: Vec<f64> = (0..self.wells.len())
@@ -208,16 +469,28 @@ impl ReservoirSimulator {
     fn completion_head_offset_bar(&self, well_idx: usize) -> f64 {
         let well = &self.wells[well_idx];
         let group = self.well_control_group_indices(well);
-        let datum_depth_m = self.well_datum_depth_m(&group);
-        let density_kg_m3 = self.wellbore_density_kg_m3(&group);
-        let offset =
-            density_kg_m3 * GRAVITY_M_S2 * (self.depth_at_k(well.k) - datum_depth_m) * 1e-5;
+        let offset = if let Some(profile) = self.lagged_segment_profile(&group) {
+            let node = group
+                .iter()
+                .position(|&entry| entry == well_idx)
+                .unwrap_or(0);
+            profile.offsets_bar[node]
+        } else if self.gravity_enabled {
+            let datum_depth_m = self.well_datum_depth_m(&group);
+            let density_kg_m3 = self.wellbore_density_kg_m3(&group);
+            density_kg_m3
+                * GRAVITY_M_S2
+                * (self.depth_at(self.well_cell_index(well)) - datum_depth_m)
+                * 1e-5
+        } else {
+            0.0
+        };
         if offset.is_finite() { offset } else { 0.0 }
     }
 
     /// Depth `bhp` is quoted at: the first explicit `datum_depth_m` in the
     /// group, else the shallowest completion — Eclipse's `WELSPECS` default.
-    fn well_datum_depth_m(&self, group: &[usize]) -> f64 {
+    pub(crate) fn well_datum_depth_m(&self, group: &[usize]) -> f64 {
         if let Some(datum_depth) = group
             .iter()
             .find_map(|&idx| self.wells[idx].datum_depth_m)
@@ -228,46 +501,34 @@ impl ReservoirSimulator {
 
         group
             .iter()
-            .map(|&idx| self.depth_at_k(self.wells[idx].k))
+            .map(|&idx| {
+                let well = &self.wells[idx];
+                self.depth_at(self.well_cell_index(well))
+            })
             .fold(f64::INFINITY, f64::min)
     }
 
+    pub(crate) fn explicit_wellbore_density(&self, group: &[usize]) -> Option<f64> {
+        group
+            .iter()
+            .find_map(|&idx| self.wells[idx].wellbore_density_kg_m3)
+            .filter(|density| density.is_finite() && *density >= 0.0)
+    }
+
     /// Density of the column standing in the wellbore: the first explicit
     /// `wellbore_density_kg_m3` in the group, else derived from what the
     /// completions are carrying — the injected phase for an injector, the
     /// mobility-weighted in-situ mixture for a producer, averaged over the
     /// group's completions.
     fn wellbore_density_kg_m3(&self, group: &[usize]) -> f64 {
-        if let Some(density) = group
-            .iter()
-            .find_map(|&idx| self.wells[idx].wellbore_density_kg_m3)
-            .filter(|density| density.is_finite() && *density >= 0.0)
-        {
+        if let Some(density) = self.explicit_wellbore_density(group) {
             return density;
         }
 
         let mut total = 0.0;
         let mut count = 0.0;
         for &idx in group {
-            let well = &self.wells[idx];
-            let id = self.idx(well.i, well.j, well.k);
-            let pressure_bar = self.pressure[id];
-            let density = if well.injector {
-                // A two-phase model injects water whatever `injected_fluid`
-                // says — same rule as `fim::wells::effective_injected_fluid`.
-                match self.injected_fluid {
-                    InjectedFluid::Gas if self.three_phase_mode => {
-                        self.gas_density_generic(pressure_bar)
-                    }
-                    _ => self.water_density_generic(pressure_bar),
-                }
-            } else {
-                let (water_fraction, oil_fraction, gas_fraction) =
-                    self.producer_control_phase_fractions_for_pressures(well, &self.pressure);
-                water_fraction * self.water_density_generic(pressure_bar)
-                    + oil_fraction * self.oil_density_generic(pressure_bar, self.rs[id].max(0.0))
-                    + gas_fraction * self.gas_density_generic(pressure_bar)
-            };
+            let density = self.completion_segment_fluid(idx, None).density_kg_m3;
             if density.is_finite() && density > 0.0 {
                 total += density;
                 count += 1.0;
@@ -277,6 +538,43 @@ impl ReservoirSimulator {
         if count > 0.0 { total / count } else { 0.0 }
     }
 
+    /// Fluid a completion carries into the wellbore: the injected phase for an
+    /// injector, the mobility-weighted in-situ mixture for a producer. An
+    /// explicit `density_kg_m3` replaces the derived density.
+    fn completion_segment_fluid(&self, idx: usize, density_kg_m3: Option<f64>) -> SegmentFluid {
+        let well = &self.wells[idx];
+        let id = self.well_cell_index(well);
+        let pressure_bar = self.pressure[id];
+        let (density, viscosity_cp) = if well.injector {
+            match self.well_injected_fluid(well) {
+                InjectedFluid::Gas => (
+                    self.gas_density_generic(pressure_bar),
+                    self.get_mu_g(pressure_bar),
+                ),
+                InjectedFluid::Water => (
+                    self.water_density_generic(pressure_bar),
+                    self.get_mu_w(pressure_bar),
+                ),
+            }
+        } else {
+            let rs = self.rs[id].max(0.0);
+            let (water_fraction, oil_fraction, gas_fraction) =
+                self.producer_control_phase_fractions_for_pressures(well, &self.pressure);
+            (
+                water_fraction * self.water_density_generic(pressure_bar)
+                    + oil_fraction * self.oil_density_generic(pressure_bar, rs)
+                    + gas_fraction * self.gas_density_generic(pressure_bar),
+                water_fraction * self.get_mu_w(pressure_bar)
+                    + oil_fraction * self.get_mu_o_for_rs(pressure_bar, rs)
+                    + gas_fraction * self.get_mu_g(pressure_bar),
+            )
+        };
+        SegmentFluid {
+            density_kg_m3: density_kg_m3.unwrap_or(density),
+            viscosity_cp,
+        }
+    }
+
     pub(crate) fn well_control_group_key(&self, well: &Well) -> WellControlGroupKey {
         if let Some(well_id) = &well.physical_well_id {
             WellControlGroupKey::ExplicitId(well_id.clone())
@@ -292,7 +590,7 @@ impl ReservoirSimulator {
         }
     }
 
-    fn well_control_group_indices(&self, well: &Well) -> Vec<usize> {
+    pub(crate) fn well_control_group_indices(&self, well: &Well) -> Vec<usize> {
         let group_key = self.well_control_group_key(well);
         self.wells
             .iter()
@@ -303,6 +601,22 @@ impl ReservoirSimulator {
             .collect()
     }
 
+    /// Shut physical well `id` when none of its completions is open any more.
+    pub(crate) fn shut_well_without_open_completions(&mut self, id: &str) {
+        if self
+            .wells
+            .iter()
+            .any(|well| well.physical_well_id.as_deref() == Some(id) && well.open)
+        {
+            return;
+        }
+        for well in self.wells.iter_mut() {
+            if well.physical_well_id.as_deref() == Some(id) {
+                well.schedule.enabled = false;
+            }
+        }
+    }
+
     pub(crate) fn well_control_config(&self, well: &Well) -> WellControlConfig {
         let explicit_schedule = well.schedule.has_explicit_control();
         let enabled = if explicit_schedule {
@@ -316,10 +630,9 @@ impl ReservoirSimulator {
         let rate_controlled = if !enabled {
             false
         } else if explicit_schedule {
-            matches!(
-                well.schedule.control_mode.as_deref(),
-                Some(mode) if mode.eq_ignore_ascii_case("rate")
-            )
+            well.schedule
+                .control_kind()
+                .is_some_and(WellScheduleControl::is_rate_control)
         } else if well.injector {
             self.injector_rate_controlled
         } else {
@@ -350,6 +663,31 @@ impl ReservoirSimulator {
             None
         };
 
+        let surface_phase = well
+            .schedule
+            .control_kind()
+            .and_then(WellScheduleControl::surface_rate_phase)
+            .unwrap_or_default();
+
+        // A group share overrides the well's own target but keeps its BHP limit.
+        let (rate_controlled, target_rate_m3_day, target_surface_rate_m3_day, surface_phase) =
+            match well.group_allocation {
+                Some(allocation) if enabled => {
+                    let rate = Some(allocation.target_rate_m3_day);
+                    if allocation.surface {
+                        (true, None, rate, SurfaceRatePhase::Oil)
+                    } else {
+                        (true, rate, None, SurfaceRatePhase::Oil)
+                    }
+                }
+                _ => (
+                    rate_controlled,
+                    target_rate_m3_day,
+                    target_surface_rate_m3_day,
+                    surface_phase,
+                ),
+            };
+
         let family_bhp_limit = if well.injector {
             self.well_bhp_max
         } else {
@@ -367,6 +705,12 @@ impl ReservoirSimulator {
                 family_bhp_limit
             },
             bhp_target: well.bhp,
+            thp: if enabled {
+                self.well_thp_control(well)
+            } else {
+                None
+            },
+            surface_phase,
         }
     }
 
@@ -378,7 +722,7 @@ impl ReservoirSimulator {
         // Use the well completion cell only. Averaging over a neighbourhood
         // dilutes the saturation signal, causing premature fractional-flow
         // response before the flood front reaches the well cell.
-        let id = self.idx(well.i, well.j, well.k);
+        let id = self.well_cell_index(well);
         let pressure_bar = pressures.get(id).copied().unwrap_or(self.pressure[id]);
 
         let (lambda_w, lambda_o, lambda_g) = if self.three_phase_mode {
@@ -402,7 +746,7 @@ impl ReservoirSimulator {
         well: &Well,
         pressures: &[f64],
     ) -> ProducerControlState {
-        let id = self.idx(well.i, well.j, well.k);
+        let id = self.well_cell_index(well);
         let pressure_bar = pressures.get(id).copied().unwrap_or(self.pressure[id]);
         let (water_fraction, oil_fraction, gas_fraction) =
             self.producer_control_phase_fractions_for_pressures(well, pressures);
@@ -417,6 +761,30 @@ impl ReservoirSimulator {
         }
     }
 
+    /// Surface rates [Sm³/day] of water, oil and gas that producer completion `well` delivers
+    /// while flowing `q_m3_day` of reservoir volume at `pressures`.
+    pub(crate) fn producer_surface_rates(
+        &self,
+        well: &Well,
+        pressures: &[f64],
+        q_m3_day: f64,
+    ) -> [f64; 3] {
+        let id = self.well_cell_index(well);
+        let pressure_bar = pressures.get(id).copied().unwrap_or(self.pressure[id]);
+        let state = self.producer_control_state_for_pressures(well, pressures);
+        let oil = q_m3_day * state.oil_fraction / state.oil_fvf;
+        let dissolved = if self.three_phase_mode && self.pvt_table.is_some() {
+            oil * state.rs_sm3_sm3
+        } else {
+            0.0
+        };
+        [
+            q_m3_day * state.water_fraction * self.water_inverse_fvf(pressure_bar),
+            oil,
+            q_m3_day * state.gas_fraction / state.gas_fvf + dissolved,
+        ]
+    }
+
     pub(crate) fn producer_control_state_from_resolved_control(
         &self,
         well: &Well,
@@ -456,18 +824,18 @@ impl ReservoirSimulator {
         pressures: &[f64],
         pressure_bar: f64,
         bhp_bar: f64,
+        surface_phase: SurfaceRatePhase,
     ) -> Option<f64> {
         let q_m3_day = self.completion_rate_for_bhp(well, pressure_bar, bhp_bar)?;
         if well.injector {
-            let injected_sc_rate = match self.injected_fluid {
+            let injected_sc_rate = match self.well_injected_fluid(well) {
                 InjectedFluid::Water => (-q_m3_day) * self.water_inverse_fvf(pressure_bar),
                 InjectedFluid::Gas => (-q_m3_day) / self.get_b_g(pressure_bar).max(1e-9),
             };
             Some(injected_sc_rate.max(0.0))
         } else {
-            let producer_state = self.producer_control_state_for_pressures(well, pressures);
-            let oil_rate_sc = q_m3_day * producer_state.oil_fraction / producer_state.oil_fvf;
-            Some(oil_rate_sc.max(0.0))
+            let rates = self.producer_surface_rates(well, pressures, q_m3_day);
+            Some(surface_phase.rate(rates).max(0.0))
         }
     }
 
@@ -492,7 +860,7 @@ impl ReservoirSimulator {
             .into_iter()
             .filter_map(|well_idx| {
                 let group_well = &self.wells[well_idx];
-                let id = self.idx(group_well.i, group_well.j, group_well.k);
+                let id = self.well_cell_index(group_well);
                 let pressure_bar = pressures[id];
                 if group_well.injector {
                     match config.target_surface_rate_m3_day {
@@ -501,6 +869,7 @@ impl ReservoirSimulator {
                             pressures,
                             pressure_bar,
                             bhp_bar,
+                            config.surface_phase,
                         ),
                         None => self
                             .completion_rate_for_bhp(group_well, pressure_bar, bhp_bar)
@@ -513,6 +882,7 @@ impl ReservoirSimulator {
                             pressures,
                             pressure_bar,
                             bhp_bar,
+                            config.surface_phase,
                         ),
                         None => self.completion_rate_for_bhp(group_well, pressure_bar, bhp_bar),
                     }
@@ -550,16 +920,14 @@ impl ReservoirSimulator {
             .iter()
             .map(|well_idx| {
                 let group_well = &self.wells[*well_idx];
-                pressures[self.idx(group_well.i, group_well.j, group_well.k)]
-                    - group_well.head_offset_bar
+                pressures[self.well_cell_index(group_well)] - group_well.head_offset_bar
             })
             .fold(f64::INFINITY, f64::min);
         let group_max_pressure = wells
             .iter()
             .map(|well_idx| {
                 let group_well = &self.wells[*well_idx];
-                pressures[self.idx(group_well.i, group_well.j, group_well.k)]
-                    - group_well.head_offset_bar
+                pressures[self.well_cell_index(group_well)] - group_well.head_offset_bar
             })
             .fold(f64::NEG_INFINITY, f64::max);
 
@@ -627,7 +995,7 @@ impl ReservoirSimulator {
             });
         }
 
-        let id = self.idx(well.i, well.j, well.k);
+        let id = self.well_cell_index(well);
         let pressure_bar = pressures[id];
         let producer_state = if well.injector {
             None
@@ -650,6 +1018,17 @@ impl ReservoirSimulator {
             });
         }
 
+        if let Some(thp) = config.thp {
+            let (bhp_bar, bhp_limited) =
+                self.solve_well_thp_bhp(well, pressures, thp, config.bhp_limit)?;
+            return Some(ResolvedWellControl {
+                decision: WellControlDecision::Bhp { bhp_bar },
+                bhp_limited,
+                producer_state,
+                flowing_bhp: Some(bhp_bar),
+            });
+        }
+
         if !well.productivity_index.is_finite()
             || !well.bhp.is_finite()
             || !pressure_bar.is_finite()
@@ -704,7 +1083,7 @@ impl ReservoirSimulator {
         well: &Well,
         pressures: &[f64],
     ) -> Option<f64> {
-        let id = self.idx(well.i, well.j, well.k);
+        let id = self.well_cell_index(well);
         let pressure_bar = pressures[id];
         match self
             .resolve_well_control_for_pressures(well, pressures)?
@@ -725,7 +1104,7 @@ impl ReservoirSimulator {
 
     fn group_pressures_with_override(&self, well: &Well, pressure_bar: f64) -> Vec<f64> {
         let mut pressures = self.pressure.clone();
-        let id = self.idx(well.i, well.j, well.k);
+        let id = self.well_cell_index(well);
         if id < pressures.len() {
             pressures[id] = pressure_bar;
         }
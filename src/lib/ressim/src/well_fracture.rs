@@ -0,0 +1,167 @@
+//! Vertical hydraulic fractures intersected with the root grid.
+//!
+//! A fracture is a vertical plane of half-length `x_f` through the centre of its well's cell,
+//! striking at an azimuth measured from the grid's x axis toward y, over the thickness of the
+//! layer it is placed in. It is taken as infinitely conductive: every cell its trace crosses
+//! gets a completion of the well, and all of them flow at the one well pressure.
+//!
+//! A crossed cell feeds the fracture by linear flow onto both faces of the trace's length `L`
+//! in the cell, over the mean distance `⟨d⟩` from the cell to the fracture plane, as an
+//! embedded discrete fracture does. The connection factor is then `2·k_n·L·h / ⟨d⟩`, with
+//! `k_n` the permeability normal to the plane, and `⟨d⟩` is integrated exactly over the cell.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ReservoirSimulator;
+use crate::well_trajectory::{crossings, cumulative_edges, edge_interval, sort_cuts};
+
+/// A completion's share of its well's hydraulic fracture.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FractureConnection {
+    /// Strike of the fracture [degrees] from the grid's x axis toward y.
+    pub azimuth_deg: f64,
+    /// Length of the fracture trace inside the completion's cell [m].
+    pub length_m: f64,
+    /// Mean distance from the cell to the fracture plane [m].
+    pub mean_normal_distance_m: f64,
+}
+
+impl FractureConnection {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if !self.azimuth_deg.is_finite()
+            || !self.length_m.is_finite()
+            || self.length_m <= 0.0
+            || !self.mean_normal_distance_m.is_finite()
+            || self.mean_normal_distance_m <= 0.0
+        {
+            return Err(format!(
+                "Fracture connection needs a finite azimuth and positive, finite length and \
+                 distance, got: {:?}",
+                self
+            ));
+        }
+        Ok(())
+    }
+
+    /// Unit normal to the fracture plane in x, y.
+    pub(crate) fn normal(&self) -> [f64; 2] {
+        let (sin, cos) = self.azimuth_deg.to_radians().sin_cos();
+        [-sin, cos]
+    }
+}
+
+impl ReservoirSimulator {
+    /// Root cells in layer `k` crossed by a fracture of half-length `half_length_m` through the
+    /// centre of column `(i, j)`, the well's own cell first and the rest from one tip to the
+    /// other. The trace is cut at every column edge and stops at the edge of the grid.
+    pub(crate) fn fracture_connections(
+        &self,
+        i: usize,
+        j: usize,
+        k: usize,
+        half_length_m: f64,
+        azimuth_deg: f64,
+    ) -> Vec<(usize, FractureConnection)> {
+        let x_edges = cumulative_edges(&self.dx);
+        let y_edges = cumulative_edges(&self.dy);
+        let centre = [
+            0.5 * (x_edges[i] + x_edges[i + 1]),
+            0.5 * (y_edges[j] + y_edges[j + 1]),
+        ];
+        let (sin, cos) = azimuth_deg.to_radians().sin_cos();
+        let start = [
+            centre[0] - half_length_m * cos,
+            centre[1] - half_length_m * sin,
+        ];
+        let delta = [2.0 * half_length_m * cos, 2.0 * half_length_m * sin];
+        let normal = [-sin, cos];
+
+        let mut cuts = vec![0.0, 1.0];
+        cuts.extend(crossings(start[0], delta[0], &x_edges));
+        cuts.extend(crossings(start[1], delta[1], &y_edges));
+        sort_cuts(&mut cuts);
+        let mut connections = Vec::new();
+        for piece in cuts.windows(2) {
+            let t = 0.5 * (piece[0] + piece[1]);
+            let (Some(ci), Some(cj)) = (
+                edge_interval(&x_edges, start[0] + t * delta[0]),
+                edge_interval(&y_edges, start[1] + t * delta[1]),
+            ) else {
+                continue;
+            };
+            let length_m = 2.0 * half_length_m * (piece[1] - piece[0]);
+            if length_m <= 0.0 {
+                continue;
+            }
+            // Signed distance to the plane is linear across the cell: its value at the cell
+            // centre plus two uniform spreads, one per cell width.
+            let offset = normal[0] * (0.5 * (x_edges[ci] + x_edges[ci + 1]) - centre[0])
+                + normal[1] * (0.5 * (y_edges[cj] + y_edges[cj + 1]) - centre[1]);
+            let mean_normal_distance_m = mean_abs_of_uniform_sum(
+                offset,
+                0.5 * normal[0].abs() * self.dx[ci],
+                0.5 * normal[1].abs() * self.dy[cj],
+            );
+            connections.push((
+                self.idx(ci, cj, k),
+                FractureConnection {
+                    azimuth_deg,
+                    length_m,
+                    mean_normal_distance_m,
+                },
+            ));
+        }
+        let well_cell = self.idx(i, j, k);
+        connections.sort_by_key(|(id, _)| *id != well_cell);
+        connections
+    }
+}
+
+/// `E|c + U + V|` for `U`, `V` uniform on `[-a, a]` and `[-b, b]`: the mean distance to a
+/// plane across a rectangle whose centre lies `c` from it.
+fn mean_abs_of_uniform_sum(c: f64, a: f64, b: f64) -> f64 {
+    let (a, b) = if a >= b { (a, b) } else { (b, a) };
+    if a <= 1e-12 * c.abs() || a == 0.0 {
+        return c.abs();
+    }
+    // Antiderivatives of |z|: once `z|z|/2`, twice `|z|³/6`. The double integral cancels badly
+    // as `b → 0`, where the single one is already exact to O((b/a)²).
+    if b <= 1e-4 * a {
+        let once = |z: f64| 0.5 * z * z.abs();
+        return (once(c + a) - once(c - a)) / (2.0 * a);
+    }
+    let twice = |z: f64| z.abs().powi(3) / 6.0;
+    (twice(c + a + b) - twice(c + a - b) - twice(c - a + b) + twice(c - a - b)) / (4.0 * a * b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mean_abs_of_uniform_sum;
+
+    #[test]
+    fn mean_normal_distance_matches_midpoint_quadrature() {
+        for &(c, a, b) in &[
+            (0.0, 5.0, 5.0),
+            (1.5, 4.0, 2.0),
+            (-7.0, 3.0, 1.0),
+            (0.3, 2.0, 1e-6),
+            (2.0, 0.0, 0.0),
+        ] {
+            let n = 400;
+            let mut sum = 0.0;
+            for p in 0..n {
+                for q in 0..n {
+                    let u = -a + (p as f64 + 0.5) * 2.0 * a / n as f64;
+                    let v = -b + (q as f64 + 0.5) * 2.0 * b / n as f64;
+                    sum += f64::abs(c + u + v);
+                }
+            }
+            let quadrature = sum / (n * n) as f64;
+            let exact = mean_abs_of_uniform_sum(c, a, b);
+            assert!(
+                (exact - quadrature).abs() < 1e-4 * quadrature.max(1.0),
+                "c={c} a={a} b={b}: {exact} vs {quadrature}"
+            );
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! Multi-segment wellbores: friction and a mixture hydrostatic head between completions.
+//!
+//! A multi-segment well threads its completions on one wellbore in the order they were added,
+//! heel first. Segment `s` runs from the node above it (the datum for the first) down to
+//! completion `s` and carries `Q_s`, everything the completions toe-side of it put into the
+//! well. Its pressure drop toward the toe is
+//!
+//! ```text
+//! Δp_s = ρ_s·g·Δz_s + F(Q_s, ρ_s, μ_s)
+//! ```
+//!
+//! with `ρ_s` and `μ_s` those of the mixture in the segment and `F` the wall friction of pipe
+//! flow: Hagen–Poiseuille below `Re = 2000`, Haaland's Darcy factor above `Re = 4000`, blended
+//! linearly between.
+//!
+//! FIM carries each node's pressure, segment flow and mixture water and gas fractions as
+//! unknowns of their own (`fim::wells_segments`), so the drops, the mixture and any crossflow
+//! between completions through the wellbore are implicit. IMPES instead holds the node pressures
+//! of `lagged_segment_profile` for the step: the drops at the rates the completions flow entering
+//! it, each completion's fluid lagged on that state like the single-column head of an ordinary
+//! well and mixed by `|q|`.
+
+use nalgebra::{DMatrix, DVector};
+use serde::{Deserialize, Serialize};
+
+use crate::ReservoirSimulator;
+use crate::fim::ad::Ad;
+use crate::well_control::GRAVITY_M_S2;
+use crate::well_trajectory::cumulative_edges;
+
+/// Flow [m³/day] below which a completion's share of the segment mixture stops shrinking, so
+/// a shut-in wellbore holds the plain average of its completion fluids.
+pub(crate) const MIXTURE_WEIGHT_FLOOR_M3_DAY: f64 = 1e-6;
+
+/// Newton iterations allowed for the rates a multi-segment well flows at entering a step.
+const LAGGED_RATE_ITERATIONS: usize = 30;
+
+const LAMINAR_REYNOLDS: f64 = 2000.0;
+const TURBULENT_REYNOLDS: f64 = 4000.0;
+
+/// Wellbore segment from the node above a completion of a multi-segment well down to it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WellSegment {
+    /// Measured length along the wellbore [m].
+    pub length_m: f64,
+    /// Inner diameter [m].
+    pub diameter_m: f64,
+    /// Absolute wall roughness [m].
+    pub roughness_m: f64,
+}
+
+impl WellSegment {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if !self.length_m.is_finite()
+            || self.length_m < 0.0
+            || !self.diameter_m.is_finite()
+            || self.diameter_m <= 0.0
+            || !self.roughness_m.is_finite()
+            || self.roughness_m < 0.0
+        {
+            return Err(format!(
+                "Well segment needs a finite, non-negative length and roughness and a positive \
+                 diameter, got: {:?}",
+                self
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Fluid a completion passes into its wellbore, lagged on the state entering the step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SegmentFluid {
+    pub density_kg_m3: f64,
+    pub viscosity_cp: f64,
+}
+
+/// One node of a multi-segment well: a completion and the segment above it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SegmentNode {
+    pub(crate) segment: WellSegment,
+    /// Depth gained over the segment [m], positive downward; zero while gravity is disabled.
+    pub(crate) depth_change_m: f64,
+    pub(crate) fluid: SegmentFluid,
+}
+
+/// Pressure below the datum at every node of a multi-segment well and its sensitivity to the
+/// connection rates.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SegmentProfile {
+    /// `bhp` plus this is node `j`'s pressure [bar].
+    pub(crate) offsets_bar: Vec<f64>,
+    /// `∂offset_j/∂q_m` [bar·day/m³], indexed `[j][m]`.
+    pub(crate) offset_rate_derivatives: Vec<Vec<f64>>,
+}
+
+/// Node pressures of a wellbore whose completions flow `rates_m3_day` (reservoir volumes,
+/// positive into the well), heel first.
+pub(crate) fn segment_profile(nodes: &[SegmentNode], rates_m3_day: &[f64]) -> SegmentProfile {
+    let n = nodes.len();
+    let weights: Vec<f64> = rates_m3_day
+        .iter()
+        .map(|q| q.hypot(MIXTURE_WEIGHT_FLOOR_M3_DAY))
+        .collect();
+    // Toe-side sums `[flow, Σw, Σw·ρ, Σw·μ]` below each node, accumulated from the toe.
+    let mut toe_sums = vec![[0.0; 4]; n + 1];
+    for m in (0..n).rev() {
+        let fluid = &nodes[m].fluid;
+        toe_sums[m] = [
+            toe_sums[m + 1][0] + rates_m3_day[m],
+            toe_sums[m + 1][1] + weights[m],
+            toe_sums[m + 1][2] + weights[m] * fluid.density_kg_m3,
+            toe_sums[m + 1][3] + weights[m] * fluid.viscosity_cp,
+        ];
+    }
+    let mut offset = 0.0;
+    let mut derivatives = vec![0.0; n];
+    let mut profile = SegmentProfile {
+        offsets_bar: Vec::with_capacity(n),
+        offset_rate_derivatives: Vec::with_capacity(n),
+    };
+    for (s, node) in nodes.iter().enumerate() {
+        let toe_side = s..n;
+        let [flow, weight, weighted_density, weighted_viscosity] = toe_sums[s];
+        let density = weighted_density / weight;
+        let viscosity = weighted_viscosity / weight;
+
+        let density_ad = Ad::<3>::variable(density, 1);
+        let drop = density_ad * (GRAVITY_M_S2 * node.depth_change_m * 1e-5)
+            + friction_drop_bar(
+                &node.segment,
+                Ad::variable(flow, 0),
+                density_ad,
+                Ad::variable(viscosity, 2),
+            );
+        offset += drop.value();
+        for m in toe_side {
+            // A completion's pull on the mixture: ∂ρ_s/∂q_m = (ρ_m − ρ_s)·w'_m / Σw.
+            let share = rates_m3_day[m] / weights[m] / weight;
+            derivatives[m] += drop.d(0)
+                + drop.d(1) * (nodes[m].fluid.density_kg_m3 - density) * share
+                + drop.d(2) * (nodes[m].fluid.viscosity_cp - viscosity) * share;
+        }
+        profile.offsets_bar.push(offset);
+        profile.offset_rate_derivatives.push(derivatives.clone());
+    }
+    profile
+}
+
+/// Wall friction [bar] over `segment` for a flow of `flow_m3_day` toward the heel, signed with
+/// the flow: the toe end sits that much above the heel end.
+pub(crate) fn friction_drop_bar<const N: usize>(
+    segment: &WellSegment,
+    flow_m3_day: Ad<N>,
+    density_kg_m3: Ad<N>,
+    viscosity_cp: Ad<N>,
+) -> Ad<N> {
+    if segment.length_m <= 0.0 || density_kg_m3.value() <= 0.0 || viscosity_cp.value() <= 0.0 {
+        return Ad::constant(0.0);
+    }
+    let diameter = segment.diameter_m;
+    let area = 0.25 * std::f64::consts::PI * diameter * diameter;
+    let velocity = flow_m3_day / (86_400.0 * area);
+    let viscosity_pa_s = viscosity_cp * 1e-3;
+    let laminar = 32.0 * segment.length_m / (diameter * diameter) * viscosity_pa_s * velocity;
+    let reynolds = density_kg_m3 * velocity.abs() * diameter / viscosity_pa_s;
+    if reynolds.value() <= LAMINAR_REYNOLDS {
+        return laminar * 1e-5;
+    }
+    // Haaland: 1/√f = −1.8·log10[(ε/D/3.7)^1.11 + 6.9/Re].
+    let relative_roughness = (segment.roughness_m / diameter / 3.7).powf(1.11);
+    let inverse_sqrt_f =
+        (6.9 / reynolds + relative_roughness).ln() * (-1.8 / std::f64::consts::LN_10);
+    let friction_factor = (inverse_sqrt_f * inverse_sqrt_f).recip();
+    let turbulent = friction_factor
+        * (0.5 * segment.length_m / diameter)
+        * density_kg_m3
+        * velocity
+        * velocity.abs();
+    if reynolds.value() >= TURBULENT_REYNOLDS {
+        return turbulent * 1e-5;
+    }
+    let blend = (reynolds - LAMINAR_REYNOLDS) / (TURBULENT_REYNOLDS - LAMINAR_REYNOLDS);
+    (laminar + blend * (turbulent - laminar)) * 1e-5
+}
+
+impl ReservoirSimulator {
+    /// Nodes of the wellbore through completions `group` (well entries, heel first), or `None`
+    /// unless every one of them is on a multi-segment well.
+    pub(crate) fn multi_segment_nodes(&self, group: &[usize]) -> Option<Vec<SegmentNode>> {
+        let mut depth = self.well_datum_depth_m(group);
+        group
+            .iter()
+            .map(|&entry| {
+                let well = &self.wells[entry];
+                let segment = well.segment?;
+                let node_depth = self.depth_at(self.well_cell_index(well));
+                let depth_change_m = if self.gravity_enabled {
+                    node_depth - depth
+                } else {
+                    0.0
+                };
+                depth = node_depth;
+                Some(SegmentNode {
+                    segment,
+                    depth_change_m,
+                    fluid: well.segment_fluid.unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// Node pressures of the wellbore through completions `group` at the rates they flow
+    /// against the well's last flowing BHP (its configured one before the first step), solved
+    /// by Newton with the node pressures they set themselves: `q_m = PI_m·(p_m − bhp − Δp_m(q))`.
+    /// IMPES holds these for the step. `None` unless the completions form a multi-segment well.
+    pub(crate) fn lagged_segment_profile(&self, group: &[usize]) -> Option<SegmentProfile> {
+        let nodes = self.multi_segment_nodes(group)?;
+        let n = group.len();
+        let mut rates = DVector::zeros(n);
+        let mut profile = segment_profile(&nodes, rates.as_slice());
+        for _ in 0..LAGGED_RATE_ITERATIONS {
+            let mut residual = DVector::zeros(n);
+            let mut jacobian = DMatrix::identity(n, n);
+            for (m, &entry) in group.iter().enumerate() {
+                let well = &self.wells[entry];
+                residual[m] = rates[m];
+                if !well.open || !well.schedule.enabled {
+                    continue;
+                }
+                let bhp_bar = well.flowing_bhp.unwrap_or(well.bhp);
+                let raw_rate = well.productivity_index
+                    * (self.pressure[self.well_cell_index(well)]
+                        - bhp_bar
+                        - profile.offsets_bar[m]);
+                let flowing = if well.injector {
+                    raw_rate < 0.0
+                } else {
+                    raw_rate > 0.0
+                };
+                if flowing {
+                    residual[m] -= raw_rate;
+                    for k in 0..n {
+                        jacobian[(m, k)] +=
+                            well.productivity_index * profile.offset_rate_derivatives[m][k];
+                    }
+                }
+            }
+            let Some(step) = jacobian.lu().solve(&residual) else {
+                break;
+            };
+            if !step.iter().all(|value| value.is_finite()) {
+                break;
+            }
+            rates -= &step;
+            let next = segment_profile(&nodes, rates.as_slice());
+            if !next.offsets_bar.iter().all(|offset| offset.is_finite()) {
+                break;
+            }
+            profile = next;
+            if step.amax() <= 1e-9 * (1.0 + rates.amax()) {
+                break;
+            }
+        }
+        Some(profile)
+    }
+
+    /// Length [m] of the segment down to the `position`-th of completions `group`: the
+    /// distance between the centres of its cell and the one above, and the vertical distance
+    /// from the datum for the first.
+    pub(crate) fn derived_segment_length_m(&self, group: &[usize], position: usize) -> f64 {
+        let x_edges = cumulative_edges(&self.dx);
+        let y_edges = cumulative_edges(&self.dy);
+        let centre = |entry: usize| {
+            let well = &self.wells[entry];
+            [
+                0.5 * (x_edges[well.i] + x_edges[well.i + 1]),
+                0.5 * (y_edges[well.j] + y_edges[well.j + 1]),
+                self.depth_at(self.well_cell_index(well)),
+            ]
+        };
+        let below = centre(group[position]);
+        if position == 0 {
+            return (below[2] - self.well_datum_depth_m(group)).abs();
+        }
+        let above = centre(group[position - 1]);
+        below
+            .iter()
+            .zip(above)
+            .map(|(b, a)| (b - a).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(length_m: f64, depth_change_m: f64, density_kg_m3: f64) -> SegmentNode {
+        SegmentNode {
+            segment: WellSegment {
+                length_m,
+                diameter_m: 0.1,
+                roughness_m: 1e-5,
+            },
+            depth_change_m,
+            fluid: SegmentFluid {
+                density_kg_m3,
+                viscosity_cp: 1.0,
+            },
+        }
+    }
+
+    #[test]
+    fn segment_profile_rate_derivatives_match_finite_differences() {
+        // Laminar, transitional and turbulent segments, with one crossflowing completion.
+        let nodes = [
+            node(5.0, 5.0, 800.0),
+            node(200.0, 0.0, 1000.0),
+            node(200.0, 2.0, 200.0),
+        ];
+        for rates in [[0.5, 1.0, 2.0], [40.0, -10.0, 25.0], [800.0, 600.0, 1200.0]] {
+            let profile = segment_profile(&nodes, &rates);
+            for m in 0..rates.len() {
+                let h = 1e-6 * rates[m].abs().max(1.0);
+                let mut up = rates;
+                let mut down = rates;
+                up[m] += h;
+                down[m] -= h;
+                let up = segment_profile(&nodes, &up).offsets_bar;
+                let down = segment_profile(&nodes, &down).offsets_bar;
+                for j in 0..nodes.len() {
+                    let fd = (up[j] - down[j]) / (2.0 * h);
+                    let exact = profile.offset_rate_derivatives[j][m];
+                    assert!(
+                        (exact - fd).abs() <= 1e-5 * fd.abs().max(1e-6),
+                        "rates {rates:?}, node {j}, rate {m}: {exact} vs {fd}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn still_wellbore_holds_only_its_mixture_column() {
+        let nodes = [node(10.0, 10.0, 1000.0), node(10.0, 10.0, 600.0)];
+        let profile = segment_profile(&nodes, &[0.0, 0.0]);
+        let column = |density: f64| density * GRAVITY_M_S2 * 10.0 * 1e-5;
+        assert!((profile.offsets_bar[0] - column(800.0)).abs() < 1e-9);
+        assert!((profile.offsets_bar[1] - column(800.0) - column(600.0)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,118 @@
+//! Deviated well trajectories intersected with the root grid.
+//!
+//! A trajectory is a polyline of `(x, y, z)` survey points: `x` and `y` measured from the
+//! `(0, 0)` corner of the grid along its axes, `z` the true vertical depth [m TVDSS]. Columns
+//! follow the tensor-product `dx`/`dy` edges, and each column stacks its layers from the cell
+//! tops, so dipping structures are honoured. Each segment is cut at every column edge and then
+//! at every layer boundary of the column it lies in; the pieces add their `|Δx|`, `|Δy|` and
+//! `|Δz|` to the penetration of the cell that holds them. Pieces outside the grid are dropped.
+
+use crate::ReservoirSimulator;
+
+impl ReservoirSimulator {
+    /// Root cells a trajectory passes through, in the order it first enters them, each with the
+    /// projections of its path through the cell on x, y and z [m].
+    pub(crate) fn trajectory_penetrations(&self, points: &[[f64; 3]]) -> Vec<(usize, [f64; 3])> {
+        let x_edges = cumulative_edges(&self.dx);
+        let y_edges = cumulative_edges(&self.dy);
+        let mut penetrations: Vec<(usize, [f64; 3])> = Vec::new();
+        for segment in points.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            let delta = [end[0] - start[0], end[1] - start[1], end[2] - start[2]];
+            let at = |t: f64| {
+                [
+                    start[0] + t * delta[0],
+                    start[1] + t * delta[1],
+                    start[2] + t * delta[2],
+                ]
+            };
+            let mut cuts = vec![0.0, 1.0];
+            cuts.extend(crossings(start[0], delta[0], &x_edges));
+            cuts.extend(crossings(start[1], delta[1], &y_edges));
+            sort_cuts(&mut cuts);
+            for column_piece in cuts.windows(2) {
+                let middle = at(0.5 * (column_piece[0] + column_piece[1]));
+                let (Some(i), Some(j)) = (
+                    edge_interval(&x_edges, middle[0]),
+                    edge_interval(&y_edges, middle[1]),
+                ) else {
+                    continue;
+                };
+                let layers: Vec<(f64, f64)> = (0..self.nz)
+                    .map(|k| {
+                        let id = self.idx(i, j, k);
+                        let top = self.depth_at(id) - 0.5 * self.dz_at(id);
+                        (top, top + self.dz_at(id))
+                    })
+                    .collect();
+                let mut layer_cuts = vec![column_piece[0], column_piece[1]];
+                for &(top, bottom) in &layers {
+                    layer_cuts.extend(
+                        crossings(start[2], delta[2], &[top, bottom])
+                            .filter(|t| *t > column_piece[0] && *t < column_piece[1]),
+                    );
+                }
+                sort_cuts(&mut layer_cuts);
+                for piece in layer_cuts.windows(2) {
+                    let depth = at(0.5 * (piece[0] + piece[1]))[2];
+                    let Some(k) = layers
+                        .iter()
+                        .position(|&(top, bottom)| depth >= top && depth < bottom)
+                    else {
+                        continue;
+                    };
+                    let span = piece[1] - piece[0];
+                    let lengths = delta.map(|d| (d * span).abs());
+                    let id = self.idx(i, j, k);
+                    match penetrations.iter_mut().find(|(cell, _)| *cell == id) {
+                        Some((_, total)) => {
+                            for axis in 0..3 {
+                                total[axis] += lengths[axis];
+                            }
+                        }
+                        None => penetrations.push((id, lengths)),
+                    }
+                }
+            }
+        }
+        penetrations.retain(|(_, lengths)| lengths.iter().any(|length| *length > 0.0));
+        penetrations
+    }
+}
+
+/// Cell edges `0, w0, w0 + w1, …` along one axis.
+pub(crate) fn cumulative_edges(widths: &[f64]) -> Vec<f64> {
+    let mut edges = Vec::with_capacity(widths.len() + 1);
+    edges.push(0.0);
+    for width in widths {
+        edges.push(edges[edges.len() - 1] + width);
+    }
+    edges
+}
+
+/// Segment parameters strictly inside `(0, 1)` where `origin + t · delta` meets an edge.
+pub(crate) fn crossings<'a>(
+    origin: f64,
+    delta: f64,
+    edges: &'a [f64],
+) -> impl Iterator<Item = f64> + 'a {
+    edges
+        .iter()
+        .filter(move |_| delta != 0.0)
+        .map(move |edge| (edge - origin) / delta)
+        .filter(|t| *t > 0.0 && *t < 1.0)
+}
+
+pub(crate) fn sort_cuts(cuts: &mut Vec<f64>) {
+    cuts.sort_by(f64::total_cmp);
+    cuts.dedup();
+}
+
+/// Index of the cell whose edges bracket `position`, if it lies inside the grid.
+pub(crate) fn edge_interval(edges: &[f64], position: f64) -> Option<usize> {
+    let last = edges.len() - 1;
+    if position < edges[0] || position >= edges[last] {
+        return None;
+    }
+    Some(edges.partition_point(|edge| *edge <= position) - 1)
+}
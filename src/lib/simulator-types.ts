@@ -289,6 +289,14 @@ export interface RateHistoryPoint {
     /** Fraction of initial mobile oil recovered [0-1]. Some only for 'both' geometry. */
     mobile_oil_recovered?: number;
   };
+  /** Economic-limit actions taken at the end of this step (absent when none) */
+  events?: {
+    time: number;
+    /** Physical well id or group name whose limit was broken */
+    source: string;
+    reason: string;
+    action: string;
+  }[];
   // additional fields produced by the simulator may exist
   [key: string]: unknown;
 }